@@ -0,0 +1,580 @@
+use anyhow::{Context, Result, bail};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::utils::{
+    config::{Config, ConfigManager},
+    connection::{Connection, ConnectionManager, default_port, mask_credentials},
+    export::{self, OutputFormat},
+    kube::KubeTarget,
+    masking::Masking,
+    notices,
+    query_executor::{QueryExecutor, RowBatch},
+    scripting::Scripts,
+    sql::{quote_identifier, split_statements},
+    text::{single_line, truncate_to_width},
+    value::Value,
+    xlsx::XlsxWriter,
+};
+
+/// Exit codes of the headless commands, clap exits with 2 on usage errors
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;
+
+/// A terminal client for PostgreSQL, MySQL/MariaDB and SQLite
+#[derive(Parser)]
+#[command(name = "rsquid", version, about)]
+pub struct Cli {
+    /// Open this saved connection instead of the connection list
+    #[arg(short, long)]
+    pub connection: Option<String>,
+
+    /// Serve query and pool counters for Prometheus at http://ADDR/metrics
+    #[arg(long, global = true, value_name = "ADDR")]
+    pub metrics_listen: Option<std::net::SocketAddr>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run SQL against a saved connection and print the results
+    Exec(ExecArgs),
+    /// Manage saved connections
+    Connections {
+        #[command(subcommand)]
+        action: ConnectionsCommand,
+    },
+    /// Stream a whole table to a file or stdout
+    Dump(DumpArgs),
+    /// Print a shell completion script
+    Completions { shell: Shell },
+    /// Answer HTTP requests that list connections and run queries, for dashboards and scripts
+    Serve(crate::server::ServeArgs),
+}
+
+#[derive(Args)]
+pub struct DumpArgs {
+    /// Saved connection to read from
+    #[arg(short, long)]
+    connection: String,
+    /// Table to export, optionally schema-qualified
+    #[arg(short, long)]
+    table: String,
+    /// csv, json, xlsx or parquet, the other formats can't be streamed
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+    /// File to write, stdout when omitted
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    /// Write the columns masked under [masking] in the config as they are
+    #[arg(long)]
+    no_mask: bool,
+}
+
+#[derive(Args)]
+#[command(group(clap::ArgGroup::new("source").required(true).args(["query", "file", "stdin"])))]
+pub struct ExecArgs {
+    /// Saved connection to run against
+    #[arg(short, long)]
+    connection: String,
+    /// SQL to run
+    #[arg(short, long)]
+    query: Option<String>,
+    /// Read the SQL from a file
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+    /// Pass - to read the SQL from stdin
+    #[arg(value_name = "-", value_parser = clap::builder::PossibleValuesParser::new(["-"]))]
+    stdin: Option<String>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+    /// Run every statement in one transaction, rolled back if any fails
+    #[arg(long)]
+    transaction: bool,
+    /// Write the columns masked under [masking] in the config as they are
+    #[arg(long)]
+    no_mask: bool,
+}
+
+#[derive(Subcommand)]
+pub enum ConnectionsCommand {
+    /// List saved connections, without passwords
+    List,
+    /// Save a new connection
+    Add(Box<AddArgs>),
+    /// Delete a saved connection
+    Remove { name: String },
+    /// Connect and run SELECT 1
+    Test { name: String },
+}
+
+#[derive(Args)]
+pub struct AddArgs {
+    #[arg(long)]
+    name: String,
+    #[arg(long = "type", value_parser = ["postgres", "mysql", "mariadb", "sqlite"])]
+    db_type: String,
+    #[arg(long, default_value = "localhost")]
+    host: String,
+    /// Defaults to the database type's standard port
+    #[arg(long)]
+    port: Option<u16>,
+    /// Database name, or the file path for SQLite
+    #[arg(long, default_value = "")]
+    database: String,
+    #[arg(long, default_value = "")]
+    username: String,
+    /// Or a secret read on connect, like vault:kv/data/db#password or aws:prod/db#password
+    #[arg(long, conflicts_with = "password_stdin")]
+    password: Option<String>,
+    /// Read the password from the first line of stdin
+    #[arg(long)]
+    password_stdin: bool,
+    /// Query timeout in seconds, overriding the global setting
+    #[arg(long)]
+    timeout: Option<u64>,
+    /// Connect through kubectl port-forward to this resource, e.g. svc/postgres;
+    /// --port is then the port inside the cluster
+    #[arg(long)]
+    kube_resource: Option<String>,
+    #[arg(long, requires = "kube_resource")]
+    kube_namespace: Option<String>,
+    /// kubeconfig context, the current one when omitted
+    #[arg(long, requires = "kube_resource")]
+    kube_context: Option<String>,
+    /// Tunnel through this SSH host or ~/.ssh/config alias; --host is then resolved by it
+    #[arg(long, conflicts_with = "kube_resource")]
+    ssh: Option<String>,
+    /// Connect to this Cloud SQL instance (project:region:instance) through cloud-sql-proxy
+    #[arg(long, conflicts_with_all = ["kube_resource", "ssh"])]
+    cloud_sql: Option<String>,
+    /// Log in with an RDS IAM token, signed with the AWS credentials found in the environment or ~/.aws
+    #[arg(long)]
+    aws_iam: bool,
+    /// Free text shown with the connection, e.g. what it's for or who owns it
+    #[arg(long)]
+    notes: Option<String>,
+}
+
+/// Writes a completion script for `shell` to stdout
+pub fn completions(shell: Shell) -> i32 {
+    clap_complete::generate(shell, &mut Cli::command(), "rsquid", &mut io::stdout());
+    EXIT_OK
+}
+
+/// `rsquid exec`: runs a query against a saved connection and prints the results,
+/// returns the process exit code
+pub async fn exec(args: ExecArgs) -> i32 {
+    let connection = match find_connection(&args.connection) {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("Error: {}", mask_credentials(&format!("{:#}", e)));
+            return EXIT_FAILURE;
+        }
+    };
+
+    match run_exec(&connection, &args).await {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("Error: {}", connection.redact(&format!("{:#}", e)));
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// `rsquid dump`: streams every row of a table to a file, returns the process exit code
+pub async fn dump(args: DumpArgs) -> i32 {
+    let connection = match find_connection(&args.connection) {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("Error: {}", mask_credentials(&format!("{:#}", e)));
+            return EXIT_FAILURE;
+        }
+    };
+
+    match run_dump(&connection, &args).await {
+        Ok(rows) => {
+            eprintln!("Dumped {} row(s) from {}", rows, args.table);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("Error: {}", connection.redact(&format!("{:#}", e)));
+            EXIT_FAILURE
+        }
+    }
+}
+
+async fn run_dump(connection: &Connection, args: &DumpArgs) -> Result<u64> {
+    // Checked before the output file gets created
+    if !args.format.is_streamable() {
+        bail!("The {:?} format can't be streamed, use csv, json, xlsx or parquet", args.format);
+    }
+    let out: Box<dyn Write> = match &args.out {
+        Some(path) => Box::new(io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| format!("Could not create {}", path.display()))?,
+        )),
+        None => {
+            refuse_binary_terminal(args.format)?;
+            Box::new(io::BufWriter::new(io::stdout().lock()))
+        }
+    };
+    let mut writer = export::StreamingWriter::new(out, args.format)?;
+
+    let config = ConfigManager::new()
+        .and_then(|m| m.load_config())
+        .unwrap_or_else(|_| Config::default());
+    let executor = QueryExecutor::new(connection, &config).await?;
+    let query = format!("SELECT * FROM {}", quote_identifier(&args.table, executor.dialect()));
+    let masking = if args.no_mask { Masking::default() } else { config.masking.clone() };
+
+    let result = async {
+        let (first, mut stream) = executor.execute_streaming(&query).await?;
+        for (headers, mut rows) in first {
+            masking.mask_rows(&headers, &mut rows);
+            writer.write_batch(&headers, &rows)?;
+        }
+        let mut reported = false;
+        while let Some(stream) = stream.as_mut()
+            && let Some(batch) = stream.next_batch().await
+        {
+            let (headers, mut rows) = batch?;
+            masking.mask_rows(&headers, &mut rows);
+            writer.write_batch(&headers, &rows)?;
+            eprint!("\r{} rows", writer.rows_written());
+            reported = true;
+        }
+        if reported {
+            eprintln!();
+        }
+        writer.finish()
+    }
+    .await;
+    executor.close().await.ok();
+    result
+}
+
+fn find_connection(name: &str) -> Result<Connection> {
+    ConnectionManager::new()?
+        .load_connections()?
+        .into_iter()
+        .find(|c| c.name == name)
+        .with_context(|| format!("Unknown connection: {}", name))
+}
+
+fn read_sql(args: &ExecArgs) -> Result<String> {
+    if let Some(query) = &args.query {
+        return Ok(query.clone());
+    }
+    if let Some(path) = &args.file {
+        return std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()));
+    }
+    let mut sql = String::new();
+    io::stdin().read_to_string(&mut sql).context("Could not read SQL from stdin")?;
+    Ok(sql)
+}
+
+async fn run_exec(connection: &Connection, args: &ExecArgs) -> Result<()> {
+    refuse_binary_terminal(args.format)?;
+    let sql = read_sql(args)?;
+    let config = ConfigManager::new()
+        .and_then(|m| m.load_config())
+        .unwrap_or_else(|_| Config::default());
+    let (scripts, script_errors) = Scripts::load(|message| eprintln!("{}", message));
+    for error in script_errors {
+        eprintln!("Warning: {}", error);
+    }
+    let executor = QueryExecutor::new(connection, &config).await?;
+    report_hook(scripts.on_connect(connection));
+
+    // Scripts report each statement on stderr, a single --query keeps stderr quiet
+    let report = args.query.is_none();
+    let masking = if args.no_mask { Masking::default() } else { config.masking };
+    let result = run_statements(&executor, &scripts, &masking, &sql, args, report).await;
+    executor.close().await.ok();
+    result
+}
+
+async fn run_statements(
+    executor: &QueryExecutor,
+    scripts: &Scripts,
+    masking: &Masking,
+    sql: &str,
+    args: &ExecArgs,
+    report: bool,
+) -> Result<()> {
+    let statements = split_statements(sql, executor.dialect());
+    if args.transaction {
+        executor.begin_transaction().await?;
+    }
+
+    // Results are printed together so several sets still make one valid JSON document
+    let mut results = Vec::new();
+    let total = statements.len();
+    for (i, statement) in statements.into_iter().enumerate() {
+        let started = Instant::now();
+        let summary = truncate_to_width(&single_line(statement.trim()), 60);
+        let (result, notices) = notices::collect(executor.execute_sets(statement)).await;
+        print_notices(notices);
+        match result {
+            Ok(sets) => {
+                let rows = sets.iter().map(|(_, rows)| rows.len()).sum();
+                report_hook(scripts.on_query(statement, started.elapsed(), Ok(rows)));
+                let mut sets: Vec<RowBatch> = if scripts.has_transform() {
+                    sets.into_iter().map(|set| scripts.transform(set)).collect::<Result<_>>()?
+                } else {
+                    sets
+                };
+                // Masked after the transform, so a script can't bring a hidden value back
+                for (headers, rows) in &mut sets {
+                    masking.mask_rows(headers, rows);
+                }
+                if report {
+                    eprintln!("[{}/{}] OK ({} ms) {}", i + 1, total, started.elapsed().as_millis(), summary);
+                }
+                results.push(StatementResult { statement, duration: started.elapsed(), sets });
+            }
+            Err(e) => {
+                report_hook(scripts.on_query(statement, started.elapsed(), Err(&e.to_string())));
+                write_results(&mut io::stdout().lock(), args.format, &results)?;
+                if report {
+                    eprintln!("[{}/{}] FAILED {}", i + 1, total, summary);
+                }
+                if args.transaction {
+                    executor.rollback_transaction().await.ok();
+                    eprintln!("Transaction rolled back");
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    if args.transaction {
+        executor.commit_transaction().await?;
+        if report {
+            eprintln!("Transaction committed");
+        }
+    }
+    write_results(&mut io::stdout().lock(), args.format, &results)
+}
+
+/// A failing event hook is worth a warning, not losing the query's output
+fn report_hook(result: Result<()>) {
+    if let Err(e) = result {
+        eprintln!("Warning: script {:#}", e);
+    }
+}
+
+struct StatementResult<'a> {
+    statement: &'a str,
+    duration: Duration,
+    sets: Vec<RowBatch>,
+}
+
+fn write_results(out: &mut impl Write, format: OutputFormat, results: &[StatementResult]) -> Result<()> {
+    if format == OutputFormat::JsonFull {
+        let statements: Vec<serde_json::Value> = results
+            .iter()
+            .map(|result| {
+                serde_json::json!({
+                    "statement": result.statement.trim(),
+                    "duration_ms": result.duration.as_millis() as u64,
+                    "result_sets": result
+                        .sets
+                        .iter()
+                        .map(|(headers, rows)| export::json_full_rows(headers, rows))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        let total: Duration = results.iter().map(|result| result.duration).sum();
+        let document = serde_json::json!({
+            "statements": statements,
+            "duration_ms": total.as_millis() as u64,
+        });
+        serde_json::to_writer_pretty(&mut *out, &document)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+
+    let result_sets: Vec<RowBatch> = results.iter().flat_map(|result| result.sets.iter().cloned()).collect();
+    write_result_sets(out, format, &result_sets)
+}
+
+/// Binary formats go to a file or a pipe, printing them would garble the terminal
+fn refuse_binary_terminal(format: OutputFormat) -> Result<()> {
+    if format.is_binary() && io::stdout().is_terminal() {
+        bail!("The {:?} format is binary, redirect the output to a file", format);
+    }
+    Ok(())
+}
+
+fn write_result_sets(out: &mut impl Write, format: OutputFormat, result_sets: &[RowBatch]) -> Result<()> {
+    // One workbook with a sheet per result set
+    if format == OutputFormat::Xlsx {
+        let mut writer = XlsxWriter::new();
+        for (i, (headers, rows)) in result_sets.iter().enumerate() {
+            writer.begin_set(&format!("Result {}", i + 1));
+            writer.write_batch(headers, rows)?;
+        }
+        return writer.finish(out);
+    }
+
+    // Several JSON documents in a row isn't valid JSON, nest them in one array instead
+    if format == OutputFormat::Json && result_sets.len() > 1 {
+        let sets: Vec<serde_json::Value> = result_sets
+            .iter()
+            .map(|(headers, rows)| export::json_rows(headers, rows))
+            .collect();
+        serde_json::to_writer_pretty(&mut *out, &sets)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+
+    // A Parquet file has a single schema, there's no place for a second result set
+    if format == OutputFormat::Parquet && result_sets.len() > 1 {
+        bail!("The query returned {} result sets, Parquet holds one per file", result_sets.len());
+    }
+
+    for (i, (headers, rows)) in result_sets.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        export::write_result(out, format, headers, rows)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// `rsquid connections ...`: manages the saved connections without the TUI,
+/// returns the process exit code
+pub async fn connections(action: ConnectionsCommand) -> i32 {
+    let result = match action {
+        ConnectionsCommand::List => list_connections(),
+        ConnectionsCommand::Add(args) => new_connection(*args).and_then(add_connection),
+        ConnectionsCommand::Remove { name } => remove_connection(&name),
+        ConnectionsCommand::Test { name } => test_connection(&name).await,
+    };
+
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("Error: {}", mask_credentials(&format!("{:#}", e)));
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn list_connections() -> Result<()> {
+    let connections = ConnectionManager::new()?.load_connections()?;
+    let headers: Vec<String> = ["name", "type", "host", "port", "database", "username"]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+    // Passwords stay out of the listing
+    let rows: Vec<Vec<Value>> = connections
+        .iter()
+        .map(|c| {
+            vec![
+                Value::from(c.name.as_str()),
+                Value::from(c.db_type.as_str()),
+                Value::from(c.host.as_str()),
+                Value::UInt(c.port as u64),
+                Value::from(c.database.as_str()),
+                Value::from(c.username.as_str()),
+            ]
+        })
+        .collect();
+    export::write_table(&mut io::stdout().lock(), &headers, &rows)
+}
+
+fn new_connection(args: AddArgs) -> Result<Connection> {
+    let password = if args.password_stdin {
+        let mut password = String::new();
+        io::stdin().read_line(&mut password).context("Could not read the password from stdin")?;
+        password.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        args.password.unwrap_or_default()
+    };
+    let Some(default_port) = default_port(&args.db_type) else {
+        bail!("Invalid database type: {}", args.db_type);
+    };
+    Ok(Connection {
+        name: args.name,
+        db_type: args.db_type,
+        host: args.host,
+        port: args.port.unwrap_or(default_port),
+        database: args.database,
+        username: args.username,
+        password,
+        query_timeout_secs: args.timeout,
+        accent_color: None,
+        kube: args.kube_resource.map(|resource| KubeTarget {
+            resource,
+            namespace: args.kube_namespace,
+            context: args.kube_context,
+        }),
+        ssh: args.ssh,
+        cloud_sql: args.cloud_sql,
+        aws_iam: args.aws_iam,
+        notes: args.notes,
+    })
+}
+
+fn add_connection(connection: Connection) -> Result<()> {
+    let manager = ConnectionManager::new()?;
+    if manager.load_connections()?.iter().any(|c| c.name == connection.name) {
+        bail!("A connection named {} already exists", connection.name);
+    }
+    let name = connection.name.clone();
+    manager.save_connection(connection)?;
+    println!("Added connection {}", name);
+    Ok(())
+}
+
+fn remove_connection(name: &str) -> Result<()> {
+    let manager = ConnectionManager::new()?;
+    let index = manager
+        .load_connections()?
+        .iter()
+        .position(|c| c.name == name)
+        .with_context(|| format!("Unknown connection: {}", name))?;
+    manager.delete_connection(index)?;
+    println!("Removed connection {}", name);
+    Ok(())
+}
+
+async fn test_connection(name: &str) -> Result<()> {
+    let connection = find_connection(name)?;
+    let config = ConfigManager::new()
+        .and_then(|m| m.load_config())
+        .unwrap_or_else(|_| Config::default());
+
+    let started = Instant::now();
+    let result = async {
+        let executor = QueryExecutor::new(&connection, &config).await?;
+        let result = executor.execute_sets("SELECT 1").await;
+        executor.close().await.ok();
+        result
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            println!("{}: OK ({} ms)", name, started.elapsed().as_millis());
+            Ok(())
+        }
+        Err(e) => Err(anyhow::anyhow!("{}", connection.redact(&format!("{}: {:#}", name, e)))),
+    }
+}
+
+/// Database notices go to stderr so they don't end up in piped output
+fn print_notices(notices: Vec<String>) {
+    for notice in notices {
+        eprintln!("{}", notice);
+    }
+}
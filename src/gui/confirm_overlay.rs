@@ -0,0 +1,138 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{Confirmation, PendingConfirmation, input_overlay::centered_rect, theme::Theme};
+
+pub fn draw_confirm_overlay(f: &mut Frame, pending: &PendingConfirmation, theme: &Theme) {
+    let area = centered_rect(60, 40, f.area());
+
+    f.render_widget(Clear, area);
+
+    let (title, color) = match pending.kind {
+        Confirmation::UnfilteredWrite { .. } => ("Unfiltered Write", theme.error),
+        Confirmation::LargeResult { .. } => ("Large Result", theme.warning),
+        Confirmation::EmptyTable { .. } => ("Empty Table", theme.error),
+        Confirmation::Maintenance { .. } => ("Maintenance", theme.accent),
+    };
+
+    let block = theme.block()
+        .title(title)
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(color).bold());
+
+    let mut text = vec![Line::from("")];
+
+    match &pending.kind {
+        Confirmation::UnfilteredWrite { write, estimated_rows } => {
+            let rows = match estimated_rows {
+                Some(rows) => format!("~{} rows", rows),
+                None => "an unknown number of rows".to_string(),
+            };
+            text.push(Line::from(Span::styled(
+                format!("{} without a WHERE clause on {}", write.verb, write.table),
+                Style::default().fg(theme.error),
+            )));
+            text.push(Line::from(Span::styled(
+                format!("This will affect every row in the table ({})", rows),
+                Style::default().fg(theme.text).not_bold(),
+            )));
+            text.push(Line::from(""));
+            text.push(Line::from(vec![
+                Span::styled("Type yes to run it: ", Style::default().fg(theme.text).not_bold()),
+                Span::styled(pending.input.clone(), Style::default().fg(theme.success).not_bold()),
+                Span::styled(theme.glyphs.cursor, Style::default().fg(theme.success).not_bold()),
+            ]));
+            text.push(Line::from(""));
+            text.push(Line::from(Span::styled(
+                "Enter: Confirm | Esc: Cancel",
+                Style::default().fg(theme.text).not_bold(),
+            )));
+        }
+        Confirmation::EmptyTable { table, rows, exact, delete, transaction } => {
+            let rows = match (rows, exact) {
+                (Some(rows), true) => format!("{} rows", rows),
+                (Some(rows), false) => format!("~{} rows", rows),
+                (None, _) => "an unknown number of rows".to_string(),
+            };
+            let plain = Style::default().fg(theme.text).not_bold();
+            text.push(Line::from(Span::styled(
+                format!("{} {} removes every row ({})", if *delete { "DELETE FROM" } else { "TRUNCATE" }, table, rows),
+                Style::default().fg(theme.error),
+            )));
+            text.push(Line::from(Span::styled(
+                if *transaction {
+                    "Runs in a transaction, commit with Ctrl+Shift+C (Alt+K) or roll back with Ctrl+Shift+R (Alt+R)"
+                } else {
+                    "Runs without a transaction and can't be undone"
+                },
+                plain,
+            )));
+            text.push(Line::from(""));
+            text.push(Line::from(vec![
+                Span::styled("Type the table name to run it: ", plain),
+                Span::styled(pending.input.clone(), Style::default().fg(theme.success).not_bold()),
+                Span::styled(theme.glyphs.cursor, Style::default().fg(theme.success).not_bold()),
+            ]));
+            text.push(Line::from(""));
+            text.push(Line::from(Span::styled(
+                "Enter: Confirm | Tab: TRUNCATE/DELETE | Ctrl+T: Transaction | Esc: Cancel",
+                plain,
+            )));
+        }
+        Confirmation::Maintenance { table, operations, selected, armed } => {
+            let plain = Style::default().fg(theme.text).not_bold();
+            text.push(Line::from(Span::styled(format!("Maintenance on {}", table), plain)));
+            text.push(Line::from(""));
+            for (i, operation) in operations.iter().enumerate() {
+                let style = if i == *selected {
+                    Style::default().fg(theme.selection_fg).bg(theme.selection_bg).bold()
+                } else {
+                    plain
+                };
+                text.push(Line::from(Span::styled(format!(" {} ", operation.label), style)));
+            }
+            text.push(Line::from(""));
+            if let Some(operation) = operations.get(*selected) {
+                let color = if operation.blocking { theme.warning } else { theme.text };
+                text.push(Line::from(Span::styled(operation.note, Style::default().fg(color).not_bold())));
+                text.push(Line::from(Span::styled(operation.statement.clone(), Style::default().fg(theme.muted).not_bold())));
+            }
+            text.push(Line::from(""));
+            text.push(Line::from(Span::styled(
+                if *armed {
+                    "Enter again: Run it anyway | Esc: Cancel"
+                } else {
+                    "Up/Down: Choose | Enter: Run | Esc: Cancel"
+                },
+                if *armed { Style::default().fg(theme.warning) } else { plain },
+            )));
+        }
+        Confirmation::LargeResult { estimated_rows } => {
+            text.push(Line::from(Span::styled(
+                format!("The planner estimates ~{} rows for this query", estimated_rows),
+                Style::default().fg(theme.warning),
+            )));
+            text.push(Line::from(Span::styled(
+                "Consider adding a LIMIT or setting a row limit with Ctrl+L",
+                Style::default().fg(theme.text).not_bold(),
+            )));
+            text.push(Line::from(""));
+            text.push(Line::from(Span::styled(
+                "y / Enter: Run anyway | n / Esc: Cancel",
+                Style::default().fg(theme.text).not_bold(),
+            )));
+        }
+    }
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,43 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+};
+
+use crate::gui::{PendingConnection, format_duration, input_overlay::centered_rect, theme::Theme};
+
+pub fn draw_connecting_overlay(f: &mut Frame, pending: &PendingConnection, theme: &Theme) {
+    let area = centered_rect(50, 20, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Connecting")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let elapsed = pending.started.elapsed();
+    let spinner = theme.glyphs.spinner;
+    let frame = spinner[(elapsed.as_millis() / 100) as usize % spinner.len()];
+
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(format!("{} ", frame), Style::default().fg(theme.accent)),
+            Span::styled(
+                format!("Connecting to {}... {}", pending.connection.name, format_duration(elapsed)),
+                Style::default().fg(theme.text).not_bold(),
+            ),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("Esc: Abort", Style::default().fg(theme.text).not_bold())),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
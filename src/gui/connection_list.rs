@@ -1,59 +1,178 @@
-use crate::utils::connection::ConnectionManager;
+use crate::gui::theme::Theme;
+use crate::utils::connection::Connection;
+use crate::utils::connection::DiscoveredDatabase;
+use anyhow::Result;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{List, ListItem, ListState, Paragraph, Wrap},
 };
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
 
+/// How long a deleted connection can be brought back
+const UNDO_WINDOW: Duration = Duration::from_secs(6);
+
+#[allow(clippy::enum_variant_names)]
 pub enum ConnectionListAction {
     NewConnection,
     SelectConnection(usize),
     DeleteConnection(usize),
-    ModifyConnection(usize)
+    /// Restores the connection deleted last, while it can still be
+    UndoDelete,
+    ModifyConnection(usize),
+    /// Moves a connection from the first position to the second
+    MoveConnection(usize, usize),
+    OpenSettings,
+    /// Looks for databases to add, e.g. in local docker containers
+    Discover(DiscoverySource),
+    /// Saves a connection to a discovered container
+    AddDiscovered(Box<Connection>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiscoverySource {
+    /// Running postgres, mysql and mariadb containers
+    Docker,
+    /// Targets of ~/.dbt/profiles.yml
+    Dbt,
+}
+
+impl DiscoverySource {
+    pub fn title(self) -> &'static str {
+        match self {
+            DiscoverySource::Docker => "Docker Databases",
+            DiscoverySource::Dbt => "dbt Profiles",
+        }
+    }
+
+    pub fn searching(self) -> &'static str {
+        match self {
+            DiscoverySource::Docker => "Asking docker for running containers...",
+            DiscoverySource::Dbt => "Reading dbt profiles...",
+        }
+    }
+
+    pub fn nothing_found(self) -> &'static str {
+        match self {
+            DiscoverySource::Docker => "No running postgres, mysql or mariadb containers",
+            DiscoverySource::Dbt => "No postgres, mysql, mariadb or sqlite targets in the dbt profiles",
+        }
+    }
+}
+
+/// Databases found outside rsquid, shown over the list until one is added
+pub struct Discovery {
+    pub(crate) source: DiscoverySource,
+    pub(crate) task: Option<JoinHandle<Result<Vec<DiscoveredDatabase>>>>,
+    pub(crate) found: Vec<DiscoveredDatabase>,
+    pub(crate) error: Option<String>,
+    pub(crate) state: ListState,
+}
+
+/// A connection just deleted, kept in memory until the undo window closes
+pub struct DeletedConnection {
+    pub(crate) index: usize,
+    pub(crate) connection: Connection,
+    deleted: Instant,
 }
 
 pub struct ConnectionListPage {
     pub(crate) list_state: ListState,
+    /// Where the list was last drawn, for mouse clicks
+    pub(crate) list_area: Rect,
+    /// Opened from a split view to pick the connection of its second pane
+    pub picking_split: bool,
+    pub(crate) discovery: Option<Discovery>,
+    /// The connection a delete is waiting to be confirmed for
+    pub(crate) pending_delete: Option<usize>,
+    pub(crate) deleted: Option<DeletedConnection>,
 }
 
 impl ConnectionListPage {
     pub fn new() -> Self {
         let mut list_state = ListState::default();
         list_state.select(Some(0));
-        Self { list_state }
+        Self {
+            list_state,
+            list_area: Rect::default(),
+            picking_split: false,
+            discovery: None,
+            pending_delete: None,
+            deleted: None,
+        }
+    }
+
+    pub fn remember_deleted(&mut self, index: usize, connection: Connection) {
+        self.deleted = Some(DeletedConnection { index, connection, deleted: Instant::now() });
+    }
+
+    /// Forgets the deleted connection once it's too late to undo
+    pub fn expire_undo(&mut self) {
+        if self.deleted.as_ref().is_some_and(|d| d.deleted.elapsed() >= UNDO_WINDOW) {
+            self.deleted = None;
+        }
+    }
+
+    /// Shows the discovery overlay while `task` searches `source`, replacing an earlier search
+    pub fn open_discovery(&mut self, source: DiscoverySource, task: JoinHandle<Result<Vec<DiscoveredDatabase>>>) {
+        if let Some(previous) = self.discovery.as_mut().and_then(|d| d.task.take()) {
+            previous.abort();
+        }
+        self.discovery = Some(Discovery { source, task: Some(task), found: Vec::new(), error: None, state: ListState::default() });
+    }
+
+    /// Picks up the containers once the search ends
+    pub fn poll_discovery(&mut self) {
+        let Some(discovery) = self.discovery.as_mut() else {
+            return;
+        };
+        if !discovery.task.as_ref().is_some_and(|task| task.is_finished()) {
+            return;
+        }
+        let Some(task) = discovery.task.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        match futures_util::FutureExt::now_or_never(task) {
+            Some(Ok(Ok(found))) => {
+                discovery.state.select((!found.is_empty()).then_some(0));
+                discovery.found = found;
+            }
+            Some(Ok(Err(e))) => discovery.error = Some(format!("{:#}", e)),
+            _ => discovery.error = Some("The search ended unexpectedly".to_string()),
+        }
     }
 
     pub fn render(
         &mut self,
         f: &mut Frame,
         area: Rect,
-        conn_manager: &ConnectionManager,
+        connections: &[Connection],
         error: &Option<String>,
+        theme: &Theme,
     ) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),
                 Constraint::Min(0),
-                Constraint::Length(if error.is_some() { 5 } else { 3 }),
+                Constraint::Length(3 + if error.is_some() { 2 } else { 0 } + self.deleted.is_some() as u16),
             ])
             .split(area);
 
-        let title = Paragraph::new("Database Client - Connection Manager")
-            .style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
+        let title = Paragraph::new(if self.picking_split {
+            "Pick a connection for the split pane (Esc: Back)"
+        } else {
+            "Database Client - Connection Manager"
+        })
+            .style(theme.title_style())
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(theme.block());
         f.render_widget(title, chunks[0]);
 
-        // Connections list
-        let connections = conn_manager.load_connections().unwrap_or_default();
-
         let mut items: Vec<ListItem> = connections
             .iter()
             .enumerate()
@@ -63,75 +182,132 @@ impl ConnectionListPage {
                     i + 1,
                     conn.name,
                     conn.db_type,
-                    conn.host
+                    conn.host_label()
                 );
-                ListItem::new(content)
+                match conn.accent_color {
+                    Some(color) => ListItem::new(Line::from(vec![
+                        Span::raw(content),
+                        Span::styled(format!(" {}", theme.glyphs.swatch), Style::default().fg(color)),
+                    ])),
+                    None => ListItem::new(content),
+                }
             })
             .collect();
 
         items.push(
             ListItem::new("+ Create New Connection").style(
                 Style::default()
-                    .fg(Color::Green)
+                    .fg(theme.success)
                     .add_modifier(Modifier::BOLD),
             ),
         );
-        let highlight = {
-            #[cfg(target_os = "windows")]
-            {
-                Style::default()
-                    .fg(Color::White)
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
+        let list = List::new(items)
+            .block(theme.block().title("Connections"))
+            .highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
 
-            #[cfg(not(target_os = "windows"))]
-            {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
+        // The highlighted connection's details sit beside the list
+        let selected = self.list_state.selected().and_then(|i| connections.get(i));
+        let list_area = match selected {
+            Some(conn) => {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .split(chunks[1]);
+                let details = Paragraph::new(details_lines(conn, theme))
+                    .block(theme.block().title("Details"))
+                    .wrap(Wrap { trim: false });
+                f.render_widget(details, columns[1]);
+                columns[0]
             }
+            None => chunks[1],
         };
 
-        let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Connections"))
-            .highlight_style(highlight)
-            .highlight_symbol(">> ");
-
-        f.render_stateful_widget(list, chunks[1], &mut self.list_state);
+        f.render_stateful_widget(list, list_area, &mut self.list_state);
+        self.list_area = list_area;
 
         // Help text or error
         let mut help_lines = vec![Line::from(vec![
-            Span::raw("↑↓: Navigate | "),
+            Span::raw(format!("{}: Navigate | ", theme.glyphs.up_down)),
             Span::raw("Enter: Select | "),
-            Span::raw("m: Modify | "),
+            Span::raw("1-9: Connect | "),
+            Span::raw("Shift+1-9 - m: Modify | "),
+            Span::raw(format!("Shift+{}: Reorder | ", theme.glyphs.up_down)),
             Span::raw("d: Delete | "),
-            Span::raw("Esc - q: Quit"),
+            Span::raw("s: Settings | "),
+            Span::raw("c: Docker | "),
+            Span::raw("b: dbt | "),
+            Span::raw("Esc - q - Ctrl+Q: Quit"),
         ])];
+        if let Some(deleted) = &self.deleted {
+            help_lines.push(Line::from(Span::styled(
+                format!("u: Undo deleting {}", deleted.connection.name),
+                Style::default().fg(theme.warning),
+            )));
+        }
 
         if let Some(err) = error {
             help_lines.push(Line::from(""));
             help_lines.push(Line::from(vec![
                 Span::styled(
                     "Error: ",
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
                 ),
-                Span::styled(err, Style::default().fg(Color::Red)),
+                Span::styled(err, Style::default().fg(theme.error)),
             ]));
         }
 
         let help = Paragraph::new(help_lines)
-            .style(Style::default().fg(Color::Gray))
+            .style(Style::default().fg(theme.help))
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(theme.block());
         f.render_widget(help, chunks[2]);
 
         // Force valid selection
         let total_items = connections.len() + 1;
-        if let Some(selected) = self.list_state.selected() {
-            if selected >= total_items {
-                self.list_state.select(Some(total_items.saturating_sub(1)));
-            }
+        if let Some(selected) = self.list_state.selected()
+            && selected >= total_items
+        {
+            self.list_state.select(Some(total_items.saturating_sub(1)));
+        }
+
+        if let Some(connection) = self.pending_delete.and_then(|index| connections.get(index)) {
+            crate::gui::delete_overlay::draw_delete_overlay(f, connection, theme);
+        }
+
+        if let Some(discovery) = &mut self.discovery {
+            crate::gui::discovery_overlay::draw_discovery_overlay(f, discovery, connections, theme);
         }
     }
 }
+
+/// What the details panel says about a connection, its notes last
+fn details_lines<'a>(conn: &'a Connection, theme: &Theme) -> Vec<Line<'a>> {
+    let label = Style::default().fg(theme.warning).add_modifier(Modifier::BOLD);
+    let mut fields = vec![
+        ("Type", conn.db_type.clone()),
+        ("Host", conn.host_label()),
+        ("Database", conn.database.clone()),
+    ];
+    if conn.db_type != "sqlite" {
+        fields.insert(2, ("Port", conn.port.to_string()));
+        fields.push(("User", conn.username.clone()));
+    }
+    if let Some(ssh) = &conn.ssh {
+        fields.push(("SSH", ssh.clone()));
+    }
+    if let Some(secs) = conn.query_timeout_secs {
+        fields.push(("Timeout", if secs == 0 { "none".to_string() } else { format!("{} s", secs) }));
+    }
+
+    let mut lines: Vec<Line> = fields
+        .into_iter()
+        .map(|(name, value)| Line::from(vec![Span::styled(format!("{}: ", name), label), Span::raw(value)]))
+        .collect();
+    if let Some(notes) = &conn.notes {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Notes", label)));
+        lines.extend(notes.lines().map(|line| Line::from(Span::styled(line, Style::default().fg(theme.text)))));
+    }
+    lines
+}
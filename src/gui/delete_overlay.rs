@@ -0,0 +1,43 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{input_overlay::centered_rect, theme::Theme};
+use crate::utils::connection::Connection;
+
+/// Asks before deleting a saved connection, naming it so the wrong one isn't removed
+pub fn draw_delete_overlay(f: &mut Frame, connection: &Connection, theme: &Theme) {
+    let area = centered_rect(60, 30, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Delete Connection")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.error).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let text = vec![
+        Line::from(""),
+        Line::from(Span::styled(format!("Delete the connection {}?", connection.name), Style::default().fg(theme.error))),
+        Line::from(Span::styled(
+            format!("{} - {}", connection.db_type, connection.host_label()),
+            Style::default().fg(theme.muted).not_bold(),
+        )),
+        Line::from(""),
+        Line::from(Span::styled("It can be brought back with u for a few seconds", plain)),
+        Line::from(""),
+        Line::from(Span::styled("y / Enter: Delete | n / Esc: Cancel", plain)),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,86 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, List, ListItem, Paragraph, Wrap},
+};
+
+use crate::gui::{Discovery, input_overlay::centered_rect, theme::Theme};
+use crate::utils::connection::Connection;
+use crate::utils::text::truncate_to_width;
+
+/// Databases found outside rsquid, marking the ones already saved
+pub fn draw_discovery_overlay(f: &mut Frame, discovery: &mut Discovery, connections: &[Connection], theme: &Theme) {
+    let area = centered_rect(80, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title(discovery.source.title())
+        .style(Style::default().bg(theme.overlay_bg).fg(theme.accent).bold());
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let message = if discovery.task.is_some() {
+        Some(discovery.source.searching().to_string())
+    } else if let Some(error) = &discovery.error {
+        Some(error.clone())
+    } else if discovery.found.is_empty() {
+        Some(discovery.source.nothing_found().to_string())
+    } else {
+        None
+    };
+    match message {
+        Some(message) => {
+            let color = if discovery.error.is_some() { theme.error } else { theme.muted };
+            let text = Paragraph::new(vec![Line::from(""), Line::from(Span::styled(message, Style::default().fg(color).not_bold()))])
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true });
+            f.render_widget(text, chunks[0]);
+        }
+        None => {
+            let width = chunks[0].width.saturating_sub(3) as usize;
+            let items: Vec<ListItem> = discovery
+                .found
+                .iter()
+                .map(|found| {
+                    let conn = &found.connection;
+                    let saved = connections.iter().any(|c| is_same_server(c, conn));
+                    let text = format!(
+                        "{} ({}) {}  [{}]{}",
+                        found.origin,
+                        conn.db_type,
+                        conn.address(),
+                        found.detail,
+                        if saved { " - saved" } else { "" }
+                    );
+                    let style = if saved { Style::default().fg(theme.muted) } else { plain };
+                    ListItem::new(truncate_to_width(&text, width)).style(style)
+                })
+                .collect();
+            let list = List::new(items)
+                .highlight_style(theme.highlight())
+                .highlight_symbol(">> ");
+            f.render_stateful_widget(list, chunks[0], &mut discovery.state);
+        }
+    }
+
+    let help = Paragraph::new(Span::styled("Enter: Add Connection | r: Search Again | Esc: Close", plain))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[1]);
+}
+
+/// Whether `saved` already points at the database `found` describes, whatever it is named
+fn is_same_server(saved: &Connection, found: &Connection) -> bool {
+    saved.db_type == found.db_type
+        && saved.host == found.host
+        && saved.port == found.port
+        && saved.database == found.database
+        && saved.username == found.username
+}
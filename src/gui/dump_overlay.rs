@@ -0,0 +1,46 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{PendingDump, input_overlay::centered_rect, theme::Theme};
+
+/// Asks for the file a table dump is written to
+pub fn draw_dump_overlay(f: &mut Frame, pending: &PendingDump, theme: &Theme) {
+    let area = centered_rect(60, 30, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Dump as SQL")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let tables = match pending.tables.as_slice() {
+        [table] => format!("CREATE TABLE and INSERT statements for {}", table),
+        tables => format!("CREATE TABLE and INSERT statements for all {} tables", tables.len()),
+    };
+    let text = vec![
+        Line::from(""),
+        Line::from(Span::styled(tables, plain)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("File: ", plain),
+            Span::styled(pending.path.clone(), Style::default().fg(theme.success).not_bold()),
+            Span::styled(theme.glyphs.cursor, Style::default().fg(theme.success).not_bold()),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("Press Enter to write the file, Esc to cancel", plain)),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,64 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{input_overlay::centered_rect, theme::Theme};
+use crate::utils::errors::ErrorDetail;
+
+pub fn draw_error_overlay(f: &mut Frame, detail: &ErrorDetail, theme: &Theme) {
+    let area = centered_rect(80, 70, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Error Details")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.error).bold());
+
+    let label = |text: &'static str| Span::styled(text, Style::default().fg(theme.warning));
+    let plain = Style::default().fg(theme.text).not_bold();
+
+    let mut text = Vec::new();
+    for (i, cause) in detail.chain.iter().enumerate() {
+        let prefix = if i == 0 { "" } else { "caused by: " };
+        text.push(Line::from(vec![
+            Span::styled(prefix, Style::default().fg(theme.muted).not_bold()),
+            Span::styled(cause.clone(), Style::default().fg(theme.error).not_bold()),
+        ]));
+    }
+    text.push(Line::from(""));
+
+    if let Some(code) = &detail.code {
+        text.push(Line::from(vec![
+            Span::styled(format!("{}: ", detail.code_label), Style::default().fg(theme.warning)),
+            Span::styled(code.clone(), plain),
+        ]));
+    }
+    if let Some(detail) = &detail.detail {
+        text.push(Line::from(vec![label("Detail: "), Span::styled(detail.clone(), plain)]));
+    }
+    for hint in &detail.hints {
+        text.push(Line::from(vec![label("Hint: "), Span::styled(hint.clone(), plain)]));
+    }
+    if let Some(position) = detail.position {
+        text.push(Line::from(vec![label("Position: "), Span::styled(position.to_string(), plain)]));
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(label("Statement:")));
+    for line in detail.statement.lines() {
+        text.push(Line::from(Span::styled(line.to_string(), Style::default().fg(theme.help).not_bold())));
+    }
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled("c: Copy to clipboard | Esc: Close", plain)));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
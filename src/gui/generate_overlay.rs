@@ -0,0 +1,45 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{PendingGenerate, input_overlay::centered_rect, theme::Theme};
+
+/// Asks how many rows of test data to insert
+pub fn draw_generate_overlay(f: &mut Frame, pending: &PendingGenerate, theme: &Theme) {
+    let area = centered_rect(60, 30, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Generate Test Data")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let text = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("Fills {} with made-up rows that follow its column types and constraints", pending.table),
+            plain,
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Rows: ", plain),
+            Span::styled(pending.rows.clone(), Style::default().fg(theme.success).not_bold()),
+            Span::styled(theme.glyphs.cursor, Style::default().fg(theme.success).not_bold()),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("Press Enter to insert the rows, Esc to cancel", plain)),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
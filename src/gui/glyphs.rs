@@ -0,0 +1,109 @@
+use ratatui::symbols::{border, scrollbar};
+
+/// Icons and line drawing used across the UI, with a plain ASCII set for
+/// terminals and fonts that can't show the Unicode ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glyphs {
+    pub folder: &'static str,
+    pub branch: &'static str,
+    pub collapsed: &'static str,
+    pub expanded: &'static str,
+    /// Text cursor in overlay inputs
+    pub cursor: &'static str,
+    /// Color sample next to a connection's accent color
+    pub swatch: &'static str,
+    /// History entry status
+    pub marker: &'static str,
+    /// Next to a form field whose value won't work
+    pub invalid: &'static str,
+    pub up_down: &'static str,
+    /// Separator between result set tabs
+    pub divider: &'static str,
+    /// Next to the title of the column a list is sorted by
+    pub sort_ascending: &'static str,
+    pub sort_descending: &'static str,
+    /// Frames of the busy animation
+    pub spinner: &'static [&'static str],
+    pub scrollbar: scrollbar::Set,
+    pub border: border::Set,
+}
+
+impl Glyphs {
+    pub const UNICODE: Self = Self {
+        folder: "📁 ",
+        branch: "  └─ ",
+        collapsed: "▸",
+        expanded: "▾",
+        cursor: "█",
+        swatch: "■",
+        marker: "● ",
+        invalid: "✗",
+        up_down: "↑↓",
+        divider: "│",
+        sort_ascending: "▲",
+        sort_descending: "▼",
+        spinner: &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+        scrollbar: scrollbar::VERTICAL,
+        border: border::PLAIN,
+    };
+
+    pub const ASCII: Self = Self {
+        folder: "+ ",
+        branch: "  `- ",
+        collapsed: ">",
+        expanded: "v",
+        cursor: "_",
+        swatch: "#",
+        marker: "* ",
+        invalid: "!",
+        up_down: "Up/Down",
+        divider: "|",
+        sort_ascending: "^",
+        sort_descending: "v",
+        spinner: &["|", "/", "-", "\\"],
+        scrollbar: scrollbar::Set {
+            track: "|",
+            thumb: "#",
+            begin: "^",
+            end: "v",
+        },
+        border: border::Set {
+            top_left: "+",
+            top_right: "+",
+            bottom_left: "+",
+            bottom_right: "+",
+            vertical_left: "|",
+            vertical_right: "|",
+            horizontal_top: "-",
+            horizontal_bottom: "-",
+        },
+    };
+
+    /// Terminals that usually lack the fonts or encoding for the Unicode set:
+    /// the Linux console, dumb terminals and non UTF-8 locales
+    pub fn detect_limited_terminal() -> bool {
+        let term = std::env::var("TERM").unwrap_or_default();
+        if matches!(term.as_str(), "linux" | "dumb" | "vt100" | "vt220") {
+            return true;
+        }
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty());
+        match locale {
+            Some(locale) => {
+                let locale = locale.to_lowercase();
+                !(locale.contains("utf-8") || locale.contains("utf8"))
+            }
+            // Windows doesn't set these, its terminals handle Unicode
+            None => false,
+        }
+    }
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self::UNICODE
+    }
+}
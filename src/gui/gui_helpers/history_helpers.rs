@@ -1,6 +1,4 @@
-use std::fs;
-
-use crate::gui::HistoryPage;
+use crate::gui::{HistoryEntry, HistoryPage};
 use anyhow::Result;
 
 impl HistoryPage {
@@ -18,33 +16,60 @@ impl HistoryPage {
         }
     }
 
-    pub fn get_selected_query(&self) -> Option<String> {
+    pub fn get_selected_index(&self) -> Option<usize> {
         let history = self.history_manager.load_history().ok()?;
         if history.is_empty() {
             return None;
         }
         
         let selected = self.list_state.selected()?;
-        let actual_index = history.len().saturating_sub(1).saturating_sub(selected);
-        history.get(actual_index).cloned()
+        Some(history.len().saturating_sub(1).saturating_sub(selected))
+    }
+
+    pub fn get_selected_entry(&self) -> Option<HistoryEntry> {
+        let index = self.get_selected_index()?;
+        self.history_manager.load_history().ok()?.get(index).cloned()
+    }
+
+    pub fn toggle_mark(&mut self) {
+        if let Some(index) = self.get_selected_index()
+            && !self.marked.remove(&index)
+        {
+            self.marked.insert(index);
+        }
+    }
+
+    /// Marked entries if any, otherwise the selected one
+    pub fn entries_to_delete(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            self.get_selected_index().into_iter().collect()
+        } else {
+            self.marked.iter().copied().collect()
+        }
+    }
+
+    pub fn get_selected_query(&self) -> Option<String> {
+        self.get_selected_entry().map(|entry| entry.query)
     }
 
     pub fn clear_history(&mut self) -> Result<()> {
         self.history_manager.clear_history()?;
+        self.marked.clear();
         self.list_state.select(Some(0));
         Ok(())
     }
 
-    pub fn delete_query(&self, query_string: String) -> Result<()> {
-        let mut history = self.history_manager.load_history().unwrap_or_default();
-
-        if let Some(index) = history.iter().position(|s| s == &query_string) {
-            history.remove(index);
-        }
+    pub fn delete_entries(&mut self, indices: &[usize]) -> Result<()> {
+        let history = self.history_manager.load_history().unwrap_or_default();
 
-        let content = serde_json::to_string_pretty(&history)?;
-        fs::write(&self.history_manager.config_path, content)?;
+        let remaining: Vec<HistoryEntry> = history
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| !indices.contains(i))
+            .map(|(_, entry)| entry)
+            .collect();
 
-        Ok(())
+        self.marked.clear();
+        self.history_manager.write_history(&remaining)
     }
 }
\ No newline at end of file
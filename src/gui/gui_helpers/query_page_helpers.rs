@@ -1,34 +1,198 @@
-use ratatui::widgets::TableState;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
-use crate::{gui::{Focus, QueryPage, TableInfo}, utils::{connection::Connection, query_executor::QueryExecutor}};
-use anyhow::Result;
+use ratatui::widgets::{ListState, TableState};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+use crate::{gui::{AppliedPipeline, CheckingQuery, Confirmation, Focus, Health, HistoryEntry, LoadingTables, Maximized, Palette, PendingMasking, PendingPipeline, PendingSchedule, QueryPageAction, ScheduleTime, ScheduledQuery, format_duration, toast, PendingConfirmation, PendingDump, PendingGenerate, QueryOutcome, QueryPage, RunningDump, RunningGenerate, RunningMaintenance, RunningQuery, TableInfo, Watch}, utils::{clipboard, columns::Columns, desktop, connection::{Connection, mask_credentials}, dump, export, generate, errors::ErrorDetail, masking::MaskMethod, pipeline::Pipeline, query_executor::{QueryExecutor, RowBatch, ScriptFailure}, notices, schema_cache::{self, SchemaCache}, sql::{Dialect, Maintenance, count_placeholders, empty_table_statement, maintenance_statements, find_unfiltered_write, quote_identifier, truncate_is_transactional, is_read_only, push_down_limit, split_statements}, state::UiState, text::{single_line, truncate_to_width}, value::Value}};
+use anyhow::{Result, anyhow, bail};
+
+/// Queries running at least this long report their completion in a toast
+const SLOW_QUERY_TOAST: Duration = Duration::from_secs(1);
+/// Wait before pinging again after a keepalive ping failed
+const KEEPALIVE_RETRY: Duration = Duration::from_secs(5);
+/// How often a running VACUUM or ANALYZE is asked how far it got
+const MAINTENANCE_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Border plus a single line of SQL
+const MIN_EDITOR_HEIGHT: u16 = 3;
+const MAX_EDITOR_HEIGHT: u16 = 60;
+const MIN_EXPLORER_WIDTH: u16 = 12;
+const MAX_EXPLORER_WIDTH: u16 = 80;
+const EXPLORER_STEP: i16 = 2;
 
 impl QueryPage {
-    pub async fn connect(&mut self, connection: Connection) -> Result<()> {
-        let executor = QueryExecutor::new(&connection).await?;
+    /// Switches the page to an executor opened by the caller, connecting is left to a
+    /// background task so a slow host doesn't freeze the UI
+    pub fn connect(&mut self, connection: Connection, executor: Arc<QueryExecutor>) -> Result<()> {
         self.connection = Some(connection.clone());
         self.executor = Some(executor);
         self.query.clear();
         self.cursor_position = 0;
         self.results.clear();
         self.headers.clear();
+        self.result_sets.clear();
+        self.active_result = 0;
         self.error = None;
         self.focus = Focus::Query;
         self.table_state = TableState::default();
         self.horizontal_scroll = 0;
+        self.row_stream = None;
+        self.results_truncated = false;
+        self.results_bytes = 0;
+        self.transaction_statements = None;
+        self.read_only = false;
         
-        // Load tables
-        self.load_tables().await?;
-        
+        self.tables.clear();
+        self.load_tables(false);
+        self.start_keepalive();
+
+        // Only informational, a server that won't say just gets no panel
+        self.server_info = None;
+        self.show_server_info = false;
+        if let Some(previous) = self.loading_server_info.take() {
+            previous.abort();
+        }
+        if let Some(executor) = self.executor.clone() {
+            self.loading_server_info = Some(tokio::spawn(async move { executor.server_info().await }));
+        }
+
         Ok(())
     }
 
-    pub async fn disconnect(&mut self) {
-        if let Some(executor) = self.executor.take() {
-            let _ = executor.close().await;
+    /// Leaves the connection, handing back its executor so the caller can keep the pool warm
+    pub fn disconnect(&mut self) -> Option<(Connection, Arc<QueryExecutor>)> {
+        self.stop_watch();
+        self.cancel_query();
+        // Scheduled queries belong to the connection they were written for
+        self.schedule.clear();
+        self.pending_schedule = None;
+        self.error = None;
+        self.row_stream = None;
+        let executor = self.executor.take();
+        let connection = self.connection.take();
+        self.transaction_statements = None;
+        if let Some(loading) = self.loading_tables.take() {
+            loading.handle.abort();
+        }
+        if let Some(loading) = self.loading_server_info.take() {
+            loading.abort();
         }
-        self.connection = None;
+        if let Some(checking) = self.checking_query.take() {
+            checking.handle.abort();
+        }
+        if let Some(keepalive) = self.keepalive.take() {
+            keepalive.abort();
+        }
+        self.set_health(None);
         self.tables.clear();
+        self.server_info = None;
+        self.show_server_info = false;
+        connection.zip(executor)
+    }
+
+    /// Pings the server on the configured interval, so an idle session isn't closed by a
+    /// server-side timeout. A failed ping is reported and retried sooner, the pool opens
+    /// fresh connections once the server answers again. Each ping's latency feeds the
+    /// health dot in the title, the first one goes out right away.
+    fn start_keepalive(&mut self) {
+        if let Some(previous) = self.keepalive.take() {
+            previous.abort();
+        }
+        self.set_health(None);
+        let (Some(interval), Some(executor), Some(connection)) =
+            (self.config.keepalive_interval(), self.executor.clone(), self.connection.clone())
+        else {
+            return;
+        };
+        let health = Arc::clone(&self.health);
+        let health_version = Arc::clone(&self.health_version);
+        self.keepalive = Some(tokio::spawn(async move {
+            let mut lapsed = false;
+            let mut first = true;
+            loop {
+                if !std::mem::take(&mut first) {
+                    tokio::time::sleep(if lapsed { interval.min(KEEPALIVE_RETRY) } else { interval }).await;
+                }
+                let started = Instant::now();
+                let result = executor.ping().await;
+                if let Ok(mut health) = health.lock() {
+                    *health = Some(Health { latency: started.elapsed(), reachable: result.is_ok() });
+                }
+                health_version.fetch_add(1, Ordering::Relaxed);
+                match result {
+                    Ok(()) if lapsed => {
+                        lapsed = false;
+                        toast::success(format!("Reconnected to {}", connection.name));
+                    }
+                    Ok(()) => {}
+                    Err(e) if !lapsed => {
+                        lapsed = true;
+                        toast::warning(connection.redact(&format!(
+                            "Connection to {} idle, reconnecting... ({:#})",
+                            connection.name, e
+                        )));
+                    }
+                    Err(_) => {}
+                }
+            }
+        }));
+    }
+
+    fn set_health(&self, health: Option<Health>) {
+        if let Ok(mut current) = self.health.lock() {
+            *current = health;
+        }
+        self.health_version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether the health dot changed since the last call, the next frame has to show it
+    pub fn take_health_change(&mut self) -> bool {
+        let version = self.health_version.load(Ordering::Relaxed);
+        std::mem::replace(&mut self.health_drawn, version) != version
+    }
+
+    /// Strips the connection's credentials from an error before it is shown or stored
+    fn redact(&self, text: &str) -> String {
+        match &self.connection {
+            Some(connection) => connection.redact(text),
+            None => mask_credentials(text),
+        }
+    }
+
+    /// Puts back the query buffer and explorer selection left for this connection
+    pub fn restore_session(&mut self, state: &UiState) {
+        let Some(name) = self.connection.as_ref().map(|c| c.name.clone()) else {
+            return;
+        };
+        if let Some(query) = state.queries.get(&name) {
+            self.set_query(query.clone());
+        }
+        if let Some(&selected) = state.explorer_selected.get(&name)
+            && selected < self.tables.len()
+        {
+            self.explorer_state.select(Some(selected));
+        }
+    }
+
+    pub fn remember_session(&self, state: &mut UiState) {
+        state.max_results = self.max_results;
+        state.panes = self.panes;
+        let Some(conn) = &self.connection else {
+            return;
+        };
+        state.last_connection = Some(conn.name.clone());
+        if self.query.trim().is_empty() {
+            state.queries.remove(&conn.name);
+        } else {
+            state.queries.insert(conn.name.clone(), self.query.clone());
+        }
+        if let Some(selected) = self.explorer_state.selected() {
+            state.explorer_selected.insert(conn.name.clone(), selected);
+        }
     }
 
     pub fn set_query(&mut self, query: String) {
@@ -37,33 +201,69 @@ impl QueryPage {
         self.focus = Focus::Query;
     }
 
-    async fn load_tables(&mut self) -> Result<()> {
-        if let Some(executor) = &self.executor {
-            if let Some(conn) = &self.connection {
-                let query = match conn.db_type.as_str() {
-                    "postgres" => "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public'",
-                    "mysql" | "mariadb" => "SHOW TABLES",
-                    "sqlite" => "SELECT name FROM sqlite_master WHERE type='table'",
-                    _ => return Ok(()),
-                };
-                
-                match executor.execute(query).await {
-                    Ok((_, rows)) => {
-                        self.tables = rows.iter()
-                            .map(|row| TableInfo {
-                                name: row[0].clone(),
-                                fields: None,
-                                expanded: false,
-                            })
-                            .collect();
-                    }
-                    Err(_) => {
-                        self.tables.clear();
-                    }
+    /// Lists the tables for the explorer in a background task, which reads them from the
+    /// schema cache while the server reports the schema unchanged. The explorer fills in
+    /// once they arrive. A load still running is dropped, so repeated refreshes only
+    /// query once more.
+    pub fn load_tables(&mut self, announce: bool) {
+        let (Some(executor), Some(connection)) = (self.executor.clone(), self.connection.clone()) else {
+            return;
+        };
+        let disk = self.config.cache_schema;
+        let handle = tokio::spawn(async move { list_tables(&executor, &connection, disk).await });
+        if let Some(previous) = self.loading_tables.replace(LoadingTables { announce, handle }) {
+            previous.handle.abort();
+        }
+    }
+
+    fn poll_tables(&mut self) {
+        if !self.loading_tables.as_ref().is_some_and(|loading| loading.handle.is_finished()) {
+            return;
+        }
+        let Some(loading) = self.loading_tables.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        match futures_util::FutureExt::now_or_never(loading.handle) {
+            Some(Ok(Ok(tables))) => {
+                self.tables = tables;
+                if loading.announce {
+                    toast::info(format!("Reloaded {} tables", self.tables.len()));
                 }
             }
+            Some(Ok(Err(e))) => {
+                self.tables.clear();
+                toast::error(self.redact(&format!("Could not list tables: {:#}", e)));
+            }
+            _ => self.tables.clear(),
+        }
+        let len = self.explorer_len();
+        if self.explorer_state.selected().is_some_and(|selected| selected >= len) {
+            self.explorer_state.select(Some(len.saturating_sub(1)));
+        }
+    }
+
+    /// Opens the server info panel once the lookup started by `connect` answered
+    fn poll_server_info(&mut self) {
+        if !self.loading_server_info.as_ref().is_some_and(|handle| handle.is_finished()) {
+            return;
+        }
+        let Some(handle) = self.loading_server_info.take() else {
+            return;
+        };
+        if let Some(Ok(Ok(info))) = futures_util::FutureExt::now_or_never(handle) {
+            self.server_info = Some(info);
+            self.show_server_info = true;
         }
-        Ok(())
+    }
+
+    /// Drops the cached schema and lists the tables again, for changes made elsewhere
+    pub fn refresh_tables(&mut self) {
+        let Some(conn) = &self.connection else {
+            return;
+        };
+        schema_cache::invalidate(&conn.name);
+        self.load_tables(true);
     }
 
     pub async fn toggle_table_expansion(&mut self) -> Result<()> {
@@ -86,35 +286,34 @@ impl QueryPage {
                 if self.tables[idx].expanded {
                     self.tables[idx].expanded = false;
                 } else {
-                    if self.tables[idx].fields.is_none() {
-                        if let Some(executor) = &self.executor {
-                            if let Some(conn) = &self.connection {
-                                let table_name = &self.tables[idx].name;
-                                let query = match conn.db_type.as_str() {
-                                    "postgres" => format!("SELECT column_name FROM information_schema.columns WHERE table_name = '{}'", table_name),
-                                    "mysql" | "mariadb" => format!("DESCRIBE {}", table_name),
-                                    "sqlite" => format!("PRAGMA table_info({})", table_name),
-                                    _ => String::new(),
-                                };
-                                
-                                match executor.execute(&query).await {
-                                    Ok((_, rows)) => {
-                                        let field_index = match conn.db_type.as_str() {
-                                            "postgres" => 0,
-                                            "mysql" | "mariadb" => 0,
-                                            "sqlite" => 1,
-                                            _ => 0,
-                                        };
-                                        
-                                        self.tables[idx].fields = Some(
-                                            rows.iter()
-                                                .map(|row| row.get(field_index).cloned().unwrap_or_default())
-                                                .collect()
-                                        );
-                                    }
-                                    Err(_) => {}
-                                }
-                            }
+                    if self.tables[idx].fields.is_none()
+                        && let Some(executor) = &self.executor
+                        && let Some(conn) = &self.connection
+                    {
+                        let table_name = &self.tables[idx].name;
+                        let query = match conn.db_type.as_str() {
+                            "postgres" => format!("SELECT column_name FROM information_schema.columns WHERE table_name = '{}'", table_name),
+                            "mysql" | "mariadb" => format!("DESCRIBE {}", table_name),
+                            "sqlite" => format!("PRAGMA table_info({})", table_name),
+                            _ => String::new(),
+                        };
+                        
+                        // Off the transaction's connection, a running query would hold it until it ends
+                        if let Ok(sets) = executor.execute_detached(&query, &[]).await
+                            && let Some((_, rows)) = sets.into_iter().next()
+                        {
+                            let field_index = match conn.db_type.as_str() {
+                                "postgres" => 0,
+                                "mysql" | "mariadb" => 0,
+                                "sqlite" => 1,
+                                _ => 0,
+                            };
+                            
+                            let fields: Vec<String> = rows.iter()
+                                .map(|row| row.get(field_index).map(|v| v.to_string()).unwrap_or_default())
+                                .collect();
+                            let _ = schema_cache::store_columns(&conn.name, table_name, fields.clone(), self.config.cache_schema);
+                            self.tables[idx].fields = Some(fields);
                         }
                     }
                     self.tables[idx].expanded = true;
@@ -124,26 +323,46 @@ impl QueryPage {
         Ok(())
     }
 
+    /// Rows shown in the results table, the row limit hides the rest
+    pub fn visible_rows(&self) -> usize {
+        if self.max_results > 0 {
+            self.max_results.min(self.results.len() as u32) as usize
+        } else {
+            self.results.len()
+        }
+    }
+
+    /// Tables plus the columns of the expanded ones
+    pub fn explorer_len(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|table| 1 + if table.expanded { table.fields.as_ref().map_or(0, |f| f.len()) } else { 0 })
+            .sum()
+    }
+
+    /// Table under the explorer cursor, also when the cursor is on one of its columns
+    pub fn selected_table(&self) -> Option<&TableInfo> {
+        let selected = self.explorer_state.selected()?;
+        let mut row = 0;
+        for table in &self.tables {
+            row += 1 + if table.expanded { table.fields.as_ref().map_or(0, |f| f.len()) } else { 0 };
+            if selected < row {
+                return Some(table);
+            }
+        }
+        None
+    }
+
      pub fn scroll_up(&mut self) {
         let i = match self.table_state.selected() {
-            Some(i) => {
-                if i > 0 {
-                    i - 1
-                } else {
-                    0
-                }
-            }
+            Some(i) => i.saturating_sub(1),
             None => 0,
         };
         self.table_state.select(Some(i));
     }
 
     pub fn scroll_down(&mut self) {
-        let max_len = if self.max_results > 0 {
-            self.max_results.min(self.results.len() as u32) as usize
-        } else {
-            self.results.len()
-        };
+        let max_len = self.visible_rows();
 
         let i = match self.table_state.selected() {
             Some(i) => {
@@ -167,11 +386,7 @@ impl QueryPage {
     }
 
     pub fn scroll_page_down(&mut self) {
-        let max_len = if self.max_results > 0 {
-            self.max_results.min(self.results.len() as u32) as usize
-        } else {
-            self.results.len()
-        };
+        let max_len = self.visible_rows();
 
         let i = match self.table_state.selected() {
             Some(i) => (i + 10).min(max_len.saturating_sub(1)),
@@ -181,61 +396,1269 @@ impl QueryPage {
     }
 
     pub fn explorer_scroll_up(&mut self) {
-        if let Some(selected) = self.explorer_state.selected() {
-            if selected > 0 {
-                self.explorer_state.select(Some(selected - 1));
-            }
+        if let Some(selected) = self.explorer_state.selected()
+            && selected > 0
+        {
+            self.explorer_state.select(Some(selected - 1));
+        }
+    }
+
+    /// Grows or shrinks the query editor by `delta` lines
+    pub fn resize_editor(&mut self, delta: i16) {
+        self.maximized = None;
+        self.panes.editor_height = self
+            .panes
+            .editor_height
+            .saturating_add_signed(delta)
+            .clamp(MIN_EDITOR_HEIGHT, MAX_EDITOR_HEIGHT);
+    }
+
+    /// Widens or narrows the explorer, narrowing past its minimum collapses it
+    pub fn resize_explorer(&mut self, delta: i16) {
+        self.maximized = None;
+        let width = self.panes.explorer_width.saturating_add_signed(delta * EXPLORER_STEP);
+        self.panes.explorer_width = if width < MIN_EXPLORER_WIDTH {
+            if delta > 0 { MIN_EXPLORER_WIDTH } else { 0 }
+        } else {
+            width.min(MAX_EXPLORER_WIDTH)
+        };
+        if self.panes.explorer_width == 0 && self.focus == Focus::Explorer {
+            self.focus = Focus::Query;
+        }
+    }
+
+    pub fn toggle_maximized(&mut self) {
+        self.maximized = match self.maximized {
+            Some(_) => None,
+            None => self.focused_pane(),
+        };
+    }
+
+    pub(crate) fn focused_pane(&self) -> Option<Maximized> {
+        match self.focus {
+            Focus::Query => Some(Maximized::Editor),
+            Focus::Results => Some(Maximized::Results),
+            Focus::Explorer => None,
         }
     }
 
     pub fn explorer_scroll_down(&mut self) {
-        let mut total_items = self.tables.len();
-        for table in &self.tables {
-            if table.expanded {
-                total_items += table.fields.as_ref().map(|f| f.len()).unwrap_or(0);
+        let total_items = self.explorer_len();
+
+        if let Some(selected) = self.explorer_state.selected()
+            && selected < total_items.saturating_sub(1)
+        {
+            self.explorer_state.select(Some(selected + 1));
+        }
+    }
+
+    /// Holds back UPDATE/DELETE statements without a WHERE clause until the user confirms them.
+    /// The row estimates for the confirmation come from a background task, picked up by
+    /// `poll_checking_query`, so a slow server doesn't freeze the UI before the query starts.
+    pub fn request_execute(&mut self) {
+        if self.running_query.is_some() || self.checking_query.is_some() {
+            return;
+        }
+        if self.watch.is_some() {
+            self.stop_watch();
+            toast::info("Watch stopped");
+        }
+        let Some(executor) = self.executor.clone() else {
+            self.proceed_execute();
+            return;
+        };
+        let dialect = executor.dialect();
+        if self.read_only
+            && !split_statements(&self.query, dialect)
+                .iter()
+                .all(|statement| is_read_only(statement, dialect))
+        {
+            self.error = Some("Read-only mode: only SELECT-like statements can run (Ctrl+O to turn it off)".to_string());
+            return;
+        }
+
+        let write = split_statements(&self.query, dialect)
+            .into_iter()
+            .find_map(|statement| find_unfiltered_write(statement, dialect));
+        // A SELECT the row limit can't be pushed into would fetch everything it matches
+        let threshold = self.config.large_result_warning_rows;
+        let unlimited_select = match split_statements(&self.query, dialect).as_slice() {
+            [statement] if threshold > 0 && self.max_results == 0 && push_down_limit(statement, 1, dialect).is_some() => {
+                Some(statement.to_string())
             }
+            _ => None,
+        };
+        if write.is_none() && unlimited_select.is_none() {
+            self.proceed_execute();
+            return;
         }
 
-        if let Some(selected) = self.explorer_state.selected() {
-            if selected < total_items.saturating_sub(1) {
-                self.explorer_state.select(Some(selected + 1));
+        let handle = tokio::spawn(async move {
+            if let Some(write) = write {
+                let estimated_rows = executor.estimate_table_rows(&write.table).await;
+                return Some(Confirmation::UnfilteredWrite { write, estimated_rows });
             }
+            let estimated_rows = executor.estimate_query_rows(&unlimited_select?).await?;
+            (estimated_rows > threshold).then_some(Confirmation::LargeResult { estimated_rows })
+        });
+        self.checking_query = Some(CheckingQuery { query: self.query.clone(), started: Instant::now(), handle });
+    }
+
+    /// Asks for confirmation or starts the query once its row estimate is in. A query
+    /// edited meanwhile isn't run, it wasn't the one checked.
+    fn poll_checking_query(&mut self) {
+        if !self.checking_query.as_ref().is_some_and(|checking| checking.handle.is_finished()) {
+            return;
+        }
+        let Some(checking) = self.checking_query.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        let confirmation = match futures_util::FutureExt::now_or_never(checking.handle) {
+            Some(Ok(confirmation)) => confirmation,
+            _ => return,
+        };
+        if checking.query != self.query {
+            toast::warning("The query changed while its rows were estimated, run it again");
+            return;
+        }
+        match confirmation {
+            Some(kind) => self.pending_confirmation = Some(PendingConfirmation { kind, input: String::new() }),
+            None => self.proceed_execute(),
         }
     }
 
-    pub async fn execute_query(&mut self) -> Result<()> {
+    /// In prepared mode, asks for parameter values first when the statement has placeholders
+    pub fn proceed_execute(&mut self) {
+        if !self.prepared_mode {
+            self.execute_query();
+            return;
+        }
+        let Some(executor) = &self.executor else {
+            self.execute_query();
+            return;
+        };
+
+        let count = count_placeholders(&self.query, executor.dialect());
+        if count == 0 {
+            self.execute_query();
+            return;
+        }
+
+        // Keep previous values so a statement can be re-run with small changes
+        self.params.resize(count, String::new());
+        self.param_index = 0;
+        self.show_params_overlay = true;
+    }
+
+    pub fn execute_with_params(&mut self) {
+        self.show_params_overlay = false;
+        let params = self.params.iter().map(|p| Value::from_input(p)).collect();
+        self.spawn_query(self.query.clone(), params);
+    }
+
+    /// Starts the query in a background task, the UI keeps running until it finishes
+    pub fn execute_query(&mut self) {
+        self.spawn_query(self.query.clone(), Vec::new());
+    }
+
+    fn spawn_query(&mut self, query: String, params: Vec<Value>) {
+        if self.running_query.is_some() {
+            return;
+        }
+
         self.error = None;
+        self.restore_fetched();
         self.results.clear();
         self.headers.clear();
+        self.result_sets.clear();
+        self.active_result = 0;
+        self.notices.clear();
         self.table_state = TableState::default();
         self.horizontal_scroll = 0;
+        self.last_duration = None;
+        self.row_stream = None;
+        self.results_truncated = false;
+        self.results_bytes = 0;
+        self.changed_cells.clear();
+
+        if query.trim().is_empty() {
+            self.error = Some("Query is empty".to_string());
+            return;
+        }
+        if self.executor.is_none() {
+            self.error = Some("Not connected to database".to_string());
+            return;
+        }
+
+        self.launch_query(query, params, false);
+    }
+
+    /// Runs `query` in the background without touching the results on screen
+    fn launch_query(&mut self, mut query: String, params: Vec<Value>, watched: bool) {
+        let Some(executor) = &self.executor else {
+            return;
+        };
+
+        // With a row limit set, let the database stop early instead of fetching rows nobody sees
+        let statement = query.clone();
+        if self.max_results > 0
+            && let [statement] = split_statements(&query, executor.dialect()).as_slice()
+            && let Some(limited) = push_down_limit(statement, self.max_results, executor.dialect())
+        {
+            query = limited;
+        }
+
+        let executor = Arc::clone(executor);
+        let (sender, receiver) = oneshot::channel();
+
+        let handle = tokio::spawn(async move {
+            let started = Instant::now();
+            let (result, notices) = notices::collect(async {
+                if params.is_empty() {
+                    executor.execute_streaming(&query).await
+                } else {
+                    executor
+                        .execute_prepared(&query, &params)
+                        .await
+                        .map(|result_sets| (result_sets, None))
+                }
+            })
+            .await;
+            let _ = sender.send((result, started.elapsed(), notices));
+        });
+
+        self.running_query = Some(RunningQuery {
+            query: statement,
+            started: Instant::now(),
+            watched,
+            scheduled: false,
+            receiver,
+            handle,
+        });
+    }
+
+    /// Picks up the outcome of the running query once its task has finished
+    pub fn poll_running_query(&mut self) {
+        let Some(running) = self.running_query.as_mut() else {
+            return;
+        };
+
+        match running.receiver.try_recv() {
+            Ok(outcome) => {
+                let (query, watched, scheduled) = (running.query.clone(), running.watched, running.scheduled);
+                self.running_query = None;
+                self.finish_query(query, outcome, watched, scheduled);
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Closed) => {
+                self.running_query = None;
+                self.error = Some("Query task ended unexpectedly".to_string());
+            }
+        }
+    }
+
+    pub fn cancel_query(&mut self) {
+        if let Some(checking) = self.checking_query.take() {
+            checking.handle.abort();
+        }
+        if let Some(running) = self.running_query.take() {
+            running.handle.abort();
+            self.error = Some("Query cancelled".to_string());
+        }
+    }
 
+    fn finish_query(&mut self, query: String, (result, elapsed, notices): QueryOutcome, watched: bool, scheduled: bool) {
+        self.notices = notices;
+        if self.notices.iter().any(|notice| notice.starts_with("RETRY")) {
+            toast::warning("Reconnected after a transient error");
+        }
+        // A re-run of the watched query is compared against what it replaces
+        let previous = (watched && !self.headers.is_empty()).then(|| {
+            let selected = self.table_state.selected();
+            (std::mem::take(&mut self.headers), std::mem::take(&mut self.results), selected)
+        });
+        match result {
+            Ok((result_sets, stream)) => {
+                self.error_detail = None;
+                // Set first, hitting the memory limit closes the stream
+                self.row_stream = stream;
+                let result_sets = self.transform_rows(result_sets);
+                self.set_result_sets(result_sets);
+                self.last_duration = Some(elapsed);
+                if !watched {
+                    self.notify_scripts(&query, elapsed, Ok(self.results.len()));
+                }
+                if let Some((headers, rows, selected)) = previous {
+                    if headers == self.headers {
+                        self.changed_cells = changed_cells(&rows, &self.results);
+                        // Stay where the user was reading, the table only changes underneath
+                        let last = self.results.len().checked_sub(1);
+                        self.table_state.select(selected.zip(last).map(|(s, last)| s.min(last)));
+                    } else {
+                        self.changed_cells.clear();
+                        self.horizontal_scroll = 0;
+                        self.table_state = TableState::default();
+                        if !self.results.is_empty() {
+                            self.table_state.select(Some(0));
+                        }
+                    }
+                    // Re-runs would bury the history under the same query
+                    return;
+                }
+                if scheduled {
+                    announce_scheduled(&query, elapsed, Ok(self.results.len()));
+                } else if elapsed >= SLOW_QUERY_TOAST && !watched {
+                    toast::success(format!(
+                        "Query finished in {} ({} rows)",
+                        format_duration(elapsed),
+                        self.results.len()
+                    ));
+                }
+                if let Some(count) = self.transaction_statements.as_mut()
+                    && let Some(executor) = &self.executor
+                {
+                    *count += split_statements(&query, executor.dialect()).len();
+                }
+                if !self.results.is_empty() {
+                    self.table_state.select(Some(0));
+                }
+                
+                if let Ok(history_manager) = crate::gui::history::HistoryManager::new() {
+                    let entry = HistoryEntry::succeeded(query).with_duration(elapsed);
+                    let _ = history_manager.save_query(entry);
+                }
+            }
+            Err(e) => {
+                if self.watch.take().is_some() {
+                    self.changed_cells.clear();
+                    toast::warning("Watch stopped after an error");
+                }
+                self.error_detail = Some(ErrorDetail::new(&e, &query, |text| self.redact(text)));
+                // A failed script still shows what ran before it and which statement failed
+                if let Some(failure) = e.downcast_ref::<ScriptFailure>() {
+                    self.set_result_sets(vec![failure.breakdown.clone()]);
+                    self.script_failed = true;
+                }
+                if !watched {
+                    self.notify_scripts(&query, elapsed, Err(&self.redact(&e.to_string())));
+                }
+                if scheduled {
+                    announce_scheduled(&query, elapsed, Err(&self.redact(&e.to_string())));
+                }
+                if let Ok(history_manager) = crate::gui::history::HistoryManager::new() {
+                    let entry = HistoryEntry::failed(query, self.redact(&e.to_string())).with_duration(elapsed);
+                    let _ = history_manager.save_query(entry);
+                }
+                // A script failure names the statement, what went wrong is its innermost cause
+                let message = match e.downcast_ref::<ScriptFailure>() {
+                    Some(_) => format!("{}: {}", e, e.root_cause()),
+                    None => e.to_string(),
+                };
+                self.error = Some(self.redact(&format!("Query error: {}", message)));
+            }
+        }
+    }
+
+    /// Starts re-running the query every few seconds, or stops a running watch.
+    /// Only read-only queries are watched, a write would repeat with every run.
+    pub fn toggle_watch(&mut self) {
+        if self.watch.is_some() {
+            self.stop_watch();
+            toast::info("Watch stopped");
+            return;
+        }
+        let Some(executor) = &self.executor else {
+            self.error = Some("Not connected to database".to_string());
+            return;
+        };
+        let dialect = executor.dialect();
         if self.query.trim().is_empty() {
             self.error = Some("Query is empty".to_string());
-            return Ok(());
+            return;
         }
+        if !split_statements(&self.query, dialect).iter().all(|statement| is_read_only(statement, dialect)) {
+            self.error = Some("Only read-only queries can be watched".to_string());
+            return;
+        }
+        if self.prepared_mode && count_placeholders(&self.query, dialect) > 0 {
+            self.error = Some("A watched query can't have parameters".to_string());
+            return;
+        }
+        let interval = self.config.watch_interval();
+        self.watch = Some(Watch {
+            query: self.query.clone(),
+            interval,
+            runs: 0,
+            last_run: None,
+        });
+        toast::info(format!("Watching every {}s, Ctrl+W to stop", interval.as_secs()));
+    }
 
-        if let Some(executor) = &self.executor {
-            match executor.execute(&self.query).await {
-                Ok((headers, rows)) => {
+    /// Ends the watch, dropping a re-run still in flight
+    pub fn stop_watch(&mut self) {
+        if self.watch.take().is_none() {
+            return;
+        }
+        self.changed_cells.clear();
+        if let Some(running) = self.running_query.take_if(|running| running.watched) {
+            running.handle.abort();
+        }
+    }
+
+    /// Starts the next run of the watched query once the interval has passed
+    pub fn poll_watch(&mut self) {
+        let Some(watch) = self.watch.as_mut() else {
+            return;
+        };
+        if self.running_query.is_some()
+            || self.pending_confirmation.is_some()
+            || watch.last_run.is_some_and(|at| at.elapsed() < watch.interval)
+        {
+            return;
+        }
+        watch.last_run = Some(Instant::now());
+        watch.runs += 1;
+        let query = watch.query.clone();
+        if watch.runs == 1 {
+            self.spawn_query(query, Vec::new());
+            if let Some(running) = self.running_query.as_mut() {
+                running.watched = true;
+            }
+        } else {
+            self.launch_query(query, Vec::new(), true);
+        }
+    }
+
+    /// Opens the schedule box for the query in the editor
+    pub fn open_schedule(&mut self) {
+        let Some(executor) = &self.executor else {
+            return;
+        };
+        let dialect = executor.dialect();
+        if self.query.trim().is_empty() {
+            self.error = Some("Query is empty".to_string());
+            return;
+        }
+        // Nobody is there to answer a confirmation when the query starts
+        if self.read_only
+            && !split_statements(&self.query, dialect).iter().all(|statement| is_read_only(statement, dialect))
+        {
+            self.error = Some("Read-only mode: only SELECT-like statements can run (Ctrl+O to turn it off)".to_string());
+            return;
+        }
+        if split_statements(&self.query, dialect)
+            .into_iter()
+            .any(|statement| find_unfiltered_write(statement, dialect).is_some())
+        {
+            self.error = Some("An UPDATE or DELETE without WHERE can't be scheduled, run it directly to confirm it".to_string());
+            return;
+        }
+        if self.prepared_mode && count_placeholders(&self.query, dialect) > 0 {
+            self.error = Some("A scheduled query can't have parameters".to_string());
+            return;
+        }
+        self.pending_schedule = Some(PendingSchedule {
+            query: self.query.clone(),
+            input: String::new(),
+            error: None,
+        });
+    }
+
+    /// Queues the query from the schedule box for the time typed into it
+    pub fn schedule_query(&mut self) {
+        let Some(pending) = self.pending_schedule.as_mut() else {
+            return;
+        };
+        let time = match parse_schedule_time(&pending.input, chrono::Local::now()) {
+            Ok(time) => time,
+            Err(e) => {
+                pending.error = Some(e.to_string());
+                return;
+            }
+        };
+        let Some(pending) = self.pending_schedule.take() else {
+            return;
+        };
+        toast::info(format!("Query scheduled to run {}", time.label()));
+        self.schedule.push(ScheduledQuery { query: pending.query, time });
+    }
+
+    /// Starts the first scheduled query that is due, once nothing else runs.
+    /// A watch counts as running until it is stopped.
+    pub fn poll_schedule(&mut self) {
+        if self.running_query.is_some() || self.pending_confirmation.is_some() || self.watch.is_some() {
+            return;
+        }
+        let now = chrono::Local::now();
+        let Some(index) = self.schedule.iter().position(|scheduled| scheduled.time.is_due(now)) else {
+            return;
+        };
+        let scheduled = self.schedule.remove(index);
+        self.spawn_query(scheduled.query.clone(), Vec::new());
+        match self.running_query.as_mut() {
+            Some(running) => running.scheduled = true,
+            None => {
+                let error = self.error.clone().unwrap_or_default();
+                announce_scheduled(&scheduled.query, Duration::ZERO, Err(&error));
+            }
+        }
+    }
+
+    fn set_result_sets(&mut self, result_sets: Vec<RowBatch>) {
+        self.active_result = 0;
+        self.script_failed = false;
+        // The active set is moved out, its slot stays empty like after switching sets
+        let mut first = None;
+        self.result_sets = result_sets
+            .into_iter()
+            .map(|(headers, rows)| match first {
+                None => {
+                    first = Some((headers, rows));
+                    Default::default()
+                }
+                Some(_) => (headers, Columns::from_rows(rows)),
+            })
+            .collect();
+        let (headers, rows) = first.unwrap_or_default();
+        self.headers = headers;
+        self.results = Columns::default();
+        self.column_widths.clear();
+        self.results_bytes = 0;
+        self.results_truncated = false;
+        self.append_rows(rows);
+        self.rerun_pipeline();
+    }
+
+    /// Adds fetched rows while they fit the memory limit. Past it the rest is dropped
+    /// and the stream closed, so one careless query can't exhaust memory.
+    fn append_rows(&mut self, rows: Vec<Vec<Value>>) {
+        let Some(limit) = self.config.result_memory_limit() else {
+            self.results.extend(rows);
+            return;
+        };
+        for row in rows {
+            let size = std::mem::size_of::<Vec<Value>>() + row.iter().map(Value::memory_size).sum::<usize>();
+            if self.results_bytes + size > limit {
+                self.results_truncated = true;
+                self.row_stream = None;
+                toast::warning(format!(
+                    "Stopped at {} rows, the result reached the {} MB memory limit",
+                    self.results.len(),
+                    self.config.result_memory_mb
+                ));
+                break;
+            }
+            self.results_bytes += size;
+            self.results.push_row(row);
+        }
+    }
+
+    /// Runs fetched rows through the scripts' `transform`, keeping them as fetched when it fails
+    fn transform_rows(&self, batches: Vec<RowBatch>) -> Vec<RowBatch> {
+        if !self.scripts.has_transform() {
+            return batches;
+        }
+        match batches.iter().cloned().map(|batch| self.scripts.transform(batch)).collect() {
+            Ok(transformed) => transformed,
+            Err(e) => {
+                toast::error(format!("Script transform failed: {:#}", e));
+                batches
+            }
+        }
+    }
+
+    /// Hands the outcome of a query the user ran to the scripts' `on_query`
+    fn notify_scripts(&self, query: &str, elapsed: Duration, outcome: Result<usize, &str>) {
+        if let Err(e) = self.scripts.on_query(query, elapsed, outcome) {
+            toast::error(format!("Script on_query failed: {:#}", e));
+        }
+    }
+
+    /// Opens the transform box with the applied pipeline, if any, to edit
+    pub fn open_pipeline(&mut self) {
+        if self.headers.is_empty() && self.pipeline.is_none() {
+            toast::info("There are no results to transform");
+            return;
+        }
+        let input = self.pipeline.as_ref().map(|applied| applied.expression.clone()).unwrap_or_default();
+        self.pending_pipeline = Some(PendingPipeline { input, error: None });
+    }
+
+    /// Runs the typed pipeline over the result set as fetched, an empty one removes it.
+    /// A pipeline that fails leaves the table as it was and the box open.
+    pub fn apply_pipeline(&mut self) {
+        let Some(pending) = self.pending_pipeline.as_mut() else {
+            return;
+        };
+        let expression = pending.input.trim().to_string();
+        if expression.is_empty() {
+            self.pending_pipeline = None;
+            self.remove_pipeline();
+            return;
+        }
+        let (headers, results) = match &self.pipeline {
+            Some(applied) => (&applied.fetched.0, &applied.fetched.1),
+            None => (&self.headers, &self.results),
+        };
+        let rows = results.rows(0..results.len()).map(|row| row.cloned().collect()).collect();
+        let piped = Pipeline::parse(&expression).and_then(|pipeline| Ok((pipeline.apply(headers, rows)?, pipeline)));
+        match piped {
+            Ok(((headers, rows), pipeline)) => {
+                let fetched = match self.pipeline.take() {
+                    Some(applied) => applied.fetched,
+                    None => (std::mem::take(&mut self.headers), std::mem::take(&mut self.results)),
+                };
+                self.headers = headers;
+                self.results = Columns::from_rows(rows);
+                self.pipeline = Some(AppliedPipeline { expression, pipeline, fetched });
+                self.pending_pipeline = None;
+                self.reset_result_view();
+            }
+            Err(e) => pending.error = Some(format!("{:#}", e)),
+        }
+    }
+
+    pub fn remove_pipeline(&mut self) {
+        if self.pipeline.is_some() {
+            self.restore_fetched();
+            self.pipeline = None;
+            self.reset_result_view();
+        }
+    }
+
+    /// Puts the rows as fetched back on screen, the pipeline stays to run on the next set
+    fn restore_fetched(&mut self) {
+        if let Some(applied) = &mut self.pipeline {
+            (self.headers, self.results) = std::mem::take(&mut applied.fetched);
+        }
+    }
+
+    /// Runs the kept pipeline over a result set that just replaced the shown one
+    fn rerun_pipeline(&mut self) {
+        let Some(applied) = self.pipeline.take() else {
+            return;
+        };
+        let rows = self.results.rows(0..self.results.len()).map(|row| row.cloned().collect()).collect();
+        match applied.pipeline.apply(&self.headers, rows) {
+            Ok((headers, rows)) => {
+                let fetched = (
+                    std::mem::replace(&mut self.headers, headers),
+                    std::mem::replace(&mut self.results, Columns::from_rows(rows)),
+                );
+                self.pipeline = Some(AppliedPipeline { fetched, ..applied });
+                self.column_widths.clear();
+            }
+            Err(e) => toast::warning(format!("Transform removed: {:#}", e)),
+        }
+    }
+
+    fn reset_result_view(&mut self) {
+        self.column_widths.clear();
+        self.changed_cells.clear();
+        self.horizontal_scroll = 0;
+        self.table_state = TableState::default();
+        if !self.results.is_empty() {
+            self.table_state.select(Some(0));
+        }
+    }
+
+    /// Opens the command palette, or says where scripts go when none registered a command
+    pub fn open_palette(&mut self) {
+        if self.scripts.commands.is_empty() {
+            toast::info("No script commands, add .rhai files to ~/.config/rsquid/scripts");
+            return;
+        }
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.palette = Some(Palette { filter: String::new(), state });
+    }
+
+    /// Runs the command selected in the palette, a query it returns replaces the editor's
+    pub fn run_palette_command(&mut self) {
+        let Some(palette) = self.palette.take() else {
+            return;
+        };
+        let Some(command) = palette.state.selected().and_then(|i| palette.matches(&self.scripts).nth(i)).cloned() else {
+            return;
+        };
+        let connection = self.connection.as_ref().map(|c| c.name.as_str());
+        match self.scripts.run_command(&command, &self.query, connection) {
+            Ok(Some(query)) => self.set_query(query),
+            Ok(None) => {}
+            Err(e) => toast::error(format!("Command {} failed: {:#}", command.label, e)),
+        }
+    }
+
+    /// Switches the table to another result set of the last run
+    pub fn select_result_set(&mut self, index: usize) {
+        if index == self.active_result || index >= self.result_sets.len() {
+            return;
+        }
+        self.restore_fetched();
+
+        self.result_sets[self.active_result] =
+            (std::mem::take(&mut self.headers), std::mem::take(&mut self.results));
+        (self.headers, self.results) = std::mem::take(&mut self.result_sets[index]);
+        self.active_result = index;
+        self.rerun_pipeline();
+        self.column_widths.clear();
+        self.horizontal_scroll = 0;
+        self.table_state = TableState::default();
+        if !self.results.is_empty() {
+            self.table_state.select(Some(0));
+        }
+    }
+
+    /// Copies the rows the table shows, with their headers, as TSV so they paste into a
+    /// spreadsheet as cells. Rows past the Ctrl+L limit are left out like on screen.
+    pub fn copy_results(&self) {
+        if self.headers.is_empty() {
+            toast::info("There are no results to copy");
+            return;
+        }
+        let rows = if self.max_results > 0 {
+            self.results.len().min(self.max_results as usize)
+        } else {
+            self.results.len()
+        };
+        let mut text = Vec::new();
+        let masking = &self.config.masking;
+        let methods = masking.methods(&self.headers);
+        let written = match &methods {
+            Some(methods) => {
+                let masked: Vec<Vec<Value>> =
+                    self.results.rows(0..rows).map(|row| masking.mask_row(&self.headers, methods, row)).collect();
+                export::write_tsv(&mut text, &self.headers, &masked)
+            }
+            None => export::write_tsv(&mut text, &self.headers, self.results.rows(0..rows)),
+        };
+        let copied = written.and_then(|()| clipboard::copy(&String::from_utf8_lossy(&text)));
+        let masked = methods.map_or(0, |methods| methods.iter().flatten().count());
+        match copied {
+            Ok(()) => toast::success(format!(
+                "Copied {} row{} to the clipboard{}",
+                rows,
+                if rows == 1 { "" } else { "s" },
+                if masked > 0 { format!(", {} column{} masked", masked, if masked == 1 { "" } else { "s" }) } else { String::new() }
+            )),
+            Err(e) => toast::error(format!("Could not copy: {}", e)),
+        }
+    }
+
+    /// Lists the result's columns with their masking rules, then the other columns that have one
+    pub fn open_masking(&mut self) {
+        let masking = &self.config.masking;
+        let mut columns: Vec<(String, Option<MaskMethod>)> =
+            self.headers.iter().map(|header| (header.clone(), masking.method(header))).collect();
+        let in_results = columns.len();
+        for rule in &masking.rules {
+            if !columns.iter().any(|(column, _)| column.eq_ignore_ascii_case(&rule.column)) {
+                columns.push((rule.column.clone(), Some(rule.method)));
+            }
+        }
+        if columns.is_empty() {
+            toast::info("Run a query first, its columns are the ones to mask");
+            return;
+        }
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.pending_masking = Some(PendingMasking { columns, in_results, state });
+    }
+
+    /// Closes the masking box, handing the rules to the app to save
+    pub fn save_masking(&mut self) -> Option<QueryPageAction> {
+        let pending = self.pending_masking.take()?;
+        let mut masking = self.config.masking.clone();
+        for (column, method) in pending.columns {
+            masking.set(&column, method);
+        }
+        Some(QueryPageAction::SaveMasking(masking))
+    }
+
+    /// Pulls the next batch from the active row stream, if any
+    pub async fn load_more_rows(&mut self) -> Result<()> {
+        let Some(stream) = self.row_stream.as_mut() else {
+            return Ok(());
+        };
+
+        match stream.next_batch().await {
+            Some(Ok(batch)) => {
+                let (headers, rows) = self.transform_rows(vec![batch]).pop().unwrap_or_default();
+                if self.headers.is_empty() {
                     self.headers = headers;
-                    self.results = rows;
-                    if !self.results.is_empty() {
-                        self.table_state.select(Some(0));
-                    }
-                    
-                    if let Ok(history_manager) = crate::gui::history::HistoryManager::new() {
-                        let _ = history_manager.save_query(self.query.clone());
+                }
+                let rows = match &mut self.pipeline {
+                    Some(applied) => {
+                        let piped = applied.pipeline.apply(&applied.fetched.0, rows.clone());
+                        applied.fetched.1.extend(rows);
+                        match piped {
+                            Ok((_, rows)) => rows,
+                            Err(e) => {
+                                toast::warning(format!("Transform removed: {:#}", e));
+                                self.remove_pipeline();
+                                return Ok(());
+                            }
+                        }
                     }
+                    None => rows,
+                };
+                self.append_rows(rows);
+            }
+            Some(Err(e)) => {
+                self.row_stream = None;
+                self.error = Some(self.redact(&format!("Fetch error: {}", e)));
+            }
+            None => {
+                self.row_stream = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads another batch once the selection gets within a page of the loaded rows
+    pub async fn load_more_if_needed(&mut self) -> Result<()> {
+        if self.row_stream.is_none() {
+            return Ok(());
+        }
+        if self.max_results > 0 && self.results.len() >= self.max_results as usize {
+            return Ok(());
+        }
+
+        let selected = self.table_state.selected().unwrap_or(0);
+        if selected + 10 >= self.results.len() {
+            self.load_more_rows().await?;
+        }
+        Ok(())
+    }
+
+    /// Switches read-only mode, on the server too. An open transaction keeps the mode it began with.
+    pub fn toggle_read_only(&mut self) {
+        if self.transaction_statements.is_some() {
+            self.error = Some("Commit or roll back the transaction before switching read-only mode".to_string());
+            return;
+        }
+        self.read_only = !self.read_only;
+        if let Some(executor) = &self.executor {
+            executor.set_read_only(self.read_only);
+        }
+    }
+
+    /// Background work that may hold the executor's transaction until it ends
+    fn transaction_busy(&self) -> bool {
+        self.running_query.is_some() || self.running_dump.is_some() || self.running_generate.is_some()
+    }
+
+    pub async fn begin_transaction(&mut self) -> Result<()> {
+        if self.transaction_statements.is_some() {
+            return Ok(());
+        }
+        if self.transaction_busy() {
+            self.error = Some("Wait for the running query to finish before starting a transaction".to_string());
+            return Ok(());
+        }
+        if let Some(executor) = &self.executor {
+            match executor.begin_transaction().await {
+                Ok(()) => {
+                    self.row_stream = None;
+                    self.transaction_statements = Some(0);
                 }
-                Err(e) => {
-                    self.error = Some(format!("Query error: {}", e));
+                Err(e) => self.error = Some(self.redact(&format!("Could not begin transaction: {}", e))),
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn commit_transaction(&mut self) -> Result<()> {
+        if self.transaction_statements.is_none() {
+            return Ok(());
+        }
+        if self.transaction_busy() {
+            self.error = Some("Wait for the running query to finish, or cancel it with Esc, before committing".to_string());
+            return Ok(());
+        }
+        if let Some(executor) = &self.executor {
+            // The transaction is consumed either way, a failed commit is rolled back
+            self.transaction_statements = None;
+            match executor.commit_transaction().await {
+                Ok(()) => toast::success("Transaction committed"),
+                Err(e) => self.error = Some(self.redact(&format!("Commit failed: {}", e))),
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn rollback_transaction(&mut self) -> Result<()> {
+        if self.transaction_statements.is_none() {
+            return Ok(());
+        }
+        if self.transaction_busy() {
+            self.error = Some("Wait for the running query to finish, or cancel it with Esc, before rolling back".to_string());
+            return Ok(());
+        }
+        if let Some(executor) = &self.executor {
+            self.transaction_statements = None;
+            match executor.rollback_transaction().await {
+                Ok(()) => toast::info("Transaction rolled back"),
+                Err(e) => self.error = Some(self.redact(&format!("Rollback failed: {}", e))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Asks to confirm emptying the table under the explorer cursor, showing how many rows it holds
+    pub async fn open_empty_table(&mut self) {
+        let (Some(table), Some(executor)) = (self.selected_table(), &self.executor) else {
+            return;
+        };
+        let table = table.name.clone();
+        if self.read_only {
+            self.error = Some("Read-only mode: tables can't be emptied (Ctrl+O to turn it off)".to_string());
+            return;
+        }
+        let dialect = executor.dialect();
+        let (rows, exact) = match executor.estimate_table_rows(&table).await {
+            Some(rows) => (Some(rows), false),
+            None => {
+                // SQLite expression columns only decode as text
+                let count = match dialect {
+                    Dialect::Sqlite => format!("SELECT CAST(COUNT(*) AS TEXT) FROM {}", quote_identifier(&table, dialect)),
+                    Dialect::Postgres | Dialect::MySql => format!("SELECT COUNT(*) FROM {}", quote_identifier(&table, dialect)),
+                };
+                let rows = executor
+                    .execute_detached(&count, &[])
+                    .await
+                    .ok()
+                    .and_then(|sets| sets.first()?.1.first()?.first()?.to_string().parse().ok());
+                (rows, true)
+            }
+        };
+        self.pending_confirmation = Some(PendingConfirmation {
+            kind: Confirmation::EmptyTable {
+                table,
+                rows,
+                exact,
+                delete: dialect == Dialect::Sqlite,
+                transaction: truncate_is_transactional(dialect),
+            },
+            input: String::new(),
+        });
+    }
+
+    /// Runs the confirmed TRUNCATE or DELETE like a query, after opening a transaction when asked
+    pub async fn empty_table(&mut self, table: &str, delete: bool, transaction: bool) -> Result<()> {
+        let Some(executor) = &self.executor else {
+            return Ok(());
+        };
+        let dialect = executor.dialect();
+        if !delete && !truncate_is_transactional(dialect) && self.transaction_statements.is_some() {
+            self.error = Some("TRUNCATE would commit the open transaction, use DELETE or finish the transaction first".to_string());
+            return Ok(());
+        }
+        let statement = empty_table_statement(table, dialect, delete);
+        if transaction && self.transaction_statements.is_none() {
+            self.begin_transaction().await?;
+            if self.transaction_statements.is_none() {
+                return Ok(());
+            }
+            toast::info("Emptying in a transaction: Ctrl+Shift+C or Alt+K to commit, Ctrl+Shift+R or Alt+R to roll back");
+        }
+        self.spawn_query(statement, Vec::new());
+        Ok(())
+    }
+
+    /// Offers the maintenance statements for the table under the explorer cursor
+    pub fn open_maintenance(&mut self) {
+        let (Some(table), Some(executor)) = (self.selected_table(), &self.executor) else {
+            return;
+        };
+        let table = table.name.clone();
+        if self.read_only {
+            self.error = Some("Read-only mode: maintenance can't run (Ctrl+O to turn it off)".to_string());
+            return;
+        }
+        let operations = maintenance_statements(&table, executor.dialect());
+        self.pending_confirmation = Some(PendingConfirmation {
+            kind: Confirmation::Maintenance { table, operations, selected: 0, armed: false },
+            input: String::new(),
+        });
+    }
+
+    /// Runs the chosen maintenance like a query, so its output lands in the results and
+    /// notices, and follows the server's progress reports while it runs
+    pub fn run_maintenance(&mut self, table: String, operation: Maintenance) {
+        let Some(executor) = self.executor.clone() else {
+            return;
+        };
+        // VACUUM refuses to run in a transaction block and OPTIMIZE would commit it
+        if self.transaction_statements.is_some() {
+            self.error = Some("Maintenance can't run in a transaction, commit or roll back first".to_string());
+            return;
+        }
+        self.stop_watch();
+        self.spawn_query(operation.statement, Vec::new());
+        if self.running_query.is_none() {
+            return;
+        }
+
+        let progress = Arc::new(Mutex::new(None));
+        let handle = tokio::spawn({
+            let (progress, table) = (progress.clone(), table.clone());
+            async move {
+                loop {
+                    tokio::time::sleep(MAINTENANCE_PROGRESS_INTERVAL).await;
+                    let latest = crate::utils::admin::maintenance_progress(&executor, &table).await;
+                    if let Ok(mut progress) = progress.lock() {
+                        *progress = latest;
+                    }
                 }
             }
+        });
+        self.running_maintenance = Some(RunningMaintenance { table, label: operation.label, progress, handle });
+    }
+
+    /// Asks where to dump the table under the explorer cursor, or every table with `all`
+    pub fn open_dump(&mut self, all: bool) {
+        let (tables, file) = if all {
+            let name = self.connection.as_ref().map_or("dump".to_string(), |c| c.name.clone());
+            (self.tables.iter().map(|t| t.name.clone()).collect::<Vec<_>>(), name)
         } else {
-            self.error = Some("Not connected to database".to_string());
+            match self.selected_table() {
+                Some(table) => (vec![table.name.clone()], table.name.clone()),
+                None => return,
+            }
+        };
+        if tables.is_empty() {
+            return;
         }
+        self.pending_dump = Some(PendingDump { tables, path: format!("{}.sql", file) });
+    }
 
-        Ok(())
+    pub fn start_dump(&mut self) {
+        let (Some(pending), Some(executor)) = (self.pending_dump.take(), self.executor.clone()) else {
+            return;
+        };
+        if self.running_dump.is_some() {
+            toast::warning("A dump is already being written");
+            return;
+        }
+        let progress = Arc::new(AtomicU64::new(0));
+        let path = PathBuf::from(&pending.path);
+        let tables = pending.tables.len();
+        let counter = progress.clone();
+        let handle = tokio::spawn(async move { dump::dump_tables(executor, pending.tables, &path, counter).await });
+        self.running_dump = Some(RunningDump { path: pending.path, tables, progress, handle });
+    }
+
+    /// Asks how many rows to generate for the table under the explorer cursor
+    pub fn open_generate(&mut self) {
+        if let Some(table) = self.selected_table() {
+            self.pending_generate = Some(PendingGenerate { table: table.name.clone(), rows: "100".to_string() });
+        }
+    }
+
+    pub fn start_generate(&mut self) {
+        let (Some(pending), Some(executor)) = (self.pending_generate.take(), self.executor.clone()) else {
+            return;
+        };
+        let Ok(rows) = pending.rows.parse::<u64>() else {
+            return;
+        };
+        if rows == 0 {
+            return;
+        }
+        if self.running_generate.is_some() {
+            toast::warning("Test rows are already being generated");
+            return;
+        }
+        let progress = Arc::new(AtomicU64::new(0));
+        let table = pending.table.clone();
+        let counter = progress.clone();
+        let handle = tokio::spawn(generate::generate_rows(executor, table, rows, counter));
+        self.running_generate = Some(RunningGenerate { table: pending.table, rows, progress, handle });
+    }
+
+    /// Reports finished dumps and generated rows in a toast
+    pub fn poll_background_tasks(&mut self) {
+        self.poll_tables();
+        self.poll_server_info();
+        self.poll_checking_query();
+        self.poll_dump();
+        self.poll_generate();
+        self.poll_maintenance();
+    }
+
+    /// Whether something runs that the page shows progress or a timer for
+    pub fn is_busy(&self) -> bool {
+        self.running_query.is_some()
+            || self.running_dump.is_some()
+            || self.running_generate.is_some()
+            || self.running_maintenance.is_some()
+            || self.loading_tables.is_some()
+            || self.loading_server_info.is_some()
+            || self.checking_query.is_some()
+            || self.watch.is_some()
+    }
+
+    /// Stops following the progress once the maintenance query is done
+    fn poll_maintenance(&mut self) {
+        if self.running_query.is_some() {
+            return;
+        }
+        let Some(maintenance) = self.running_maintenance.take() else {
+            return;
+        };
+        maintenance.handle.abort();
+        if self.error.is_none() {
+            toast::success(format!("{} on {} finished", maintenance.label, maintenance.table));
+        }
+    }
+
+    fn poll_generate(&mut self) {
+        if !self.running_generate.as_ref().is_some_and(|generate| generate.handle.is_finished()) {
+            return;
+        }
+        let Some(generate) = self.running_generate.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        match futures_util::FutureExt::now_or_never(generate.handle) {
+            Some(Ok(Ok(rows))) => toast::success(format!("Inserted {} generated rows into {}", rows, generate.table)),
+            Some(Ok(Err(e))) => toast::error(self.redact(&format!("Generating rows failed: {:#}", e))),
+            _ => toast::error("Row generator ended unexpectedly"),
+        }
+    }
+
+    fn poll_dump(&mut self) {
+        if !self.running_dump.as_ref().is_some_and(|dump| dump.handle.is_finished()) {
+            return;
+        }
+        let Some(dump) = self.running_dump.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        match futures_util::FutureExt::now_or_never(dump.handle) {
+            Some(Ok(Ok(rows))) => toast::success(format!(
+                "Dumped {} table{} ({} rows) to {}",
+                dump.tables,
+                if dump.tables == 1 { "" } else { "s" },
+                rows,
+                dump.path
+            )),
+            Some(Ok(Err(e))) => toast::error(self.redact(&format!("Dump failed: {:#}", e))),
+            _ => toast::error("Dump task ended unexpectedly"),
+        }
+    }
+}
+
+/// Tells the user a scheduled query ended, in a toast and through the terminal
+fn announce_scheduled(query: &str, elapsed: Duration, outcome: Result<usize, &str>) {
+    let query = truncate_to_width(&single_line(query), 60);
+    let body = match outcome {
+        Ok(rows) => {
+            toast::success(format!("Scheduled query finished in {} ({} rows)", format_duration(elapsed), rows));
+            format!("Finished in {} ({} rows): {}", format_duration(elapsed), rows, query)
+        }
+        Err(error) => {
+            toast::error(format!("Scheduled query failed: {}", error));
+            format!("Failed: {}", query)
+        }
+    };
+    // The toast already tells anyone looking at rsquid
+    let _ = desktop::notify("rsquid", &body);
+}
+
+/// Reads the time typed into the schedule box: empty for after the current query,
+/// a time of day like 14:30 (tomorrow once it passed today) or a delay like +10m
+fn parse_schedule_time(input: &str, now: chrono::DateTime<chrono::Local>) -> Result<ScheduleTime> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(ScheduleTime::AfterCurrent);
+    }
+    if let Some(delay) = input.strip_prefix('+') {
+        let delay = delay.trim();
+        let split = delay.find(|c: char| !c.is_ascii_digit()).unwrap_or(delay.len());
+        let (amount, unit) = delay.split_at(split);
+        let amount: i64 = amount.parse().map_err(|_| anyhow!("Expected a delay like +90s, +10m or +2h"))?;
+        let seconds = match unit.trim() {
+            "s" => amount,
+            "" | "m" => amount * 60,
+            "h" => amount * 3600,
+            _ => bail!("Expected a delay like +90s, +10m or +2h"),
+        };
+        return Ok(ScheduleTime::At(now + chrono::Duration::seconds(seconds)));
+    }
+    let time = chrono::NaiveTime::parse_from_str(input, "%H:%M:%S")
+        .or_else(|_| chrono::NaiveTime::parse_from_str(input, "%H:%M"))
+        .map_err(|_| anyhow!("Expected a time like 14:30, a delay like +10m, or nothing to run after the current query"))?;
+    let mut date = now.date_naive();
+    if time <= now.time() {
+        date = date.succ_opt().unwrap_or(date);
+    }
+    date.and_time(time)
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(ScheduleTime::At)
+        .ok_or_else(|| anyhow!("{} doesn't exist in the local time zone", input))
+}
+
+/// Cells of `new` that differ from the same row and column of `old`, rows past its end included
+fn changed_cells(old: &Columns, new: &Columns) -> HashSet<(usize, usize)> {
+    (0..new.width())
+        .flat_map(|c| {
+            new.column(c)
+                .iter()
+                .enumerate()
+                .filter(move |&(r, value)| old.get(r, c) != Some(value))
+                .map(move |(r, _)| (r, c))
+        })
+        .collect()
+}
+
+/// The tables of `connection`, from the schema cache while the schema is unchanged
+async fn list_tables(executor: &QueryExecutor, connection: &Connection, disk: bool) -> Result<Vec<TableInfo>> {
+    let version = schema_cache::schema_version(executor).await;
+    if let Some(version) = &version
+        && let Some(cache) = schema_cache::load(&connection.name, version, disk)
+    {
+        return Ok(cache
+            .tables
+            .iter()
+            .map(|name| TableInfo {
+                name: name.clone(),
+                fields: cache.columns.get(name).cloned(),
+                expanded: false,
+            })
+            .collect());
+    }
+
+    let query = match connection.db_type.as_str() {
+        "postgres" => "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public'",
+        "mysql" | "mariadb" => "SHOW TABLES",
+        "sqlite" => "SELECT name FROM sqlite_master WHERE type='table'",
+        _ => return Ok(Vec::new()),
+    };
+    // Off the transaction's connection, like the column lookups in the explorer
+    let sets = executor.execute_detached(query, &[]).await?;
+    let tables: Vec<TableInfo> = sets
+        .iter()
+        .flat_map(|(_, rows)| rows)
+        .map(|row| TableInfo {
+            name: row[0].to_string(),
+            fields: None,
+            expanded: false,
+        })
+        .collect();
+    if let Some(version) = version {
+        let cache = SchemaCache {
+            version,
+            tables: tables.iter().map(|t| t.name.clone()).collect(),
+            columns: Default::default(),
+        };
+        if let Err(e) = schema_cache::store(&connection.name, cache, disk) {
+            toast::warning(format!("Could not cache the schema: {:#}", e));
+        }
     }
-}
\ No newline at end of file
+    Ok(tables)
+}
@@ -2,18 +2,76 @@ use anyhow::{Context, Ok, Result};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{List, ListItem, ListState, Paragraph},
 };
-use std::fs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::gui::theme::Theme;
+use crate::utils::text::{single_line, truncate_to_width};
 
 pub enum HistoryPageAction {
     Back,
     SelectQuery(String),
-    DeleteQuery(String),
+    ExecuteQuery(String),
+    DeleteEntries(Vec<usize>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+impl HistoryEntry {
+    pub fn succeeded(query: String) -> Self {
+        Self { query, success: true, error: None, duration_ms: None }
+    }
+
+    pub fn failed(query: String, error: String) -> Self {
+        Self { query, success: false, error: Some(error), duration_ms: None }
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration_ms = Some(duration.as_millis() as u64);
+        self
+    }
+}
+
+// Older history files stored plain query strings, only successful ones were saved
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEntry {
+    Entry(HistoryEntry),
+    Legacy(String),
+}
+
+impl From<StoredEntry> for HistoryEntry {
+    fn from(stored: StoredEntry) -> Self {
+        match stored {
+            StoredEntry::Entry(entry) => entry,
+            StoredEntry::Legacy(query) => HistoryEntry::succeeded(query),
+        }
+    }
 }
 
+/// Entries kept by the compaction at startup, older ones are dropped
+const MAX_ENTRIES: usize = 10_000;
+/// Bytes read from the end of the file per step while looking for the last entry
+const TAIL_CHUNK: u64 = 4096;
+
+/// History is stored one JSON entry per line, so saving a query appends a line
+/// instead of rewriting the file
 pub struct HistoryManager {
     pub(crate) config_path: PathBuf,
 }
@@ -26,49 +84,113 @@ impl HistoryManager {
         
         fs::create_dir_all(&config_dir)?;
         
-        let config_path = config_dir.join("history.json");
+        let config_path = config_dir.join("history.jsonl");
+        let manager = Self { config_path };
+
+        // Earlier versions kept the whole history in one JSON array
+        let legacy_path = config_dir.join("history.json");
+        if legacy_path.exists() && !manager.config_path.exists() {
+            let entries: Vec<StoredEntry> = serde_json::from_str(&fs::read_to_string(&legacy_path)?)?;
+            let entries: Vec<HistoryEntry> = entries.into_iter().map(HistoryEntry::from).collect();
+            manager.write_history(&entries)?;
+            fs::remove_file(&legacy_path)?;
+        }
         
-        Ok(Self { config_path })
+        Ok(manager)
     }
 
-    pub fn load_history(&self) -> Result<Vec<String>> {
+    pub fn load_history(&self) -> Result<Vec<HistoryEntry>> {
         if !self.config_path.exists() {
             return Ok(Vec::new());
         }
 
         let content = fs::read_to_string(&self.config_path)?;
-        let queries: Vec<String> = serde_json::from_str(&content)?;
-        Ok(queries)
+        // A line cut short by a crash mid-append is skipped, not fatal
+        Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
     }
 
-    pub fn save_query(&self, query_string: String) -> Result<()> {
-        let mut queries = self.load_history().unwrap_or_default();
-        
-        // Wont save consecutive identical queries
-        if let Some(last) = queries.last() {
-            if last == &query_string {
-                return Ok(());
+    pub fn save_query(&self, entry: HistoryEntry) -> Result<()> {
+        // Wont save consecutive identical queries with the same outcome
+        if let Some(last) = self.last_entry()?
+            && last.query == entry.query
+            && last.success == entry.success
+        {
+            return Ok(());
+        }
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.config_path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Reads the file backwards from the end until the last complete line
+    fn last_entry(&self) -> Result<Option<HistoryEntry>> {
+        let mut file = match File::open(&self.config_path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            file => file?,
+        };
+        let len = file.metadata()?.len();
+        let mut tail = Vec::new();
+        let mut start = len;
+        while start > 0 {
+            let step = TAIL_CHUNK.min(start);
+            start -= step;
+            let mut chunk = vec![0; step as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut chunk)?;
+            chunk.extend(tail);
+            tail = chunk;
+            // The file ends with a newline, the one before it starts the last line
+            let body = tail.strip_suffix(b"\n").unwrap_or(&tail);
+            if let Some(newline) = body.iter().rposition(|&b| b == b'\n') {
+                return Ok(serde_json::from_slice(&body[newline + 1..]).ok());
             }
         }
-        
-        queries.push(query_string);
-        
-        let content = serde_json::to_string_pretty(&queries)?;
-        fs::write(&self.config_path, content)?;
-        
+        Ok(serde_json::from_slice(tail.strip_suffix(b"\n").unwrap_or(&tail)).ok())
+    }
+
+    /// Replaces the whole history, through a temporary file so a crash leaves the old one
+    pub fn write_history(&self, entries: &[HistoryEntry]) -> Result<()> {
+        let mut content = String::new();
+        for entry in entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        let temp_path = self.config_path.with_extension("jsonl.tmp");
+        fs::write(&temp_path, content)?;
+        fs::rename(&temp_path, &self.config_path)?;
         Ok(())
     }
 
+    /// Rewrites the file without unreadable lines and past the newest `MAX_ENTRIES`,
+    /// once per start so appending never has to
+    pub fn compact(&self) -> Result<()> {
+        if !self.config_path.exists() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(&self.config_path)?;
+        let lines = content.lines().count();
+        let entries = self.load_history()?;
+        if entries.len() == lines && entries.len() <= MAX_ENTRIES {
+            return Ok(());
+        }
+        self.write_history(&entries[entries.len().saturating_sub(MAX_ENTRIES)..])
+    }
+
     pub fn clear_history(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(&Vec::<String>::new())?;
-        fs::write(&self.config_path, content)?;
-        Ok(())
+        self.write_history(&[])
     }
 }
 
 pub struct HistoryPage {
     pub(crate) list_state: ListState,
     pub(crate) history_manager: HistoryManager,
+    // Indices into the stored history, not into the reversed list
+    pub(crate) marked: BTreeSet<usize>,
+    /// Where the list was last drawn, for mouse clicks
+    pub(crate) list_area: Rect,
 }
 
 impl HistoryPage {
@@ -76,39 +198,44 @@ impl HistoryPage {
         let mut list_state = ListState::default();
         list_state.select(Some(0));
         let history_manager = HistoryManager::new()?;
+        // Losing the compaction only costs disk space
+        history_manager.compact().ok();
         
         Ok(Self {
             list_state,
             history_manager,
+            marked: BTreeSet::new(),
+            list_area: Rect::default(),
         })
     }
 
-    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let history = self.history_manager.load_history().unwrap_or_default();
+        let selected_error = self
+            .list_state
+            .selected()
+            .and_then(|selected| history.iter().rev().nth(selected))
+            .and_then(|entry| entry.error.clone());
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),
                 Constraint::Min(0),
-                Constraint::Length(3),
+                Constraint::Length(if selected_error.is_some() { 5 } else { 3 }),
             ])
             .split(area);
 
         let title = Paragraph::new("Query History")
-            .style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
+            .style(theme.title_style())
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(theme.block());
         f.render_widget(title, chunks[0]);
 
-        let history = self.history_manager.load_history().unwrap_or_default();
-
         let items: Vec<ListItem> = if history.is_empty() {
             vec![ListItem::new("No query history yet").style(
                 Style::default()
-                    .fg(Color::DarkGray)
+                    .fg(theme.muted)
                     .add_modifier(Modifier::ITALIC),
             )]
         } else {
@@ -116,59 +243,82 @@ impl HistoryPage {
                 .iter()
                 .rev()
                 .enumerate()
-                .map(|(i, query)| {
+                .map(|(i, entry)| {
+                    let index = history.len() - 1 - i;
                     // Truncate long queries for display
-                    let display = if query.len() > 100 {
-                        format!("{}. {}...", history.len() - i, &query[..97])
+                    let display = format!(
+                        "{}. {}",
+                        history.len() - i,
+                        truncate_to_width(&single_line(&entry.query), 100)
+                    );
+                    let marker_color = if entry.success { theme.success } else { theme.error };
+                    let is_marked = self.marked.contains(&index);
+                    let mut spans = vec![
+                        Span::raw(if is_marked { "[x] " } else { "[ ] " }),
+                        Span::styled(theme.glyphs.marker, Style::default().fg(marker_color)),
+                        Span::raw(display),
+                    ];
+                    if let Some(ms) = entry.duration_ms {
+                        spans.push(Span::styled(
+                            format!(" ({} ms)", ms),
+                            Style::default().fg(theme.muted),
+                        ));
+                    }
+                    let item = ListItem::new(Line::from(spans));
+                    if is_marked {
+                        item.style(Style::default().fg(theme.focus))
                     } else {
-                        format!("{}. {}", history.len() - i, query.replace('\n', " "))
-                    };
-                    ListItem::new(display)
+                        item
+                    }
                 })
                 .collect()
         };
 
-        let highlight = {
-            #[cfg(target_os = "windows")]
-            {
-                Style::default()
-                    .fg(Color::White)
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
-
-            #[cfg(not(target_os = "windows"))]
-            {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
-        };
-
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Queries"))
-            .highlight_style(highlight)
+            .block(theme.block().title("Queries"))
+            .highlight_style(theme.highlight())
             .highlight_symbol(">> ");
 
         f.render_stateful_widget(list, chunks[1], &mut self.list_state);
+        self.list_area = chunks[1];
+        theme.render_scrollbar(
+            f,
+            chunks[1],
+            history.len(),
+            self.list_state.selected().unwrap_or(0),
+            chunks[1].height.saturating_sub(2) as usize,
+        );
 
         let help_text = if history.is_empty() {
-            "Esc: Back"
+            "Esc: Back".to_string()
         } else {
-            "↑↓: Navigate | Enter: Use Query | d: Delete Selection | c: Clear History | Esc: Back"
+            format!("{}: Navigate | Enter: Use Query | x / Ctrl+E: Run Query | Space: Mark | d: Delete Marked/Selection | c: Clear History | Esc: Back", theme.glyphs.up_down)
         };
 
-        let help = Paragraph::new(help_text)
-            .style(Style::default().fg(Color::Gray))
+        let mut help_lines = vec![Line::from(help_text)];
+
+        if let Some(err) = &selected_error {
+            help_lines.push(Line::from(""));
+            help_lines.push(Line::from(vec![
+                Span::styled(
+                    "Error: ",
+                    Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(err.replace('\n', " "), Style::default().fg(theme.error)),
+            ]));
+        }
+
+        let help = Paragraph::new(help_lines)
+            .style(Style::default().fg(theme.help))
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(theme.block());
         f.render_widget(help, chunks[2]);
 
         let total_items = if history.is_empty() { 1 } else { history.len() };
-        if let Some(selected) = self.list_state.selected() {
-            if selected >= total_items {
-                self.list_state.select(Some(total_items.saturating_sub(1)));
-            }
+        if let Some(selected) = self.list_state.selected()
+            && selected >= total_items
+        {
+            self.list_state.select(Some(total_items.saturating_sub(1)));
         }
     }
 }
\ No newline at end of file
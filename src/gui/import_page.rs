@@ -0,0 +1,389 @@
+use crate::gui::theme::Theme;
+use crate::utils::import::{ImportColumn, ImportData, ImportJob, SourceFormat, cell_text};
+use crate::utils::text::{display_width, truncate_to_width};
+use anyhow::Result;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, Gauge, List, ListItem, ListState, Paragraph, Row, Table, Wrap},
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::JoinHandle;
+
+/// Rows shown in the preview above the column mapping
+const PREVIEW_ROWS: usize = 5;
+/// Rows of the mapping list before the columns: target table, mode and the column for unmapped keys
+pub const MAPPING_HEADER_ROWS: usize = 3;
+
+/// Files the wizard lists: CSV, JSON arrays and NDJSON
+const IMPORT_EXTENSIONS: [&str; 4] = ["csv", "json", "jsonl", "ndjson"];
+
+pub enum ImportAction {
+    Cancel,
+    Start(ImportJob),
+    /// Leaves the finished import, the explorer needs reloading if a table was created
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportStep {
+    PickFile,
+    Map,
+    Running,
+    Finished,
+}
+
+pub struct FileEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+pub struct ImportPage {
+    pub(crate) step: ImportStep,
+    pub(crate) dir: PathBuf,
+    pub(crate) entries: Vec<FileEntry>,
+    pub(crate) list_state: ListState,
+    pub(crate) file: PathBuf,
+    pub(crate) data: ImportData,
+    pub(crate) table: String,
+    pub(crate) create: bool,
+    /// JSON column for the columns left out, empty to drop them
+    pub(crate) extra: String,
+    pub(crate) columns: Vec<ImportColumn>,
+    pub(crate) field_state: ListState,
+    /// Tables of the connection, to check the target against
+    pub(crate) tables: Vec<String>,
+    pub(crate) progress: Arc<AtomicU64>,
+    pub(crate) task: Option<JoinHandle<Result<u64>>>,
+    pub(crate) result: Option<Result<u64, String>>,
+    pub(crate) error: Option<String>,
+    /// Where the file or mapping list was last drawn, for mouse clicks
+    pub(crate) list_area: Rect,
+}
+
+impl ImportPage {
+    pub fn new() -> Self {
+        Self {
+            step: ImportStep::PickFile,
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            list_state: ListState::default(),
+            file: PathBuf::new(),
+            data: ImportData::default(),
+            table: String::new(),
+            create: true,
+            extra: String::new(),
+            columns: Vec::new(),
+            field_state: ListState::default(),
+            tables: Vec::new(),
+            progress: Arc::new(AtomicU64::new(0)),
+            task: None,
+            result: None,
+            error: None,
+            list_area: Rect::default(),
+        }
+    }
+
+    /// Starts over in the working directory. `target` preselects appending to that table.
+    pub fn open(&mut self, tables: Vec<String>, target: Option<String>) {
+        *self = Self::new();
+        self.tables = tables;
+        if let Some(table) = target {
+            self.table = table;
+            self.create = false;
+        }
+        let dir = std::env::current_dir().unwrap_or_default();
+        self.change_dir(dir);
+    }
+
+    pub fn change_dir(&mut self, dir: PathBuf) {
+        let mut entries: Vec<FileEntry> = match std::fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir
+                .filter_map(|entry| entry.ok())
+                .map(|entry| FileEntry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_dir: entry.path().is_dir(),
+                    path: entry.path(),
+                })
+                .filter(|entry| {
+                    !entry.name.starts_with('.')
+                        && (entry.is_dir
+                            || entry.path.extension().is_some_and(|e| {
+                                IMPORT_EXTENSIONS.iter().any(|ext| e.eq_ignore_ascii_case(ext))
+                            }))
+                })
+                .collect(),
+            Err(e) => {
+                self.error = Some(format!("Could not read {}: {}", dir.display(), e));
+                return;
+            }
+        };
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        if let Some(parent) = dir.parent() {
+            entries.insert(0, FileEntry { name: "..".to_string(), path: parent.to_path_buf(), is_dir: true });
+        }
+        self.entries = entries;
+        self.dir = dir;
+        self.error = None;
+        self.list_state.select(Some(0));
+    }
+
+    /// Reads the file and moves on to the column mapping
+    pub fn pick_file(&mut self, path: &Path) {
+        match ImportData::read(path) {
+            Ok(data) => {
+                self.columns = data.infer_columns();
+                if self.table.is_empty() {
+                    self.table = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().replace(|c: char| !c.is_alphanumeric(), "_"))
+                        .unwrap_or_default();
+                }
+                self.data = data;
+                self.file = path.to_path_buf();
+                self.field_state.select(Some(0));
+                self.step = ImportStep::Map;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{:#}", e)),
+        }
+    }
+
+    fn table_exists(&self) -> bool {
+        self.tables.iter().any(|t| t.eq_ignore_ascii_case(&self.table))
+    }
+
+    /// The import as configured, or None with `error` set
+    pub fn validate(&mut self) -> Option<ImportJob> {
+        let table = self.table.trim().to_string();
+        if table.is_empty() {
+            self.error = Some("Table name is required".to_string());
+            return None;
+        }
+        if self.create && self.table_exists() {
+            self.error = Some(format!("Table {} already exists, switch the mode to append", table));
+            return None;
+        }
+        if !self.create && !self.table_exists() {
+            self.error = Some(format!("Table {} doesn't exist, switch the mode to create it", table));
+            return None;
+        }
+        if !self.columns.iter().any(|c| c.include) {
+            self.error = Some("Select at least one column".to_string());
+            return None;
+        }
+        if let Some(column) = self.columns.iter().find(|c| c.include && c.name.trim().is_empty()) {
+            self.error = Some(format!("Column {} needs a name", column.source));
+            return None;
+        }
+        let extra = Some(self.extra.trim().to_string()).filter(|extra| !extra.is_empty());
+        if let Some(extra) = &extra
+            && self.columns.iter().any(|c| c.include && c.name.trim().eq_ignore_ascii_case(extra))
+        {
+            self.error = Some(format!("Column {} is already mapped, pick another name for the unmapped keys", extra));
+            return None;
+        }
+        Some(ImportJob {
+            table,
+            create: self.create,
+            columns: self.columns.clone(),
+            extra,
+            data: self.data.clone(),
+        })
+    }
+
+    /// Picks up the result once the background import ends
+    pub fn poll(&mut self) {
+        if !self.task.as_ref().is_some_and(|task| task.is_finished()) {
+            return;
+        }
+        if let Some(task) = self.task.take() {
+            // The handle is finished, so this doesn't wait
+            let outcome = futures_util::FutureExt::now_or_never(task);
+            self.result = Some(match outcome {
+                Some(Ok(Ok(rows))) => Ok(rows),
+                Some(Ok(Err(e))) => Err(format!("{:#}", e)),
+                Some(Err(e)) => Err(e.to_string()),
+                None => Err("Import task ended unexpectedly".to_string()),
+            });
+            self.step = ImportStep::Finished;
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(if self.error.is_some() { 5 } else { 4 }),
+            ])
+            .split(area);
+
+        let title = match self.step {
+            ImportStep::PickFile => format!("Import - {}", self.dir.display()),
+            _ => format!("Import - {}", self.file.display()),
+        };
+        let title = Paragraph::new(title)
+            .style(theme.title_style())
+            .alignment(Alignment::Center)
+            .block(theme.block());
+        f.render_widget(title, chunks[0]);
+
+        let help = match self.step {
+            ImportStep::PickFile => "Enter: Open | Backspace: Parent Directory | Esc: Cancel".to_string(),
+            ImportStep::Map => format!(
+                "{}: Navigate | Type: Rename | Space: Include Column | Left/Right: Type / Mode | Ctrl+S: Import | Esc: Back",
+                theme.glyphs.up_down
+            ),
+            ImportStep::Running => "Importing...".to_string(),
+            ImportStep::Finished => "Enter / Esc: Back to the query page".to_string(),
+        };
+
+        match self.step {
+            ImportStep::PickFile => self.render_files(f, chunks[1], theme),
+            ImportStep::Map => self.render_mapping(f, chunks[1], theme),
+            ImportStep::Running | ImportStep::Finished => self.render_progress(f, chunks[1], theme),
+        }
+
+        let mut help_lines = vec![Line::from(help)];
+        if let Some(err) = &self.error {
+            help_lines.push(Line::from(vec![
+                Span::styled("Error: ", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
+                Span::styled(err, Style::default().fg(theme.error)),
+            ]));
+        }
+        let help = Paragraph::new(help_lines)
+            .style(Style::default().fg(theme.help))
+            .alignment(Alignment::Center)
+            .block(theme.block())
+            .wrap(Wrap { trim: false });
+        f.render_widget(help, chunks[2]);
+    }
+
+    fn render_files(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                if entry.is_dir {
+                    ListItem::new(format!("{}{}", theme.glyphs.folder, entry.name)).style(Style::default().fg(theme.accent))
+                } else {
+                    ListItem::new(entry.name.clone())
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(theme.block().title("CSV and JSON Files"))
+            .highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, area, &mut self.list_state);
+        self.list_area = area;
+        theme.render_scrollbar(
+            f,
+            area,
+            self.entries.len(),
+            self.list_state.selected().unwrap_or(0),
+            area.height.saturating_sub(2) as usize,
+        );
+    }
+
+    fn render_mapping(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(PREVIEW_ROWS as u16 + 4), Constraint::Min(0)])
+            .split(area);
+
+        let included: Vec<(usize, &ImportColumn)> =
+            self.columns.iter().enumerate().filter(|(_, c)| c.include).collect();
+        let widths: Vec<Constraint> = included
+            .iter()
+            .map(|(i, c)| {
+                let width = self
+                    .data
+                    .rows
+                    .iter()
+                    .take(PREVIEW_ROWS)
+                    .filter_map(|row| row.get(*i))
+                    .map(|v| display_width(&cell_text(v)))
+                    .fold(display_width(&c.name), usize::max)
+                    .clamp(3, 30);
+                Constraint::Length(width as u16)
+            })
+            .collect();
+        let header = Row::new(included.iter().map(|(_, c)| {
+            Cell::from(c.name.clone()).style(Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))
+        }))
+        .bottom_margin(1);
+        let rows = self.data.rows.iter().take(PREVIEW_ROWS).map(|row| {
+            Row::new(included.iter().map(|(i, _)| {
+                Cell::from(truncate_to_width(&row.get(*i).map(cell_text).unwrap_or_default(), 30))
+            }))
+        });
+        let preview = Table::new(rows, widths)
+            .header(header)
+            .block(theme.block().title(format!("Preview ({} rows in file)", self.data.rows.len())));
+        f.render_widget(preview, chunks[0]);
+
+        let mode = if self.create { "Create new table" } else { "Append to existing table" };
+        let source = match self.data.format {
+            SourceFormat::Csv => "columns",
+            SourceFormat::Json => "keys",
+        };
+        let extra = if self.extra.is_empty() { "(type a name to keep them)" } else { &self.extra };
+        let mut items = vec![
+            ListItem::new(format!("Table: {}", self.table)),
+            ListItem::new(format!("Mode (Left/Right to change): {}", mode)),
+            ListItem::new(format!("JSON column for unmapped {}: {}", source, extra)),
+        ];
+        items.extend(self.columns.iter().map(|c| {
+            let style = if c.include { Style::default() } else { Style::default().fg(theme.muted) };
+            ListItem::new(format!(
+                "[{}] {} -> {} ({})",
+                if c.include { "x" } else { " " },
+                c.source,
+                c.name,
+                c.column_type.name()
+            ))
+            .style(style)
+        }));
+        let list = List::new(items)
+            .block(theme.block().title("Columns"))
+            .highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut self.field_state);
+        self.list_area = chunks[1];
+    }
+
+    fn render_progress(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let total = self.data.rows.len() as u64;
+        let done = self.progress.load(Ordering::Relaxed);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        let gauge = Gauge::default()
+            .block(theme.block().title("Progress"))
+            .gauge_style(Style::default().fg(theme.accent))
+            .ratio(if total == 0 { 1.0 } else { (done as f64 / total as f64).min(1.0) })
+            .label(format!("{} / {} rows", done, total));
+        f.render_widget(gauge, chunks[0]);
+
+        let (text, color) = match &self.result {
+            None => (format!("Importing into {}...", self.table), theme.help),
+            Some(Ok(rows)) => (format!("Imported {} rows into {}", rows, self.table), theme.success),
+            Some(Err(e)) => (format!("Import failed, no rows were written: {}", e), theme.error),
+        };
+        let status = Paragraph::new(text)
+            .style(Style::default().fg(color))
+            .block(theme.block())
+            .wrap(Wrap { trim: false });
+        f.render_widget(status, chunks[1]);
+    }
+}
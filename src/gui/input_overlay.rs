@@ -1,52 +1,50 @@
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style, Stylize},
+    style::{Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Clear, Paragraph},
 };
 
-use crate::gui::{QueryPage};
+use crate::gui::{QueryPage, theme::Theme};
 
-pub fn draw_input_overlay(f: &mut Frame, qpage: &QueryPage) {
+pub fn draw_input_overlay(f: &mut Frame, qpage: &QueryPage, theme: &Theme) {
     let area = centered_rect(60, 20, f.area());
 
     f.render_widget(Clear, area);
 
-    let block = Block::default()
+    let block = theme.block()
         .title("Set Max Rows (0 = unlimited)")
-        .borders(Borders::ALL)
-        .style(Style::default().bg(Color::Black)
-        .fg(Color::Yellow).bold());
-
-    let input = qpage.input_buffer.clone();
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
 
     let text = vec![
         Line::from(""),
-        Line::from(vec![
-            Span::styled("Enter number: ", Style::default().fg(Color::White).not_bold()),
-            Span::styled(input, Style::default().fg(Color::Green).not_bold()),
-            Span::styled("█", Style::default().fg(Color::Green).not_bold()),
-        ]),
+        Line::from(
+            [Span::styled("Enter number: ", Style::default().fg(theme.text).not_bold())]
+                .into_iter()
+                .chain(qpage.input_cursor.spans(&qpage.input_buffer, Style::default().fg(theme.success).not_bold(), theme))
+                .collect::<Vec<_>>(),
+        ),
         Line::from(""),
-        Line::from(Span::styled("Current: ", Style::default().fg(Color::Gray).not_bold())),
+        Line::from(Span::styled("Current: ", Style::default().fg(theme.help).not_bold())),
         Line::from(Span::styled(
             if qpage.max_results == 0 { "unlimited".to_string() } else { qpage.max_results.to_string() },
-            Style::default().fg(Color::Cyan).not_bold()
+            Style::default().fg(theme.accent).not_bold()
         )),
         Line::from(""),
-        Line::from(Span::styled("Press Enter to confirm, Esc to cancel", Style::default().fg(Color::White).not_bold())),
+        Line::from(Span::styled("Press Enter to confirm, Esc to cancel", Style::default().fg(theme.text).not_bold())),
     ];
 
     let paragraph = Paragraph::new(text)
         .block(block)
         .alignment(ratatui::layout::Alignment::Center)
-        .style(Style::default().bg(Color::Black));
+        .style(Style::default().bg(theme.overlay_bg));
 
     f.render_widget(paragraph, area);
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+pub(crate) fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -0,0 +1,54 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{input_overlay::centered_rect, theme::Theme};
+use crate::utils::admin::Session;
+use crate::utils::text::{single_line, truncate_to_width};
+
+/// Asks before cancelling the query of a session, or ending the session with `kill`
+pub fn draw_kill_overlay(f: &mut Frame, session: &Session, kill: bool, theme: &Theme) {
+    let area = centered_rect(60, 35, f.area());
+
+    f.render_widget(Clear, area);
+
+    let (title, color) = if kill { ("Terminate Session", theme.error) } else { ("Cancel Query", theme.warning) };
+    let block = theme.block()
+        .title(title)
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(color).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let action = if kill {
+        format!("Terminate session {} of {}?", session.id, session.user)
+    } else {
+        format!("Cancel the running query of session {} ({})?", session.id, session.user)
+    };
+    let consequence = if kill {
+        "Its connection is closed and an open transaction is rolled back"
+    } else {
+        "The session stays connected, its statement fails with an error"
+    };
+    let query = if session.query.is_empty() { "(no query)".to_string() } else { single_line(&session.query) };
+    let width = area.width.saturating_sub(4) as usize;
+    let text = vec![
+        Line::from(""),
+        Line::from(Span::styled(action, Style::default().fg(color))),
+        Line::from(Span::styled(consequence, plain)),
+        Line::from(""),
+        Line::from(Span::styled(truncate_to_width(&query, width), Style::default().fg(theme.muted).not_bold())),
+        Line::from(""),
+        Line::from(Span::styled("y / Enter: Confirm | n / Esc: Cancel", plain)),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,164 @@
+use crate::gui::theme::Theme;
+use ratatui::{
+    style::{Modifier, Style},
+    text::Span,
+};
+
+/// The cursor of a one-line text input, shared by the connection form fields and the
+/// overlay prompts. The text lives with its owner and is passed in, so one cursor can
+/// follow whichever field is selected. Positions count characters and are clamped to
+/// the text, a cursor past the end sits at the end.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineInput {
+    cursor: usize,
+}
+
+impl LineInput {
+    /// A cursor that starts after the last character
+    pub fn at_end() -> Self {
+        Self { cursor: usize::MAX }
+    }
+
+    fn position(&self, text: &str) -> usize {
+        self.cursor.min(text.chars().count())
+    }
+
+    /// Byte offset of the character at `position`, for editing the string in place
+    fn byte_offset(text: &str, position: usize) -> usize {
+        text.char_indices().nth(position).map_or(text.len(), |(i, _)| i)
+    }
+
+    pub fn insert(&mut self, text: &mut String, c: char) {
+        let position = self.position(text);
+        text.insert(Self::byte_offset(text, position), c);
+        self.cursor = position + 1;
+    }
+
+    pub fn backspace(&mut self, text: &mut String) {
+        let position = self.position(text);
+        if position > 0 {
+            text.remove(Self::byte_offset(text, position - 1));
+            self.cursor = position - 1;
+        }
+    }
+
+    pub fn delete(&mut self, text: &mut String) {
+        let position = self.position(text);
+        if position < text.chars().count() {
+            text.remove(Self::byte_offset(text, position));
+        }
+    }
+
+    /// Removes the word before the cursor, as Ctrl+W does in a shell
+    pub fn delete_word(&mut self, text: &mut String) {
+        let end = self.position(text);
+        let start = word_start(text, end);
+        text.replace_range(Self::byte_offset(text, start)..Self::byte_offset(text, end), "");
+        self.cursor = start;
+    }
+
+    pub fn left(&mut self, text: &str) {
+        self.cursor = self.position(text).saturating_sub(1);
+    }
+
+    pub fn right(&mut self, text: &str) {
+        self.cursor = (self.position(text) + 1).min(text.chars().count());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self, text: &str) {
+        self.cursor = text.chars().count();
+    }
+
+    pub fn word_left(&mut self, text: &str) {
+        self.cursor = word_start(text, self.position(text));
+    }
+
+    /// To the end of the next word
+    pub fn word_right(&mut self, text: &str) {
+        let chars: Vec<char> = text.chars().collect();
+        let mut position = self.position(text);
+        while position < chars.len() && !is_word(chars[position]) {
+            position += 1;
+        }
+        while position < chars.len() && is_word(chars[position]) {
+            position += 1;
+        }
+        self.cursor = position;
+    }
+
+    /// `text` in `style` with the cursor drawn where it is: over the character it's
+    /// on, or as the theme's cursor glyph after the last one
+    pub fn spans(&self, text: &str, style: Style, theme: &Theme) -> Vec<Span<'static>> {
+        let position = self.position(text);
+        let split = Self::byte_offset(text, position);
+        let (before, rest) = text.split_at(split);
+        let mut spans = vec![Span::styled(before.to_string(), style)];
+        match rest.chars().next() {
+            Some(under) => {
+                // Reversed rather than colored, a list's highlight would paint over the colors
+                spans.push(Span::styled(under.to_string(), style.add_modifier(Modifier::REVERSED)));
+                spans.push(Span::styled(rest[under.len_utf8()..].to_string(), style));
+            }
+            None => spans.push(Span::styled(theme.glyphs.cursor, style)),
+        }
+        spans
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Where the word before `position` starts, skipping the separators in between
+fn word_start(text: &str, position: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut start = position;
+    while start > 0 && !is_word(chars[start - 1]) {
+        start -= 1;
+    }
+    while start > 0 && is_word(chars[start - 1]) {
+        start -= 1;
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_in_the_middle_of_multibyte_text() {
+        let mut text = "héllo".to_string();
+        let mut input = LineInput::at_end();
+        input.left(&text);
+        input.left(&text);
+        input.insert(&mut text, 'X');
+        assert_eq!(text, "hélXlo");
+        input.backspace(&mut text);
+        input.backspace(&mut text);
+        assert_eq!(text, "hélo");
+        input.home();
+        input.delete(&mut text);
+        assert_eq!(text, "élo");
+    }
+
+    #[test]
+    fn jumps_and_deletes_words() {
+        let mut text = "db.example-host.com".to_string();
+        let mut input = LineInput::at_end();
+        input.word_left(&text);
+        input.word_left(&text);
+        assert_eq!(input.position(&text), "db.example-".len());
+        input.word_right(&text);
+        assert_eq!(input.position(&text), "db.example-host".len());
+        input.delete_word(&mut text);
+        assert_eq!(text, "db.example-.com");
+        input.end(&text);
+        input.delete_word(&mut text);
+        assert_eq!(text, "db.example-.");
+    }
+}
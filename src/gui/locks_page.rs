@@ -0,0 +1,238 @@
+use crate::gui::{format_age, theme::Theme};
+use crate::utils::admin::{LockSession, Session, blocking_tree};
+use crate::utils::text::{single_line, truncate_to_width};
+use anyhow::Result;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// How often the page reloads while live refresh is on
+const LIVE_INTERVAL: Duration = Duration::from_secs(2);
+
+pub enum LocksAction {
+    Back,
+    /// Loads the lock waits again
+    Refresh,
+    /// Confirmed: end session `id`, or only cancel its query without `kill`
+    Stop { id: i64, kill: bool },
+}
+
+pub struct LocksPage {
+    /// Name of the connection whose server is listed
+    pub(crate) connection: String,
+    pub(crate) sessions: Vec<LockSession>,
+    /// (depth, index into `sessions`) in display order
+    pub(crate) tree: Vec<(usize, usize)>,
+    pub(crate) table_state: TableState,
+    pub(crate) task: Option<JoinHandle<Result<Vec<LockSession>>>>,
+    pub(crate) error: Option<String>,
+    /// Local time of the last completed refresh
+    pub(crate) refreshed: Option<String>,
+    /// Reload every few seconds
+    pub(crate) live: bool,
+    pub(crate) last_load: Option<Instant>,
+    /// Where the table was last drawn, for mouse clicks
+    pub(crate) table_area: Rect,
+    /// Session waiting for the user to confirm, true to terminate it
+    pub(crate) pending_stop: Option<(Session, bool)>,
+}
+
+impl LocksPage {
+    pub fn new() -> Self {
+        Self {
+            connection: String::new(),
+            sessions: Vec::new(),
+            tree: Vec::new(),
+            table_state: TableState::default(),
+            task: None,
+            error: None,
+            refreshed: None,
+            live: true,
+            last_load: None,
+            table_area: Rect::default(),
+            pending_stop: None,
+        }
+    }
+
+    /// Starts over for `connection`, the first load is started by the caller
+    pub fn open(&mut self, connection: String) {
+        *self = Self::new();
+        self.connection = connection;
+    }
+
+    /// Takes over a load started in the background, dropping one still running
+    pub fn start_refresh(&mut self, task: JoinHandle<Result<Vec<LockSession>>>) {
+        if let Some(previous) = self.task.replace(task) {
+            previous.abort();
+        }
+        self.last_load = Some(Instant::now());
+    }
+
+    /// Whether live refresh wants the next load. Waits while a stop is being confirmed.
+    pub fn refresh_due(&self) -> bool {
+        self.live
+            && self.task.is_none()
+            && self.pending_stop.is_none()
+            && self.last_load.is_none_or(|at| at.elapsed() >= LIVE_INTERVAL)
+    }
+
+    /// Picks up the lock waits once the background load ends. The selection
+    /// stays on the same session when it is still there.
+    pub fn poll(&mut self) {
+        if !self.task.as_ref().is_some_and(|task| task.is_finished()) {
+            return;
+        }
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        match futures_util::FutureExt::now_or_never(task) {
+            Some(Ok(Ok(sessions))) => {
+                let selected_id = self.selected_session().map(|s| s.id);
+                self.tree = blocking_tree(&sessions);
+                self.sessions = sessions;
+                let index = selected_id
+                    .and_then(|id| self.tree.iter().position(|&(_, i)| self.sessions[i].session.id == id))
+                    .unwrap_or(0);
+                self.table_state.select((!self.tree.is_empty()).then_some(index));
+                self.refreshed = Some(chrono::Local::now().format("%H:%M:%S").to_string());
+                self.error = None;
+            }
+            Some(Ok(Err(e))) => self.error = Some(format!("{:#}", e)),
+            _ => self.error = Some("Loading the locks ended unexpectedly".to_string()),
+        }
+    }
+
+    pub fn selected_session(&self) -> Option<&Session> {
+        let &(_, index) = self.tree.get(self.table_state.selected()?)?;
+        self.sessions.get(index).map(|s| &s.session)
+    }
+
+    /// Asks to stop the selected session, leaving rsquid's own connection alone
+    pub fn request_stop(&mut self, kill: bool) {
+        let Some(session) = self.selected_session() else {
+            return;
+        };
+        if session.current {
+            self.error = Some("That is rsquid's own connection".to_string());
+            return;
+        }
+        self.pending_stop = Some((session.clone(), kill));
+    }
+
+    pub fn scroll_up(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some((selected + 1).min(self.tree.len().saturating_sub(1))));
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(7),
+                Constraint::Length(if self.error.is_some() { 4 } else { 3 }),
+            ])
+            .split(area);
+
+        let waiting = self.sessions.iter().filter(|s| !s.blocked_by.is_empty()).count();
+        let status = match (&self.refreshed, waiting) {
+            (None, _) => "loading...".to_string(),
+            (Some(time), 0) => format!("no lock waits at {}", time),
+            (Some(time), n) => format!("{} waiting at {}", n, time),
+        };
+        let live = if self.live { ", live" } else { "" };
+        let title = Paragraph::new(format!("Locks - {} ({}{})", self.connection, status, live))
+            .style(theme.title_style())
+            .alignment(Alignment::Center)
+            .block(theme.block());
+        f.render_widget(title, chunks[0]);
+
+        let header = Row::new(["Session", "User", "State", "Duration", "Waiting For", "Held", "Query"].map(|title| {
+            Cell::from(title).style(Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))
+        }))
+        .bottom_margin(1);
+        let rows = self.tree.iter().map(|&(depth, index)| {
+            let lock = &self.sessions[index];
+            let session = &lock.session;
+            let id = if depth == 0 {
+                session.id.to_string()
+            } else {
+                format!("{}{}{}", "   ".repeat(depth - 1), theme.glyphs.branch, session.id)
+            };
+            let style = if lock.blocked_by.is_empty() {
+                // Holds up the sessions below it
+                Style::default().fg(theme.error)
+            } else {
+                Style::default()
+            };
+            Row::new([
+                Cell::from(format!("{}{}", id, if session.current { " *" } else { "" })),
+                Cell::from(session.user.clone()),
+                Cell::from(session.state.clone()),
+                Cell::from(session.duration.map(format_age).unwrap_or_default()),
+                Cell::from(lock.waiting_for.clone()),
+                Cell::from(lock.locks_held.map(|n| n.to_string()).unwrap_or_default()),
+                Cell::from(truncate_to_width(&single_line(&session.query), 200)),
+            ])
+            .style(style)
+        });
+        let widths = [
+            Constraint::Length(18),
+            Constraint::Length(12),
+            Constraint::Length(20),
+            Constraint::Length(9),
+            Constraint::Length(36),
+            Constraint::Length(5),
+            Constraint::Min(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(theme.block().title("Blockers and the sessions waiting on them (* is rsquid)"))
+            .row_highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, chunks[1], &mut self.table_state);
+        self.table_area = chunks[1];
+
+        let query = self.selected_session().map(|s| s.query.clone()).unwrap_or_default();
+        let detail = Paragraph::new(query)
+            .block(theme.block().title("Query"))
+            .wrap(Wrap { trim: false });
+        f.render_widget(detail, chunks[2]);
+
+        let mut help_lines = vec![Line::from(format!(
+            "{}: Navigate | r: Refresh | l: Live Refresh | c: Cancel Query | k: Terminate Session | Esc: Back",
+            theme.glyphs.up_down
+        ))];
+        if let Some(err) = &self.error {
+            help_lines.push(Line::from(vec![
+                Span::styled("Error: ", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
+                Span::styled(err, Style::default().fg(theme.error)),
+            ]));
+        }
+        let help = Paragraph::new(help_lines)
+            .style(Style::default().fg(theme.help))
+            .alignment(Alignment::Center)
+            .block(theme.block())
+            .wrap(Wrap { trim: false });
+        f.render_widget(help, chunks[3]);
+
+        if let Some((session, kill)) = &self.pending_stop {
+            crate::gui::kill_overlay::draw_kill_overlay(f, session, *kill, theme);
+        }
+    }
+}
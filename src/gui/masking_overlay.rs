@@ -0,0 +1,67 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, List, ListItem, Paragraph, Wrap},
+};
+
+use crate::gui::{PendingMasking, input_overlay::centered_rect, theme::Theme};
+use crate::utils::text::truncate_to_width;
+
+/// The masking rule of each column, applied when results are copied or exported
+pub fn draw_masking_overlay(f: &mut Frame, pending: &mut PendingMasking, theme: &Theme) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Mask Columns")
+        .style(Style::default().bg(theme.overlay_bg).fg(theme.focus).bold());
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0), Constraint::Length(2)])
+        .split(inner);
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let muted = Style::default().fg(theme.muted).not_bold();
+    let intro = Paragraph::new(Span::styled(
+        "Masked columns are hidden in Y copies and rsquid exec output, the table here stays as is",
+        muted,
+    ))
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: false });
+    f.render_widget(intro, chunks[0]);
+
+    let width = chunks[1].width.saturating_sub(16) as usize;
+    let items: Vec<ListItem> = pending
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, (column, method))| {
+            let rule = match method {
+                Some(method) => Span::styled(format!("{:<8}", method.label()), Style::default().fg(theme.warning).bold()),
+                None => Span::styled(format!("{:<8}", "-"), muted),
+            };
+            let mut spans = vec![rule, Span::styled(truncate_to_width(column, width), plain)];
+            if index >= pending.in_results {
+                spans.push(Span::styled("  (not in these results)", muted));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(theme.highlight())
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, chunks[1], &mut pending.state);
+
+    let help = Paragraph::new(Span::styled(
+        "Up/Down: Select | Left/Right: Cycle | h: Hash | r: Redact | f: Fake | n: None | Enter: Save | Esc: Cancel",
+        plain,
+    ))
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: false });
+    f.render_widget(help, chunks[2]);
+}
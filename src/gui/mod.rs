@@ -3,17 +3,65 @@ mod new_connection;
 mod query_page;
 pub mod history;
 mod input_overlay;
+mod line_input;
+mod params_overlay;
+mod confirm_overlay;
+mod delete_overlay;
+mod quit_overlay;
+mod connecting_overlay;
+mod error_overlay;
+mod restore_overlay;
+mod row_overlay;
+mod dump_overlay;
+mod generate_overlay;
+mod kill_overlay;
+mod discovery_overlay;
+mod masking_overlay;
+mod palette_overlay;
+mod pipeline_overlay;
+mod schedule_overlay;
+mod server_info_overlay;
+pub mod theme;
+pub mod glyphs;
+mod settings;
+mod import_page;
+mod locks_page;
+mod notifications_page;
+mod sessions_page;
+mod sizes_page;
+mod split_view;
+pub mod toast;
 pub mod gui_helpers;
 
 pub use connection_list::*;
 pub use new_connection::*;
 pub use query_page::*;
 pub use history::*;
+pub use settings::*;
+pub use import_page::*;
+pub use locks_page::*;
+pub use notifications_page::*;
+pub use sessions_page::*;
+pub use sizes_page::*;
+pub use split_view::*;
+pub use line_input::*;
 
-use crate::utils::connection::ConnectionManager;
+use crate::utils::config::{Config, ConfigManager};
+use crate::utils::connection::{Connection, ConnectionManager};
+use crate::utils::pool_manager::PoolManager;
+use crate::utils::query_executor::QueryExecutor;
+use crate::utils::scripting::Scripts;
+use crate::utils::sql::Dialect;
+use crate::utils::state::{StateManager, UiState};
+use crate::utils::swap::{self, SwapFile, SwapManager};
+use theme::Theme;
 use anyhow::Result;
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent};
 use ratatui::Frame;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppState {
@@ -21,6 +69,24 @@ pub enum AppState {
     NewConnection,
     QueryPage,
     History,
+    Settings,
+    Import,
+    Sessions,
+    Locks,
+    Sizes,
+    Notifications,
+}
+
+/// How often the query buffers are written to the swap file
+const SWAP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A connection opened in a background task, so the UI keeps drawing while a slow host answers
+pub struct PendingConnection {
+    pub connection: Connection,
+    pub started: Instant,
+    /// Opens the split pane instead of the main query page
+    pub for_split: bool,
+    pub(crate) handle: JoinHandle<Result<Arc<QueryExecutor>>>,
 }
 
 pub struct App {
@@ -29,86 +95,617 @@ pub struct App {
     pub new_connection: NewConnectionPage,
     pub query_page: QueryPage,
     pub history_page: HistoryPage,
+    pub import_page: ImportPage,
+    pub sessions_page: SessionsPage,
+    pub locks_page: LocksPage,
+    pub sizes_page: SizesPage,
+    pub notifications_page: NotificationsPage,
+    pub settings_page: SettingsPage,
     pub connection_manager: ConnectionManager,
+    /// Saved connections, read once and again after every change made here
+    pub connections: Vec<Connection>,
+    /// Pools of the connections left recently, reused when going back to them
+    pub pools: PoolManager,
     pub error_message: Option<String>,
+    pub theme: Theme,
+    pub ui_state: UiState,
+    pub split: Option<SplitView>,
+    /// Set while the quit prompt is shown, with the work quitting would lose
+    pub quit_confirmation: Option<Vec<String>>,
+    pub should_quit: bool,
+    pub connecting: Option<PendingConnection>,
+    /// Buffers a crashed run left behind, until the user restores or discards them
+    pub recovered: Option<SwapFile>,
+    /// Buffers as last written to the swap file
+    pub swapped: SwapFile,
+    pub last_swap: Instant,
 }
 
 impl App {
     pub fn new() -> Result<Self> {
         let connection_manager = ConnectionManager::new()?;
         let history_page = HistoryPage::new()?;
+
+        // A broken config file shouldn't keep the app from starting
+        let (config, mut error_message) = match ConfigManager::new().and_then(|m| m.load_config()) {
+            Ok(config) => (config, None),
+            Err(e) => (Config::default(), Some(format!("{:#}", e))),
+        };
+        let connections = connection_manager.load_connections().unwrap_or_else(|e| {
+            error_message.get_or_insert(format!("Could not read the connections: {:#}", e));
+            Vec::new()
+        });
+        let theme = config.theme();
+        let ui_state = StateManager::new()
+            .and_then(|m| m.load_state())
+            .unwrap_or_default();
+
+        let mut connection_list = ConnectionListPage::new();
+        if let Some(name) = &ui_state.last_connection
+            && let Some(idx) = connections.iter().position(|c| &c.name == name)
+        {
+            connection_list.list_state.select(Some(idx));
+        }
+
+        let pools = PoolManager::new(config.warm_pools);
+        let settings_page = SettingsPage::new(&config);
+        let (scripts, script_errors) = Scripts::load(|message| toast::info(message));
+        for error in script_errors {
+            toast::error(error);
+        }
+        let mut query_page = QueryPage::new(config);
+        query_page.scripts = Arc::new(scripts);
+        query_page.max_results = ui_state.max_results;
+        query_page.panes = ui_state.panes;
         
         Ok(Self {
             state: AppState::ConnectionList,
-            connection_list: ConnectionListPage::new(),
+            connection_list,
             new_connection: NewConnectionPage::new(),
-            query_page: QueryPage::new(),
+            query_page,
             history_page,
+            import_page: ImportPage::new(),
+            sessions_page: SessionsPage::new(),
+            locks_page: LocksPage::new(),
+            sizes_page: SizesPage::new(),
+            notifications_page: NotificationsPage::new(),
+            settings_page,
             connection_manager,
-            error_message: None,
+            connections,
+            pools,
+            error_message,
+            theme,
+            ui_state,
+            split: None,
+            quit_confirmation: None,
+            should_quit: false,
+            connecting: None,
+            recovered: SwapManager::new().and_then(|m| m.load()).ok().flatten(),
+            swapped: SwapFile::default(),
+            last_swap: Instant::now(),
         })
     }
 
+    /// Connects straight to a saved connection by name, used at startup. Unknown or
+    /// unreachable connections leave the app on the connection list with an error.
+    pub fn open_named_connection(&mut self, name: &str) {
+        match self.connections.iter().position(|c| c.name == name) {
+            Some(idx) => {
+                self.connection_list.list_state.select(Some(idx));
+                self.start_connecting(self.connections[idx].clone(), false);
+            }
+            None => self.error_message = Some(format!("Unknown connection: {}", name)),
+        }
+    }
+
+    /// Reads the saved connections again after this app changed them
+    fn reload_connections(&mut self) {
+        match self.connection_manager.load_connections() {
+            Ok(connections) => self.connections = connections,
+            Err(e) => self.error_message = Some(format!("Could not read the connections: {:#}", e)),
+        }
+        self.pools.retain(&self.connections);
+    }
+
+    fn start_connecting(&mut self, conn: Connection, for_split: bool) {
+        if let Some(pending) = self.connecting.take() {
+            pending.handle.abort();
+        }
+        let handle = match self.pools.take(&conn) {
+            // Still goes through the pending connection, so both paths finish the same way
+            Some(executor) => tokio::spawn(async move { Ok(executor) }),
+            None => {
+                let config = self.query_page.config.clone();
+                let connection = conn.clone();
+                tokio::spawn(async move { QueryExecutor::new(&connection, &config).await.map(Arc::new) })
+            }
+        };
+        self.connecting = Some(PendingConnection {
+            connection: conn,
+            started: Instant::now(),
+            for_split,
+            handle,
+        });
+    }
+
+    /// Hands a finished connection attempt to its page and switches to it
+    async fn finish_connecting(&mut self) {
+        if !self.connecting.as_ref().is_some_and(|p| p.handle.is_finished()) {
+            return;
+        }
+        let Some(pending) = self.connecting.take() else {
+            return;
+        };
+        let conn = pending.connection;
+
+        let result = match pending.handle.await.map_err(anyhow::Error::from).and_then(|r| r) {
+            Ok(executor) if pending.for_split => {
+                let mut page = QueryPage::new(self.query_page.config.clone());
+                page.panes = self.query_page.panes;
+                page.scripts = self.query_page.scripts.clone();
+                let result = page.connect(conn.clone(), executor);
+                if result.is_ok() {
+                    self.close_split().await;
+                    self.split = Some(SplitView::new(page));
+                    self.connection_list.picking_split = false;
+                }
+                result
+            }
+            Ok(executor) => {
+                let result = self.query_page.connect(conn.clone(), executor);
+                if result.is_ok() {
+                    self.query_page.restore_session(&self.ui_state);
+                }
+                result
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => {
+                toast::success(format!("Connected to {}", conn.name));
+                if let Err(e) = self.query_page.scripts.on_connect(&conn) {
+                    toast::error(format!("Script on_connect failed: {:#}", e));
+                }
+                self.state = AppState::QueryPage;
+                self.error_message = None;
+            }
+            Err(e) => {
+                let message = conn.redact(&format!("Connection failed: {}", e));
+                // A split opened from the query page has no error line to show it in
+                if self.state == AppState::QueryPage {
+                    toast::error(message);
+                } else {
+                    self.error_message = Some(message);
+                }
+            }
+        }
+    }
+
+    /// The query page keys go to, the split pane when it is active
+    fn active_query_page(&mut self) -> &mut QueryPage {
+        match &mut self.split {
+            Some(split) if split.active => &mut split.page,
+            _ => &mut self.query_page,
+        }
+    }
+
+    /// Loads the process list of the active connection's server in the background
+    fn refresh_sessions(&mut self) {
+        if let Some(executor) = self.active_query_page().executor.clone() {
+            let task = tokio::spawn(async move { crate::utils::admin::list_sessions(&executor).await });
+            self.sessions_page.start_refresh(task);
+        }
+    }
+
+    /// LISTENs on `channels` in the background, replacing the channels listened on before
+    fn listen(&mut self, channels: Vec<String>) {
+        if let Some(executor) = self.active_query_page().executor.clone() {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let listened = channels.clone();
+            let task = tokio::spawn(async move { executor.listen(&listened, sender).await });
+            self.notifications_page.start_listening(channels, receiver, task);
+        }
+    }
+
+    /// Loads the lock waits of the active connection's server in the background
+    fn refresh_locks(&mut self) {
+        if let Some(executor) = self.active_query_page().executor.clone() {
+            let task = tokio::spawn(async move { crate::utils::admin::list_locks(&executor).await });
+            self.locks_page.start_refresh(task);
+        }
+    }
+
+    /// Cancels the query of another session, or terminates it with `kill`
+    async fn stop_session(&mut self, id: i64, kill: bool) {
+        let Some(executor) = self.active_query_page().executor.clone() else {
+            return;
+        };
+        match crate::utils::admin::stop_session(&executor, id, kill).await {
+            Ok(()) if kill => toast::success(format!("Terminated session {}", id)),
+            Ok(()) => toast::success(format!("Cancelled the query of session {}", id)),
+            Err(e) => toast::error(format!("Could not stop session {}: {:#}", id, e)),
+        }
+    }
+
+    /// Loads the sizes of the sizes page's scope in the background
+    fn refresh_sizes(&mut self) {
+        if let Some(executor) = self.active_query_page().executor.clone() {
+            let scope = self.sizes_page.scope;
+            let task = tokio::spawn(async move { crate::utils::admin::list_sizes(&executor, scope).await });
+            self.sizes_page.start_refresh(task);
+        }
+    }
+
+    async fn close_split(&mut self) {
+        if let Some(mut split) = self.split.take()
+            && let Some((connection, executor)) = split.page.disconnect()
+        {
+            self.pools.release(connection, executor).await;
+        }
+    }
+
+    /// Work that doesn't survive a restart. The main query buffer isn't listed,
+    /// it is saved with the UI state on exit.
+    fn unsaved_work(&self) -> Vec<String> {
+        let mut reasons = Vec::new();
+        let split = self.split.as_ref().map(|s| &s.page);
+        for page in std::iter::once(&self.query_page).chain(split) {
+            if page.running_query.is_some() {
+                reasons.push("A query is still running".to_string());
+            }
+            if let Some(dump) = &page.running_dump {
+                reasons.push(format!("A dump to {} is still being written", dump.path));
+            }
+            if let Some(generate) = &page.running_generate {
+                reasons.push(format!("Test rows are still being inserted into {}", generate.table));
+            }
+            if let Some(n) = page.transaction_statements {
+                reasons.push(format!("A transaction is open ({} statements)", n));
+            }
+            if !page.schedule.is_empty() {
+                reasons.push(format!("{} scheduled queries haven't run yet", page.schedule.len()));
+            }
+        }
+        if split.is_some_and(|page| !page.query.trim().is_empty()) {
+            reasons.push("The split pane has an unsaved query".to_string());
+        }
+        if self.import_page.task.is_some() {
+            reasons.push("An import is still running".to_string());
+        }
+        match self.state {
+            AppState::NewConnection => reasons.push("The connection form has unsaved changes".to_string()),
+            AppState::Settings => reasons.push("Settings have unsaved changes".to_string()),
+            _ => {}
+        }
+        reasons
+    }
+
+    /// Quits right away unless work would be lost or `always_confirm` is set
+    fn request_quit(&mut self, always_confirm: bool) {
+        let reasons = self.unsaved_work();
+        if reasons.is_empty() && !always_confirm {
+            self.should_quit = true;
+        } else {
+            self.quit_confirmation = Some(reasons);
+        }
+    }
+
+    /// Writes the working context to disk so the next launch picks up where this one left off
+    pub fn save_ui_state(&mut self) -> Result<()> {
+        self.query_page.remember_session(&mut self.ui_state);
+        StateManager::new()?.save_state(&self.ui_state)
+    }
+
+    /// Keeps the swap file in step with the query buffers. The panic hook gets every
+    /// change, the file only one write per `SWAP_INTERVAL`.
+    fn update_swap(&mut self) {
+        // The file still holds the crashed run's buffers until the user decides
+        if self.recovered.is_some() {
+            return;
+        }
+        let mut current = SwapFile {
+            connection: self.query_page.connection.as_ref().map(|c| c.name.clone()),
+            ..SwapFile::default()
+        };
+        let split = self.split.as_ref().map(|s| &s.page);
+        for page in std::iter::once(&self.query_page).chain(split) {
+            if let Some(conn) = &page.connection
+                && !page.query.trim().is_empty()
+            {
+                current.queries.insert(conn.name.clone(), page.query.clone());
+            }
+        }
+        current.saved_at = self.swapped.saved_at.clone();
+        if current == self.swapped {
+            return;
+        }
+        current.saved_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        swap::record(&current);
+        if self.last_swap.elapsed() >= SWAP_INTERVAL
+            && let Ok(manager) = SwapManager::new()
+            && manager.save(&current).is_ok()
+        {
+            self.swapped = current;
+            self.last_swap = Instant::now();
+        }
+    }
+
+    /// Removes the swap file on a clean exit, unless its recovery is still undecided
+    pub fn remove_swap(&self) -> Result<()> {
+        if self.recovered.is_none() {
+            SwapManager::new()?.remove()?;
+        }
+        Ok(())
+    }
+
+    /// Hands the recovered buffers to their connections, connecting to the main one
+    fn restore_recovered(&mut self) {
+        let Some(recovered) = self.recovered.take() else {
+            return;
+        };
+        for (name, query) in &recovered.queries {
+            self.ui_state.queries.insert(name.clone(), query.clone());
+            if self.query_page.connection.as_ref().is_some_and(|c| &c.name == name) {
+                self.query_page.set_query(query.clone());
+            }
+        }
+        if self.query_page.connection.is_none()
+            && self.connecting.is_none()
+            && let Some(name) = &recovered.connection
+        {
+            self.open_named_connection(name);
+        }
+        SwapManager::new().and_then(|m| m.remove()).ok();
+    }
+
+    /// Called once per loop iteration to pick up background work. Returns whether a
+    /// background task changed what's on screen without making the app busy.
+    pub async fn on_tick(&mut self) -> bool {
+        self.finish_connecting().await;
+        self.update_swap();
+        self.query_page.poll_running_query();
+        self.query_page.poll_background_tasks();
+        self.query_page.poll_watch();
+        self.query_page.poll_schedule();
+        if let Some(split) = &mut self.split {
+            split.page.poll_running_query();
+            split.page.poll_background_tasks();
+            split.page.poll_watch();
+            split.page.poll_schedule();
+        }
+        self.connection_list.poll_discovery();
+        self.connection_list.expire_undo();
+        self.import_page.poll();
+        self.sessions_page.poll();
+        self.locks_page.poll();
+        if self.state == AppState::Locks && self.locks_page.refresh_due() {
+            self.refresh_locks();
+        }
+        self.sizes_page.poll();
+        self.notifications_page.poll();
+        // Both are checked, each page keeps its own last seen version
+        self.query_page.take_health_change()
+            | self.split.as_mut().is_some_and(|split| split.page.take_health_change())
+    }
+
+    /// Whether the screen changes without input: timers, progress, live pages and
+    /// toasts counting down. Idle frames are skipped otherwise.
+    pub fn is_busy(&self) -> bool {
+        self.connecting.is_some()
+            || self.query_page.is_busy()
+            || self.split.as_ref().is_some_and(|split| split.page.is_busy())
+            || self.connection_list.discovery.as_ref().is_some_and(|d| d.task.is_some())
+            || self.import_page.task.is_some()
+            || self.sessions_page.task.is_some()
+            || self.locks_page.task.is_some()
+            || self.sizes_page.task.is_some()
+            || self.notifications_page.task.is_some()
+            || (self.state == AppState::Locks && self.locks_page.live)
+            || toast::any()
+    }
+
     pub fn render(&mut self, f: &mut Frame) {
         let area = f.area();
         match self.state {
             AppState::ConnectionList => {
                 self.connection_list
-                    .render(f, area, &self.connection_manager, &self.error_message);
+                    .render(f, area, &self.connections, &self.error_message, &self.theme);
             }
             AppState::NewConnection => {
-                self.new_connection.render(f, area);
-            }
-            AppState::QueryPage => {
-                self.query_page.render(f, area);
+                self.new_connection.render(f, area, &self.theme);
             }
+            AppState::QueryPage => match &mut self.split {
+                Some(split) => split.render(f, area, &mut self.query_page, &self.theme),
+                None => self.query_page.render(f, area, &self.theme),
+            },
             AppState::History => {
-                self.history_page.render(f, area);
+                self.history_page.render(f, area, &self.theme);
+            }
+            AppState::Settings => {
+                self.settings_page.render(f, area, &self.theme);
+            }
+            AppState::Import => {
+                self.import_page.render(f, area, &self.theme);
+            }
+            AppState::Sessions => {
+                self.sessions_page.render(f, area, &self.theme);
+            }
+            AppState::Locks => {
+                self.locks_page.render(f, area, &self.theme);
             }
+            AppState::Sizes => {
+                self.sizes_page.render(f, area, &self.theme);
+            }
+            AppState::Notifications => {
+                self.notifications_page.render(f, area, &self.theme);
+            }
+        }
+        if let Some(pending) = &self.connecting {
+            connecting_overlay::draw_connecting_overlay(f, pending, &self.theme);
+        }
+        toast::render(f, area, &self.theme);
+        if let Some(recovered) = &self.recovered {
+            restore_overlay::draw_restore_overlay(f, recovered, &self.theme);
+        }
+        if let Some(reasons) = &self.quit_confirmation {
+            quit_overlay::draw_quit_overlay(f, reasons, &self.theme);
         }
     }
 
+    pub async fn handle_mouse(&mut self, event: MouseEvent) -> Result<()> {
+        if self.connecting.is_some() || self.recovered.is_some() || self.quit_confirmation.is_some() {
+            return Ok(());
+        }
+        match self.state {
+            AppState::ConnectionList => {
+                let len = self.connections.len() + 1;
+                self.connection_list.handle_mouse(event, len);
+            }
+            AppState::NewConnection => self.new_connection.handle_mouse(event),
+            AppState::QueryPage => {
+                if let Some(split) = &mut self.split {
+                    if split.page.contains(&event) {
+                        split.active = true;
+                    } else if self.query_page.contains(&event) {
+                        split.active = false;
+                    }
+                }
+                self.active_query_page().handle_mouse(event).await?;
+            }
+            AppState::History => self.history_page.handle_mouse(event),
+            AppState::Settings => self.settings_page.handle_mouse(event),
+            AppState::Import => self.import_page.handle_mouse(event),
+            AppState::Sessions => self.sessions_page.handle_mouse(event),
+            AppState::Locks => self.locks_page.handle_mouse(event),
+            AppState::Sizes => self.sizes_page.handle_mouse(event),
+            AppState::Notifications => self.notifications_page.handle_mouse(event),
+        }
+        Ok(())
+    }
+
     pub async fn handle_input(&mut self, key: KeyEvent) -> Result<()> {
+        if self.quit_confirmation.is_some() {
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter) {
+                self.should_quit = true;
+            }
+            self.quit_confirmation = None;
+            return Ok(());
+        }
+        if key.code == KeyCode::Char('q') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.request_quit(false);
+            return Ok(());
+        }
+        if self.recovered.is_some() {
+            match key.code {
+                KeyCode::Char('r') | KeyCode::Enter => self.restore_recovered(),
+                KeyCode::Char('d') | KeyCode::Esc => {
+                    self.recovered = None;
+                    SwapManager::new().and_then(|m| m.remove()).ok();
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        if let Some(pending) = &self.connecting {
+            if key.code == KeyCode::Esc {
+                pending.handle.abort();
+                self.connecting = None;
+                toast::info("Connection attempt aborted");
+            }
+            return Ok(());
+        }
+        if self.state == AppState::ConnectionList
+            && !self.connection_list.picking_split
+            && self.connection_list.discovery.is_none()
+            && self.connection_list.pending_delete.is_none()
+        {
+            // Esc means back everywhere else, so quitting with it is always confirmed
+            if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+                self.request_quit(key.code == KeyCode::Esc);
+                return Ok(());
+            }
+        }
+
         if self.state == AppState::ConnectionList && self.error_message.is_some() {
             self.error_message = None;
         }
 
+        if self.state == AppState::ConnectionList && self.connection_list.picking_split && key.code == KeyCode::Esc {
+            self.connection_list.picking_split = false;
+            self.state = AppState::QueryPage;
+            return Ok(());
+        }
+
         match self.state {
             AppState::ConnectionList => {
-                if let Some(action) = self.connection_list.handle_input(key, key.kind) {
+                if let Some(action) = self.connection_list.handle_input(key, key.kind, self.connections.len()) {
                     match action {
                         ConnectionListAction::NewConnection => {
                             self.state = AppState::NewConnection;
                             self.new_connection.reset();
                         }
                         ConnectionListAction::SelectConnection(idx) => {
-                            let connections = self.connection_manager.load_connections()?;
-                            if idx < connections.len() {
-                                let conn = connections[idx].clone();
-                                match self.query_page.connect(conn).await {
-                                    Ok(_) => {
-                                        self.state = AppState::QueryPage;
-                                        self.error_message = None;
-                                    }
-                                    Err(e) => {
-                                        self.error_message =
-                                            Some(format!("Connection failed: {}", e));
-                                    }
-                                }
+                            if let Some(conn) = self.connections.get(idx).cloned() {
+                                self.start_connecting(conn, self.connection_list.picking_split);
                             }
                         }
                         ConnectionListAction::DeleteConnection(idx) => {
-                            self.connection_manager.delete_connection(idx)?;
+                            if let Some(conn) = self.connections.get(idx).cloned() {
+                                self.connection_manager.delete_connection(idx)?;
+                                self.reload_connections();
+                                toast::info(format!("Deleted connection {} (u: Undo)", conn.name));
+                                self.connection_list.remember_deleted(idx, conn);
+                            }
+                        }
+                        ConnectionListAction::UndoDelete => {
+                            if let Some(deleted) = self.connection_list.deleted.take() {
+                                let name = deleted.connection.name.clone();
+                                self.connection_manager.insert_connection(deleted.index, deleted.connection)?;
+                                self.reload_connections();
+                                self.connection_list.list_state.select(Some(deleted.index.min(self.connections.len().saturating_sub(1))));
+                                toast::success(format!("Restored connection {}", name));
+                            }
+                        }
+                        ConnectionListAction::MoveConnection(from, to) => {
+                            self.connection_manager.move_connection(from, to)?;
+                            self.reload_connections();
+                            self.connection_list.list_state.select(Some(to));
                         }
                         ConnectionListAction::ModifyConnection(idx) => {
-                            let connections = self.connection_manager.load_connections()?;
-                            if idx < connections.len() {
+                            if let Some(conn) = self.connections.get(idx) {
                                 self.new_connection.reset();
-                                self.new_connection.load_connection(&connections[idx]);
+                                self.new_connection.load_connection(conn);
                                 self.new_connection.modifying_index = Some(idx);
                                 self.state = AppState::NewConnection;
                             }
                         }
+                        ConnectionListAction::Discover(source) => {
+                            let task = match source {
+                                DiscoverySource::Docker => tokio::spawn(crate::utils::docker::discover()),
+                                DiscoverySource::Dbt => tokio::spawn(crate::utils::dbt::discover()),
+                            };
+                            self.connection_list.open_discovery(source, task);
+                        }
+                        ConnectionListAction::AddDiscovered(conn) => {
+                            let mut conn = *conn;
+                            // Another container or an older save may have the name already
+                            let base = conn.name.clone();
+                            let mut n = 2;
+                            while self.connections.iter().any(|c| c.name == conn.name) {
+                                conn.name = format!("{}-{}", base, n);
+                                n += 1;
+                            }
+                            self.connection_manager.save_connection(conn.clone())?;
+                            self.reload_connections();
+                            self.connection_list.discovery = None;
+                            self.connection_list.list_state.select(Some(self.connections.len().saturating_sub(1)));
+                            toast::success(format!("Added connection {}", conn.name));
+                        }
+                        ConnectionListAction::OpenSettings => {
+                            self.settings_page = SettingsPage::new(&self.query_page.config);
+                            self.state = AppState::Settings;
+                        }
                     }
                 }
             }
@@ -120,26 +717,140 @@ impl App {
                         }
                         NewConnectionAction::Save(conn) => {
                             self.connection_manager.save_connection(conn.clone())?;
+                            self.reload_connections();
+                            toast::success(format!("Saved connection {}", conn.name));
                             self.state = AppState::ConnectionList;
                         }
                         NewConnectionAction::Update(idx, conn) => {
                             self.connection_manager
                                 .update_connection(idx, conn.clone())?;
+                            self.reload_connections();
+                            toast::success(format!("Updated connection {}", conn.name));
                             self.state = AppState::ConnectionList;
                         }
                     }
                 }
             }
             AppState::QueryPage => {
-                if let Some(action) = self.query_page.handle_input(key, key.kind).await? {
+                if let Some(action) = self.active_query_page().handle_input(key, key.kind).await? {
                     match action {
+                        QueryPageAction::Back if self.split.as_ref().is_some_and(|s| s.active) => {
+                            self.close_split().await;
+                        }
                         QueryPageAction::Back => {
-                            self.query_page.disconnect().await;
+                            // Losing the saved context isn't worth interrupting the user over
+                            self.save_ui_state().ok();
+                            self.close_split().await;
+                            if let Some((connection, executor)) = self.query_page.disconnect() {
+                                self.pools.release(connection, executor).await;
+                            }
                             self.state = AppState::ConnectionList;
                         }
                         QueryPageAction::OpenHistory => {
                             self.state = AppState::History;
                         }
+                        QueryPageAction::ToggleSplit if self.split.is_some() => {
+                            self.close_split().await;
+                        }
+                        QueryPageAction::ToggleSplit => {
+                            if let Some(conn) = self.query_page.connection.clone() {
+                                self.start_connecting(conn, true);
+                            }
+                        }
+                        QueryPageAction::SwitchPane => {
+                            if let Some(split) = &mut self.split {
+                                split.active = !split.active;
+                            }
+                        }
+                        QueryPageAction::FlipSplit => {
+                            if let Some(split) = &mut self.split {
+                                split.flip();
+                            }
+                        }
+                        QueryPageAction::PickSplitConnection => {
+                            self.connection_list.picking_split = true;
+                            self.state = AppState::ConnectionList;
+                        }
+                        QueryPageAction::OpenImport => {
+                            let page = self.active_query_page();
+                            let tables = page.tables.iter().map(|t| t.name.clone()).collect();
+                            let target = (page.focus == Focus::Explorer)
+                                .then(|| page.selected_table().map(|t| t.name.clone()))
+                                .flatten();
+                            self.import_page.open(tables, target);
+                            self.state = AppState::Import;
+                        }
+                        QueryPageAction::SaveMasking(mut masking) => {
+                            let mut config = self.query_page.config.clone();
+                            // Hashes are only as private as their salt, pick one the first time
+                            if masking.salt.is_empty() {
+                                masking.salt = (0..16).map(|_| format!("{:02x}", rand::random::<u8>())).collect();
+                            }
+                            config.masking = masking;
+                            match ConfigManager::new().and_then(|m| m.save_config(&config)) {
+                                Ok(()) => {
+                                    let rules = config.masking.rules.len();
+                                    if let Some(split) = &mut self.split {
+                                        split.page.config.masking = config.masking.clone();
+                                    }
+                                    self.query_page.config = config;
+                                    toast::success(format!("Masking {} column{} in copies and exports", rules, if rules == 1 { "" } else { "s" }));
+                                }
+                                Err(e) => toast::error(format!("Could not save the masking rules: {:#}", e)),
+                            }
+                        }
+                        QueryPageAction::OpenSizes => {
+                            if let Some(conn) = &self.active_query_page().connection {
+                                let name = conn.name.clone();
+                                self.sizes_page.open(name);
+                                self.refresh_sizes();
+                                self.state = AppState::Sizes;
+                            }
+                        }
+                        QueryPageAction::OpenSessions => {
+                            let page = self.active_query_page();
+                            match (&page.connection, &page.executor) {
+                                (_, Some(executor)) if executor.dialect() == Dialect::Sqlite => {
+                                    toast::info("SQLite runs in-process and has no server sessions");
+                                }
+                                (Some(conn), Some(_)) => {
+                                    let name = conn.name.clone();
+                                    self.sessions_page.open(name);
+                                    self.refresh_sessions();
+                                    self.state = AppState::Sessions;
+                                }
+                                _ => {}
+                            }
+                        }
+                        QueryPageAction::OpenNotifications => {
+                            let page = self.active_query_page();
+                            match (&page.connection, &page.executor) {
+                                (_, Some(executor)) if executor.dialect() != Dialect::Postgres => {
+                                    toast::info("LISTEN/NOTIFY is only available on PostgreSQL");
+                                }
+                                (Some(conn), Some(_)) => {
+                                    let name = conn.name.clone();
+                                    self.notifications_page.open(name);
+                                    self.state = AppState::Notifications;
+                                }
+                                _ => {}
+                            }
+                        }
+                        QueryPageAction::OpenLocks => {
+                            let page = self.active_query_page();
+                            match (&page.connection, &page.executor) {
+                                (_, Some(executor)) if executor.dialect() == Dialect::Sqlite => {
+                                    toast::info("SQLite locks the whole file and has no lock waits to show");
+                                }
+                                (Some(conn), Some(_)) => {
+                                    let name = conn.name.clone();
+                                    self.locks_page.open(name);
+                                    self.refresh_locks();
+                                    self.state = AppState::Locks;
+                                }
+                                _ => {}
+                            }
+                        }
                     }
                 }
             }
@@ -150,11 +861,127 @@ impl App {
                             self.state = AppState::QueryPage;
                         }
                         HistoryPageAction::SelectQuery(query) => {
-                            self.query_page.set_query(query);
+                            self.active_query_page().set_query(query);
+                            self.state = AppState::QueryPage;
+                        }
+                        HistoryPageAction::ExecuteQuery(query) => {
+                            let page = self.active_query_page();
+                            page.set_query(query);
+                            page.request_execute();
+                            self.state = AppState::QueryPage;
+                        }
+                        HistoryPageAction::DeleteEntries(indices) => {
+                            if let Err(e) = self.history_page.delete_entries(&indices) {
+                                toast::error(format!("Could not delete history entries: {:#}", e));
+                            }
+                        }
+                    }
+                }
+            }
+            AppState::Import => {
+                if let Some(action) = self.import_page.handle_input(key, key.kind) {
+                    match action {
+                        ImportAction::Cancel => {
+                            self.state = AppState::QueryPage;
+                        }
+                        ImportAction::Start(job) => {
+                            if let Some(executor) = self.active_query_page().executor.clone() {
+                                let progress = self.import_page.progress.clone();
+                                self.import_page.task =
+                                    Some(tokio::spawn(crate::utils::import::run_import(executor, job, progress)));
+                                self.import_page.step = ImportStep::Running;
+                            }
+                        }
+                        ImportAction::Done => {
+                            if let Some(Ok(rows)) = &self.import_page.result {
+                                toast::success(format!("Imported {} rows into {}", rows, self.import_page.table));
+                            }
+                            self.active_query_page().load_tables(false);
+                            self.state = AppState::QueryPage;
+                        }
+                    }
+                }
+            }
+            AppState::Sessions => {
+                if let Some(action) = self.sessions_page.handle_input(key, key.kind) {
+                    match action {
+                        SessionsAction::Back => {
+                            if let Some(task) = self.sessions_page.task.take() {
+                                task.abort();
+                            }
+                            self.state = AppState::QueryPage;
+                        }
+                        SessionsAction::Refresh => self.refresh_sessions(),
+                        SessionsAction::Stop { id, kill } => {
+                            self.stop_session(id, kill).await;
+                            self.refresh_sessions();
+                        }
+                    }
+                }
+            }
+            AppState::Notifications => {
+                if let Some(action) = self.notifications_page.handle_input(key, key.kind) {
+                    match action {
+                        NotificationsAction::Back => {
+                            self.notifications_page.stop_listening();
+                            self.state = AppState::QueryPage;
+                        }
+                        NotificationsAction::Listen(channels) => self.listen(channels),
+                    }
+                }
+            }
+            AppState::Locks => {
+                if let Some(action) = self.locks_page.handle_input(key, key.kind) {
+                    match action {
+                        LocksAction::Back => {
+                            if let Some(task) = self.locks_page.task.take() {
+                                task.abort();
+                            }
+                            self.state = AppState::QueryPage;
+                        }
+                        LocksAction::Refresh => self.refresh_locks(),
+                        LocksAction::Stop { id, kill } => {
+                            self.stop_session(id, kill).await;
+                            self.refresh_locks();
+                        }
+                    }
+                }
+            }
+            AppState::Sizes => {
+                if let Some(action) = self.sizes_page.handle_input(key, key.kind) {
+                    match action {
+                        SizesAction::Back => {
+                            if let Some(task) = self.sizes_page.task.take() {
+                                task.abort();
+                            }
                             self.state = AppState::QueryPage;
                         }
-                        HistoryPageAction::DeleteQuery(query) => {
-                            self.history_page.delete_query(query).ok();
+                        SizesAction::Refresh => self.refresh_sizes(),
+                    }
+                }
+            }
+            AppState::Settings => {
+                if let Some(action) = self.settings_page.handle_input(key, key.kind) {
+                    match action {
+                        SettingsAction::Cancel => {
+                            self.state = AppState::ConnectionList;
+                        }
+                        SettingsAction::Save(config) => {
+                            match ConfigManager::new().and_then(|m| m.save_config(&config)) {
+                                Ok(()) => {
+                                    // Timeouts and retries take effect on the next connection,
+                                    // so pools opened with the old ones can't be reused
+                                    self.pools.clear();
+                                    self.pools.set_capacity(config.warm_pools);
+                                    self.theme = config.theme();
+                                    self.query_page.config = *config;
+                                    self.state = AppState::ConnectionList;
+                                    toast::success("Settings saved");
+                                }
+                                Err(e) => {
+                                    self.settings_page.error = Some(format!("Could not save settings: {:#}", e));
+                                }
+                            }
                         }
                     }
                 }
@@ -1,11 +1,19 @@
-use crate::utils::connection::Connection;
+use crate::gui::{LineInput, theme::Theme};
+use crate::utils::connection::{Connection, DB_TYPES, default_port};
+use crate::utils::kube::KubeTarget;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{List, ListItem, ListState, Paragraph},
 };
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long Ctrl+H shows the password before it's masked again
+const REVEAL_FOR: Duration = Duration::from_secs(10);
 
 pub enum NewConnectionAction {
     Cancel,
@@ -22,6 +30,16 @@ pub enum Field {
     Database,
     Username,
     Password,
+    ConfirmPassword,
+    Timeout,
+    Accent,
+    KubeResource,
+    KubeNamespace,
+    KubeContext,
+    Ssh,
+    CloudSql,
+    AwsIam,
+    Notes,
 }
 
 pub struct NewConnectionPage {
@@ -34,8 +52,25 @@ pub struct NewConnectionPage {
     pub(crate) database: String,
     pub(crate) username: String,
     pub(crate) password: String,
+    /// Typed again to catch typos nobody can see, filled in when modifying
+    pub(crate) confirm_password: String,
+    /// When Ctrl+H showed the password, it stays readable for a while on the password fields
+    pub(crate) revealed: Option<Instant>,
+    pub(crate) timeout: String,
+    pub(crate) accent: String,
+    pub(crate) kube_resource: String,
+    pub(crate) kube_namespace: String,
+    pub(crate) kube_context: String,
+    pub(crate) ssh: String,
+    pub(crate) cloud_sql: String,
+    pub(crate) aws_iam: bool,
+    pub(crate) notes: String,
+    /// Cursor in the selected field, back at the end whenever another field is selected
+    pub(crate) input: LineInput,
     pub(crate) error: Option<String>,
     pub(crate) modifying_index: Option<usize>,
+    /// Where the form was last drawn, for mouse clicks
+    pub(crate) list_area: Rect,
 }
 
 impl NewConnectionPage {
@@ -43,25 +78,30 @@ impl NewConnectionPage {
         let mut field_state = ListState::default();
         field_state.select(Some(0));
         Self {
-            fields: vec![
-                Field::Name,
-                Field::DbType,
-                Field::Host,
-                Field::Port,
-                Field::Database,
-                Field::Username,
-                Field::Password,
-            ],
+            fields: relevant_fields("mysql"),
             field_state,
             name: String::new(),
             db_type: String::from("mysql"),
             host: String::from("localhost"),
-            port: String::from("5432"),
+            port: String::from("3306"),
             database: String::new(),
             username: String::new(),
             password: String::new(),
+            confirm_password: String::new(),
+            revealed: None,
+            timeout: String::new(),
+            accent: String::new(),
+            kube_resource: String::new(),
+            kube_namespace: String::new(),
+            kube_context: String::new(),
+            ssh: String::new(),
+            cloud_sql: String::new(),
+            aws_iam: false,
+            notes: String::new(),
+            input: LineInput::at_end(),
             error: None,
             modifying_index: None,
+            list_area: Rect::default(),
         }
     }
 
@@ -69,7 +109,53 @@ impl NewConnectionPage {
         *self = Self::new();
     }
 
-    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+    /// Moves to the next or previous database type. The port follows along unless it
+    /// was changed from the old type's default.
+    pub fn cycle_db_type(&mut self, forward: bool) {
+        let current = DB_TYPES.iter().position(|t| *t == self.db_type).unwrap_or(0);
+        let next = if forward {
+            (current + 1) % DB_TYPES.len()
+        } else {
+            (current + DB_TYPES.len() - 1) % DB_TYPES.len()
+        };
+        let old_default = default_port(&self.db_type).map(|port| port.to_string());
+        if self.port.trim().is_empty() || old_default.as_deref() == Some(self.port.trim()) {
+            self.port = default_port(DB_TYPES[next]).unwrap_or(0).to_string();
+        }
+        self.db_type = DB_TYPES[next].to_string();
+        self.update_fields();
+    }
+
+    /// Ctrl+H: shows the password for a few seconds, or masks it again
+    pub fn toggle_password(&mut self) {
+        self.revealed = (!self.password_shown()).then(Instant::now);
+    }
+
+    fn password_shown(&self) -> bool {
+        let on_password = matches!(
+            self.field_state.selected().and_then(|i| self.fields.get(i)),
+            Some(Field::Password | Field::ConfirmPassword)
+        );
+        on_password && self.revealed.is_some_and(|at| at.elapsed() < REVEAL_FOR)
+    }
+
+    fn masked(&self, password: &str) -> String {
+        if self.password_shown() {
+            password.to_string()
+        } else {
+            "*".repeat(password.chars().count())
+        }
+    }
+
+    /// Shows only the fields that mean something for the database type
+    fn update_fields(&mut self) {
+        self.fields = relevant_fields(&self.db_type);
+        if let Some(selected) = self.field_state.selected() {
+            self.field_state.select(Some(selected.min(self.fields.len() - 1)));
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -87,62 +173,47 @@ impl NewConnectionPage {
         };
 
         let title = Paragraph::new(title_text)
-            .style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
+            .style(theme.title_style())
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(theme.block());
 
         f.render_widget(title, chunks[0]);
 
         // Form fields
-        let items: Vec<ListItem> = vec![
-            ListItem::new(format!("Name: {}", self.name)),
-            ListItem::new(format!(
-                "Database Type (mysql/mariadb/postgres/sqlite): {}",
-                self.db_type
-            )),
-            ListItem::new(format!("Host: {}", self.host)),
-            ListItem::new(format!("Port: {}", self.port)),
-            ListItem::new(format!("Database: {}", self.database)),
-            ListItem::new(format!("Username: {}", self.username)),
-            ListItem::new(format!("Password: {}", "*".repeat(self.password.len()))),
-        ];
-        
-        let highlight = {
-            #[cfg(target_os = "windows")]
-            {
-                Style::default()
-                    .fg(Color::White)
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
-
-            #[cfg(not(target_os = "windows"))]
-            {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
-        };
+        let selected = self.field_state.selected();
+        let items: Vec<ListItem> = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let mut line = self.field_line(field, selected == Some(i), theme);
+                if let Some(problem) = self.field_problem(field) {
+                    line.push_span(Span::styled(
+                        format!("  {} {}", theme.glyphs.invalid, problem),
+                        Style::default().fg(theme.error),
+                    ));
+                }
+                ListItem::new(line)
+            })
+            .collect();
 
         let list = List::new(items)
             .block(
-                Block::default()
-                    .borders(Borders::ALL)
+                theme.block()
                     .title("Connection Details"),
             )
-            .highlight_style(highlight)
+            .highlight_style(theme.highlight())
             .highlight_symbol(">> ");
 
         f.render_stateful_widget(list, chunks[1], &mut self.field_state);
+        self.list_area = chunks[1];
 
         // Help and error
         let mut help_lines = vec![Line::from(vec![
-            Span::raw("↑↓: Navigate | "),
+            Span::raw(format!("{}: Navigate | ", theme.glyphs.up_down)),
             Span::raw("Type: Edit | "),
+            Span::raw("Home/End - Ctrl+Left/Right: Move | "),
+            Span::raw("Ctrl+H: Show Password | "),
             Span::raw("Ctrl+S: Save | "),
             Span::raw("Esc: Cancel"),
         ])];
@@ -151,45 +222,191 @@ impl NewConnectionPage {
             help_lines.push(Line::from(vec![
                 Span::styled(
                     "Error: ",
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
                 ),
-                Span::styled(err, Style::default().fg(Color::Red)),
+                Span::styled(err, Style::default().fg(theme.error)),
             ]));
         }
 
         let help = Paragraph::new(help_lines)
-            .style(Style::default().fg(Color::Gray))
+            .style(Style::default().fg(theme.help))
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(theme.block());
         f.render_widget(help, chunks[2]);
     }
 
+    fn field_line(&self, field: &Field, selected: bool, theme: &Theme) -> Line<'static> {
+        let value = match field {
+            Field::DbType => self.db_type.clone(),
+            Field::AwsIam => (if self.aws_iam { "yes" } else { "no" }).to_string(),
+            Field::Password | Field::ConfirmPassword => self.masked(self.field_text(field).map_or("", |text| text)),
+            _ => self.field_text(field).cloned().unwrap_or_default(),
+        };
+        let mut spans = vec![Span::raw(format!("{}: ", field_label(field, &self.db_type)))];
+        if selected && self.field_text(field).is_some() {
+            spans.extend(self.input.spans(&value, Style::default(), theme));
+        } else {
+            spans.push(Span::raw(value));
+        }
+        if *field == Field::Accent && !self.accent.trim().is_empty() {
+            spans.push(Span::styled(
+                format!(" {}", theme.glyphs.swatch),
+                Style::default().fg(self.accent.trim().parse().unwrap_or(Color::Reset)),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    pub fn selected_field(&self) -> Option<&Field> {
+        self.fields.get(self.field_state.selected()?)
+    }
+
+    /// The text typed in `field`, None for the ones that are picked rather than typed
+    pub fn field_text(&self, field: &Field) -> Option<&String> {
+        match field {
+            Field::Name => Some(&self.name),
+            Field::Host => Some(&self.host),
+            Field::Port => Some(&self.port),
+            Field::Database => Some(&self.database),
+            Field::Username => Some(&self.username),
+            Field::Password => Some(&self.password),
+            Field::ConfirmPassword => Some(&self.confirm_password),
+            Field::Timeout => Some(&self.timeout),
+            Field::Accent => Some(&self.accent),
+            Field::KubeResource => Some(&self.kube_resource),
+            Field::KubeNamespace => Some(&self.kube_namespace),
+            Field::KubeContext => Some(&self.kube_context),
+            Field::Ssh => Some(&self.ssh),
+            Field::CloudSql => Some(&self.cloud_sql),
+            Field::Notes => Some(&self.notes),
+            Field::DbType | Field::AwsIam => None,
+        }
+    }
+
+    pub fn field_text_mut(&mut self, field: &Field) -> Option<&mut String> {
+        match field {
+            Field::Name => Some(&mut self.name),
+            Field::Host => Some(&mut self.host),
+            Field::Port => Some(&mut self.port),
+            Field::Database => Some(&mut self.database),
+            Field::Username => Some(&mut self.username),
+            Field::Password => Some(&mut self.password),
+            Field::ConfirmPassword => Some(&mut self.confirm_password),
+            Field::Timeout => Some(&mut self.timeout),
+            Field::Accent => Some(&mut self.accent),
+            Field::KubeResource => Some(&mut self.kube_resource),
+            Field::KubeNamespace => Some(&mut self.kube_namespace),
+            Field::KubeContext => Some(&mut self.kube_context),
+            Field::Ssh => Some(&mut self.ssh),
+            Field::CloudSql => Some(&mut self.cloud_sql),
+            Field::Notes => Some(&mut self.notes),
+            Field::DbType | Field::AwsIam => None,
+        }
+    }
+
+    /// What's wrong with the value typed in `field`, checked as it's typed. Empty
+    /// fields are only complained about on save.
+    pub fn field_problem(&self, field: &Field) -> Option<&'static str> {
+        fn filled(value: &str) -> Option<&str> {
+            Some(value.trim()).filter(|v| !v.is_empty())
+        }
+        match field {
+            Field::Host => host_problem(filled(&self.host)?),
+            Field::Port => match filled(&self.port)?.parse::<u16>() {
+                Ok(port) if port > 0 => None,
+                _ => Some("must be a number from 1 to 65535"),
+            },
+            Field::Database if self.db_type == "sqlite" => {
+                let file = filled(&self.database)?;
+                (file != ":memory:" && !Path::new(file).is_file()).then_some("no such file")
+            }
+            Field::ConfirmPassword => {
+                (!self.password.starts_with(filled(&self.confirm_password)?)).then_some("doesn't match the password")
+            }
+            Field::Timeout => filled(&self.timeout)?.parse::<u64>().is_err().then_some("must be a number of seconds"),
+            Field::Accent => filled(&self.accent)?.parse::<Color>().is_err().then_some("must be a color name or #rrggbb"),
+            _ => None,
+        }
+    }
+
     pub fn validate_and_save(&mut self) -> Option<NewConnectionAction> {
         if self.name.is_empty() {
             self.error = Some("Name is required".to_string());
             return None;
         }
-        if !["postgres", "mysql", "sqlite", "mariadb"].contains(&self.db_type.as_str()) {
+        if !DB_TYPES.contains(&self.db_type.as_str()) {
             self.error = Some("Invalid database type".to_string());
             return None;
         }
-        if self.host.is_empty() {
+        // A sqlite file has no server, whatever was typed in the hidden fields doesn't apply
+        let server = self.db_type != "sqlite";
+        if server && self.host.trim().is_empty() {
             self.error = Some("Host is required".to_string());
             return None;
         }
+        if !server && self.database.trim().is_empty() {
+            self.error = Some("Database file is required".to_string());
+            return None;
+        }
+        if server && self.password != self.confirm_password {
+            self.error = Some("Confirm Password: doesn't match the password".to_string());
+            self.field_state.select(self.fields.iter().position(|f| *f == Field::ConfirmPassword));
+            self.input = LineInput::at_end();
+            return None;
+        }
+        if let Some((index, problem)) =
+            self.fields.iter().enumerate().find_map(|(i, field)| Some((i, self.field_problem(field)?)))
+        {
+            self.error = Some(format!("{}: {}", field_name(&self.fields[index]), problem));
+            self.field_state.select(Some(index));
+            self.input = LineInput::at_end();
+            return None;
+        }
 
-        if self.host == "127.0.0.1" {
-            self.host = "localhost".to_string();
+        // Both were checked with the other fields, so only empty ones fail to parse
+        let query_timeout_secs = self.timeout.trim().parse::<u64>().ok();
+        let accent_color = self.accent.trim().parse::<Color>().ok();
+
+        let optional = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        let server_only = |value: &str| optional(value).filter(|_| server);
+        let kube = match server_only(&self.kube_resource) {
+            Some(resource) => Some(KubeTarget {
+                resource,
+                namespace: optional(&self.kube_namespace),
+                context: optional(&self.kube_context),
+            }),
+            None if server_only(&self.kube_namespace).is_some() || server_only(&self.kube_context).is_some() => {
+                self.error = Some("Kubernetes namespace and context need a resource to forward to".to_string());
+                return None;
+            }
+            None => None,
+        };
+        let ssh = server_only(&self.ssh);
+        if ssh.is_some() && kube.is_some() {
+            self.error = Some("SSH tunnels don't combine with Kubernetes".to_string());
+            return None;
+        }
+        let cloud_sql = server_only(&self.cloud_sql);
+        if cloud_sql.is_some() && (kube.is_some() || ssh.is_some()) {
+            self.error = Some("Cloud SQL instances don't combine with Kubernetes or SSH".to_string());
+            return None;
         }
 
         let conn = Connection {
             name: self.name.clone(),
             db_type: self.db_type.clone(),
-            host: self.host.clone(),
-            port: self.port.parse().unwrap_or(5432),
+            host: self.host.trim().to_string(),
+            port: self.port.trim().parse().unwrap_or_else(|_| default_port(&self.db_type).unwrap_or(5432)),
             database: self.database.clone(),
-            username: self.username.clone(),
-            password: self.password.clone(),
+            username: if server { self.username.clone() } else { String::new() },
+            password: if server { self.password.clone() } else { String::new() },
+            query_timeout_secs,
+            accent_color,
+            kube,
+            ssh,
+            cloud_sql,
+            aws_iam: self.aws_iam && server,
+            notes: optional(&self.notes),
         };
 
         if let Some(index) = self.modifying_index {
@@ -202,12 +419,111 @@ impl NewConnectionPage {
     pub fn load_connection(&mut self, connection: &Connection) {
         self.name = connection.name.clone();
         self.db_type = connection.db_type.clone();
+        self.update_fields();
         self.host = connection.host.clone();
         self.port = connection.port.to_string();
         self.database = connection.database.clone();
         self.username = connection.username.clone();
         self.password = connection.password.clone();
+        self.confirm_password = connection.password.clone();
+        self.timeout = connection
+            .query_timeout_secs
+            .map(|secs| secs.to_string())
+            .unwrap_or_default();
+        self.accent = connection
+            .accent_color
+            .map(|color| color.to_string())
+            .unwrap_or_default();
+        let kube = connection.kube.as_ref();
+        self.kube_resource = kube.map(|k| k.resource.clone()).unwrap_or_default();
+        self.kube_namespace = kube.and_then(|k| k.namespace.clone()).unwrap_or_default();
+        self.kube_context = kube.and_then(|k| k.context.clone()).unwrap_or_default();
+        self.ssh = connection.ssh.clone().unwrap_or_default();
+        self.cloud_sql = connection.cloud_sql.clone().unwrap_or_default();
+        self.aws_iam = connection.aws_iam;
+        self.notes = connection.notes.clone().unwrap_or_default();
         self.error = None;
         self.field_state.select(Some(0));
+        self.input = LineInput::at_end();
+    }
+}
+
+/// The form's fields for `db_type`, sqlite files have no server to reach or log in to
+fn relevant_fields(db_type: &str) -> Vec<Field> {
+    if db_type == "sqlite" {
+        return vec![Field::Name, Field::DbType, Field::Database, Field::Timeout, Field::Accent, Field::Notes];
+    }
+    vec![
+        Field::Name,
+        Field::DbType,
+        Field::Host,
+        Field::Port,
+        Field::Database,
+        Field::Username,
+        Field::Password,
+        Field::ConfirmPassword,
+        Field::Timeout,
+        Field::Accent,
+        Field::KubeResource,
+        Field::KubeNamespace,
+        Field::KubeContext,
+        Field::Ssh,
+        Field::CloudSql,
+        Field::AwsIam,
+        Field::Notes,
+    ]
+}
+
+fn field_label(field: &Field, db_type: &str) -> &'static str {
+    match field {
+        Field::Name => "Name",
+        Field::DbType => "Database Type (Left/Right or Enter to change)",
+        Field::Host => "Host",
+        Field::Port => "Port",
+        Field::Database if db_type == "sqlite" => "Database File",
+        Field::Database => "Database",
+        Field::Username => "Username",
+        Field::Password => "Password (or vault:path#field, aws:secret#key)",
+        Field::ConfirmPassword => "Confirm Password",
+        Field::Timeout => "Query Timeout (seconds, empty = global default)",
+        Field::Accent => "Accent Color (red, green, #ff8800, empty = theme default)",
+        Field::KubeResource => "Kubernetes Forward (svc/name or pod/name, empty = connect directly)",
+        Field::KubeNamespace => "Kubernetes Namespace (empty = context default)",
+        Field::KubeContext => "Kubernetes Context (empty = current context)",
+        Field::Ssh => "SSH Tunnel (host or ~/.ssh/config alias, empty = connect directly)",
+        Field::CloudSql => "Cloud SQL Instance (project:region:instance, empty = connect directly)",
+        Field::AwsIam => "AWS IAM Auth (Space to toggle, an RDS token replaces the password)",
+        Field::Notes => "Notes (shown in the connection list)",
+    }
+}
+
+/// How a field is called in save errors
+fn field_name(field: &Field) -> &'static str {
+    match field {
+        Field::Host => "Host",
+        Field::Port => "Port",
+        Field::Database => "Database file",
+        Field::Timeout => "Query timeout",
+        Field::Accent => "Accent color",
+        _ => "Field",
+    }
+}
+
+/// Accepts host names and IP addresses, IPv6 ones with or without brackets
+fn host_problem(host: &str) -> Option<&'static str> {
+    if let Some(inner) = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+        return inner.parse::<Ipv6Addr>().is_err().then_some("not an IPv6 address");
+    }
+    if host.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    if host.contains("://") {
+        return Some("just the host, without a scheme");
+    }
+    if host.contains(':') {
+        return Some("the port goes in the Port field");
     }
+    let valid_label =
+        |label: &str| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    (!host.trim_end_matches('.').split('.').all(valid_label)).then_some("not a host name or IP address")
 }
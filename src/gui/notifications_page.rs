@@ -0,0 +1,299 @@
+use crate::gui::theme::Theme;
+use crate::utils::postgres::Notification;
+use crate::utils::text::{single_line, truncate_to_width};
+use anyhow::Result;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::task::JoinHandle;
+
+/// Older notifications are dropped past this many
+const MAX_NOTIFICATIONS: usize = 10_000;
+
+pub enum NotificationsAction {
+    Back,
+    /// Replace the channels being listened on
+    Listen(Vec<String>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NotificationsInput {
+    Channels,
+    Filter,
+}
+
+pub struct NotificationsPage {
+    /// Name of the connection listening
+    pub(crate) connection: String,
+    pub(crate) channels: Vec<String>,
+    /// Oldest first
+    pub(crate) notifications: Vec<Notification>,
+    /// Indexes into the notifications the filter lets through
+    pub(crate) table_state: TableState,
+    pub(crate) receiver: Option<UnboundedReceiver<Notification>>,
+    pub(crate) task: Option<JoinHandle<Result<()>>>,
+    /// Case-insensitive text matched against channel and payload
+    pub(crate) filter: String,
+    /// The line being edited and what it is for
+    pub(crate) input: Option<(NotificationsInput, String)>,
+    /// Keeps the newest notification selected as more arrive
+    pub(crate) follow: bool,
+    pub(crate) error: Option<String>,
+    /// Where the table was last drawn, for mouse clicks
+    pub(crate) table_area: Rect,
+}
+
+impl NotificationsPage {
+    pub fn new() -> Self {
+        Self {
+            connection: String::new(),
+            channels: Vec::new(),
+            notifications: Vec::new(),
+            table_state: TableState::default(),
+            receiver: None,
+            task: None,
+            filter: String::new(),
+            input: None,
+            follow: true,
+            error: None,
+            table_area: Rect::default(),
+        }
+    }
+
+    /// Starts over for `connection`, asking for the channels first
+    pub fn open(&mut self, connection: String) {
+        self.stop_listening();
+        *self = Self::new();
+        self.connection = connection;
+        self.input = Some((NotificationsInput::Channels, String::new()));
+    }
+
+    /// Takes over a listener started in the background, stopping the previous one.
+    /// Notifications already received are kept.
+    pub fn start_listening(
+        &mut self,
+        channels: Vec<String>,
+        receiver: UnboundedReceiver<Notification>,
+        task: JoinHandle<Result<()>>,
+    ) {
+        self.stop_listening();
+        self.channels = channels;
+        self.receiver = Some(receiver);
+        self.task = Some(task);
+        self.error = None;
+    }
+
+    pub fn stop_listening(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.receiver = None;
+    }
+
+    /// Picks up what arrived since the last tick, and why the listener stopped if it did
+    pub fn poll(&mut self) {
+        let mut received = false;
+        if let Some(receiver) = self.receiver.as_mut() {
+            loop {
+                match receiver.try_recv() {
+                    Ok(notification) => {
+                        self.notifications.push(notification);
+                        received = true;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.receiver = None;
+                        break;
+                    }
+                }
+            }
+        }
+        if received {
+            let excess = self.notifications.len().saturating_sub(MAX_NOTIFICATIONS);
+            self.notifications.drain(..excess);
+            self.fix_selection();
+        }
+
+        if !self.task.as_ref().is_some_and(|task| task.is_finished()) {
+            return;
+        }
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        self.error = Some(match futures_util::FutureExt::now_or_never(task) {
+            Some(Ok(Err(e))) => format!("Stopped listening: {:#}", e),
+            _ => "Stopped listening".to_string(),
+        });
+    }
+
+    /// Notifications the filter lets through, oldest first
+    pub fn visible(&self) -> Vec<&Notification> {
+        let filter = self.filter.to_lowercase();
+        self.notifications
+            .iter()
+            .filter(|n| {
+                filter.is_empty()
+                    || n.channel.to_lowercase().contains(&filter)
+                    || n.payload.to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+
+    pub fn selected_notification(&self) -> Option<&Notification> {
+        self.visible().get(self.table_state.selected()?).copied()
+    }
+
+    /// Moves the selection to the newest notification when following, or keeps it in range
+    pub fn fix_selection(&mut self) {
+        let count = self.visible().len();
+        let selected = match self.table_state.selected() {
+            _ if count == 0 => None,
+            Some(selected) if !self.follow => Some(selected.min(count - 1)),
+            _ => Some(count - 1),
+        };
+        self.table_state.select(selected);
+    }
+
+    /// Channels typed in the prompt, separated by commas or spaces
+    pub fn submit_channels(&mut self, text: &str) -> Option<NotificationsAction> {
+        let mut channels: Vec<String> = Vec::new();
+        for channel in text.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty()) {
+            if !channels.iter().any(|c| c == channel) {
+                channels.push(channel.to_string());
+            }
+        }
+        if channels.is_empty() {
+            self.error = Some("Name at least one channel to listen on".to_string());
+            return None;
+        }
+        Some(NotificationsAction::Listen(channels))
+    }
+
+    pub fn clear(&mut self) {
+        self.notifications.clear();
+        self.table_state.select(None);
+        self.follow = true;
+    }
+
+    pub fn scroll_up(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+            self.follow = false;
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            let last = self.visible().len().saturating_sub(1);
+            self.table_state.select(Some((selected + 1).min(last)));
+            self.follow = selected + 1 >= last;
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let mut help_lines = Vec::new();
+        if let Some((input, text)) = &self.input {
+            let prompt = match input {
+                NotificationsInput::Channels => "Listen on (comma separated): ",
+                NotificationsInput::Filter => "Filter: ",
+            };
+            help_lines.push(Line::from(vec![
+                Span::styled(prompt, Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)),
+                Span::styled(format!("{}{}", text, theme.glyphs.cursor), Style::default().fg(theme.text)),
+            ]));
+        }
+        help_lines.push(Line::from(format!(
+            "{}: Navigate | c: Channels | /: Filter | f: Follow {} | x: Clear | Esc: Back",
+            theme.glyphs.up_down,
+            if self.follow { "on" } else { "off" }
+        )));
+        if let Some(err) = &self.error {
+            help_lines.push(Line::from(vec![
+                Span::styled("Error: ", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
+                Span::styled(err, Style::default().fg(theme.error)),
+            ]));
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(7),
+                Constraint::Length(help_lines.len() as u16 + 2),
+            ])
+            .split(area);
+
+        let status = if self.task.is_some() {
+            format!("listening on {} | {} received", self.channels.join(", "), self.notifications.len())
+        } else {
+            format!("not listening | {} received", self.notifications.len())
+        };
+        let title = Paragraph::new(format!("Notifications - {} ({})", self.connection, status))
+            .style(theme.title_style())
+            .alignment(Alignment::Center)
+            .block(theme.block());
+        f.render_widget(title, chunks[0]);
+
+        let visible = self.visible();
+        let header = Row::new(["Time", "Channel", "PID", "Payload"].map(|title| {
+            Cell::from(title).style(Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))
+        }))
+        .bottom_margin(1);
+        let rows = visible.iter().map(|n| {
+            Row::new([
+                Cell::from(n.received.format("%H:%M:%S%.3f").to_string()),
+                Cell::from(n.channel.clone()),
+                Cell::from(n.process_id.to_string()),
+                Cell::from(truncate_to_width(&single_line(&n.payload), 200)),
+            ])
+        });
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Length(20),
+            Constraint::Length(8),
+            Constraint::Min(10),
+        ];
+        let table_title = if self.filter.is_empty() {
+            "Received".to_string()
+        } else {
+            format!("Received matching {:?} ({} of {})", self.filter, visible.len(), self.notifications.len())
+        };
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(theme.block().title(table_title))
+            .row_highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, chunks[1], &mut self.table_state);
+        self.table_area = chunks[1];
+
+        // JSON payloads, as triggers often send, are easier to read indented
+        let payload = self
+            .selected_notification()
+            .map(|n| match serde_json::from_str::<serde_json::Value>(&n.payload) {
+                Ok(json) if json.is_object() || json.is_array() => {
+                    serde_json::to_string_pretty(&json).unwrap_or_else(|_| n.payload.clone())
+                }
+                _ => n.payload.clone(),
+            })
+            .unwrap_or_default();
+        let detail = Paragraph::new(payload)
+            .block(theme.block().title("Payload"))
+            .wrap(Wrap { trim: false });
+        f.render_widget(detail, chunks[2]);
+
+        let help = Paragraph::new(help_lines)
+            .style(Style::default().fg(theme.help))
+            .alignment(Alignment::Center)
+            .block(theme.block())
+            .wrap(Wrap { trim: false });
+        f.render_widget(help, chunks[3]);
+    }
+}
@@ -0,0 +1,60 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, List, ListItem, Paragraph},
+};
+
+use crate::gui::{Palette, input_overlay::centered_rect, theme::Theme};
+use crate::utils::scripting::Scripts;
+use crate::utils::text::truncate_to_width;
+
+/// Commands the user's scripts registered, narrowed down by what was typed
+pub fn draw_palette_overlay(f: &mut Frame, palette: &mut Palette, scripts: &Scripts, theme: &Theme) {
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Commands")
+        .style(Style::default().bg(theme.overlay_bg).fg(theme.accent).bold());
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let filter = Paragraph::new(Line::from(vec![
+        Span::styled("> ", Style::default().fg(theme.accent)),
+        Span::styled(palette.filter.as_str(), plain),
+    ]));
+    f.render_widget(filter, chunks[0]);
+
+    let width = chunks[1].width.saturating_sub(3) as usize;
+    let items: Vec<ListItem> = palette
+        .matches(scripts)
+        .map(|command| {
+            ListItem::new(Line::from(vec![
+                Span::styled(truncate_to_width(&command.label, width), plain),
+                Span::styled(format!("  {}", command.script), Style::default().fg(theme.muted).not_bold()),
+            ]))
+        })
+        .collect();
+    if items.is_empty() {
+        let none = Paragraph::new(Span::styled("No command matches", Style::default().fg(theme.muted).not_bold()))
+            .alignment(Alignment::Center);
+        f.render_widget(none, chunks[1]);
+    } else {
+        let list = List::new(items)
+            .highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut palette.state);
+    }
+
+    let help = Paragraph::new(Span::styled("Type: Filter | Up/Down: Select | Enter: Run | Esc: Close", plain))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[2]);
+}
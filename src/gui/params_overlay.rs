@@ -0,0 +1,61 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+};
+
+use crate::gui::{QueryPage, input_overlay::centered_rect, theme::Theme};
+use crate::utils::sql::Dialect;
+
+pub fn draw_params_overlay(f: &mut Frame, qpage: &QueryPage, theme: &Theme) {
+    let area = centered_rect(60, 40, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Bind Parameters")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let postgres = qpage
+        .executor
+        .as_ref()
+        .is_some_and(|e| e.dialect() == Dialect::Postgres);
+
+    let mut text = vec![Line::from("")];
+
+    for (i, value) in qpage.params.iter().enumerate() {
+        let label = if postgres {
+            format!("${}: ", i + 1)
+        } else {
+            format!("?{}: ", i + 1)
+        };
+        let selected = i == qpage.param_index;
+        let mut spans = vec![
+            Span::styled(label, Style::default().fg(if selected { theme.focus } else { theme.text }).not_bold()),
+            Span::styled(value.clone(), Style::default().fg(theme.success).not_bold()),
+        ];
+        if selected {
+            spans.push(Span::styled(theme.glyphs.cursor, Style::default().fg(theme.success).not_bold()));
+        }
+        text.push(Line::from(spans));
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "Numbers, true/false and NULL are typed, quote a value to force text",
+        Style::default().fg(theme.help).not_bold(),
+    )));
+    text.push(Line::from(Span::styled(
+        "Up/Down: Select | Enter: Execute | Esc: Cancel",
+        Style::default().fg(theme.text).not_bold(),
+    )));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
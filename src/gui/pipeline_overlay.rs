@@ -0,0 +1,46 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{PendingPipeline, input_overlay::centered_rect, theme::Theme};
+
+/// Asks for the pipeline the shown rows are run through, without querying again
+pub fn draw_pipeline_overlay(f: &mut Frame, pending: &PendingPipeline, theme: &Theme) {
+    let area = centered_rect(70, 35, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Transform Results")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let muted = Style::default().fg(theme.muted).not_bold();
+    let mut text = vec![
+        Line::from(""),
+        Line::from(Span::styled("Stages: select a, b * 2 as c, * | where c > 0 and b is not null | rename a as id", muted)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(pending.input.clone(), Style::default().fg(theme.success).not_bold()),
+            Span::styled(theme.glyphs.cursor, Style::default().fg(theme.success).not_bold()),
+        ]),
+        Line::from(""),
+    ];
+    if let Some(error) = &pending.error {
+        text.push(Line::from(Span::styled(error.clone(), Style::default().fg(theme.error).not_bold())));
+        text.push(Line::from(""));
+    }
+    text.push(Line::from(Span::styled("Enter: Apply (empty shows the rows as fetched) | Esc: Cancel", plain)));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
@@ -1,14 +1,53 @@
-use crate::utils::{connection::Connection, query_executor::QueryExecutor};
+use crate::gui::LineInput;
+use crate::gui::theme::Theme;
+use crate::utils::{columns::Columns, config::Config, connection::Connection, errors::ErrorDetail, state::PaneSizes, pipeline::Pipeline, query_executor::{QueryExecutor, RowBatch, RowStream, ServerInfo}, masking::{MaskMethod, Masking}, scripting::{ScriptCommand, Scripts}, sql::{Maintenance, UnfilteredWrite}, text::{display_width, single_line, truncate_to_width}, value::Value};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use tokio::{sync::oneshot, task::JoinHandle};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, TableState, Wrap},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{List, ListItem, ListState, Paragraph, Row, Table, TableState, Tabs, Wrap},
 };
 
+/// Widest a result column gets before its cells are cut with an ellipsis
+const MAX_COLUMN_WIDTH: usize = 40;
+/// Rows looked at to size the result columns
+const WIDTH_SAMPLE_ROWS: usize = 1000;
+/// Keepalive pings slower than this show the connection as degraded
+const SLOW_PING: Duration = Duration::from_millis(250);
+
 pub enum QueryPageAction {
     Back,
     OpenHistory,
+    ToggleSplit,
+    SwitchPane,
+    FlipSplit,
+    PickSplitConnection,
+    /// CSV or JSON import, appending to the table under the explorer cursor if there is one
+    OpenImport,
+    /// Process list of the server behind the active connection
+    OpenSessions,
+    /// Sessions waiting on locks and the sessions holding them
+    OpenLocks,
+    /// LISTEN on channels and watch the notifications arrive
+    OpenNotifications,
+    /// Sizes of the server's databases and the current database's tables
+    OpenSizes,
+    /// Confirmed column masking rules, saved to the config
+    SaveMasking(Masking),
+}
+
+/// Pane taking the whole query page, toggled with Ctrl+Z
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Maximized {
+    Editor,
+    Results,
 }
 
 #[derive(PartialEq)]
@@ -18,6 +57,205 @@ pub enum Focus {
     Explorer,
 }
 
+/// The result, how long it took and the notices the database raised while it ran
+pub type QueryOutcome = (Result<(Vec<RowBatch>, Option<RowStream>)>, Duration, Vec<String>);
+
+/// How the last keepalive ping went, shown next to the connection name
+#[derive(Clone, Copy)]
+pub struct Health {
+    pub latency: Duration,
+    pub reachable: bool,
+}
+
+/// A query executing in a background task, its outcome is picked up by `poll_running_query`
+pub struct RunningQuery {
+    pub query: String,
+    pub started: Instant,
+    /// A re-run of the watched query, which keeps the table on screen until it finishes
+    pub watched: bool,
+    /// Started from the schedule, its end is announced with a desktop notification
+    pub scheduled: bool,
+    pub(crate) receiver: oneshot::Receiver<QueryOutcome>,
+    pub(crate) handle: JoinHandle<()>,
+}
+
+/// A query re-run on an interval, toggled with Ctrl+W
+pub struct Watch {
+    /// The query as it was when the watch started, later edits don't change it
+    pub query: String,
+    pub interval: Duration,
+    pub runs: u64,
+    pub(crate) last_run: Option<Instant>,
+}
+
+/// When a scheduled query starts
+#[derive(Clone, Copy)]
+pub enum ScheduleTime {
+    /// As soon as nothing else runs on the page
+    AfterCurrent,
+    At(chrono::DateTime<chrono::Local>),
+}
+
+impl ScheduleTime {
+    pub fn is_due(&self, now: chrono::DateTime<chrono::Local>) -> bool {
+        match self {
+            ScheduleTime::AfterCurrent => true,
+            ScheduleTime::At(at) => *at <= now,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ScheduleTime::AfterCurrent => "after the current query".to_string(),
+            ScheduleTime::At(at) if at.date_naive() == chrono::Local::now().date_naive() => {
+                format!("at {}", at.format("%H:%M:%S"))
+            }
+            ScheduleTime::At(at) => format!("at {}", at.format("%a %H:%M:%S")),
+        }
+    }
+}
+
+/// A query queued with Alt+T, started by `poll_schedule`
+pub struct ScheduledQuery {
+    pub query: String,
+    pub time: ScheduleTime,
+}
+
+/// The schedule box, the query is queued as it was when the box opened
+pub struct PendingSchedule {
+    pub query: String,
+    pub input: String,
+    /// Why the time typed didn't parse
+    pub error: Option<String>,
+}
+
+/// Why a query is held back until the user confirms it
+pub enum Confirmation {
+    /// An UPDATE/DELETE without WHERE, only runs once the user types "yes"
+    UnfilteredWrite {
+        write: UnfilteredWrite,
+        estimated_rows: Option<u64>,
+    },
+    /// A SELECT the planner expects to return more rows than the configured threshold
+    LargeResult { estimated_rows: u64 },
+    /// Emptying a table from the explorer, only runs once the user types its name
+    EmptyTable {
+        table: String,
+        /// The catalog's estimate, or an exact count when it has none
+        rows: Option<u64>,
+        exact: bool,
+        /// DELETE FROM instead of TRUNCATE, the only choice on SQLite
+        delete: bool,
+        /// Run it in a transaction left open for a commit or rollback
+        transaction: bool,
+    },
+    /// Picking a maintenance statement for a table from the explorer
+    Maintenance {
+        table: String,
+        operations: Vec<Maintenance>,
+        selected: usize,
+        /// Enter was pressed once on a blocking operation, the next one runs it
+        armed: bool,
+    },
+}
+
+pub struct PendingConfirmation {
+    pub kind: Confirmation,
+    pub input: String,
+}
+
+/// Tables about to be dumped, while the user picks the file
+pub struct PendingDump {
+    pub tables: Vec<String>,
+    pub path: String,
+}
+
+/// Table about to get generated rows, while the user types how many
+pub struct PendingGenerate {
+    pub table: String,
+    pub rows: String,
+}
+
+/// Generated rows being inserted in the background
+pub struct RunningGenerate {
+    pub table: String,
+    pub rows: u64,
+    /// Rows inserted so far
+    pub progress: Arc<AtomicU64>,
+    pub(crate) handle: JoinHandle<Result<u64>>,
+}
+
+/// Maintenance running as the current query, with what the server reports about it
+pub struct RunningMaintenance {
+    pub table: String,
+    pub label: &'static str,
+    /// Latest phase and share done, Postgres only
+    pub progress: Arc<Mutex<Option<String>>>,
+    pub(crate) handle: JoinHandle<()>,
+}
+
+/// A dump writing in the background
+pub struct RunningDump {
+    pub path: String,
+    pub tables: usize,
+    /// Rows written so far
+    pub progress: Arc<AtomicU64>,
+    pub(crate) handle: JoinHandle<Result<u64>>,
+}
+
+/// Tables being listed in the background, after connecting or a refresh
+pub struct LoadingTables {
+    /// Report how many were found, for a refresh the user asked for
+    pub announce: bool,
+    pub(crate) handle: JoinHandle<Result<Vec<TableInfo>>>,
+}
+
+/// A query waiting on its row estimate before it's confirmed or run, see `request_execute`
+pub struct CheckingQuery {
+    /// The query as it was checked, it only runs if the editor still holds it
+    pub query: String,
+    pub started: Instant,
+    pub(crate) handle: JoinHandle<Option<Confirmation>>,
+}
+
+/// Pipeline being typed into the transform box, opened with Ctrl+F
+pub struct PendingPipeline {
+    pub input: String,
+    /// Why the last attempt didn't parse or run
+    pub error: Option<String>,
+}
+
+/// A pipeline shown instead of the active result set, and re-run on the sets that replace it
+pub struct AppliedPipeline {
+    pub expression: String,
+    pub(crate) pipeline: Pipeline,
+    /// The result set as fetched, back on screen once the pipeline is removed
+    pub(crate) fetched: (Vec<String>, Columns),
+}
+
+/// Masking rules being edited, opened with M on the results
+pub struct PendingMasking {
+    /// The result's columns and then any other column with a rule
+    pub columns: Vec<(String, Option<MaskMethod>)>,
+    /// How many of `columns` are in the result on screen
+    pub in_results: usize,
+    pub state: ListState,
+}
+
+/// Script commands being picked from, opened with Ctrl+K
+pub struct Palette {
+    pub filter: String,
+    pub state: ListState,
+}
+
+impl Palette {
+    /// Commands whose label contains the filter, ignoring case
+    pub fn matches<'a>(&self, scripts: &'a Scripts) -> impl Iterator<Item = &'a ScriptCommand> + use<'a> {
+        let filter = self.filter.to_lowercase();
+        scripts.commands.iter().filter(move |command| command.label.to_lowercase().contains(&filter))
+    }
+}
+
 #[derive(Clone)]
 pub struct TableInfo {
     pub name: String,
@@ -28,33 +266,109 @@ pub struct TableInfo {
 pub struct QueryPage {
     pub query: String,
     pub cursor_position: usize,
-    pub results: Vec<Vec<String>>,
+    pub results: Columns,
     pub headers: Vec<String>,
     pub error: Option<String>,
+    /// Full context of the last query error, opened with Ctrl+D
+    pub error_detail: Option<ErrorDetail>,
+    pub show_error_detail: bool,
+    /// The results are the breakdown of a script that failed, shown under its error
+    pub script_failed: bool,
     pub connection: Option<Connection>,
-    pub executor: Option<QueryExecutor>,
+    pub executor: Option<Arc<QueryExecutor>>,
     pub focus: Focus,
     pub query_scroll: u16,
     pub table_state: TableState,
     pub horizontal_scroll: usize,
     pub max_results: u32,
     pub input_buffer: String,
+    pub input_cursor: LineInput,
     pub show_input_overlay: bool,
     pub tables: Vec<TableInfo>,
     pub explorer_state: ListState,
+    pub loading_tables: Option<LoadingTables>,
+    /// The server info lookup started on connect
+    pub(crate) loading_server_info: Option<JoinHandle<Result<ServerInfo>>>,
+    pub checking_query: Option<CheckingQuery>,
+    /// Pings the server while connected, see `start_keepalive`
+    pub(crate) keepalive: Option<JoinHandle<()>>,
+    /// Written by the keepalive task after every ping, None until the first one
+    pub(crate) health: Arc<Mutex<Option<Health>>>,
+    /// Bumped with every write to `health`, an idle screen has no other reason to redraw
+    pub(crate) health_version: Arc<AtomicU64>,
+    /// `health_version` when the health dot was last drawn
+    pub(crate) health_drawn: u64,
+    pub last_duration: Option<Duration>,
+    pub row_stream: Option<RowStream>,
+    pub results_truncated: bool,
+    /// Estimated memory held by `results`, checked against the configured limit
+    pub results_bytes: usize,
+    /// Display width of each result column, measured over the first `widths_sampled` rows
+    pub column_widths: Vec<usize>,
+    pub widths_sampled: usize,
+    /// Every result set of the last run. The active one is moved out into
+    /// `headers`/`results`, its slot here stays empty until another set is selected.
+    pub result_sets: Vec<(Vec<String>, Columns)>,
+    pub active_result: usize,
+    /// Notices and warnings the database reported for the last run
+    pub notices: Vec<String>,
+    pub show_notices: bool,
+    /// What the server reported when the connection opened
+    pub server_info: Option<ServerInfo>,
+    pub show_server_info: bool,
+    /// Statements run in the open transaction, None when not in transaction mode
+    pub transaction_statements: Option<usize>,
+    pub running_query: Option<RunningQuery>,
+    pub watch: Option<Watch>,
+    /// Queries waiting for their time, in the order they were queued
+    pub schedule: Vec<ScheduledQuery>,
+    pub pending_schedule: Option<PendingSchedule>,
+    /// Cells that differ from the previous run of the watched query, as (row, column)
+    pub changed_cells: HashSet<(usize, usize)>,
+    pub prepared_mode: bool,
+    pub params: Vec<String>,
+    pub param_index: usize,
+    pub show_params_overlay: bool,
+    pub pending_confirmation: Option<PendingConfirmation>,
+    pub pending_dump: Option<PendingDump>,
+    pub running_dump: Option<RunningDump>,
+    pub pending_generate: Option<PendingGenerate>,
+    pub running_generate: Option<RunningGenerate>,
+    pub running_maintenance: Option<RunningMaintenance>,
+    /// Rejects anything but read-only queries until switched off again
+    pub read_only: bool,
+    /// The user's scripts, shared with the split pane
+    pub scripts: Arc<Scripts>,
+    pub palette: Option<Palette>,
+    pub pending_pipeline: Option<PendingPipeline>,
+    pub pipeline: Option<AppliedPipeline>,
+    pub pending_masking: Option<PendingMasking>,
+    /// Scroll offset of the selected row's popup, opened with Enter on the results
+    pub row_detail: Option<u16>,
+    /// Pane areas from the last render, mouse events are routed by them.
+    /// Empty while a pane isn't shown.
+    pub explorer_area: Rect,
+    pub query_area: Rect,
+    pub results_area: Rect,
+    pub panes: PaneSizes,
+    pub maximized: Option<Maximized>,
+    pub config: Config,
 }
 
 impl QueryPage {
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
         let mut explorer_state = ListState::default();
         explorer_state.select(Some(0));
         
         Self {
             query: String::new(),
             cursor_position: 0,
-            results: Vec::new(),
+            results: Columns::default(),
             headers: Vec::new(),
             error: None,
+            error_detail: None,
+            show_error_detail: false,
+            script_failed: false,
             connection: None,
             executor: None,
             focus: Focus::Query,
@@ -63,25 +377,93 @@ impl QueryPage {
             horizontal_scroll: 0,
             max_results: 0,
             input_buffer: String::new(),
+            input_cursor: LineInput::at_end(),
             show_input_overlay: false,
             tables: Vec::new(),
             explorer_state,
+            loading_tables: None,
+            loading_server_info: None,
+            checking_query: None,
+            keepalive: None,
+            health: Arc::default(),
+            health_version: Arc::default(),
+            health_drawn: 0,
+            last_duration: None,
+            row_stream: None,
+            results_truncated: false,
+            results_bytes: 0,
+            column_widths: Vec::new(),
+            widths_sampled: 0,
+            result_sets: Vec::new(),
+            active_result: 0,
+            notices: Vec::new(),
+            show_notices: false,
+            server_info: None,
+            show_server_info: false,
+            transaction_statements: None,
+            running_query: None,
+            watch: None,
+            schedule: Vec::new(),
+            pending_schedule: None,
+            changed_cells: HashSet::new(),
+            prepared_mode: false,
+            params: Vec::new(),
+            param_index: 0,
+            show_params_overlay: false,
+            pending_confirmation: None,
+            pending_dump: None,
+            running_dump: None,
+            pending_generate: None,
+            running_generate: None,
+            running_maintenance: None,
+            read_only: false,
+            scripts: Arc::new(Scripts::default()),
+            palette: None,
+            pending_pipeline: None,
+            pipeline: None,
+            pending_masking: None,
+            row_detail: None,
+            explorer_area: Rect::default(),
+            query_area: Rect::default(),
+            results_area: Rect::default(),
+            panes: PaneSizes::default(),
+            maximized: None,
+            config,
         }
     }
 
-    pub fn render(&mut self, f: &mut Frame, area: Rect) {
-        let use_explorer = self.focus == Focus::Explorer || !self.tables.is_empty();
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        self.explorer_area = Rect::default();
+        self.results_area = Rect::default();
+        let accented;
+        let theme = match self.connection.as_ref().and_then(|c| c.accent_color) {
+            Some(color) => {
+                accented = theme.clone().with_accent(color);
+                &accented
+            }
+            None => theme,
+        };
+        let page_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        self.render_status_bar(f, page_chunks[1], theme);
+        let area = page_chunks[0];
+
+        let use_explorer = self.maximized.is_none()
+            && self.panes.explorer_width > 0
+            && (self.focus == Focus::Explorer || !self.tables.is_empty());
         
         let main_area = if use_explorer {
             let main_chunks = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints([
-                    Constraint::Length(30),
+                    Constraint::Length(self.panes.explorer_width),
                     Constraint::Min(0),
                 ])
                 .split(area);
             
-            self.render_explorer(f, main_chunks[0]);
+            self.render_explorer(f, main_chunks[0], theme);
             
             // Return the right panel for main content
             main_chunks[1]
@@ -89,12 +471,25 @@ impl QueryPage {
             area
         };
 
+        let notices_height = match (self.notices.is_empty(), self.show_notices) {
+            (true, _) => 0,
+            (false, false) => 1,
+            (false, true) => self.notices.len().min(8) as u16 + 2,
+        };
+
+        let (editor, results) = match self.maximized {
+            Some(Maximized::Editor) => (Constraint::Min(0), Constraint::Length(0)),
+            Some(Maximized::Results) => (Constraint::Length(0), Constraint::Min(0)),
+            None => (Constraint::Length(self.panes.editor_height), Constraint::Min(0)),
+        };
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),
-                Constraint::Length(10),
-                Constraint::Min(0),
+                editor,
+                results,
+                Constraint::Length(notices_height),
                 Constraint::Length(4),
             ])
             .split(main_area);
@@ -104,120 +499,341 @@ impl QueryPage {
             .as_ref()
             .map(|c| c.name.as_str())
             .unwrap_or("No Connection");
-        let title = Paragraph::new(format!("Query Editor - {}", conn_name))
-            .style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
+        let mut title = vec![Span::raw(format!("Query Editor - {}", conn_name))];
+        let health = self.health.lock().ok().and_then(|health| *health);
+        if self.connection.is_some()
+            && let Some(health) = health
+        {
+            let (color, label) = match health {
+                Health { reachable: false, .. } => (theme.error, "unreachable".to_string()),
+                Health { latency, .. } if latency >= SLOW_PING => (theme.warning, format_duration(latency)),
+                Health { latency, .. } => (theme.success, format_duration(latency)),
+            };
+            title.push(Span::raw("  "));
+            title.push(Span::styled(theme.glyphs.marker, Style::default().fg(color)));
+            title.push(Span::raw(label));
+        }
+        let title = Paragraph::new(Line::from(title))
+            .style(theme.title_style())
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
+            .block(theme.block().border_style(theme.border_style(false)));
         f.render_widget(title, chunks[0]);
 
-        self.render_query_input(f, chunks[1]);
+        self.render_query_input(f, chunks[1], theme);
 
         if let Some(err) = &self.error {
             let error_text = Paragraph::new(err.as_str())
-                .style(Style::default().fg(Color::Red))
-                .block(Block::default().borders(Borders::ALL).title("Error"))
+                .style(Style::default().fg(theme.error))
+                .block(theme.block().title(if self.error_detail.is_some() {
+                    "Error (Ctrl+D: Details)"
+                } else {
+                    "Error"
+                }))
                 .wrap(Wrap { trim: false });
-            f.render_widget(error_text, chunks[2]);
+            if self.script_failed && !self.results.is_empty() {
+                let error_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(0)])
+                    .split(chunks[2]);
+                f.render_widget(error_text, error_chunks[0]);
+                self.render_table(f, error_chunks[1], theme);
+            } else {
+                f.render_widget(error_text, chunks[2]);
+            }
+        } else if let Some(running) = &self.running_query
+            && (!running.watched || self.headers.is_empty())
+        {
+            let copied = self.executor.as_ref().map_or(0, |e| e.copy_progress());
+            let maintenance = self.running_maintenance.as_ref();
+            let progress = if copied > 0 {
+                format!(" ({} transferred)", format_bytes(copied))
+            } else if let Some(progress) = maintenance.and_then(|m| m.progress.lock().ok()?.clone()) {
+                format!(" ({})", progress)
+            } else {
+                String::new()
+            };
+            let running_text = Paragraph::new(format!(
+                "{}... {}{}",
+                maintenance.map_or("Running query".to_string(), |m| format!("Running {} on {}", m.label, m.table)),
+                format_duration(running.started.elapsed()),
+                progress
+            ))
+            .style(Style::default().fg(theme.warning))
+            .block(theme.block().title("Results"))
+            .alignment(Alignment::Center);
+            f.render_widget(running_text, chunks[2]);
+        } else if self.result_sets.len() > 1 {
+            let result_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(chunks[2]);
+            let titles = (1..=self.result_sets.len()).map(|n| format!("Result {}", n));
+            let tabs = Tabs::new(titles)
+                .select(self.active_result)
+                .divider(theme.glyphs.divider)
+                .style(Style::default().fg(theme.help))
+                .highlight_style(Style::default().fg(theme.focus).add_modifier(Modifier::BOLD));
+            f.render_widget(tabs, result_chunks[0]);
+            self.render_table(f, result_chunks[1], theme);
         } else if !self.results.is_empty() {
-            self.render_table(f, chunks[2]);
+            self.render_table(f, chunks[2], theme);
         } else {
             let placeholder =
                 Paragraph::new("No results yet. Execute a query to see results here.")
-                    .style(Style::default().fg(Color::DarkGray))
-                    .block(Block::default().borders(Borders::ALL).title("Results"))
+                    .style(Style::default().fg(theme.muted))
+                    .block(theme.block().title("Results"))
                     .alignment(Alignment::Center);
             f.render_widget(placeholder, chunks[2]);
         }
 
-        let help_text = if matches!(self.focus, Focus::Results) && !self.results.is_empty() {
-            "Up/Down: Scroll | Left/Right: Columns | PgUp/PgDn: Page | T/B: Top/Bottom | Tab: Query Focus| Ctrl+L: Limit rows | Esc: Back"
+        if !self.notices.is_empty() {
+            self.render_notices(f, chunks[3], theme);
+        }
+
+        let help_text = if self.watch.is_some() {
+            "Ctrl+W / Esc: Stop Watching | Tab: Switch Focus | Ctrl+E: Explorer"
+        } else if self.running_query.is_some() || self.checking_query.is_some() {
+            "Esc: Cancel Query | Tab: Switch Focus | Ctrl+E: Explorer"
+        } else if matches!(self.focus, Focus::Results) && self.result_sets.len() > 1 {
+            "Up/Down: Scroll | Left/Right: Columns | [/]: Result Set | PgUp/PgDn: Page | T/B: Top/Bottom | Enter: Row Detail | Y: Copy as TSV | M: Mask Columns | Ctrl+F: Transform | Tab: Query Focus| Ctrl+L: Limit rows | Ctrl+Z: Maximize | Esc: Back"
+        } else if matches!(self.focus, Focus::Results) && !self.results.is_empty() {
+            "Up/Down: Scroll | Left/Right: Columns | PgUp/PgDn: Page | T/B: Top/Bottom | N: Load more | Enter: Row Detail | Y: Copy as TSV | M: Mask Columns | Ctrl+F: Transform | Tab: Query Focus| Ctrl+L: Limit rows | Ctrl+Z: Maximize | Esc: Back"
         } else if matches!(self.focus, Focus::Explorer) {
-            "Up/Down: Navigate | Enter: Expand/Collapse | Tab / Ctrl+E: Query Focus | Esc: Back"
+            "Up/Down: Navigate | Enter: Expand/Collapse | d: Dump Table | D: Dump All | g: Generate Rows | x: Empty Table | m: Maintenance | r: Refresh | Alt+I: Import File | Ctrl+Left/Right: Resize | Tab / Ctrl+E: Query Focus | Esc: Back"
         } else {
-            "Ctrl+S: Execute | Ctrl+C: Clear | Ctrl+R: History | Tab: Results Focus | Ctrl+E: Explorer | Esc: Back"
+            if self.transaction_statements.is_some() {
+                "Ctrl+S: Execute | Ctrl+Shift+C / Alt+K: Commit | Ctrl+Shift+R / Alt+R: Rollback | Ctrl+R: History | Tab: Results Focus | Ctrl+E: Explorer | Esc: Back"
+            } else {
+                "Ctrl+S: Execute | Ctrl+C: Clear | Ctrl+R: History | Ctrl+T: Begin Transaction | Ctrl+P: Prepared Mode | Ctrl+O: Read-only | Ctrl+W: Watch | Ctrl+Arrows: Resize | Ctrl+Z: Maximize | Ctrl+K: Commands | Alt+S: Split | Alt+I: Import File | Alt+P: Sessions | Alt+L: Locks | Alt+N: Notifications | Alt+T: Schedule | Alt+U: Disk Usage | Tab: Results Focus | Ctrl+E: Explorer | Esc: Back"
+            }
         };
 
         let help = Paragraph::new(help_text)
-            .style(Style::default().fg(Color::Gray))
+            .style(Style::default().fg(theme.help))
             .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL))
+            .block(theme.block())
             .wrap(Wrap { trim: false });
-        f.render_widget(help, chunks[3]);
+        f.render_widget(help, chunks[4]);
 
         // Render input overlay if active
         if self.show_input_overlay {
-            crate::gui::input_overlay::draw_input_overlay(f, self);
+            crate::gui::input_overlay::draw_input_overlay(f, self, theme);
+        }
+        if self.show_params_overlay {
+            crate::gui::params_overlay::draw_params_overlay(f, self, theme);
+        }
+        if let Some(pending) = &self.pending_confirmation {
+            crate::gui::confirm_overlay::draw_confirm_overlay(f, pending, theme);
+        }
+        if let Some(pending) = &self.pending_dump {
+            crate::gui::dump_overlay::draw_dump_overlay(f, pending, theme);
+        }
+        if let Some(pending) = &self.pending_generate {
+            crate::gui::generate_overlay::draw_generate_overlay(f, pending, theme);
+        }
+        if let Some(pending) = &self.pending_schedule {
+            crate::gui::schedule_overlay::draw_schedule_overlay(f, pending, &self.schedule, theme);
         }
+        if let Some(pending) = self.pending_masking.as_mut() {
+            crate::gui::masking_overlay::draw_masking_overlay(f, pending, theme);
+        }
+        if let Some(pending) = &self.pending_pipeline {
+            crate::gui::pipeline_overlay::draw_pipeline_overlay(f, pending, theme);
+        }
+        if let Some(palette) = &mut self.palette {
+            crate::gui::palette_overlay::draw_palette_overlay(f, palette, &self.scripts, theme);
+        }
+        if let Some(scroll) = self.row_detail
+            && let Some(row) = self.table_state.selected()
+        {
+            crate::gui::row_overlay::draw_row_overlay(f, self, row, scroll, theme);
+        }
+        if self.show_error_detail
+            && let Some(detail) = &self.error_detail
+        {
+            crate::gui::error_overlay::draw_error_overlay(f, detail, theme);
+        }
+        if self.show_server_info
+            && let Some(info) = &self.server_info
+        {
+            crate::gui::server_info_overlay::draw_server_info_overlay(f, info, theme);
+        }
+    }
+
+    /// Connection, session modes, the last run and background work, always on the bottom line
+    fn render_status_bar(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let divider = format!(" {} ", theme.glyphs.divider);
+        let mut spans = Vec::new();
+
+        if let Some(conn) = &self.connection {
+            spans.push(Span::styled(format!(" {} ", conn.db_type), theme.title_style()));
+            spans.push(Span::raw(conn.address()));
+        }
+
+        let mut modes = Vec::new();
+        if self.read_only {
+            modes.push("READ-ONLY".to_string());
+        }
+        if let Some(n) = self.transaction_statements {
+            modes.push(format!("IN TRANSACTION ({} statements)", n));
+        }
+        if self.prepared_mode {
+            modes.push("PREPARED".to_string());
+        }
+        if let Some(watch) = &self.watch {
+            modes.push(format!(
+                "WATCH every {}s ({} runs, {} changed)",
+                watch.interval.as_secs(),
+                watch.runs,
+                self.changed_cells.len()
+            ));
+        }
+        if !self.schedule.is_empty() {
+            // Queries waiting for the current one go first, then the earliest time
+            let next = self
+                .schedule
+                .iter()
+                .map(|scheduled| scheduled.time)
+                .min_by_key(|time| match time {
+                    ScheduleTime::AfterCurrent => None,
+                    ScheduleTime::At(at) => Some(*at),
+                });
+            modes.push(format!(
+                "{} SCHEDULED (next {})",
+                self.schedule.len(),
+                next.map(|time| time.label()).unwrap_or_default()
+            ));
+        }
+        for mode in modes {
+            spans.push(Span::raw(divider.clone()));
+            spans.push(Span::styled(mode, Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)));
+        }
+
+        if let Some(duration) = self.last_duration {
+            let rows = if self.row_stream.is_some() {
+                format!("{}+", self.results.len())
+            } else {
+                self.results.len().to_string()
+            };
+            spans.push(Span::raw(divider.clone()));
+            spans.push(Span::raw(format!("Last: {} rows in {}", rows, format_duration(duration))));
+        }
+
+        let background = if let Some(running) = self.running_query.as_ref().filter(|r| !r.watched) {
+            Some(format!("Running query {}", format_duration(running.started.elapsed())))
+        } else if let Some(checking) = &self.checking_query {
+            Some(format!("Estimating rows {}", format_duration(checking.started.elapsed())))
+        } else if let Some(dump) = &self.running_dump {
+            Some(format!("Dumping to {} ({} rows)", dump.path, dump.progress.load(Ordering::Relaxed)))
+        } else if let Some(generate) = &self.running_generate {
+            Some(format!(
+                "Generating rows for {} ({}/{})",
+                generate.table,
+                generate.progress.load(Ordering::Relaxed),
+                generate.rows
+            ))
+        } else if self.row_stream.is_some() {
+            Some("More rows available".to_string())
+        } else {
+            None
+        };
+        if let Some(background) = background {
+            spans.push(Span::raw(divider.clone()));
+            spans.push(Span::styled(background, Style::default().fg(theme.accent)));
+        }
+
+        let status = Paragraph::new(Line::from(spans)).style(Style::default().fg(theme.help));
+        f.render_widget(status, area);
+    }
+
+    fn render_notices(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        if !self.show_notices {
+            let summary = Paragraph::new(format!(
+                "{} {} notice(s) from the database (Ctrl+N to expand)",
+                theme.glyphs.collapsed,
+                self.notices.len()
+            ))
+            .style(Style::default().fg(theme.warning));
+            f.render_widget(summary, area);
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .notices
+            .iter()
+            .map(|notice| {
+                let color = if ["WARNING", "ERROR", "RETRY"].iter().any(|level| notice.starts_with(level)) {
+                    theme.warning
+                } else {
+                    theme.help
+                };
+                ListItem::new(notice.as_str()).style(Style::default().fg(color))
+            })
+            .collect();
+
+        // Keep the latest notices in view when there are more than fit
+        let hidden = self.notices.len().saturating_sub(area.height.saturating_sub(2) as usize);
+        let list = List::new(items.into_iter().skip(hidden).collect::<Vec<_>>()).block(
+            theme.block()
+                .border_style(theme.border_style(false))
+                .title(format!("{} Notices ({}) - Ctrl+N to collapse", theme.glyphs.expanded, self.notices.len())),
+        );
+        f.render_widget(list, area);
     }
 
-    fn render_explorer(&mut self, f: &mut Frame, area: Rect) {
+    fn render_explorer(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
         let mut items = Vec::new();
+        // Borders and the highlight symbol take 5 columns
+        let available = (area.width as usize).saturating_sub(5);
         
         for table in &self.tables {
-            items.push(ListItem::new(format!("📁 {}", table.name)));
+            let name = truncate_to_width(&table.name, available.saturating_sub(display_width(theme.glyphs.folder)));
+            items.push(ListItem::new(format!("{}{}", theme.glyphs.folder, name)));
             
-            if table.expanded {
-                if let Some(fields) = &table.fields {
-                    for field in fields {
-                        items.push(ListItem::new(format!("  └─ {}", field))
-                            .style(Style::default().fg(Color::Gray)));
-                    }
+            if table.expanded
+                && let Some(fields) = &table.fields
+            {
+                for field in fields {
+                    let field = truncate_to_width(field, available.saturating_sub(display_width(theme.glyphs.branch)));
+                    items.push(ListItem::new(format!("{}{}", theme.glyphs.branch, field))
+                        .style(Style::default().fg(theme.help)));
                 }
             }
         }
 
-        let highlight = {
-            #[cfg(target_os = "windows")]
-            {
-                Style::default()
-                    .fg(Color::White)
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
-
-            #[cfg(not(target_os = "windows"))]
-            {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
-        };
-
+        let title = if self.loading_tables.is_some() { "Tables (loading...)" } else { "Tables" };
         let list = List::new(items)
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title("Tables")
-                .border_style(if self.focus == Focus::Explorer {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default()
-                }))
-            .highlight_style(highlight)
+            .block(theme.block()
+                .title(title)
+                .border_style(theme.border_style(self.focus == Focus::Explorer)))
+            .highlight_style(theme.highlight())
             .highlight_symbol(">> ");
 
         f.render_stateful_widget(list, area, &mut self.explorer_state);
+        self.explorer_area = area;
+        theme.render_scrollbar(
+            f,
+            area,
+            self.explorer_len(),
+            self.explorer_state.selected().unwrap_or(0),
+            area.height.saturating_sub(2) as usize,
+        );
     }
 
-    fn render_query_input(&mut self, f: &mut Frame, area: Rect) {
+    fn render_query_input(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        self.query_area = area;
         let is_focused = matches!(self.focus, Focus::Query);
 
-        let query_block = Block::default()
-            .borders(Borders::ALL)
+        let query_block = theme.block()
             .title(if is_focused {
                 "SQL Query (Ctrl+Enter to Execute) [EDITING]"
             } else {
                 "SQL Query (Ctrl+Enter to Execute)"
             })
             .border_style(if is_focused {
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD)
+                theme.border_style(true).add_modifier(Modifier::BOLD)
             } else {
-                Style::default()
+                theme.border_style(false)
             });
 
         let display_text = if is_focused {
@@ -236,7 +852,22 @@ impl QueryPage {
         f.render_widget(query_text, area);
     }
 
-    fn render_table(&mut self, f: &mut Frame, area: Rect) {
+    /// Width of every column over its first `rows` cells, in terminal columns rather than bytes or chars
+    fn measure_columns(&self, rows: usize) -> Vec<usize> {
+        self.headers
+            .iter()
+            .enumerate()
+            .map(|(col, header)| {
+                self.results.column(col)[..rows.min(self.results.column(col).len())]
+                    .iter()
+                    .map(|value| display_width(&single_line(&value.format(&self.config.format))))
+                    .fold(display_width(header), usize::max)
+                    .clamp(3, MAX_COLUMN_WIDTH)
+            })
+            .collect()
+    }
+
+    fn render_table(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
         let selected_row = self.table_state.selected().unwrap_or(0);
 
         let visible_headers: Vec<&String> =
@@ -245,68 +876,92 @@ impl QueryPage {
         let visible_headers: Vec<&String> =
             visible_headers.iter().take(num_visible).copied().collect();
 
+        let display_len = if self.max_results > 0 {
+            self.results.len().min(self.max_results as usize)
+        } else {
+            self.results.len()
+        };
+
+        // Only the rows on screen become widgets, so a frame costs the same for any result size.
+        // Borders plus the header row and its margin leave this many.
+        let viewport = area.height.saturating_sub(4).max(1) as usize;
+        let mut offset = self.table_state.offset().min(display_len.saturating_sub(viewport));
+        if selected_row < offset {
+            offset = selected_row;
+        } else if selected_row >= offset + viewport {
+            offset = selected_row + 1 - viewport;
+        }
+        let end = (offset + viewport).min(display_len);
+
+        // Formatting every sampled cell is the costly part of a frame, so it happens once
+        // per result and again only when more rows or another limit change the sample
+        let sampled = display_len.min(WIDTH_SAMPLE_ROWS);
+        if self.column_widths.len() != self.headers.len() || self.widths_sampled != sampled {
+            self.column_widths = self.measure_columns(sampled);
+            self.widths_sampled = sampled;
+        }
+        let column_widths = &self.column_widths[self.horizontal_scroll..][..num_visible];
+
         let header_cells = visible_headers.iter().enumerate().map(|(idx, h)| {
             let actual_col_idx = idx + self.horizontal_scroll;
             let style = if actual_col_idx == self.horizontal_scroll {
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.warning)
                     .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
             } else {
-                Style::default().fg(Color::Yellow)
+                Style::default().fg(theme.warning)
             };
-            ratatui::widgets::Cell::from(h.as_str()).style(style)
+            ratatui::widgets::Cell::from(truncate_to_width(h, column_widths[idx])).style(style)
         });
         let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-        let display_results: Vec<&Vec<String>> = if self.max_results > 0 {
-            self.results.iter().take(self.max_results as usize).collect()
-        } else {
-            self.results.iter().collect()
-        };
-
-        let rows = display_results.iter().enumerate().map(|(row_idx, row)| {
-            let visible_cells: Vec<String> = row
-                .iter()
+        let rows = self.results.rows(offset..end).enumerate().map(|(visible_idx, row)| {
+            let row_idx = offset + visible_idx;
+            let visible_cells: Vec<&Value> = row
                 .skip(self.horizontal_scroll)
                 .take(num_visible)
-                .cloned()
                 .collect();
 
-            let cells = visible_cells.into_iter().enumerate().map(|(col_idx, c)| {
+            let cells = visible_cells.into_iter().enumerate().map(|(col_idx, value)| {
                 let actual_col_idx = col_idx + self.horizontal_scroll;
 
                 let style = if row_idx == selected_row && actual_col_idx == self.horizontal_scroll {
                     Style::default()
-                        .fg(Color::Green)
-                        .bg(Color::Cyan)
+                        .fg(theme.cursor_fg)
+                        .bg(theme.cursor_bg)
                         .add_modifier(Modifier::BOLD)
                 } else if row_idx == selected_row {
                     Style::default()
-                        .fg(Color::White)
+                        .fg(theme.selection_fg)
                         .add_modifier(Modifier::BOLD)
+                } else if self.changed_cells.contains(&(row_idx, actual_col_idx)) {
+                    Style::default()
+                        .fg(theme.warning)
+                        .add_modifier(Modifier::BOLD | Modifier::REVERSED)
                 } else if actual_col_idx == self.horizontal_scroll {
-                    Style::default().fg(Color::LightBlue)
+                    Style::default().fg(theme.accent)
+                } else if value.is_null() {
+                    Style::default()
+                        .fg(theme.muted)
+                        .add_modifier(Modifier::ITALIC)
                 } else {
                     Style::default()
                 };
 
-                ratatui::widgets::Cell::from(c).style(style)
+                let text = truncate_to_width(&single_line(&value.format(&self.config.format)), column_widths[col_idx]);
+                ratatui::widgets::Cell::from(text).style(style)
             });
 
             Row::new(cells).height(1)
         });
 
         let widths = if num_visible > 0 {
-            vec![Constraint::Percentage(100 / num_visible as u16); num_visible]
+            column_widths.iter().map(|w| Constraint::Length(*w as u16)).collect()
         } else {
             vec![Constraint::Percentage(100)]
         };
 
-        let total_rows = if self.max_results > 0 {
-            self.max_results.min(self.results.len() as u32)
-        } else {
-            self.results.len() as u32
-        };
+        let total_rows = display_len as u32;
 
         let scroll_info = if self.headers.len() > num_visible {
             format!(
@@ -320,49 +975,84 @@ impl QueryPage {
             format!(" [Row {}/{}] ", selected_row + 1, total_rows)
         };
 
-        let row_highlight = {
-            #[cfg(target_os = "windows")]
-            {
-                Style::default()
-                    .fg(Color::White)
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
+        let loaded = if self.row_stream.is_some() {
+            format!("{}+", self.results.len())
+        } else {
+            self.results.len().to_string()
+        };
 
-            #[cfg(not(target_os = "windows"))]
-            {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
-            }
+        let truncated = if self.results_truncated {
+            format!(" [truncated at {} rows, {} MB limit]", self.results.len(), self.config.result_memory_mb)
+        } else {
+            String::new()
         };
 
         let title = if self.max_results > 0 {
             format!(
-                "Results ({} of {} rows, limit: {}){}",
+                "Results ({} of {} rows, limit: {}){}{}",
                 total_rows,
-                self.results.len(),
+                loaded,
                 self.max_results,
+                truncated,
                 scroll_info
             )
         } else {
-            format!("Results ({} rows){}", self.results.len(), scroll_info)
+            format!("Results ({} rows){}{}", loaded, truncated, scroll_info)
+        };
+        let title = match &self.pipeline {
+            Some(applied) => format!("{} | {}", title, applied.expression),
+            None => title,
         };
 
         let table = Table::new(rows, widths)
             .header(header)
             .block(
-                Block::default()
-                    .borders(Borders::ALL)
+                theme.block()
                     .title(title)
-                    .border_style(match self.focus {
-                        Focus::Results => Style::default().fg(Color::Yellow),
-                        _ => Style::default(),
-                    }),
+                    .border_style(theme.border_style(self.focus == Focus::Results)),
             )
-            .row_highlight_style(row_highlight)
+            .row_highlight_style(theme.highlight())
             .highlight_symbol(">> ");
 
-        f.render_stateful_widget(table, area, &mut self.table_state);
+        // The widget sees just the window, the page keeps the real position for scrolling and clicks
+        let mut window_state = TableState::default().with_selected(self.table_state.selected().map(|s| s - offset));
+        f.render_stateful_widget(table, area, &mut window_state);
+        *self.table_state.offset_mut() = offset;
+        self.results_area = area;
+        // Borders plus the header row and its margin
+        theme.render_scrollbar(f, area, total_rows as usize, selected_row, area.height.saturating_sub(4) as usize);
+    }
+}
+pub fn format_duration(duration: Duration) -> String {
+    let ms = duration.as_millis();
+    if ms < 1000 {
+        format!("{} ms", ms)
+    } else {
+        format!("{:.2} s", duration.as_secs_f64())
+    }
+}
+
+/// Compact age like "42s", "3m 05s" or "2h 10m", the two largest units
+pub fn format_age(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {:02}h", secs / 86400, secs % 86400 / 3600),
     }
-}
\ No newline at end of file
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -0,0 +1,50 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{input_overlay::centered_rect, theme::Theme};
+
+/// Asks before quitting, listing the work that would be lost
+pub fn draw_quit_overlay(f: &mut Frame, reasons: &[String], theme: &Theme) {
+    let area = centered_rect(50, 30, f.area());
+
+    f.render_widget(Clear, area);
+
+    let color = if reasons.is_empty() { theme.focus } else { theme.warning };
+    let block = theme.block()
+        .title("Quit")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(color).bold());
+
+    let mut text = vec![Line::from("")];
+    if reasons.is_empty() {
+        text.push(Line::from(Span::styled(
+            "Quit rsquid?",
+            Style::default().fg(theme.text).not_bold(),
+        )));
+    } else {
+        for reason in reasons {
+            text.push(Line::from(Span::styled(reason.clone(), Style::default().fg(theme.warning))));
+        }
+        text.push(Line::from(Span::styled(
+            "Quitting now will lose it",
+            Style::default().fg(theme.text).not_bold(),
+        )));
+    }
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "y / Enter: Quit | n / Esc: Stay",
+        Style::default().fg(theme.text).not_bold(),
+    )));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,48 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{input_overlay::centered_rect, theme::Theme};
+use crate::utils::{swap::SwapFile, text::single_line, text::truncate_to_width};
+
+/// Offers the query buffers a crashed run left behind
+pub fn draw_restore_overlay(f: &mut Frame, swap: &SwapFile, theme: &Theme) {
+    let area = centered_rect(60, 40, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Recover Unsaved Queries")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.warning).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let mut text = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("rsquid didn't exit cleanly, these buffers were saved at {}", swap.saved_at),
+            plain,
+        )),
+        Line::from(""),
+    ];
+    let width = area.width.saturating_sub(8) as usize;
+    for (connection, query) in swap.queries.iter().filter(|(_, q)| !q.trim().is_empty()) {
+        text.push(Line::from(vec![
+            Span::styled(format!("{}: ", connection), Style::default().fg(theme.accent)),
+            Span::styled(truncate_to_width(&single_line(query), width.saturating_sub(connection.len() + 2)), plain),
+        ]));
+    }
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled("r / Enter: Restore | d / Esc: Discard", plain)));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,139 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{QueryPage, input_overlay::centered_rect, theme::Theme};
+use crate::utils::geometry::Shape;
+use crate::utils::value::{Value, ValueFormat};
+
+/// Every column of result row `row` with its whole value. Arrays list one element per
+/// line, JSON is indented, binary data is dumped in hex and geometries list their
+/// coordinates, what the table cuts to one line is readable here.
+pub fn draw_row_overlay(f: &mut Frame, page: &QueryPage, row: usize, scroll: u16, theme: &Theme) {
+    let area = centered_rect(70, 70, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title(format!("Row {} (Up/Down: Scroll | Esc: Close)", row + 1))
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let label = Style::default().fg(theme.accent).not_bold();
+    let muted = Style::default().fg(theme.muted).not_bold();
+    let format = &page.config.format;
+    let mut text = Vec::new();
+    for (header, value) in page.headers.iter().zip(page.results.row(row)) {
+        let mut lines = Vec::new();
+        value_lines(value, format, 0, &mut lines);
+        match value {
+            Value::Array(_) | Value::Json(_) | Value::Bytes(_) | Value::Geometry(_) => {
+                let kind = match value {
+                    Value::Bytes(bytes) => format!("{} bytes", bytes.len()),
+                    Value::Geometry(geometry) => match geometry.srid {
+                        Some(srid) => format!("geometry, SRID {}", srid),
+                        None => "geometry, no SRID".to_string(),
+                    },
+                    _ => value.type_name().to_string(),
+                };
+                text.push(Line::from(vec![
+                    Span::styled(header.clone(), label),
+                    Span::styled(format!("  {}", kind), muted),
+                ]));
+                text.extend(lines.into_iter().map(|line| Line::from(Span::styled(format!("  {}", line), plain))));
+            }
+            _ => {
+                let mut lines = lines.into_iter();
+                let first = lines.next().unwrap_or_default();
+                text.push(Line::from(vec![Span::styled(format!("{}: ", header), label), Span::styled(first, plain)]));
+                text.extend(lines.map(|line| Line::from(Span::styled(format!("  {}", line), plain))));
+            }
+        }
+    }
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
+
+/// The lines showing `value`, nested array elements one level further in
+fn value_lines(value: &Value, format: &ValueFormat, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    match value {
+        Value::Array(items) if items.is_empty() => lines.push(format!("{}(empty)", indent)),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                if let Value::Array(_) = item {
+                    lines.push(format!("{}[{}]", indent, index + 1));
+                    value_lines(item, format, depth + 1, lines);
+                } else {
+                    lines.push(format!("{}[{}] {}", indent, index + 1, item.format(format)));
+                }
+            }
+        }
+        Value::Json(json) => {
+            let pretty = serde_json::to_string_pretty(json).unwrap_or_else(|_| json.to_string());
+            lines.extend(pretty.lines().map(|line| format!("{}{}", indent, line)));
+        }
+        // Offset, 16 bytes in hex, then the printable ones
+        Value::Bytes(bytes) => {
+            for (chunk_index, chunk) in bytes.chunks(16).enumerate() {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+                let ascii: String = chunk
+                    .iter()
+                    .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                    .collect();
+                lines.push(format!("{}{:08x}  {:<47}  {}", indent, chunk_index * 16, hex.join(" "), ascii));
+            }
+            if bytes.is_empty() {
+                lines.push(format!("{}(empty)", indent));
+            }
+        }
+        Value::Geometry(geometry) => {
+            if let Some((min_x, min_y, max_x, max_y)) = geometry.bounds() {
+                lines.push(format!("{}Bounds: x {} to {}, y {} to {}", indent, min_x, max_x, min_y, max_y));
+            }
+            lines.push(format!("{}Points: {}", indent, geometry.coordinates().len()));
+            shape_lines(&geometry.shape, depth, lines);
+        }
+        other => lines.extend(other.format(format).lines().map(|line| format!("{}{}", indent, line))),
+    }
+}
+
+/// Each coordinate of `shape` on its own line, under the part or ring it belongs to
+fn shape_lines(shape: &Shape, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let coordinate = |c: &[f64]| c.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ");
+    match shape {
+        Shape::Point(point) if point.is_empty() => lines.push(format!("{}POINT EMPTY", indent)),
+        Shape::Point(point) => lines.push(format!("{}POINT {}", indent, coordinate(point))),
+        Shape::LineString(points) => {
+            lines.push(format!("{}LINESTRING", indent));
+            lines.extend(points.iter().enumerate().map(|(i, c)| format!("{}  [{}] {}", indent, i + 1, coordinate(c))));
+        }
+        Shape::Polygon(rings) => {
+            lines.push(format!("{}POLYGON", indent));
+            for (ring_index, ring) in rings.iter().enumerate() {
+                let ring_name = if ring_index == 0 { "Outer ring".to_string() } else { format!("Hole {}", ring_index) };
+                lines.push(format!("{}  {}", indent, ring_name));
+                lines.extend(ring.iter().enumerate().map(|(i, c)| format!("{}    [{}] {}", indent, i + 1, coordinate(c))));
+            }
+        }
+        Shape::MultiPoint(parts) | Shape::MultiLineString(parts) | Shape::MultiPolygon(parts) => {
+            lines.push(format!("{}{} of {}", indent, shape.name(), parts.len()));
+            parts.iter().for_each(|part| shape_lines(part, depth + 1, lines));
+        }
+        Shape::GeometryCollection(parts) => {
+            lines.push(format!("{}{} of {}", indent, shape.name(), parts.len()));
+            parts.iter().for_each(|part| shape_lines(&part.shape, depth + 1, lines));
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{PendingSchedule, ScheduledQuery, input_overlay::centered_rect, theme::Theme};
+use crate::utils::text::{single_line, truncate_to_width};
+
+/// Queued queries listed below the input, the rest are summed up
+const LISTED: usize = 5;
+
+/// Asks when to run the query, and lists the ones already waiting
+pub fn draw_schedule_overlay(f: &mut Frame, pending: &PendingSchedule, schedule: &[ScheduledQuery], theme: &Theme) {
+    let area = centered_rect(70, 45, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Schedule Query")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let muted = Style::default().fg(theme.muted).not_bold();
+    let mut text = vec![
+        Line::from(""),
+        Line::from(Span::styled(truncate_to_width(&single_line(&pending.query), 80), plain)),
+        Line::from(""),
+        Line::from(Span::styled("Run at 14:30, after +10m, or leave empty to run after the current query", muted)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(pending.input.clone(), Style::default().fg(theme.success).not_bold()),
+            Span::styled(theme.glyphs.cursor, Style::default().fg(theme.success).not_bold()),
+        ]),
+        Line::from(""),
+    ];
+    if let Some(error) = &pending.error {
+        text.push(Line::from(Span::styled(error.clone(), Style::default().fg(theme.error).not_bold())));
+        text.push(Line::from(""));
+    }
+    if !schedule.is_empty() {
+        text.push(Line::from(Span::styled(format!("Scheduled ({})", schedule.len()), plain.bold())));
+        for scheduled in schedule.iter().take(LISTED) {
+            text.push(Line::from(Span::styled(
+                format!("{}: {}", scheduled.time.label(), truncate_to_width(&single_line(&scheduled.query), 50)),
+                muted,
+            )));
+        }
+        if schedule.len() > LISTED {
+            text.push(Line::from(Span::styled(format!("and {} more", schedule.len() - LISTED), muted)));
+        }
+        text.push(Line::from(""));
+    }
+    let help = if schedule.is_empty() {
+        "Enter: Schedule | Esc: Cancel"
+    } else {
+        "Enter: Schedule | Del: Cancel All Scheduled | Esc: Cancel"
+    };
+    text.push(Line::from(Span::styled(help, plain)));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
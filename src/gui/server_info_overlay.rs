@@ -0,0 +1,44 @@
+use ratatui::{
+    Frame,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::gui::{format_age, input_overlay::centered_rect, theme::Theme};
+use crate::utils::query_executor::ServerInfo;
+
+/// What the server told about itself when the connection opened
+pub fn draw_server_info_overlay(f: &mut Frame, info: &ServerInfo, theme: &Theme) {
+    let area = centered_rect(50, 35, f.area());
+
+    f.render_widget(Clear, area);
+
+    let block = theme.block()
+        .title("Server")
+        .style(Style::default().bg(theme.overlay_bg)
+        .fg(theme.focus).bold());
+
+    let plain = Style::default().fg(theme.text).not_bold();
+    let label = Style::default().fg(theme.accent).not_bold();
+    let fields = [
+        ("Version", info.version.clone()),
+        ("User", info.user.clone()),
+        ("Encoding", info.encoding.clone()),
+        ("Time zone", info.timezone.clone()),
+        ("Uptime", info.uptime.map(format_age).unwrap_or_default()),
+    ];
+    let mut text = vec![Line::from("")];
+    text.extend(fields.into_iter().filter(|(_, value)| !value.is_empty()).map(|(name, value)| {
+        Line::from(vec![Span::styled(format!("{:>10}: ", name), label), Span::styled(value, plain)])
+    }));
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled("Any key: Close | Alt+V: Show again", plain)));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(theme.overlay_bg));
+
+    f.render_widget(paragraph, area);
+}
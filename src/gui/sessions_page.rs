@@ -0,0 +1,215 @@
+use crate::gui::{format_age, theme::Theme};
+use crate::utils::admin::Session;
+use crate::utils::text::{single_line, truncate_to_width};
+use anyhow::Result;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Active sessions running longer than this are highlighted
+const LONG_RUNNING: Duration = Duration::from_secs(60);
+
+pub enum SessionsAction {
+    Back,
+    /// Loads the process list again
+    Refresh,
+    /// Confirmed: end session `id`, or only cancel its query without `kill`
+    Stop { id: i64, kill: bool },
+}
+
+pub struct SessionsPage {
+    /// Name of the connection whose server is listed
+    pub(crate) connection: String,
+    pub(crate) sessions: Vec<Session>,
+    pub(crate) table_state: TableState,
+    pub(crate) task: Option<JoinHandle<Result<Vec<Session>>>>,
+    pub(crate) error: Option<String>,
+    /// Local time of the last completed refresh
+    pub(crate) refreshed: Option<String>,
+    /// Where the table was last drawn, for mouse clicks
+    pub(crate) table_area: Rect,
+    /// Session waiting for the user to confirm, true to terminate it
+    pub(crate) pending_stop: Option<(Session, bool)>,
+}
+
+impl SessionsPage {
+    pub fn new() -> Self {
+        Self {
+            connection: String::new(),
+            sessions: Vec::new(),
+            table_state: TableState::default(),
+            task: None,
+            error: None,
+            refreshed: None,
+            table_area: Rect::default(),
+            pending_stop: None,
+        }
+    }
+
+    /// Starts over for `connection`, the first load is started by the caller
+    pub fn open(&mut self, connection: String) {
+        *self = Self::new();
+        self.connection = connection;
+    }
+
+    /// Takes over a load started in the background, dropping one still running
+    pub fn start_refresh(&mut self, task: JoinHandle<Result<Vec<Session>>>) {
+        if let Some(previous) = self.task.replace(task) {
+            previous.abort();
+        }
+    }
+
+    /// Picks up the process list once the background load ends. The selection
+    /// stays on the same session when it is still there.
+    pub fn poll(&mut self) {
+        if !self.task.as_ref().is_some_and(|task| task.is_finished()) {
+            return;
+        }
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        match futures_util::FutureExt::now_or_never(task) {
+            Some(Ok(Ok(sessions))) => {
+                let selected_id = self.selected_session().map(|s| s.id);
+                self.sessions = sessions;
+                let index = selected_id
+                    .and_then(|id| self.sessions.iter().position(|s| s.id == id))
+                    .unwrap_or(0);
+                self.table_state.select((!self.sessions.is_empty()).then_some(index));
+                self.refreshed = Some(chrono::Local::now().format("%H:%M:%S").to_string());
+                self.error = None;
+            }
+            Some(Ok(Err(e))) => self.error = Some(format!("{:#}", e)),
+            _ => self.error = Some("Loading the sessions ended unexpectedly".to_string()),
+        }
+    }
+
+    pub fn selected_session(&self) -> Option<&Session> {
+        self.sessions.get(self.table_state.selected()?)
+    }
+
+    /// Asks to stop the selected session. rsquid's own connection is left alone,
+    /// stopping it would only break the lookup.
+    pub fn request_stop(&mut self, kill: bool) {
+        let Some(session) = self.selected_session() else {
+            return;
+        };
+        if session.current {
+            self.error = Some("That is rsquid's own connection".to_string());
+            return;
+        }
+        self.pending_stop = Some((session.clone(), kill));
+    }
+
+    pub fn scroll_up(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some((selected + 1).min(self.sessions.len().saturating_sub(1))));
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(7),
+                Constraint::Length(if self.error.is_some() { 4 } else { 3 }),
+            ])
+            .split(area);
+
+        let status = match (&self.task, &self.refreshed) {
+            (Some(_), _) => "loading...".to_string(),
+            (None, Some(time)) => format!("{} sessions at {}", self.sessions.len(), time),
+            (None, None) => String::new(),
+        };
+        let title = Paragraph::new(format!("Sessions - {} ({})", self.connection, status))
+            .style(theme.title_style())
+            .alignment(Alignment::Center)
+            .block(theme.block());
+        f.render_widget(title, chunks[0]);
+
+        let header = Row::new(["ID", "User", "Database", "Client", "State", "Duration", "Query"].map(|title| {
+            Cell::from(title).style(Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))
+        }))
+        .bottom_margin(1);
+        let rows = self.sessions.iter().map(|session| {
+            let duration = session.duration.map(format_age).unwrap_or_default();
+            let long_running = session.state.eq_ignore_ascii_case("active")
+                || session.state.starts_with("Query");
+            let style = if session.current {
+                Style::default().fg(theme.muted)
+            } else if long_running && session.duration.is_some_and(|d| d >= LONG_RUNNING) {
+                Style::default().fg(theme.warning)
+            } else {
+                Style::default()
+            };
+            Row::new([
+                Cell::from(format!("{}{}", session.id, if session.current { " *" } else { "" })),
+                Cell::from(session.user.clone()),
+                Cell::from(session.database.clone()),
+                Cell::from(session.client.clone()),
+                Cell::from(session.state.clone()),
+                Cell::from(duration),
+                Cell::from(truncate_to_width(&single_line(&session.query), 200)),
+            ])
+            .style(style)
+        });
+        let widths = [
+            Constraint::Length(9),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(22),
+            Constraint::Length(20),
+            Constraint::Length(9),
+            Constraint::Min(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(theme.block().title("Sessions (* is rsquid)"))
+            .row_highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, chunks[1], &mut self.table_state);
+        self.table_area = chunks[1];
+
+        let query = self.selected_session().map(|s| s.query.clone()).unwrap_or_default();
+        let detail = Paragraph::new(query)
+            .block(theme.block().title("Query"))
+            .wrap(Wrap { trim: false });
+        f.render_widget(detail, chunks[2]);
+
+        let mut help_lines = vec![Line::from(format!(
+            "{}: Navigate | r: Refresh | c: Cancel Query | k: Terminate Session | Esc: Back",
+            theme.glyphs.up_down
+        ))];
+        if let Some(err) = &self.error {
+            help_lines.push(Line::from(vec![
+                Span::styled("Error: ", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
+                Span::styled(err, Style::default().fg(theme.error)),
+            ]));
+        }
+        let help = Paragraph::new(help_lines)
+            .style(Style::default().fg(theme.help))
+            .alignment(Alignment::Center)
+            .block(theme.block())
+            .wrap(Wrap { trim: false });
+        f.render_widget(help, chunks[3]);
+
+        if let Some((session, kill)) = &self.pending_stop {
+            crate::gui::kill_overlay::draw_kill_overlay(f, session, *kill, theme);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use crate::gui::theme::Theme;
+use crate::utils::config::Config;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{List, ListItem, ListState, Paragraph},
+};
+
+pub enum SettingsAction {
+    Cancel,
+    Save(Box<Config>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsField {
+    Theme,
+    QueryTimeout,
+    RetryAttempts,
+    Keepalive,
+    LargeResultRows,
+    ResultMemory,
+    WatchInterval,
+    CacheSchema,
+    TimestampFormat,
+    LocalTime,
+    FloatPrecision,
+    DecimalPlaces,
+    ThousandsSeparator,
+    AsciiOnly,
+}
+
+const KEYBINDINGS: [(&str, &str); 22] = [
+    ("Ctrl+S", "Execute the query"),
+    ("Ctrl+R", "Query history"),
+    ("Ctrl+T", "Begin a transaction"),
+    ("Ctrl+P", "Prepared mode"),
+    ("Ctrl+O", "Read-only session"),
+    ("Ctrl+L", "Limit result rows"),
+    ("Ctrl+W", "Watch the query"),
+    ("Ctrl+E", "Table explorer"),
+    ("Tab", "Switch focus"),
+    ("Ctrl+Z", "Maximize pane"),
+    ("Alt+S", "Split view"),
+    ("Alt+I", "Import CSV or JSON"),
+    ("Alt+P", "Server sessions"),
+    ("Alt+V", "Server info"),
+    ("Alt+U", "Database and table sizes"),
+    ("Alt+L", "Lock waits"),
+    ("Alt+N", "LISTEN/NOTIFY notifications"),
+    ("Alt+T", "Schedule the query"),
+    ("Enter", "Show the selected row in full"),
+    ("Y", "Copy results as TSV"),
+    ("M", "Mask columns in copies and exports"),
+    ("Ctrl+Q", "Quit"),
+];
+
+pub struct SettingsPage {
+    pub(crate) fields: Vec<SettingsField>,
+    pub(crate) field_state: ListState,
+    // Kept so options the page doesn't edit, like custom themes, survive a save
+    pub(crate) base: Config,
+    pub(crate) theme: String,
+    pub(crate) query_timeout: String,
+    pub(crate) retry_attempts: String,
+    pub(crate) keepalive: String,
+    pub(crate) large_result_rows: String,
+    pub(crate) result_memory: String,
+    pub(crate) watch_interval: String,
+    pub(crate) cache_schema: bool,
+    pub(crate) timestamp_format: String,
+    pub(crate) local_time: bool,
+    pub(crate) float_precision: String,
+    pub(crate) decimal_places: String,
+    pub(crate) thousands_separator: String,
+    pub(crate) ascii_only: Option<bool>,
+    pub(crate) error: Option<String>,
+    /// Where the form was last drawn, for mouse clicks
+    pub(crate) list_area: Rect,
+}
+
+impl SettingsPage {
+    pub fn new(config: &Config) -> Self {
+        let mut field_state = ListState::default();
+        field_state.select(Some(0));
+        Self {
+            fields: vec![
+                SettingsField::Theme,
+                SettingsField::QueryTimeout,
+                SettingsField::RetryAttempts,
+                SettingsField::Keepalive,
+                SettingsField::LargeResultRows,
+                SettingsField::ResultMemory,
+                SettingsField::WatchInterval,
+                SettingsField::CacheSchema,
+                SettingsField::TimestampFormat,
+                SettingsField::LocalTime,
+                SettingsField::FloatPrecision,
+                SettingsField::DecimalPlaces,
+                SettingsField::ThousandsSeparator,
+                SettingsField::AsciiOnly,
+            ],
+            field_state,
+            base: config.clone(),
+            theme: config.theme.clone(),
+            query_timeout: config.query_timeout_secs.to_string(),
+            retry_attempts: config.retry_attempts.to_string(),
+            keepalive: config.keepalive_secs.to_string(),
+            large_result_rows: config.large_result_warning_rows.to_string(),
+            result_memory: config.result_memory_mb.to_string(),
+            watch_interval: config.watch_interval_secs.to_string(),
+            cache_schema: config.cache_schema,
+            timestamp_format: config.format.timestamp_format.clone(),
+            local_time: config.format.local_time,
+            float_precision: config
+                .format
+                .float_precision
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            decimal_places: config
+                .format
+                .decimal_places
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            thousands_separator: config
+                .format
+                .thousands_separator
+                .map(String::from)
+                .unwrap_or_default(),
+            ascii_only: config.ascii_only,
+            error: None,
+            list_area: Rect::default(),
+        }
+    }
+
+    /// Built-in themes followed by the ones defined in the config file
+    pub fn theme_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Theme::BUILT_IN.iter().map(|n| n.to_string()).collect();
+        names.extend(self.base.themes.keys().filter(|n| !names.contains(n)).cloned().collect::<Vec<_>>());
+        names
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(KEYBINDINGS.len().div_ceil(2) as u16 + 2),
+                Constraint::Length(4),
+            ])
+            .split(area);
+
+        let title = Paragraph::new("Settings")
+            .style(theme.title_style())
+            .alignment(Alignment::Center)
+            .block(theme.block());
+        f.render_widget(title, chunks[0]);
+
+        let ascii_only = match self.ascii_only {
+            None => "auto",
+            Some(true) => "on",
+            Some(false) => "off",
+        };
+        let items: Vec<ListItem> = vec![
+            ListItem::new(format!("Theme (Left/Right to change): {}", self.theme)),
+            ListItem::new(format!("Query Timeout (seconds, 0 = none): {}", self.query_timeout)),
+            ListItem::new(format!("Retry Attempts: {}", self.retry_attempts)),
+            ListItem::new(format!("Keepalive Ping (seconds, 0 = off): {}", self.keepalive)),
+            ListItem::new(format!("Large Result Warning (rows, 0 = off): {}", self.large_result_rows)),
+            ListItem::new(format!("Result Memory Limit (MB, 0 = none): {}", self.result_memory)),
+            ListItem::new(format!("Watch Interval (seconds): {}", self.watch_interval)),
+            ListItem::new(format!("Cache Schema on Disk (Left/Right to change): {}", if self.cache_schema { "on" } else { "off" })),
+            ListItem::new(format!("Timestamp Format (e.g. %Y-%m-%d %H:%M, empty = as stored): {}", self.timestamp_format)),
+            ListItem::new(format!("Local Time (Left/Right to change): {}", if self.local_time { "on" } else { "off" })),
+            ListItem::new(format!("Float Precision (digits, empty = as stored): {}", self.float_precision)),
+            ListItem::new(format!("Decimal Places (digits, empty = every digit stored): {}", self.decimal_places)),
+            ListItem::new(format!("Thousands Separator (one character, empty = none): {}", self.thousands_separator)),
+            ListItem::new(format!("ASCII Only (Left/Right to change): {}", ascii_only)),
+        ];
+
+        let list = List::new(items)
+            .block(theme.block().title("Options"))
+            .highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut self.field_state);
+        self.list_area = chunks[1];
+
+        // Two bindings per line keeps the summary short
+        let keybindings: Vec<Line> = KEYBINDINGS
+            .chunks(2)
+            .map(|pair| {
+                let mut spans = Vec::new();
+                for (key, action) in pair {
+                    spans.push(Span::styled(format!("{:>8}", key), Style::default().fg(theme.accent)));
+                    spans.push(Span::raw(format!("  {:<24}", action)));
+                }
+                Line::from(spans)
+            })
+            .collect();
+        let keybindings = Paragraph::new(keybindings).block(theme.block().title("Query Page Keys"));
+        f.render_widget(keybindings, chunks[2]);
+
+        let mut help_lines = vec![Line::from(vec![
+            Span::raw(format!("{}: Navigate | ", theme.glyphs.up_down)),
+            Span::raw("Type: Edit | "),
+            Span::raw("Ctrl+S: Save | "),
+            Span::raw("Esc: Cancel"),
+        ])];
+
+        if let Some(err) = &self.error {
+            help_lines.push(Line::from(vec![
+                Span::styled(
+                    "Error: ",
+                    Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(err, Style::default().fg(theme.error)),
+            ]));
+        }
+
+        let help = Paragraph::new(help_lines)
+            .style(Style::default().fg(theme.help))
+            .alignment(Alignment::Center)
+            .block(theme.block());
+        f.render_widget(help, chunks[3]);
+    }
+
+    pub fn validate_and_save(&mut self) -> Option<SettingsAction> {
+        let mut config = self.base.clone();
+
+        if !self.theme_names().contains(&self.theme) {
+            self.error = Some(format!("Unknown theme {}", self.theme));
+            return None;
+        }
+        config.theme = self.theme.clone();
+
+        let Ok(query_timeout_secs) = self.query_timeout.trim().parse() else {
+            self.error = Some("Query timeout must be a number of seconds".to_string());
+            return None;
+        };
+        config.query_timeout_secs = query_timeout_secs;
+
+        let Ok(retry_attempts) = self.retry_attempts.trim().parse() else {
+            self.error = Some("Retry attempts must be a number".to_string());
+            return None;
+        };
+        config.retry_attempts = retry_attempts;
+
+        let Ok(keepalive_secs) = self.keepalive.trim().parse() else {
+            self.error = Some("Keepalive must be a number of seconds".to_string());
+            return None;
+        };
+        config.keepalive_secs = keepalive_secs;
+
+        let Ok(large_result_warning_rows) = self.large_result_rows.trim().parse() else {
+            self.error = Some("Large result warning must be a number of rows".to_string());
+            return None;
+        };
+        config.large_result_warning_rows = large_result_warning_rows;
+
+        let Ok(result_memory_mb) = self.result_memory.trim().parse() else {
+            self.error = Some("Result memory limit must be a number of megabytes".to_string());
+            return None;
+        };
+        config.result_memory_mb = result_memory_mb;
+
+        match self.watch_interval.trim().parse() {
+            Ok(secs) if secs > 0 => config.watch_interval_secs = secs,
+            _ => {
+                self.error = Some("Watch interval must be a number of seconds, at least 1".to_string());
+                return None;
+            }
+        }
+
+        config.cache_schema = self.cache_schema;
+        config.format.timestamp_format = self.timestamp_format.clone();
+        config.format.local_time = self.local_time;
+
+        config.format.float_precision = if self.float_precision.trim().is_empty() {
+            None
+        } else if let Ok(precision) = self.float_precision.trim().parse() {
+            Some(precision)
+        } else {
+            self.error = Some("Float precision must be a number of digits".to_string());
+            return None;
+        };
+
+        config.format.decimal_places = if self.decimal_places.trim().is_empty() {
+            None
+        } else if let Ok(places) = self.decimal_places.trim().parse() {
+            Some(places)
+        } else {
+            self.error = Some("Decimal places must be a number of digits".to_string());
+            return None;
+        };
+
+        let mut separator = self.thousands_separator.chars();
+        config.format.thousands_separator = match (separator.next(), separator.next()) {
+            (None, _) => None,
+            (Some(c), None) => Some(c),
+            _ => {
+                self.error = Some("Thousands separator must be a single character".to_string());
+                return None;
+            }
+        };
+
+        config.ascii_only = self.ascii_only;
+
+        Some(SettingsAction::Save(Box::new(config)))
+    }
+}
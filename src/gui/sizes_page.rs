@@ -0,0 +1,240 @@
+use crate::gui::{format_bytes, theme::Theme};
+use crate::utils::admin::{SizeEntry, SizeScope};
+use anyhow::Result;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use std::cmp::Ordering;
+use tokio::task::JoinHandle;
+
+pub enum SizesAction {
+    Back,
+    /// Loads the sizes of the current scope again
+    Refresh,
+}
+
+/// Column the list is sorted by, in the order the columns are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeSort {
+    Name,
+    Total,
+    Data,
+    Index,
+    Rows,
+}
+
+impl SizeSort {
+    pub const ALL: [SizeSort; 5] = [SizeSort::Name, SizeSort::Total, SizeSort::Data, SizeSort::Index, SizeSort::Rows];
+
+    fn title(self) -> &'static str {
+        match self {
+            SizeSort::Name => "Name",
+            SizeSort::Total => "Total",
+            SizeSort::Data => "Data",
+            SizeSort::Index => "Indexes",
+            SizeSort::Rows => "Rows (est.)",
+        }
+    }
+
+    /// Unknown sizes sort below every known one
+    fn compare(self, a: &SizeEntry, b: &SizeEntry) -> Ordering {
+        match self {
+            SizeSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SizeSort::Total => a.total_bytes.cmp(&b.total_bytes),
+            SizeSort::Data => a.data_bytes.cmp(&b.data_bytes),
+            SizeSort::Index => a.index_bytes.cmp(&b.index_bytes),
+            SizeSort::Rows => a.rows.cmp(&b.rows),
+        }
+    }
+}
+
+pub struct SizesPage {
+    pub(crate) connection: String,
+    pub(crate) scope: SizeScope,
+    pub(crate) entries: Vec<SizeEntry>,
+    pub(crate) sort: SizeSort,
+    pub(crate) descending: bool,
+    pub(crate) table_state: TableState,
+    pub(crate) task: Option<JoinHandle<Result<Vec<SizeEntry>>>>,
+    pub(crate) error: Option<String>,
+    /// Where the table was last drawn, for mouse clicks
+    pub(crate) table_area: Rect,
+}
+
+impl SizesPage {
+    pub fn new() -> Self {
+        Self {
+            connection: String::new(),
+            scope: SizeScope::Tables,
+            entries: Vec::new(),
+            sort: SizeSort::Total,
+            descending: true,
+            table_state: TableState::default(),
+            task: None,
+            error: None,
+            table_area: Rect::default(),
+        }
+    }
+
+    /// Starts over for `connection` with the biggest tables first, the caller starts the load
+    pub fn open(&mut self, connection: String) {
+        *self = Self::new();
+        self.connection = connection;
+    }
+
+    /// Takes over a load started in the background, dropping one still running
+    pub fn start_refresh(&mut self, task: JoinHandle<Result<Vec<SizeEntry>>>) {
+        if let Some(previous) = self.task.replace(task) {
+            previous.abort();
+        }
+    }
+
+    pub fn toggle_scope(&mut self) {
+        self.scope = match self.scope {
+            SizeScope::Databases => SizeScope::Tables,
+            SizeScope::Tables => SizeScope::Databases,
+        };
+        self.entries.clear();
+        self.table_state.select(None);
+    }
+
+    /// Sorts by `sort`, or flips the order when the list is already sorted by it.
+    /// Names start A to Z, sizes biggest first.
+    pub fn sort_by(&mut self, sort: SizeSort) {
+        if self.sort == sort {
+            self.descending = !self.descending;
+        } else {
+            self.sort = sort;
+            self.descending = sort != SizeSort::Name;
+        }
+        self.apply_sort();
+    }
+
+    fn apply_sort(&mut self) {
+        let (sort, descending) = (self.sort, self.descending);
+        self.entries.sort_by(|a, b| {
+            let order = sort.compare(a, b);
+            if descending { order.reverse() } else { order }
+        });
+    }
+
+    /// Picks up the sizes once the background load ends
+    pub fn poll(&mut self) {
+        if !self.task.as_ref().is_some_and(|task| task.is_finished()) {
+            return;
+        }
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        // The handle is finished, so this doesn't wait
+        match futures_util::FutureExt::now_or_never(task) {
+            Some(Ok(Ok(entries))) => {
+                self.entries = entries;
+                self.apply_sort();
+                self.table_state.select((!self.entries.is_empty()).then_some(0));
+                self.error = None;
+            }
+            Some(Ok(Err(e))) => self.error = Some(format!("{:#}", e)),
+            _ => self.error = Some("Loading the sizes ended unexpectedly".to_string()),
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some((selected + 1).min(self.entries.len().saturating_sub(1))));
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(if self.error.is_some() { 4 } else { 3 }),
+            ])
+            .split(area);
+
+        let scope = match self.scope {
+            SizeScope::Databases => "Databases",
+            SizeScope::Tables => "Tables",
+        };
+        let total: u64 = self.entries.iter().filter_map(|e| e.total_bytes).sum();
+        let status = if self.task.is_some() {
+            "loading...".to_string()
+        } else {
+            format!("{} {}, {}", self.entries.len(), scope.to_lowercase(), format_bytes(total))
+        };
+        let title = Paragraph::new(format!("{} - {} ({})", scope, self.connection, status))
+            .style(theme.title_style())
+            .alignment(Alignment::Center)
+            .block(theme.block());
+        f.render_widget(title, chunks[0]);
+
+        let arrow = if self.descending { theme.glyphs.sort_descending } else { theme.glyphs.sort_ascending };
+        let header = Row::new(SizeSort::ALL.iter().enumerate().map(|(i, sort)| {
+            let marker = if *sort == self.sort { arrow } else { "" };
+            Cell::from(format!("{} {}{}", i + 1, sort.title(), marker))
+                .style(Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))
+        }))
+        .bottom_margin(1);
+        let size = |bytes: Option<u64>| bytes.map(format_bytes).unwrap_or_default();
+        let rows = self.entries.iter().map(|entry| {
+            Row::new([
+                Cell::from(entry.name.clone()),
+                Cell::from(size(entry.total_bytes)),
+                Cell::from(size(entry.data_bytes)),
+                Cell::from(size(entry.index_bytes)),
+                Cell::from(entry.rows.map(|rows| rows.to_string()).unwrap_or_default()),
+            ])
+        });
+        let widths = [
+            Constraint::Min(20),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(14),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(theme.block().title(scope))
+            .row_highlight_style(theme.highlight())
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, chunks[1], &mut self.table_state);
+        self.table_area = chunks[1];
+        theme.render_scrollbar(
+            f,
+            chunks[1],
+            self.entries.len(),
+            self.table_state.selected().unwrap_or(0),
+            chunks[1].height.saturating_sub(4) as usize,
+        );
+
+        let mut help_lines = vec![Line::from(format!(
+            "{}: Navigate | 1-5: Sort (again to reverse) | Tab: Databases/Tables | r: Refresh | Esc: Back",
+            theme.glyphs.up_down
+        ))];
+        if let Some(err) = &self.error {
+            help_lines.push(Line::from(vec![
+                Span::styled("Error: ", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
+                Span::styled(err, Style::default().fg(theme.error)),
+            ]));
+        }
+        let help = Paragraph::new(help_lines)
+            .style(Style::default().fg(theme.help))
+            .alignment(Alignment::Center)
+            .block(theme.block())
+            .wrap(Wrap { trim: false });
+        f.render_widget(help, chunks[2]);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::gui::{QueryPage, theme::Theme};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitDirection {
+    /// Panes side by side
+    Horizontal,
+    /// Panes stacked on top of each other
+    Vertical,
+}
+
+/// A second query page shown next to the main one, with its own connection, buffer and results
+pub struct SplitView {
+    pub page: QueryPage,
+    pub direction: SplitDirection,
+    /// Whether keys go to the second pane instead of the main one
+    pub active: bool,
+}
+
+impl SplitView {
+    pub fn new(page: QueryPage) -> Self {
+        Self {
+            page,
+            direction: SplitDirection::Horizontal,
+            active: true,
+        }
+    }
+
+    pub fn flip(&mut self) {
+        self.direction = match self.direction {
+            SplitDirection::Horizontal => SplitDirection::Vertical,
+            SplitDirection::Vertical => SplitDirection::Horizontal,
+        };
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, main: &mut QueryPage, theme: &Theme) {
+        let direction = match self.direction {
+            SplitDirection::Horizontal => Direction::Horizontal,
+            SplitDirection::Vertical => Direction::Vertical,
+        };
+        let chunks = Layout::default()
+            .direction(direction)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+
+        let inactive = theme.inactive();
+        let (main_theme, split_theme) = if self.active {
+            (&inactive, theme)
+        } else {
+            (theme, &inactive)
+        };
+        main.render(f, chunks[0], main_theme);
+        self.page.render(f, chunks[1], split_theme);
+    }
+}
@@ -0,0 +1,192 @@
+use ratatui::{
+    Frame,
+    layout::{Margin, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Scrollbar, ScrollbarOrientation, ScrollbarState},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::gui::glyphs::Glyphs;
+
+/// Every color the UI uses. Custom themes in the config file only need the
+/// fields they change, the rest come from the dark theme.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Page titles
+    pub title: Color,
+    /// Text inside overlays
+    pub text: Color,
+    /// Placeholders, NULL cells and other secondary details
+    pub muted: Color,
+    /// Help lines and notices
+    pub help: Color,
+    /// Unfocused pane borders
+    pub border: Color,
+    /// Focused borders, column headers and overlay frames
+    pub focus: Color,
+    /// The current column and informational values
+    pub accent: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    /// The cell under the cursor in the results table
+    pub cursor_fg: Color,
+    pub cursor_bg: Color,
+    pub overlay_bg: Color,
+    /// Picked from the ascii_only setting rather than per theme
+    #[serde(skip)]
+    pub glyphs: Glyphs,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub const BUILT_IN: [&'static str; 3] = ["dark", "light", "high-contrast"];
+
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            title: Color::Cyan,
+            text: Color::White,
+            muted: Color::DarkGray,
+            help: Color::Gray,
+            border: Color::Reset,
+            focus: Color::Yellow,
+            accent: Color::LightBlue,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+            selection_fg: Color::White,
+            selection_bg: Color::DarkGray,
+            cursor_fg: Color::Green,
+            cursor_bg: Color::Cyan,
+            overlay_bg: Color::Black,
+            glyphs: Glyphs::UNICODE,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            title: Color::Blue,
+            text: Color::Black,
+            muted: Color::Gray,
+            help: Color::DarkGray,
+            border: Color::Reset,
+            focus: Color::Magenta,
+            accent: Color::Blue,
+            success: Color::Green,
+            warning: Color::Rgb(175, 95, 0),
+            error: Color::Red,
+            selection_fg: Color::Black,
+            selection_bg: Color::Rgb(200, 200, 200),
+            cursor_fg: Color::White,
+            cursor_bg: Color::Blue,
+            overlay_bg: Color::White,
+            glyphs: Glyphs::UNICODE,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            title: Color::White,
+            text: Color::White,
+            muted: Color::Gray,
+            help: Color::White,
+            border: Color::White,
+            focus: Color::LightYellow,
+            accent: Color::LightCyan,
+            success: Color::LightGreen,
+            warning: Color::LightYellow,
+            error: Color::LightRed,
+            selection_fg: Color::Black,
+            selection_bg: Color::White,
+            cursor_fg: Color::Black,
+            cursor_bg: Color::LightYellow,
+            overlay_bg: Color::Black,
+            glyphs: Glyphs::UNICODE,
+        }
+    }
+
+    /// Paints the title bar and pane borders in a connection's own color
+    pub fn with_accent(mut self, color: Color) -> Self {
+        self.title = color;
+        self.border = color;
+        self
+    }
+
+    /// Dimmed variant for the pane of a split view that doesn't take keys
+    pub fn inactive(&self) -> Self {
+        let mut theme = self.clone();
+        theme.title = self.muted;
+        theme.focus = self.muted;
+        theme
+    }
+
+    /// A bordered block drawn with the active glyph set
+    pub fn block(&self) -> Block<'static> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(self.glyphs.border)
+    }
+
+    pub fn title_style(&self) -> Style {
+        Style::default().fg(self.title).add_modifier(Modifier::BOLD)
+    }
+
+    pub fn border_style(&self, focused: bool) -> Style {
+        if focused {
+            Style::default().fg(self.focus)
+        } else {
+            Style::default().fg(self.border)
+        }
+    }
+
+    /// Scrollbar on the right border of a bordered list, only drawn when
+    /// `len` items don't fit in the `viewport` rows
+    pub fn render_scrollbar(&self, f: &mut Frame, area: Rect, len: usize, position: usize, viewport: usize) {
+        if len <= viewport {
+            return;
+        }
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .symbols(self.glyphs.scrollbar.clone())
+            .style(Style::default().fg(self.muted))
+            .thumb_style(Style::default().fg(self.accent));
+        let mut state = ScrollbarState::new(len)
+            .position(position)
+            .viewport_content_length(viewport);
+        f.render_stateful_widget(scrollbar, area.inner(Margin { vertical: 1, horizontal: 0 }), &mut state);
+    }
+
+    /// Selected row in lists and tables
+    pub fn highlight(&self) -> Style {
+        #[cfg(target_os = "windows")]
+        {
+            Style::default()
+                .fg(self.selection_fg)
+                .bg(self.selection_bg)
+                .add_modifier(Modifier::BOLD)
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            Style::default()
+                .bg(self.selection_bg)
+                .add_modifier(Modifier::BOLD)
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::gui::theme::Theme;
+use crate::utils::text::display_width;
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::Style,
+    widgets::{Clear, Paragraph, Wrap},
+};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+/// Older toasts wait in the queue until one of these slots frees up
+const MAX_VISIBLE: usize = 3;
+const TOAST_WIDTH: u16 = 44;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+struct Toast {
+    message: String,
+    level: ToastLevel,
+    /// Set once the toast is first drawn, queued toasts don't count down
+    shown: Option<Instant>,
+}
+
+/// Short messages about background work, drawn in the top right corner of every page.
+/// Global like the notice buffer, so background tasks can report without a handle to the app.
+static TOASTS: Mutex<VecDeque<Toast>> = Mutex::new(VecDeque::new());
+
+pub fn push(level: ToastLevel, message: impl Into<String>) {
+    if let Ok(mut toasts) = TOASTS.lock() {
+        toasts.push_back(Toast {
+            message: message.into(),
+            level,
+            shown: None,
+        });
+    }
+}
+
+pub fn info(message: impl Into<String>) {
+    push(ToastLevel::Info, message);
+}
+
+pub fn success(message: impl Into<String>) {
+    push(ToastLevel::Success, message);
+}
+
+pub fn warning(message: impl Into<String>) {
+    push(ToastLevel::Warning, message);
+}
+
+pub fn error(message: impl Into<String>) {
+    push(ToastLevel::Error, message);
+}
+
+/// Whether a toast is shown or queued, the screen has to be redrawn for it to go away
+pub fn any() -> bool {
+    TOASTS.lock().is_ok_and(|toasts| !toasts.is_empty())
+}
+
+/// Drops expired toasts and draws the visible ones stacked below each other
+pub fn render(f: &mut Frame, area: Rect, theme: &Theme) {
+    let Ok(mut toasts) = TOASTS.lock() else {
+        return;
+    };
+    toasts.retain(|toast| toast.shown.is_none_or(|shown| shown.elapsed() < TOAST_DURATION));
+
+    let width = TOAST_WIDTH.min(area.width);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let mut y = area.y;
+    for toast in toasts.iter_mut().take(MAX_VISIBLE) {
+        toast.shown.get_or_insert_with(Instant::now);
+
+        let lines = display_width(&toast.message).div_ceil(inner_width).max(1) as u16;
+        let height = (lines + 2).min(area.bottom().saturating_sub(y));
+        if height < 3 {
+            break;
+        }
+        let toast_area = Rect::new(area.right() - width, y, width, height);
+        y += height;
+
+        let color = match toast.level {
+            ToastLevel::Info => theme.help,
+            ToastLevel::Success => theme.success,
+            ToastLevel::Warning => theme.warning,
+            ToastLevel::Error => theme.error,
+        };
+        let paragraph = Paragraph::new(toast.message.as_str())
+            .style(Style::default().fg(color).bg(theme.overlay_bg))
+            .block(theme.block().border_style(Style::default().fg(color)))
+            .wrap(Wrap { trim: true });
+        f.render_widget(Clear, toast_area);
+        f.render_widget(paragraph, toast_area);
+    }
+}
@@ -1,30 +1,73 @@
+mod cli;
+mod server;
 mod gui;
 mod utils;
 
 use anyhow::Result;
+use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::time::Duration;
 
-use gui::{App, AppState};
+use cli::{Cli, Command};
+use gui::App;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     sqlx::any::install_default_drivers();
+    utils::notices::install();
+
+    let cli = Cli::parse();
+    if let Some(addr) = cli.metrics_listen {
+        tokio::spawn(utils::metrics::serve(utils::metrics::bind(addr).await?));
+    }
+    match cli.command {
+        Some(Command::Exec(args)) => std::process::exit(cli::exec(args).await),
+        Some(Command::Connections { action }) => std::process::exit(cli::connections(action).await),
+        Some(Command::Dump(args)) => std::process::exit(cli::dump(args).await),
+        Some(Command::Completions { shell }) => std::process::exit(cli::completions(shell)),
+        Some(Command::Serve(args)) => std::process::exit(server::serve(args).await),
+        None => {}
+    }
+
     enable_raw_mode()?;
+    // Without this most terminals send Ctrl+Shift+C as Ctrl+C, the transaction keys need it
+    let enhanced_keys = matches!(supports_keyboard_enhancement(), Ok(true));
+    install_panic_hook(enhanced_keys);
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    if enhanced_keys {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let mut app = App::new()?;
+    if let Some(name) = cli.connection.or_else(|| app.query_page.config.default_connection.clone()) {
+        app.open_named_connection(&name);
+    }
 
     let res = run_app(&mut terminal, &mut app).await;
+    app.save_ui_state().ok();
+    app.remove_swap().ok();
 
+    if enhanced_keys {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -34,27 +77,56 @@ async fn main() -> Result<()> {
     terminal.show_cursor()?;
 
     if let Err(err) = res {
-        println!("Error: {:?}", err);
+        println!("Error: {}", utils::connection::mask_credentials(&format!("{:?}", err)));
     }
 
     Ok(())
 }
 
+/// Saves the query buffers and gives the terminal back before the panic message is
+/// printed, otherwise it ends up garbled on the alternate screen
+fn install_panic_hook(enhanced_keys: bool) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        utils::swap::save_recorded();
+        if enhanced_keys {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+        default_hook(info);
+    }));
+}
+
+/// How often running work is polled and drawn
+const TICK: Duration = Duration::from_millis(100);
+/// Polling interval with nothing running, only the swap file is written on it
+const IDLE_TICK: Duration = Duration::from_secs(1);
+
 async fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
 ) -> Result<()> {
+    let mut redraw = true;
     loop {
-        terminal.draw(|f| app.render(f))?;
+        // Checked before the tick, so the frame showing a finished task is still drawn
+        let busy = app.is_busy();
+        let changed = app.on_tick().await;
+        if redraw || busy || changed {
+            terminal.draw(|f| app.render(f))?;
+        }
 
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == crossterm::event::KeyEventKind::Press {
-                    if (key.code == KeyCode::Esc || key.code == KeyCode::Char('q')) && app.state == AppState::ConnectionList {
+        redraw = event::poll(if busy { TICK } else { IDLE_TICK })?;
+        if redraw {
+            match event::read()? {
+                Event::Key(key) if key.kind == crossterm::event::KeyEventKind::Press => {
+                    app.handle_input(key).await?;
+                    if app.should_quit {
                         return Ok(());
                     }
-                    app.handle_input(key).await?;
                 }
+                Event::Mouse(mouse) => app.handle_mouse(mouse).await?,
+                _ => {}
             }
         }
     }
@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::{Value as Json, json};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+use crate::cli::{EXIT_FAILURE, EXIT_OK};
+use crate::utils::{
+    config::{Config, ConfigManager},
+    connection::{Connection, ConnectionManager},
+    export::json_full_rows,
+    http::{Request, percent_decode, read_request, write_response},
+    metrics,
+    query_executor::QueryExecutor,
+    sql::{push_down_limit, split_statements},
+    value::Value,
+};
+
+/// Rows returned per result set unless the request asks for another limit
+const DEFAULT_MAX_ROWS: usize = 10_000;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to accept requests on
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    listen: SocketAddr,
+    /// Bearer token clients must send, RSQUID_API_TOKEN when omitted, else a random one
+    #[arg(long)]
+    token: Option<String>,
+}
+
+/// What the handlers share: the token and one pool per connection, opened on first use
+struct Server {
+    token: String,
+    config: Config,
+    executors: Mutex<HashMap<String, (Connection, Arc<QueryExecutor>)>>,
+}
+
+/// Body of POST /connections/{name}/query
+#[derive(Deserialize)]
+struct QueryRequest {
+    sql: String,
+    /// Bind parameters, the SQL must then be a single statement
+    #[serde(default)]
+    params: Vec<Json>,
+    max_rows: Option<usize>,
+}
+
+/// A response before it is written, errors become `{"error": ...}`
+type Reply = (u16, Json);
+
+/// `rsquid serve`: answers HTTP requests until stopped, returns the process exit code
+pub async fn serve(args: ServeArgs) -> i32 {
+    match run(args).await {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+async fn run(args: ServeArgs) -> Result<()> {
+    let token = match args.token.or_else(|| std::env::var("RSQUID_API_TOKEN").ok()) {
+        Some(token) => token,
+        None => {
+            let mut rng = rand::thread_rng();
+            let token: String = (0..16).map(|_| format!("{:02x}", rng.r#gen::<u8>())).collect();
+            eprintln!("API token: {}", token);
+            token
+        }
+    };
+    let config = ConfigManager::new()
+        .and_then(|m| m.load_config())
+        .unwrap_or_else(|_| Config::default());
+    let listener = TcpListener::bind(args.listen)
+        .await
+        .with_context(|| format!("Could not listen on {}", args.listen))?;
+    eprintln!("Listening on http://{}", args.listen);
+
+    let server = Arc::new(Server { token, config, executors: Mutex::new(HashMap::new()) });
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move { server.handle(stream).await });
+    }
+}
+
+impl Server {
+    async fn handle(&self, stream: TcpStream) {
+        let mut stream = BufReader::new(stream);
+        let (status, body) = match read_request(&mut stream).await {
+            // Counters only, scrapers don't have to know the token
+            Ok(request) if request.method == "GET" && request.path == "/metrics" => {
+                let _ = write_response(&mut stream, 200, "text/plain; version=0.0.4", metrics::render().as_bytes()).await;
+                return;
+            }
+            Ok(request) => self.route(&request).await,
+            Err(e) => (400, json!({ "error": format!("{:#}", e) })),
+        };
+        let body = serde_json::to_vec_pretty(&body).unwrap_or_default();
+        let _ = write_response(&mut stream, status, "application/json", &body).await;
+    }
+
+    async fn route(&self, request: &Request) -> Reply {
+        let segments: Vec<String> = request.path.split('/').filter(|s| !s.is_empty()).map(percent_decode).collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["health"]) => (200, json!({ "status": "ok" })),
+            _ if !self.authorized(request) => (401, json!({ "error": "Missing or wrong bearer token" })),
+            ("GET", ["connections"]) => list_connections(),
+            ("POST", ["connections", name, "query"]) => self.query(name, &request.body).await,
+            (_, ["connections"]) | (_, ["connections", _, "query"]) => (405, json!({ "error": "Method not allowed" })),
+            _ => (404, json!({ "error": "Unknown endpoint" })),
+        }
+    }
+
+    fn authorized(&self, request: &Request) -> bool {
+        let Some(token) = request.header("Authorization").and_then(|h| h.strip_prefix("Bearer ")) else {
+            return false;
+        };
+        // Compared in full every time, so the timing doesn't tell how much matched
+        token.len() == self.token.len() && token.bytes().zip(self.token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+
+    async fn query(&self, name: &str, body: &[u8]) -> Reply {
+        let request: QueryRequest = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => return (400, json!({ "error": format!("Expected {{\"sql\": ...}}: {}", e) })),
+        };
+        let connection = match find_connection(name) {
+            Ok(Some(connection)) => connection,
+            Ok(None) => return (404, json!({ "error": format!("Unknown connection: {}", name) })),
+            Err(e) => return (500, json!({ "error": format!("{:#}", e) })),
+        };
+        let executor = match self.executor(&connection).await {
+            Ok(executor) => executor,
+            Err(e) => return (502, json!({ "error": connection.redact(&format!("{:#}", e)) })),
+        };
+
+        let max_rows = request.max_rows.unwrap_or(DEFAULT_MAX_ROWS);
+        match run_statements(&executor, &request, max_rows).await {
+            Ok(statements) => (200, json!({ "connection": connection.name, "statements": statements })),
+            Err((statements, e)) => (
+                400,
+                json!({ "error": connection.redact(&format!("{:#}", e)), "statements": statements }),
+            ),
+        }
+    }
+
+    /// The pool of `connection`, reopened when its settings changed since the last request.
+    /// Connecting happens outside the lock, a slow database doesn't hold up the others.
+    async fn executor(&self, connection: &Connection) -> Result<Arc<QueryExecutor>> {
+        if let Some((opened_with, executor)) = self.executors.lock().await.get(&connection.name)
+            && opened_with == connection
+        {
+            return Ok(executor.clone());
+        }
+        let executor = Arc::new(QueryExecutor::new(connection, &self.config).await?);
+
+        let mut executors = self.executors.lock().await;
+        // Another request may have opened the same pool meanwhile, keep only one
+        if let Some((opened_with, current)) = executors.get(&connection.name)
+            && opened_with == connection
+        {
+            let current = current.clone();
+            tokio::spawn(async move { executor.close().await });
+            return Ok(current);
+        }
+        if let Some((_, stale)) = executors.insert(connection.name.clone(), (connection.clone(), executor.clone())) {
+            tokio::spawn(async move { stale.close().await });
+        }
+        Ok(executor)
+    }
+}
+
+/// Saved connections without their passwords
+fn list_connections() -> Reply {
+    let connections = match ConnectionManager::new().and_then(|m| m.load_connections()) {
+        Ok(connections) => connections,
+        Err(e) => return (500, json!({ "error": format!("{:#}", e) })),
+    };
+    let listed: Vec<Json> = connections
+        .iter()
+        .map(|c| {
+            json!({
+                "name": c.name,
+                "type": c.db_type,
+                "host": c.host_label(),
+                "port": c.port,
+                "database": c.database,
+                "username": c.username,
+            })
+        })
+        .collect();
+    (200, Json::Array(listed))
+}
+
+/// Read again on every request, so connections edited in the TUI apply right away
+fn find_connection(name: &str) -> Result<Option<Connection>> {
+    Ok(ConnectionManager::new()?.load_connections()?.into_iter().find(|c| c.name == name))
+}
+
+/// Runs each statement, stopping at the first failure with the results so far
+async fn run_statements(
+    executor: &QueryExecutor,
+    request: &QueryRequest,
+    max_rows: usize,
+) -> std::result::Result<Vec<Json>, (Vec<Json>, anyhow::Error)> {
+    // One row past the limit tells whether the result was cut, the database stops there
+    let limit = u32::try_from(max_rows.saturating_add(1)).ok();
+    let limited = |statement: &str| limit.and_then(|limit| push_down_limit(statement, limit, executor.dialect()));
+
+    if !request.params.is_empty() {
+        let params: Vec<Value> = request.params.iter().map(json_param).collect();
+        let started = Instant::now();
+        let sql = limited(&request.sql).unwrap_or_else(|| request.sql.clone());
+        return match executor.execute_prepared(&sql, &params).await {
+            Ok(sets) => Ok(vec![statement_json(&request.sql, started, sets, max_rows)]),
+            Err(e) => Err((Vec::new(), e)),
+        };
+    }
+
+    let mut statements = Vec::new();
+    for statement in split_statements(&request.sql, executor.dialect()) {
+        let started = Instant::now();
+        let sql = limited(statement);
+        match executor.execute_sets(sql.as_deref().unwrap_or(statement)).await {
+            Ok(sets) => statements.push(statement_json(statement, started, sets, max_rows)),
+            Err(e) => return Err((statements, e.context(format!("Statement failed: {}", statement.trim())))),
+        }
+    }
+    Ok(statements)
+}
+
+fn statement_json(statement: &str, started: Instant, sets: Vec<(Vec<String>, Vec<Vec<Value>>)>, max_rows: usize) -> Json {
+    let result_sets: Vec<Json> = sets
+        .into_iter()
+        .map(|(headers, mut rows)| {
+            let truncated = rows.len() > max_rows;
+            rows.truncate(max_rows);
+            let mut set = json_full_rows(&headers, &rows);
+            set["truncated"] = json!(truncated);
+            set
+        })
+        .collect();
+    json!({
+        "statement": statement.trim(),
+        "duration_ms": started.elapsed().as_millis() as u64,
+        "result_sets": result_sets,
+    })
+}
+
+fn json_param(param: &Json) -> Value {
+    match param {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Bool(*b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Int(i),
+            None => Value::Float(n.as_f64().unwrap_or_default()),
+        },
+        Json::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
@@ -0,0 +1,358 @@
+use crate::utils::query_executor::QueryExecutor;
+use crate::utils::sql::Dialect;
+use crate::utils::value::Value;
+use anyhow::{Result, bail};
+use std::time::Duration;
+
+/// A connection to the server as its process list shows it
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Backend pid on Postgres, connection id on MySQL
+    pub id: i64,
+    pub user: String,
+    pub database: String,
+    /// Client address, with the application name when it sent one
+    pub client: String,
+    pub state: String,
+    /// How long the session has been in its current state
+    pub duration: Option<Duration>,
+    pub query: String,
+    /// The connection that ran the lookup, so one of rsquid's own
+    pub current: bool,
+}
+
+/// Client sessions of the server, busiest first. The backend pid is cast
+/// because only 64 bit integers decode, and the epoch because it is NUMERIC.
+const POSTGRES_SESSIONS: &str = "\
+    SELECT pid::bigint, COALESCE(usename::text, ''), COALESCE(datname::text, ''), \
+    CONCAT_WS(' @ ', NULLIF(application_name, ''), COALESCE(host(client_addr), 'local')), \
+    COALESCE(state, ''), \
+    EXTRACT(EPOCH FROM clock_timestamp() - COALESCE(CASE WHEN state = 'active' THEN query_start ELSE state_change END, backend_start))::float8, \
+    COALESCE(query, ''), pid = pg_backend_pid() \
+    FROM pg_stat_activity WHERE backend_type = 'client backend' \
+    ORDER BY state = 'active' DESC, 6 DESC NULLS LAST";
+
+/// The same as SHOW FULL PROCESSLIST, but marks the connection asking
+const MYSQL_SESSIONS: &str = "\
+    SELECT CAST(ID AS SIGNED), USER, COALESCE(DB, ''), HOST, \
+    TRIM(CONCAT(COMMAND, ' ', COALESCE(STATE, ''))), CAST(TIME AS SIGNED), COALESCE(INFO, ''), \
+    ID = CONNECTION_ID() \
+    FROM information_schema.PROCESSLIST \
+    ORDER BY COMMAND <> 'Sleep' DESC, TIME DESC";
+
+/// Lists the sessions connected to the server. SQLite runs in-process and has none.
+pub async fn list_sessions(executor: &QueryExecutor) -> Result<Vec<Session>> {
+    let dialect = executor.dialect();
+    let query = match dialect {
+        Dialect::Postgres => POSTGRES_SESSIONS,
+        Dialect::MySql => MYSQL_SESSIONS,
+        Dialect::Sqlite => bail!("SQLite has no server sessions to list"),
+    };
+    let sets = executor.execute_sets(query).await?;
+    Ok(sets
+        .into_iter()
+        .flat_map(|(_, rows)| rows)
+        .filter_map(|row| parse_session(&row))
+        .collect())
+}
+
+/// Reads the columns of the sessions queries, which the locks queries start with too
+fn parse_session(row: &[Value]) -> Option<Session> {
+    let [id, user, database, client, state, duration, query, current, ..] = row else {
+        return None;
+    };
+    let seconds = match duration {
+        Value::Float(v) => Some(*v),
+        Value::Int(v) => Some(*v as f64),
+        _ => None,
+    };
+    Some(Session {
+        id: as_int(id)?,
+        user: as_text(user),
+        database: as_text(database),
+        client: as_text(client),
+        state: as_text(state),
+        duration: seconds.filter(|s| s.is_finite() && *s >= 0.0).map(Duration::from_secs_f64),
+        query: as_text(query),
+        current: matches!(current, Value::Bool(true)) || as_int(current) == Some(1),
+    })
+}
+
+/// A session taking part in a lock wait, either waiting or in the way of one
+#[derive(Debug, Clone)]
+pub struct LockSession {
+    pub session: Session,
+    /// Sessions holding the lock this one waits for
+    pub blocked_by: Vec<i64>,
+    /// The lock waited for, empty when the session isn't waiting
+    pub waiting_for: String,
+    /// Number of granted locks, MySQL doesn't say
+    pub locks_held: Option<u64>,
+}
+
+/// Sessions waiting on a lock and the sessions they wait for, with the same leading
+/// columns as the sessions query
+const POSTGRES_LOCKS: &str = "\
+    WITH waits AS (SELECT pid, pg_blocking_pids(pid) AS blockers FROM pg_stat_activity \
+    WHERE cardinality(pg_blocking_pids(pid)) > 0) \
+    SELECT a.pid::bigint, COALESCE(a.usename::text, ''), COALESCE(a.datname::text, ''), \
+    CONCAT_WS(' @ ', NULLIF(a.application_name, ''), COALESCE(host(a.client_addr), 'local')), \
+    COALESCE(a.state, ''), \
+    EXTRACT(EPOCH FROM clock_timestamp() - COALESCE(CASE WHEN a.state = 'active' THEN a.query_start ELSE a.state_change END, a.backend_start))::float8, \
+    COALESCE(a.query, ''), a.pid = pg_backend_pid(), \
+    COALESCE(array_to_string(w.blockers, ','), ''), \
+    COALESCE((SELECT l.mode || ' on ' || COALESCE(l.relation::regclass::text, l.locktype) \
+    FROM pg_locks l WHERE l.pid = a.pid AND NOT l.granted LIMIT 1), ''), \
+    (SELECT count(*) FROM pg_locks l WHERE l.pid = a.pid AND l.granted) \
+    FROM pg_stat_activity a LEFT JOIN waits w ON w.pid = a.pid \
+    WHERE w.pid IS NOT NULL OR a.pid IN (SELECT unnest(blockers) FROM waits)";
+
+/// Row lock waits of InnoDB and table metadata lock waits, both from the sys schema of MySQL 8
+const MYSQL_LOCKS: &str = "\
+    WITH waits AS (\
+    SELECT waiting_pid, blocking_pid, CONCAT(waiting_lock_mode, ' on ', locked_table) AS lock_name \
+    FROM sys.innodb_lock_waits \
+    UNION ALL SELECT waiting_pid, blocking_pid, CONCAT(waiting_lock_type, ' metadata on ', object_schema, '.', object_name) \
+    FROM sys.schema_table_lock_waits WHERE waiting_pid <> blocking_pid) \
+    SELECT CAST(p.ID AS SIGNED), p.USER, COALESCE(p.DB, ''), p.HOST, \
+    TRIM(CONCAT(p.COMMAND, ' ', COALESCE(p.STATE, ''))), CAST(p.TIME AS SIGNED), COALESCE(p.INFO, ''), \
+    p.ID = CONNECTION_ID(), COALESCE(w.blockers, ''), COALESCE(w.lock_name, ''), NULL \
+    FROM information_schema.PROCESSLIST p \
+    LEFT JOIN (SELECT waiting_pid, GROUP_CONCAT(DISTINCT blocking_pid) AS blockers, MAX(lock_name) AS lock_name \
+    FROM waits GROUP BY waiting_pid) w ON w.waiting_pid = p.ID \
+    WHERE w.waiting_pid IS NOT NULL OR p.ID IN (SELECT blocking_pid FROM waits)";
+
+/// Lists the sessions involved in lock waits. SQLite locks the whole file and has no sessions to show.
+pub async fn list_locks(executor: &QueryExecutor) -> Result<Vec<LockSession>> {
+    let query = match executor.dialect() {
+        Dialect::Postgres => POSTGRES_LOCKS,
+        Dialect::MySql => MYSQL_LOCKS,
+        Dialect::Sqlite => bail!("SQLite has no server sessions to hold locks"),
+    };
+    let sets = executor.execute_sets(query).await?;
+    Ok(sets
+        .into_iter()
+        .flat_map(|(_, rows)| rows)
+        .filter_map(|row| {
+            let session = parse_session(&row)?;
+            let [.., blocked_by, waiting_for, locks_held] = row.as_slice() else {
+                return None;
+            };
+            Some(LockSession {
+                session,
+                blocked_by: as_text(blocked_by).split(',').filter_map(|id| id.trim().parse().ok()).collect(),
+                waiting_for: as_text(waiting_for),
+                locks_held: as_count(locks_held),
+            })
+        })
+        .collect())
+}
+
+/// Arranges the sessions as blocker -> blocked trees, as (depth, index) rows in
+/// display order. Sessions waiting on nobody listed are the roots, the longest
+/// running first. A session blocked by several shows up under each of them,
+/// and the sessions of a deadlock cycle start a tree of their own.
+pub fn blocking_tree(sessions: &[LockSession]) -> Vec<(usize, usize)> {
+    fn visit(sessions: &[LockSession], index: usize, depth: usize, path: &mut Vec<usize>, rows: &mut Vec<(usize, usize)>) {
+        rows.push((depth, index));
+        path.push(index);
+        let id = sessions[index].session.id;
+        for (child, session) in sessions.iter().enumerate() {
+            if session.blocked_by.contains(&id) && !path.contains(&child) {
+                visit(sessions, child, depth + 1, path, rows);
+            }
+        }
+        path.pop();
+    }
+
+    let listed = |id: &i64| sessions.iter().any(|s| s.session.id == *id);
+    let mut roots: Vec<usize> = (0..sessions.len())
+        .filter(|&i| !sessions[i].blocked_by.iter().any(listed))
+        .collect();
+    roots.sort_by(|&a, &b| sessions[b].session.duration.cmp(&sessions[a].session.duration));
+
+    let mut rows = Vec::new();
+    let mut path = Vec::new();
+    for root in roots {
+        visit(sessions, root, 0, &mut path, &mut rows);
+    }
+    for index in 0..sessions.len() {
+        if !rows.iter().any(|&(_, shown)| shown == index) {
+            visit(sessions, index, 0, &mut path, &mut rows);
+        }
+    }
+    rows
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        other => other.to_string(),
+    }
+}
+
+fn as_int(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int(v) => Some(*v),
+        Value::UInt(v) => i64::try_from(*v).ok(),
+        _ => None,
+    }
+}
+
+/// Stops another session: `kill` ends its connection, otherwise only its running
+/// query is cancelled. Postgres needs the rights to signal that backend.
+pub async fn stop_session(executor: &QueryExecutor, id: i64, kill: bool) -> Result<()> {
+    match executor.dialect() {
+        Dialect::Postgres => {
+            let function = if kill { "pg_terminate_backend" } else { "pg_cancel_backend" };
+            // pids are int4, a bigint argument finds no function
+            let sets = executor
+                .execute_prepared(&format!("SELECT {}(CAST($1 AS integer))", function), &[Value::Int(id)])
+                .await?;
+            match sets.first().and_then(|(_, rows)| rows.first()).and_then(|row| row.first()) {
+                Some(Value::Bool(true)) => Ok(()),
+                _ => bail!("Session {} has already ended", id),
+            }
+        }
+        Dialect::MySql => {
+            let statement = if kill { "KILL CONNECTION" } else { "KILL QUERY" };
+            executor.execute_sets(&format!("{} {}", statement, id)).await?;
+            Ok(())
+        }
+        Dialect::Sqlite => bail!("SQLite has no server sessions"),
+    }
+}
+
+/// Phases of a VACUUM, VACUUM FULL or ANALYZE running on `table`, from a connection other
+/// than the one running it. Only Postgres reports them.
+const POSTGRES_MAINTENANCE_PROGRESS: &str = "\
+    SELECT phase, heap_blks_scanned::bigint, heap_blks_total::bigint FROM pg_stat_progress_vacuum \
+    WHERE relid = CAST($1 AS regclass) \
+    UNION ALL SELECT phase, heap_blks_scanned::bigint, heap_blks_total::bigint FROM pg_stat_progress_cluster \
+    WHERE relid = CAST($1 AS regclass) \
+    UNION ALL SELECT phase, sample_blks_scanned::bigint, sample_blks_total::bigint FROM pg_stat_progress_analyze \
+    WHERE relid = CAST($1 AS regclass)";
+
+/// Like "scanning heap 42%", None when nothing is reported for `table`
+pub async fn maintenance_progress(executor: &QueryExecutor, table: &str) -> Option<String> {
+    if executor.dialect() != Dialect::Postgres {
+        return None;
+    }
+    let quoted = crate::utils::sql::quote_identifier(table, Dialect::Postgres);
+    let sets = executor
+        .execute_detached(POSTGRES_MAINTENANCE_PROGRESS, &[Value::Text(quoted)])
+        .await
+        .ok()?;
+    let row = sets.into_iter().flat_map(|(_, rows)| rows).next()?;
+    let [phase, scanned, total] = row.as_slice() else {
+        return None;
+    };
+    let phase = as_text(phase);
+    match (as_count(scanned), as_count(total)) {
+        (Some(scanned), Some(total)) if total > 0 => Some(format!("{} {}%", phase, scanned * 100 / total)),
+        _ => Some(phase),
+    }
+}
+
+/// What the sizes page lists
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeScope {
+    Databases,
+    /// Tables of the current database
+    Tables,
+}
+
+/// Disk usage of a database or table. Sizes the server doesn't report stay None.
+#[derive(Debug, Clone)]
+pub struct SizeEntry {
+    pub name: String,
+    pub total_bytes: Option<u64>,
+    pub data_bytes: Option<u64>,
+    pub index_bytes: Option<u64>,
+    /// The catalog's row estimate, not a count
+    pub rows: Option<u64>,
+}
+
+/// Databases the user may connect to; the others would make pg_database_size fail
+const POSTGRES_DATABASE_SIZES: &str = "\
+    SELECT datname::text, pg_database_size(datname)::bigint, NULL::bigint, NULL::bigint, NULL::bigint \
+    FROM pg_database WHERE datallowconn AND has_database_privilege(datname, 'CONNECT')";
+
+/// Tables, partitioned tables and materialized views outside the system schemas.
+/// reltuples is -1 until the table was first analyzed.
+const POSTGRES_TABLE_SIZES: &str = "\
+    SELECT n.nspname || '.' || c.relname, pg_total_relation_size(c.oid), pg_table_size(c.oid), \
+    pg_indexes_size(c.oid), CASE WHEN c.reltuples < 0 THEN NULL ELSE c.reltuples::bigint END \
+    FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+    WHERE c.relkind IN ('r', 'p', 'm') AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+    AND n.nspname NOT LIKE 'pg_toast%'";
+
+/// SUM gives DECIMAL, which only decodes as a float
+const MYSQL_DATABASE_SIZES: &str = "\
+    SELECT TABLE_SCHEMA, CAST(SUM(DATA_LENGTH + INDEX_LENGTH) AS SIGNED), CAST(SUM(DATA_LENGTH) AS SIGNED), \
+    CAST(SUM(INDEX_LENGTH) AS SIGNED), CAST(SUM(TABLE_ROWS) AS SIGNED) \
+    FROM information_schema.TABLES GROUP BY TABLE_SCHEMA";
+
+const MYSQL_TABLE_SIZES: &str = "\
+    SELECT TABLE_NAME, CAST(DATA_LENGTH + INDEX_LENGTH AS SIGNED), CAST(DATA_LENGTH AS SIGNED), \
+    CAST(INDEX_LENGTH AS SIGNED), CAST(TABLE_ROWS AS SIGNED) \
+    FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_TYPE = 'BASE TABLE'";
+
+/// The file of each attached database, page count times page size
+const SQLITE_DATABASE_SIZES: &str = "\
+    SELECT name, CAST((SELECT page_count FROM pragma_page_count) * (SELECT page_size FROM pragma_page_size) AS TEXT), \
+    NULL, NULL, NULL FROM pragma_database_list WHERE name = 'main'";
+
+/// Pages per table and index from the dbstat table, which not every build of SQLite has
+const SQLITE_TABLE_SIZES: &str = "\
+    SELECT t.name, CAST(SUM(s.pgsize) AS TEXT), \
+    CAST(SUM(CASE WHEN s.name = t.name THEN s.pgsize ELSE 0 END) AS TEXT), \
+    CAST(SUM(CASE WHEN s.name = t.name THEN 0 ELSE s.pgsize END) AS TEXT), NULL \
+    FROM sqlite_master t JOIN sqlite_master o ON o.tbl_name = t.name JOIN dbstat s ON s.name = o.name \
+    WHERE t.type = 'table' AND t.name NOT LIKE 'sqlite_%' GROUP BY t.name";
+
+const SQLITE_TABLE_NAMES: &str = "\
+    SELECT name, NULL, NULL, NULL, NULL FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'";
+
+/// Sizes of the server's databases or the current database's tables, unsorted
+pub async fn list_sizes(executor: &QueryExecutor, scope: SizeScope) -> Result<Vec<SizeEntry>> {
+    let query = match (executor.dialect(), scope) {
+        (Dialect::Postgres, SizeScope::Databases) => POSTGRES_DATABASE_SIZES,
+        (Dialect::Postgres, SizeScope::Tables) => POSTGRES_TABLE_SIZES,
+        (Dialect::MySql, SizeScope::Databases) => MYSQL_DATABASE_SIZES,
+        (Dialect::MySql, SizeScope::Tables) => MYSQL_TABLE_SIZES,
+        (Dialect::Sqlite, SizeScope::Databases) => SQLITE_DATABASE_SIZES,
+        (Dialect::Sqlite, SizeScope::Tables) => SQLITE_TABLE_SIZES,
+    };
+    let sets = match executor.execute_sets(query).await {
+        // Without dbstat the tables are still listed, just without sizes
+        Err(_) if query == SQLITE_TABLE_SIZES => executor.execute_sets(SQLITE_TABLE_NAMES).await?,
+        result => result?,
+    };
+    Ok(sets
+        .into_iter()
+        .flat_map(|(_, rows)| rows)
+        .filter_map(|row| {
+            let [name, total, data, index, rows] = row.as_slice() else {
+                return None;
+            };
+            Some(SizeEntry {
+                name: as_text(name),
+                total_bytes: as_count(total),
+                data_bytes: as_count(data),
+                index_bytes: as_count(index),
+                rows: as_count(rows),
+            })
+        })
+        .collect())
+}
+
+fn as_count(value: &Value) -> Option<u64> {
+    match value {
+        Value::Int(v) => u64::try_from(*v).ok(),
+        Value::UInt(v) => Some(*v),
+        Value::Text(text) => text.parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,13 @@
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::io::Write;
+
+/// Puts `text` on the system clipboard through the OSC 52 escape sequence. The terminal
+/// does the copying, so it works over SSH and without a display server, as long as the
+/// terminal (or tmux with `set-clipboard on`) supports it.
+pub fn copy(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anyhow::{Context, Result};
+use std::process::Stdio;
+use tokio::process::Command;
+
+use crate::utils::tunnel::{Tunnel, free_port};
+
+/// Starts the Cloud SQL Auth Proxy for `instance`, a project:region:instance connection
+/// name, returning once it listens. The proxy finds the instance, authorizes through the
+/// gcloud or application default credentials and brings its own certificates, so the
+/// instance needs no allowlisted IP.
+pub async fn proxy(instance: &str) -> Result<Tunnel> {
+    let local_port = free_port()?;
+    let child = Command::new("cloud-sql-proxy")
+        .args(["--address", "127.0.0.1", "--port", &local_port.to_string(), instance])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Could not run cloud-sql-proxy, is the Cloud SQL Auth Proxy installed?")?;
+    Tunnel::open(child, local_port, &format!("Cloud SQL proxy for {}", instance)).await
+}
@@ -0,0 +1,81 @@
+use std::ops::Range;
+
+use crate::utils::value::Value;
+
+/// Result cells stored a column at a time. A column is one allocation instead of one
+/// per row, and work on a single column, like sizing it, walks contiguous memory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Columns {
+    columns: Vec<Vec<Value>>,
+    len: usize,
+}
+
+impl Columns {
+    pub fn from_rows(rows: Vec<Vec<Value>>) -> Self {
+        let mut columns = Self::default();
+        columns.extend(rows);
+        columns
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of columns
+    pub fn width(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends a row, short rows are padded with NULL
+    pub fn push_row(&mut self, row: Vec<Value>) {
+        // Rows seen so far get NULL in a column only a later row has
+        while self.columns.len() < row.len() {
+            self.columns.push(vec![Value::Null; self.len]);
+        }
+        let width = row.len();
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+        for column in &mut self.columns[width..] {
+            column.push(Value::Null);
+        }
+        self.len += 1;
+    }
+
+    pub fn extend(&mut self, rows: Vec<Vec<Value>>) {
+        for column in &mut self.columns {
+            column.reserve(rows.len());
+        }
+        rows.into_iter().for_each(|row| self.push_row(row));
+    }
+
+    pub fn clear(&mut self) {
+        self.columns.clear();
+        self.len = 0;
+    }
+
+    /// Every value of column `index`, empty past the last column
+    pub fn column(&self, index: usize) -> &[Value] {
+        self.columns.get(index).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Option<&Value> {
+        self.columns.get(column)?.get(row)
+    }
+
+    /// The cells of row `index` from left to right
+    pub fn row(&self, index: usize) -> impl Iterator<Item = &Value> {
+        self.columns.iter().filter_map(move |column| column.get(index))
+    }
+
+    /// The rows in `range`, clamped to the rows there are
+    pub fn rows(&self, range: Range<usize>) -> impl Iterator<Item = impl Iterator<Item = &Value>> {
+        let end = range.end.min(self.len);
+        (range.start.min(end)..end).map(|index| self.row(index))
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::gui::{glyphs::Glyphs, theme::Theme};
+use crate::utils::masking::Masking;
+use crate::utils::value::ValueFormat;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Seconds a single statement may run before it is cancelled, 0 disables the limit
+    pub query_timeout_secs: u64,
+    /// Times a statement is retried after a dropped connection, serialization
+    /// failure or deadlock, 0 surfaces the error right away. Writes are not retried
+    /// after the connection dropped, they may have committed already.
+    pub retry_attempts: u32,
+    /// Ask before running a SELECT without LIMIT the planner expects to return more
+    /// rows than this (Postgres and MySQL only), 0 disables the check
+    pub large_result_warning_rows: u64,
+    /// Seconds between pings that keep an idle connection from timing out, 0 disables them
+    pub keepalive_secs: u64,
+    /// Megabytes a result may take in memory before fetching stops, 0 disables the limit
+    pub result_memory_mb: u64,
+    /// Seconds between the runs of a query watched with Ctrl+W
+    pub watch_interval_secs: u64,
+    /// Connection pools kept open after going back to the connection list, 0 closes them right away
+    pub warm_pools: usize,
+    /// Keep the explorer's tables and columns on disk between runs, not only in memory
+    pub cache_schema: bool,
+    /// A built-in theme (dark, light, high-contrast) or one defined under [themes]
+    pub theme: String,
+    /// Custom themes by name, missing colors fall back to the dark theme
+    pub themes: BTreeMap<String, Theme>,
+    /// Timestamp and number rendering in the results table
+    pub format: ValueFormat,
+    /// Columns masked in copied and exported results
+    pub masking: Masking,
+    /// Draw icons and borders with plain ASCII, unset picks it for limited terminals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ascii_only: Option<bool>,
+    /// Saved connection opened at startup instead of the connection list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_connection: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            query_timeout_secs: 0,
+            retry_attempts: 1,
+            large_result_warning_rows: 0,
+            keepalive_secs: 60,
+            result_memory_mb: 512,
+            watch_interval_secs: 2,
+            warm_pools: 3,
+            cache_schema: true,
+            theme: "dark".to_string(),
+            themes: BTreeMap::new(),
+            format: ValueFormat::default(),
+            masking: Masking::default(),
+            ascii_only: None,
+            default_connection: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn query_timeout(&self) -> Option<Duration> {
+        (self.query_timeout_secs > 0).then(|| Duration::from_secs(self.query_timeout_secs))
+    }
+
+    pub fn keepalive_interval(&self) -> Option<Duration> {
+        (self.keepalive_secs > 0).then(|| Duration::from_secs(self.keepalive_secs))
+    }
+
+    pub fn result_memory_limit(&self) -> Option<usize> {
+        (self.result_memory_mb > 0).then(|| self.result_memory_mb as usize * 1024 * 1024)
+    }
+
+    /// Never faster than once a second
+    pub fn watch_interval(&self) -> Duration {
+        Duration::from_secs(self.watch_interval_secs.max(1))
+    }
+
+    /// Custom themes shadow built-in ones, an unknown name falls back to dark
+    pub fn theme(&self) -> Theme {
+        let mut theme = self
+            .themes
+            .get(&self.theme)
+            .cloned()
+            .or_else(|| Theme::built_in(&self.theme))
+            .unwrap_or_default();
+        let ascii_only = self.ascii_only.unwrap_or_else(Glyphs::detect_limited_terminal);
+        theme.glyphs = if ascii_only { Glyphs::ASCII } else { Glyphs::UNICODE };
+        theme
+    }
+}
+
+pub struct ConfigManager {
+    config_path: PathBuf,
+}
+
+impl ConfigManager {
+    pub fn new() -> Result<Self> {
+        let config_dir = dirs::config_dir()
+            .context("Could not find config directory")?
+            .join("rsquid");
+
+        fs::create_dir_all(&config_dir)?;
+
+        let config_path = config_dir.join("config.toml");
+
+        Ok(Self { config_path })
+    }
+
+    pub fn load_config(&self) -> Result<Config> {
+        if !self.config_path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(&self.config_path)?;
+        let config: Config = toml::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", self.config_path.display()))?;
+        Ok(config)
+    }
+
+    pub fn save_config(&self, config: &Config) -> Result<()> {
+        let content = toml::to_string_pretty(config)?;
+        fs::write(&self.config_path, content)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use serde_yaml::Value as Yaml;
+use std::path::PathBuf;
+
+use crate::utils::connection::{Connection, DiscoveredDatabase};
+
+/// Every target of the dbt profiles whose adapter speaks a protocol rsquid has
+pub async fn discover() -> Result<Vec<DiscoveredDatabase>> {
+    let path = profiles_path()?;
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    let profiles: Yaml = serde_yaml::from_str(&content).with_context(|| format!("Could not parse {}", path.display()))?;
+
+    let mut found = Vec::new();
+    for (profile, body) in profiles.as_mapping().into_iter().flatten() {
+        let Some(profile) = profile.as_str() else {
+            continue;
+        };
+        // Old profiles files keep global settings next to the profiles
+        if profile == "config" {
+            continue;
+        }
+        for (target, output) in body["outputs"].as_mapping().into_iter().flatten() {
+            if let Some(target) = target.as_str()
+                && let Some(connection) = connection_of(&format!("{}-{}", profile, target), output)
+            {
+                found.push(DiscoveredDatabase {
+                    origin: format!("{}.{}", profile, target),
+                    detail: format!("dbt-{}", text(&output["type"]).unwrap_or_default()),
+                    connection,
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// DBT_PROFILES_DIR when set, like dbt itself
+fn profiles_path() -> Result<PathBuf> {
+    let dir = match std::env::var_os("DBT_PROFILES_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir().context("Could not find home directory")?.join(".dbt"),
+    };
+    Ok(dir.join("profiles.yml"))
+}
+
+fn connection_of(name: &str, output: &Yaml) -> Option<Connection> {
+    let field = |names: &[&str]| names.iter().find_map(|name| text(&output[*name]));
+    // Redshift and the other postgres lookalikes take the same connections
+    let (db_type, port) = match text(&output["type"])?.as_str() {
+        "postgres" | "redshift" | "materialize" | "cockroachdb" | "timescaledb" => ("postgres", 5432),
+        "mysql" | "mysql5" | "tidb" | "singlestore" => ("mysql", 3306),
+        "mariadb" => ("mariadb", 3306),
+        "sqlite" => ("sqlite", 0),
+        _ => return None,
+    };
+
+    let database = if db_type == "sqlite" {
+        // dbt-sqlite attaches files per schema, main is the one to open
+        text(&output["schemas_and_paths"]["main"])?
+    } else if db_type == "postgres" {
+        field(&["dbname", "database"]).unwrap_or_default()
+    } else {
+        // MySQL has no databases within schemas, dbt-mysql calls it the schema
+        field(&["schema", "database"]).unwrap_or_default()
+    };
+
+    Some(Connection {
+        name: name.to_string(),
+        db_type: db_type.to_string(),
+        host: field(&["host", "server"]).unwrap_or_else(|| "localhost".to_string()),
+        port: field(&["port"]).and_then(|port| port.parse().ok()).unwrap_or(port),
+        database,
+        username: field(&["user", "username"]).unwrap_or_default(),
+        password: field(&["password", "pass"]).unwrap_or_default(),
+        query_timeout_secs: None,
+        accent_color: None,
+        kube: None,
+        ssh: None,
+        cloud_sql: None,
+        aws_iam: false,
+        notes: None,
+    })
+}
+
+/// A scalar as text with its `{{ env_var(...) }}` calls filled in
+fn text(value: &Yaml) -> Option<String> {
+    let text = match value {
+        Yaml::String(s) => s.clone(),
+        Yaml::Number(n) => n.to_string(),
+        Yaml::Bool(b) => b.to_string(),
+        _ => return None,
+    };
+    Some(render_env_vars(&text))
+}
+
+/// Replaces `{{ env_var('NAME') }}` and `{{ env_var('NAME', 'default') }}`, ignoring
+/// filters like `| as_number`. Other Jinja is left alone.
+fn render_env_vars(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let expression = rest[start + 2..start + end].trim();
+        result.push_str(&rest[..start]);
+        match env_var(expression) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn env_var(expression: &str) -> Option<String> {
+    let call = expression.split('|').next()?.trim();
+    let arguments = call.strip_prefix("env_var")?.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let mut arguments = arguments.split(',').map(|argument| argument.trim().trim_matches(['\'', '"']));
+    let name = arguments.next()?;
+    std::env::var(name).ok().or_else(|| arguments.next().map(str::to_string))
+}
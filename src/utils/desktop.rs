@@ -0,0 +1,17 @@
+use anyhow::Result;
+use std::io::Write;
+
+/// Rings the terminal bell and asks the terminal for a desktop notification. OSC 9 is
+/// understood by iTerm2, kitty, WezTerm and Windows Terminal, OSC 777 by foot, Konsole
+/// and urxvt. Terminals that know neither ignore them and only ring.
+pub fn notify(title: &str, body: &str) -> Result<()> {
+    // A control character would end the sequence early, `;` separates the OSC 777 fields
+    let clean = |text: &str| -> String {
+        text.chars().filter(|c| !c.is_control()).map(|c| if c == ';' { ',' } else { c }).collect()
+    };
+    let (title, body) = (clean(title), clean(body));
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x07\x1b]9;{}: {}\x07\x1b]777;notify;{};{}\x07", title, body, title, body)?;
+    stdout.flush()?;
+    Ok(())
+}
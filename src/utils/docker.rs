@@ -0,0 +1,115 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value as Json;
+use tokio::process::Command;
+
+use crate::utils::connection::{Connection, DiscoveredDatabase};
+
+/// Lists the running postgres, mysql and mariadb containers through the docker CLI,
+/// so it follows the user's docker context and socket settings
+pub async fn discover() -> Result<Vec<DiscoveredDatabase>> {
+    let ids = docker(&["ps", "--quiet", "--no-trunc"]).await?;
+    let ids: Vec<&str> = ids.lines().map(str::trim).filter(|id| !id.is_empty()).collect();
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec!["inspect"];
+    args.extend(&ids);
+    let inspected: Vec<Json> = serde_json::from_str(&docker(&args).await?).context("Unexpected docker inspect output")?;
+    Ok(inspected.iter().filter_map(database_of).collect())
+}
+
+async fn docker(args: &[&str]) -> Result<String> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .context("Could not run docker, is it installed?")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("docker {} failed: {}", args[0], stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The connection for one inspected container, None when it isn't a database we know
+fn database_of(container: &Json) -> Option<DiscoveredDatabase> {
+    let config = &container["Config"];
+    let image = config["Image"].as_str()?.to_string();
+    let env: Vec<(&str, &str)> = config["Env"]
+        .as_array()?
+        .iter()
+        .filter_map(|var| var.as_str()?.split_once('='))
+        .collect();
+    let var = |name: &str| env.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string());
+
+    // The image name says more than the port, e.g. for a postgres on 3306
+    let base = image.rsplit('/').next().unwrap_or(&image);
+    let base = base.split([':', '@']).next().unwrap_or(base);
+    let (db_type, port) = if base.contains("postgres") || base.contains("postgis") || base.contains("timescale") {
+        ("postgres", 5432)
+    } else if base.contains("mariadb") {
+        ("mariadb", 3306)
+    } else if base.contains("mysql") || base.contains("percona") {
+        ("mysql", 3306)
+    } else {
+        return None;
+    };
+
+    let (username, password, database) = if db_type == "postgres" {
+        let user = var("POSTGRES_USER").unwrap_or_else(|| "postgres".to_string());
+        let database = var("POSTGRES_DB").unwrap_or_else(|| user.clone());
+        (user, var("POSTGRES_PASSWORD").unwrap_or_default(), database)
+    } else {
+        // MariaDB images take the MYSQL_ names too
+        let mysql = |suffix: &str| var(&format!("MARIADB_{}", suffix)).or_else(|| var(&format!("MYSQL_{}", suffix)));
+        let database = mysql("DATABASE").unwrap_or_default();
+        match (mysql("USER"), mysql("PASSWORD")) {
+            (Some(user), password) => (user, password.unwrap_or_default(), database),
+            (None, _) => ("root".to_string(), mysql("ROOT_PASSWORD").unwrap_or_default(), database),
+        }
+    };
+
+    let name = container["Name"].as_str()?.trim_start_matches('/').to_string();
+    let (host, port) = published_port(container, port).or_else(|| {
+        // Without a published port the container's own address works from a Linux host
+        let ip = container["NetworkSettings"]["Networks"]
+            .as_object()?
+            .values()
+            .find_map(|network| network["IPAddress"].as_str().filter(|ip| !ip.is_empty()))?;
+        Some((ip.to_string(), port))
+    })?;
+
+    Some(DiscoveredDatabase {
+        origin: name.clone(),
+        detail: image,
+        connection: Connection {
+            name,
+            db_type: db_type.to_string(),
+            host,
+            port,
+            database,
+            username,
+            password,
+            query_timeout_secs: None,
+            accent_color: None,
+            kube: None,
+            ssh: None,
+            cloud_sql: None,
+            aws_iam: false,
+            notes: None,
+        },
+    })
+}
+
+/// Host address and port the container's `port` is published on
+fn published_port(container: &Json, port: u16) -> Option<(String, u16)> {
+    let bindings = container["NetworkSettings"]["Ports"][format!("{}/tcp", port)].as_array()?;
+    let binding = bindings.first()?;
+    let host_port = binding["HostPort"].as_str()?.parse().ok()?;
+    let host = match binding["HostIp"].as_str().unwrap_or_default() {
+        // Docker publishes on both families, and the IPv4 loopback needs no brackets in a URL
+        "" | "0.0.0.0" | "::" => "127.0.0.1".to_string(),
+        ip => ip.to_string(),
+    };
+    Some((host, host_port))
+}
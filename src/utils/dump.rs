@@ -0,0 +1,231 @@
+use crate::utils::query_executor::QueryExecutor;
+use crate::utils::sql::{Dialect, quote_identifier};
+use crate::utils::value::Value;
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Rows per INSERT statement in the dump
+const ROWS_PER_INSERT: usize = 100;
+
+/// Writes `value` as a literal the dialect reads back to the same value
+pub fn sql_literal(value: &Value, dialect: Dialect) -> Result<String> {
+    Ok(match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(v) if dialect == Dialect::Sqlite => (if *v { "1" } else { "0" }).to_string(),
+        Value::Bool(v) => (if *v { "TRUE" } else { "FALSE" }).to_string(),
+        Value::Int(v) => v.to_string(),
+        Value::UInt(v) => v.to_string(),
+        Value::Float(v) if v.is_finite() => v.to_string(),
+        // Only Postgres has a spelling for NaN and infinity
+        Value::Float(v) if dialect == Dialect::Postgres => format!("'{}'::float8", pg_float_special(*v)),
+        Value::Float(_) => "NULL".to_string(),
+        Value::Decimal(v) => v.to_string(),
+        Value::Text(v) => quote_string(v, dialect),
+        Value::Bytes(v) => {
+            let hex: String = v.iter().map(|b| format!("{:02x}", b)).collect();
+            match dialect {
+                Dialect::Postgres => format!("'\\x{}'::bytea", hex),
+                Dialect::MySql | Dialect::Sqlite => format!("X'{}'", hex),
+            }
+        }
+        Value::Json(v) => quote_string(&v.to_string(), dialect),
+        Value::Timestamp(v) => quote_string(&v.format("%Y-%m-%d %H:%M:%S%.f").to_string(), dialect),
+        Value::TimestampTz(v) if dialect == Dialect::Postgres => quote_string(&v.to_rfc3339(), dialect),
+        Value::TimestampTz(v) => quote_string(&v.format("%Y-%m-%d %H:%M:%S%.f").to_string(), dialect),
+        Value::Date(v) => quote_string(&v.to_string(), dialect),
+        Value::Uuid(v) => quote_string(&v.to_string(), dialect),
+        // Postgres reads its own array text back into the column's array type
+        Value::Array(_) if dialect == Dialect::Postgres => quote_string(&value.to_string(), dialect),
+        Value::Array(_) => quote_string(&value.to_json().to_string(), dialect),
+        // PostGIS casts extended WKT back into the column's geometry
+        Value::Geometry(v) => quote_string(&v.to_string(), dialect),
+        Value::Unsupported(type_name) => bail!("Values of type {} can't be written as SQL", type_name),
+        Value::Invalid => bail!("A value failed to decode"),
+    })
+}
+
+fn pg_float_special(v: f64) -> &'static str {
+    if v.is_nan() {
+        "NaN"
+    } else if v > 0.0 {
+        "Infinity"
+    } else {
+        "-Infinity"
+    }
+}
+
+/// MySQL treats backslashes in string literals as escapes unless told otherwise
+fn quote_string(text: &str, dialect: Dialect) -> String {
+    let escaped = text.replace('\'', "''");
+    match dialect {
+        Dialect::MySql => format!("'{}'", escaped.replace('\\', "\\\\")),
+        Dialect::Postgres | Dialect::Sqlite => format!("'{}'", escaped),
+    }
+}
+
+/// The CREATE TABLE statement of `table`. SQLite and MySQL keep it themselves,
+/// for Postgres it is put together from the catalog.
+pub async fn create_table_sql(executor: &QueryExecutor, table: &str) -> Result<String> {
+    let dialect = executor.dialect();
+    match dialect {
+        Dialect::Sqlite => {
+            let sets = executor
+                .execute_prepared("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?", &[Value::from(table)])
+                .await?;
+            first_text(&sets, 0).ok_or_else(|| anyhow!("Table {} not found", table))
+        }
+        Dialect::MySql => {
+            let sets = executor
+                .execute_sets(&format!("SHOW CREATE TABLE {}", quote_identifier(table, dialect)))
+                .await?;
+            first_text(&sets, 1).ok_or_else(|| anyhow!("Table {} not found", table))
+        }
+        Dialect::Postgres => postgres_create_table(executor, table).await,
+    }
+}
+
+fn first_text(sets: &[(Vec<String>, Vec<Vec<Value>>)], column: usize) -> Option<String> {
+    match sets.first()?.1.first()?.get(column)? {
+        Value::Text(text) => Some(text.clone()),
+        Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        _ => None,
+    }
+}
+
+/// Columns with their types, NOT NULL and defaults, then primary key, unique and
+/// check constraints. Columns filled from a sequence become serial columns, so the
+/// statement doesn't depend on a sequence the target database lacks.
+async fn postgres_create_table(executor: &QueryExecutor, table: &str) -> Result<String> {
+    let name = Value::Text(quote_identifier(table, Dialect::Postgres));
+    let columns = executor
+        .execute_prepared(
+            "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), a.attnotnull, \
+             pg_get_expr(d.adbin, d.adrelid) \
+             FROM pg_attribute a LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+             WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped \
+             ORDER BY a.attnum",
+            std::slice::from_ref(&name),
+        )
+        .await?;
+    let constraints = executor
+        .execute_prepared(
+            "SELECT pg_get_constraintdef(oid) FROM pg_constraint \
+             WHERE conrelid = to_regclass($1) AND contype IN ('p', 'u', 'c') ORDER BY contype, conname",
+            &[name],
+        )
+        .await?;
+
+    let mut lines = Vec::new();
+    for row in columns.iter().flat_map(|(_, rows)| rows) {
+        let [Value::Text(column), Value::Text(data_type), not_null, default] = row.as_slice() else {
+            continue;
+        };
+        let default = match default {
+            Value::Text(default) => Some(default.as_str()),
+            _ => None,
+        };
+        let serial = match (data_type.as_str(), default) {
+            ("integer", Some(d)) if d.starts_with("nextval(") => Some("serial"),
+            ("bigint", Some(d)) if d.starts_with("nextval(") => Some("bigserial"),
+            ("smallint", Some(d)) if d.starts_with("nextval(") => Some("smallserial"),
+            _ => None,
+        };
+        let mut line = format!("    {} {}", quote_identifier(column, Dialect::Postgres), serial.unwrap_or(data_type));
+        if *not_null == Value::Bool(true) && serial.is_none() {
+            line.push_str(" NOT NULL");
+        }
+        if let Some(default) = default.filter(|_| serial.is_none()) {
+            line.push_str(&format!(" DEFAULT {}", default));
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        bail!("Table {} not found", table);
+    }
+    for row in constraints.iter().flat_map(|(_, rows)| rows) {
+        if let Some(Value::Text(definition)) = row.first() {
+            lines.push(format!("    {}", definition));
+        }
+    }
+    Ok(format!(
+        "CREATE TABLE {} (\n{}\n)",
+        quote_identifier(table, Dialect::Postgres),
+        lines.join(",\n")
+    ))
+}
+
+/// Writes CREATE TABLE and INSERT statements for `tables` to `path`, one table after
+/// the other, and returns the number of rows written. `progress` counts them as they go.
+pub async fn dump_tables(
+    executor: Arc<QueryExecutor>,
+    tables: Vec<String>,
+    path: &Path,
+    progress: Arc<AtomicU64>,
+) -> Result<u64> {
+    let dialect = executor.dialect();
+    let file = File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    writeln!(
+        out,
+        "-- rsquid dump of {} table{}, {}\n",
+        tables.len(),
+        if tables.len() == 1 { "" } else { "s" },
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )?;
+
+    let mut total = 0;
+    for table in &tables {
+        let create = create_table_sql(&executor, table)
+            .await
+            .with_context(|| format!("Could not read the definition of {}", table))?;
+        writeln!(out, "{};\n", create.trim_end().trim_end_matches(';'))?;
+
+        let quoted = quote_identifier(table, dialect);
+        let (batches, mut stream) = executor.execute_streaming(&format!("SELECT * FROM {}", quoted)).await?;
+        let mut pending = batches;
+        loop {
+            for (headers, rows) in pending.drain(..) {
+                let columns: Vec<String> = headers.iter().map(|h| quote_identifier(h, dialect)).collect();
+                for chunk in rows.chunks(ROWS_PER_INSERT) {
+                    let tuples = chunk
+                        .iter()
+                        .map(|row| {
+                            let values = row
+                                .iter()
+                                .zip(&headers)
+                                .map(|(value, header)| {
+                                    sql_literal(value, dialect)
+                                        .with_context(|| format!("Column {} of {}", header, table))
+                                })
+                                .collect::<Result<Vec<_>>>()?;
+                            Ok(format!("({})", values.join(", ")))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    writeln!(
+                        out,
+                        "INSERT INTO {} ({}) VALUES\n{};",
+                        quoted,
+                        columns.join(", "),
+                        tuples.join(",\n")
+                    )?;
+                    total += chunk.len() as u64;
+                    progress.store(total, Ordering::Relaxed);
+                }
+            }
+            match stream.as_mut() {
+                Some(rows) => match rows.next_batch().await {
+                    Some(batch) => pending.push(batch?),
+                    None => break,
+                },
+                None => break,
+            }
+        }
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(total)
+}
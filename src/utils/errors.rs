@@ -0,0 +1,95 @@
+use sqlx::{postgres::PgDatabaseError, sqlite::SqliteError};
+
+/// Everything known about a failed query, shown in the error popup
+#[derive(Debug, Clone)]
+pub struct ErrorDetail {
+    /// Outermost error first, then each cause
+    pub chain: Vec<String>,
+    pub code: Option<String>,
+    /// "SQLSTATE", or "Error code" for SQLite, whose codes are its own
+    pub code_label: &'static str,
+    pub statement: String,
+    /// Extra detail the server sent along with the message
+    pub detail: Option<String>,
+    pub hints: Vec<String>,
+    /// Character offset into the statement, 1-based like Postgres reports it
+    pub position: Option<usize>,
+}
+
+impl ErrorDetail {
+    /// `redact` strips credentials from every piece of text taken from the error
+    pub fn new(error: &anyhow::Error, statement: &str, redact: impl Fn(&str) -> String) -> Self {
+        let chain: Vec<String> = error.chain().map(|cause| redact(&cause.to_string())).collect();
+        let database_error = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<sqlx::Error>())
+            .and_then(|e| e.as_database_error());
+
+        let code = database_error.and_then(|e| e.code()).map(|code| code.into_owned());
+        let sqlite = database_error.is_some_and(|e| e.try_downcast_ref::<SqliteError>().is_some());
+        let postgres = database_error.and_then(|e| e.try_downcast_ref::<PgDatabaseError>());
+
+        let mut hints = Vec::new();
+        if let Some(hint) = postgres.and_then(|e| e.hint()) {
+            hints.push(redact(hint));
+        }
+        if !sqlite
+            && let Some(hint) = code.as_deref().and_then(hint_for)
+        {
+            hints.push(hint.to_string());
+        }
+
+        let position = postgres.and_then(|e| match e.position() {
+            Some(sqlx::postgres::PgErrorPosition::Original(position)) => Some(position),
+            _ => None,
+        });
+
+        Self {
+            chain,
+            code,
+            code_label: if sqlite { "Error code" } else { "SQLSTATE" },
+            statement: statement.to_string(),
+            detail: postgres.and_then(|e| e.detail()).map(&redact),
+            hints,
+            position,
+        }
+    }
+
+    /// Plain text version, for the clipboard
+    pub fn to_text(&self) -> String {
+        let mut text = self.chain.join("\ncaused by: ");
+        if let Some(code) = &self.code {
+            text.push_str(&format!("\n{}: {}", self.code_label, code));
+        }
+        if let Some(detail) = &self.detail {
+            text.push_str(&format!("\nDetail: {}", detail));
+        }
+        for hint in &self.hints {
+            text.push_str(&format!("\nHint: {}", hint));
+        }
+        if let Some(position) = self.position {
+            text.push_str(&format!("\nPosition: {}", position));
+        }
+        text.push_str(&format!("\n\n{}", self.statement));
+        text
+    }
+}
+
+/// Advice for the SQLSTATE codes people run into most. Postgres, MySQL and
+/// SQLite share few codes, so some errors have one entry per dialect.
+fn hint_for(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "42P01" | "42S02" => "The table doesn't exist, check the name and schema in the explorer (Ctrl+E)",
+        "42703" | "42S22" => "The column doesn't exist, expand the table in the explorer to see its columns",
+        "42601" | "42000" => "Syntax error, look around the reported position for a typo or missing comma",
+        "23505" | "23000" => "A row with the same unique key already exists",
+        "23503" => "The row is still referenced by a foreign key, or references a missing row",
+        "23502" => "A NOT NULL column was left empty",
+        "28P01" | "28000" => "Authentication failed, check the username and password of the connection",
+        "3D000" => "The database doesn't exist on this server",
+        "40001" | "40P01" => "The transaction lost a conflict with another one, running it again usually works",
+        "57014" => "The statement was cancelled, possibly by the query timeout in the settings",
+        "25P02" => "An earlier statement failed, roll back the transaction (Ctrl+Shift+R or Alt+R) before running more",
+        _ => return None,
+    })
+}
@@ -0,0 +1,258 @@
+use anyhow::{Result, bail};
+use std::io::Write;
+
+use crate::utils::parquet::ParquetWriter;
+use crate::utils::text::{display_width, single_line};
+use crate::utils::value::Value;
+use crate::utils::xlsx::XlsxWriter;
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+    /// One JSON document with column types, row counts and timings
+    JsonFull,
+    /// Excel workbook, one sheet per result set
+    Xlsx,
+    /// Parquet file with typed columns, one result set per file
+    Parquet,
+}
+
+impl OutputFormat {
+    /// Formats that can be written without seeing every row first
+    pub fn is_streamable(self) -> bool {
+        matches!(self, OutputFormat::Csv | OutputFormat::Json | OutputFormat::Xlsx | OutputFormat::Parquet)
+    }
+
+    /// Formats that would fill a terminal with binary data
+    pub fn is_binary(self) -> bool {
+        matches!(self, OutputFormat::Xlsx | OutputFormat::Parquet)
+    }
+}
+
+pub fn write_result(
+    out: &mut impl Write,
+    format: OutputFormat,
+    headers: &[String],
+    rows: &[Vec<Value>],
+) -> Result<()> {
+    match format {
+        OutputFormat::Table => write_table(out, headers, rows),
+        OutputFormat::Csv => write_csv(out, headers, rows),
+        OutputFormat::Json => write_json(out, headers, rows),
+        OutputFormat::JsonFull => {
+            serde_json::to_writer_pretty(&mut *out, &json_full_rows(headers, rows))?;
+            writeln!(out)?;
+            Ok(())
+        }
+        OutputFormat::Xlsx => {
+            let mut writer = XlsxWriter::new();
+            writer.write_batch(headers, rows)?;
+            writer.finish(out)
+        }
+        OutputFormat::Parquet => {
+            let mut writer = ParquetWriter::new();
+            writer.write_batch(out, headers, rows)?;
+            writer.finish(out)
+        }
+    }
+}
+
+/// Columns padded to their widest cell, like the psql and mysql clients print them
+pub fn write_table(out: &mut impl Write, headers: &[String], rows: &[Vec<Value>]) -> Result<()> {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(|value| single_line(&value.to_string())).collect())
+        .collect();
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| display_width(cell))
+                .fold(display_width(header), usize::max)
+        })
+        .collect();
+
+    let pad = |text: &str, width: usize| format!("{}{}", text, " ".repeat(width.saturating_sub(display_width(text))));
+
+    let header_line: Vec<String> = headers.iter().zip(&widths).map(|(h, w)| pad(h, *w)).collect();
+    writeln!(out, "{}", header_line.join(" | ").trim_end())?;
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    writeln!(out, "{}", rule.join("-+-"))?;
+
+    for row in &cells {
+        let line: Vec<String> = row.iter().zip(&widths).map(|(cell, w)| pad(cell, *w)).collect();
+        writeln!(out, "{}", line.join(" | ").trim_end())?;
+    }
+    writeln!(out, "({} row{})", rows.len(), if rows.len() == 1 { "" } else { "s" })?;
+    Ok(())
+}
+
+/// RFC 4180 CSV, NULL is written as an empty field
+pub fn write_csv(out: &mut impl Write, headers: &[String], rows: &[Vec<Value>]) -> Result<()> {
+    let header_line: Vec<String> = headers.iter().map(|h| csv_field(h)).collect();
+    write!(out, "{}\r\n", header_line.join(","))?;
+    write_csv_rows(out, rows)
+}
+
+fn write_csv_rows(out: &mut impl Write, rows: &[Vec<Value>]) -> Result<()> {
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .map(|value| match value {
+                Value::Null => String::new(),
+                other => csv_field(&other.to_string()),
+            })
+            .collect();
+        write!(out, "{}\r\n", line.join(","))?;
+    }
+    Ok(())
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Tab separated with a header line, the format spreadsheets expect on paste. Cells
+/// holding tabs, line breaks or quotes are quoted the way Excel and Google Sheets read them.
+pub fn write_tsv<'a>(
+    out: &mut impl Write,
+    headers: &[String],
+    rows: impl IntoIterator<Item = impl IntoIterator<Item = &'a Value>>,
+) -> Result<()> {
+    let header_line: Vec<String> = headers.iter().map(|h| tsv_field(h)).collect();
+    write!(out, "{}\r\n", header_line.join("\t"))?;
+    for row in rows {
+        let line: Vec<String> = row
+            .into_iter()
+            .map(|value| match value {
+                Value::Null => String::new(),
+                other => tsv_field(&other.to_string()),
+            })
+            .collect();
+        write!(out, "{}\r\n", line.join("\t"))?;
+    }
+    Ok(())
+}
+
+fn tsv_field(text: &str) -> String {
+    if text.contains(['\t', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// An array with one object per row, keyed by column name
+pub fn json_rows(headers: &[String], rows: &[Vec<Value>]) -> serde_json::Value {
+    rows.iter()
+        .map(|row| {
+            let object = headers
+                .iter()
+                .zip(row)
+                .map(|(header, value)| (header.clone(), value.to_json()))
+                .collect::<serde_json::Map<_, _>>();
+            serde_json::Value::Object(object)
+        })
+        .collect()
+}
+
+/// Columns with their types, rows as arrays and the row count. A column's type comes
+/// from its first non-NULL value, "null" when every value is NULL.
+pub fn json_full_rows(headers: &[String], rows: &[Vec<Value>]) -> serde_json::Value {
+    let columns: Vec<serde_json::Value> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let type_name = rows
+                .iter()
+                .filter_map(|row| row.get(i))
+                .find(|value| !value.is_null())
+                .map_or("null", Value::type_name);
+            serde_json::json!({ "name": name, "type": type_name })
+        })
+        .collect();
+    let data: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| row.iter().map(Value::to_json).collect())
+        .collect();
+
+    serde_json::json!({
+        "columns": columns,
+        "rows": data,
+        "row_count": rows.len(),
+    })
+}
+
+pub fn write_json(out: &mut impl Write, headers: &[String], rows: &[Vec<Value>]) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &json_rows(headers, rows))?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Writes a result batch by batch, so a whole table never has to fit in memory.
+/// The aligned table format needs every row up front and isn't supported.
+pub struct StreamingWriter<W: Write> {
+    out: W,
+    format: OutputFormat,
+    rows: u64,
+    xlsx: XlsxWriter,
+    parquet: ParquetWriter,
+}
+
+impl<W: Write> StreamingWriter<W> {
+    pub fn new(out: W, format: OutputFormat) -> Result<Self> {
+        if !format.is_streamable() {
+            bail!("The {:?} format can't be streamed, use csv, json, xlsx or parquet", format);
+        }
+        Ok(Self { out, format, rows: 0, xlsx: XlsxWriter::new(), parquet: ParquetWriter::new() })
+    }
+
+    pub fn write_batch(&mut self, headers: &[String], rows: &[Vec<Value>]) -> Result<()> {
+        match self.format {
+            OutputFormat::Csv if self.rows == 0 => write_csv(&mut self.out, headers, rows)?,
+            OutputFormat::Csv => write_csv_rows(&mut self.out, rows)?,
+            OutputFormat::Json => {
+                let objects = json_rows(headers, rows);
+                for object in objects.as_array().into_iter().flatten() {
+                    let separator = if self.rows == 0 { "[\n" } else { ",\n" };
+                    write!(self.out, "{}  {}", separator, object)?;
+                    self.rows += 1;
+                }
+                return Ok(());
+            }
+            OutputFormat::Xlsx => self.xlsx.write_batch(headers, rows)?,
+            OutputFormat::Parquet => self.parquet.write_batch(&mut self.out, headers, rows)?,
+            OutputFormat::Table | OutputFormat::JsonFull => unreachable!("rejected in StreamingWriter::new"),
+        }
+        self.rows += rows.len() as u64;
+        Ok(())
+    }
+
+    pub fn rows_written(&self) -> u64 {
+        self.rows
+    }
+
+    /// Closes the document and flushes, returns the number of rows written
+    pub fn finish(mut self) -> Result<u64> {
+        if self.format == OutputFormat::Json {
+            let close = if self.rows == 0 { "[]\n" } else { "\n]\n" };
+            write!(self.out, "{}", close)?;
+        }
+        match self.format {
+            OutputFormat::Xlsx => self.xlsx.finish(&mut self.out)?,
+            OutputFormat::Parquet => self.parquet.finish(&mut self.out)?,
+            _ => {}
+        }
+        self.out.flush()?;
+        Ok(self.rows)
+    }
+}
@@ -0,0 +1,623 @@
+use crate::utils::import::{MAX_BATCH_PARAMS, MAX_BATCH_ROWS};
+use crate::utils::query_executor::{QueryExecutor, RowBatch};
+use crate::utils::sql::{Dialect, quote_identifier};
+use crate::utils::value::Value;
+use anyhow::{Context, Result, bail};
+use chrono::{Duration, Local};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Share of NULLs in nullable columns
+const NULL_RATE: f64 = 0.1;
+/// Existing values loaded for each foreign key to pick from
+const MAX_REFERENCED_VALUES: usize = 1000;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bruno", "Chiara", "David", "Elena", "Farid", "Giulia", "Hiro", "Ines", "Jonas", "Kara", "Luca",
+    "Maya", "Noah", "Olga", "Pablo", "Quinn", "Rosa", "Samir", "Tara", "Ugo", "Vera", "Wen", "Yusuf", "Zoe",
+];
+const LAST_NAMES: &[&str] = &[
+    "Rossi", "Smith", "Garcia", "Meyer", "Tanaka", "Novak", "Dubois", "Silva", "Kowalski", "Jensen", "Costa",
+    "Ivanova", "Okafor", "Larsen", "Bianchi", "Moreau", "Nguyen", "Schmidt", "Haddad", "Fischer",
+];
+const CITIES: &[&str] = &[
+    "Milan", "Lisbon", "Berlin", "Osaka", "Toronto", "Lyon", "Krakow", "Austin", "Porto", "Nairobi", "Oslo",
+    "Seville", "Turin", "Melbourne", "Ghent",
+];
+const COUNTRIES: &[&str] = &[
+    "Italy", "Portugal", "Germany", "Japan", "Canada", "France", "Poland", "United States", "Kenya", "Norway",
+    "Spain", "Australia", "Belgium",
+];
+const DOMAINS: &[&str] = &["example.com", "example.org", "example.net", "mail.test"];
+const WORDS: &[&str] = &[
+    "alpha", "amber", "bright", "cedar", "delta", "ember", "forest", "harbor", "iron", "jade", "lunar", "maple",
+    "nova", "orbit", "pixel", "quartz", "river", "solar", "timber", "vector", "willow", "zenith",
+];
+
+/// A column as the generator sees it
+#[derive(Debug, Clone)]
+pub struct ColumnSpec {
+    pub name: String,
+    /// Database type, lowercase
+    pub data_type: String,
+    pub nullable: bool,
+    /// Filled by the database: serial, identity, auto-increment and generated columns
+    pub automatic: bool,
+    pub unique: bool,
+    pub max_length: Option<usize>,
+    /// Allowed values of an enum column
+    pub enum_values: Vec<String>,
+    /// Referenced table, already quoted, and column of a single-column foreign key
+    pub references: Option<(String, String)>,
+}
+
+fn text(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::Text(text) => Some(text.clone()),
+        Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::Bool(v)) => *v,
+        Some(Value::Int(v)) => *v != 0,
+        Some(Value::UInt(v)) => *v != 0,
+        Some(Value::Text(v)) => matches!(v.as_str(), "1" | "t" | "true"),
+        _ => false,
+    }
+}
+
+fn rows(sets: Vec<RowBatch>) -> Vec<Vec<Value>> {
+    sets.into_iter().flat_map(|(_, rows)| rows).collect()
+}
+
+/// Length limit written in the type, e.g. 40 in `character varying(40)`
+fn length_from_type(data_type: &str) -> Option<usize> {
+    if !data_type.contains("char") {
+        return None;
+    }
+    let start = data_type.find('(')?;
+    let end = data_type[start..].find(')')? + start;
+    data_type[start + 1..end].trim().parse().ok()
+}
+
+/// The values listed in a MySQL `enum('a','b')` type
+fn mysql_enum_values(data_type: &str) -> Vec<String> {
+    let Some(list) = data_type.strip_prefix("enum(").and_then(|rest| rest.strip_suffix(')')) else {
+        return Vec::new();
+    };
+    list.split(',')
+        .map(|value| value.trim().trim_matches('\'').replace("''", "'"))
+        .collect()
+}
+
+/// Columns of `table` with what the catalog says about NULLs, keys and defaults
+pub async fn inspect_columns(executor: &QueryExecutor, table: &str) -> Result<Vec<ColumnSpec>> {
+    let dialect = executor.dialect();
+    let mut columns = match dialect {
+        Dialect::Postgres => postgres_columns(executor, table).await?,
+        Dialect::MySql => mysql_columns(executor, table).await?,
+        Dialect::Sqlite => sqlite_columns(executor, table).await?,
+    };
+    if columns.is_empty() {
+        bail!("Table {} not found", table);
+    }
+    for column in &mut columns {
+        column.max_length = column.max_length.or_else(|| length_from_type(&column.data_type));
+    }
+    Ok(columns)
+}
+
+async fn postgres_columns(executor: &QueryExecutor, table: &str) -> Result<Vec<ColumnSpec>> {
+    let name = [Value::Text(quote_identifier(table, Dialect::Postgres))];
+    let columns = executor
+        .execute_prepared(
+            "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), NOT a.attnotnull, \
+             coalesce(pg_get_expr(d.adbin, d.adrelid), '') LIKE 'nextval(%' OR a.attidentity <> '' OR a.attgenerated <> '', \
+             EXISTS (SELECT 1 FROM pg_constraint c WHERE c.conrelid = a.attrelid AND c.contype IN ('p', 'u') \
+                     AND c.conkey = ARRAY[a.attnum]), \
+             (SELECT string_agg(e.enumlabel, E'\\n' ORDER BY e.enumsortorder) FROM pg_enum e WHERE e.enumtypid = a.atttypid) \
+             FROM pg_attribute a LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+             WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped ORDER BY a.attnum",
+            &name,
+        )
+        .await?;
+    let foreign_keys = executor
+        .execute_prepared(
+            "SELECT a.attname::text, c.confrelid::regclass::text, af.attname::text FROM pg_constraint c \
+             JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1] \
+             JOIN pg_attribute af ON af.attrelid = c.confrelid AND af.attnum = c.confkey[1] \
+             WHERE c.conrelid = to_regclass($1) AND c.contype = 'f' AND array_length(c.conkey, 1) = 1",
+            &name,
+        )
+        .await?;
+    let foreign_keys = rows(foreign_keys);
+
+    Ok(rows(columns)
+        .iter()
+        .filter_map(|row| {
+            let name = text(row.first())?;
+            let references = foreign_keys
+                .iter()
+                .find(|fk| text(fk.first()).as_deref() == Some(name.as_str()))
+                .and_then(|fk| Some((text(fk.get(1))?, text(fk.get(2))?)));
+            Some(ColumnSpec {
+                data_type: text(row.get(1)).unwrap_or_default().to_lowercase(),
+                nullable: truthy(row.get(2)),
+                automatic: truthy(row.get(3)),
+                unique: truthy(row.get(4)),
+                max_length: None,
+                enum_values: text(row.get(5)).map(|v| v.lines().map(str::to_string).collect()).unwrap_or_default(),
+                references,
+                name,
+            })
+        })
+        .collect())
+}
+
+async fn mysql_columns(executor: &QueryExecutor, table: &str) -> Result<Vec<ColumnSpec>> {
+    let name = [Value::from(table)];
+    let columns = executor
+        .execute_prepared(
+            "SELECT COLUMN_NAME, COLUMN_TYPE, IS_NULLABLE, EXTRA, COLUMN_KEY, CHARACTER_MAXIMUM_LENGTH \
+             FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+             ORDER BY ORDINAL_POSITION",
+            &name,
+        )
+        .await?;
+    let foreign_keys = executor
+        .execute_prepared(
+            "SELECT COLUMN_NAME, REFERENCED_TABLE_NAME, REFERENCED_COLUMN_NAME \
+             FROM information_schema.KEY_COLUMN_USAGE \
+             WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND REFERENCED_TABLE_NAME IS NOT NULL",
+            &name,
+        )
+        .await?;
+    let foreign_keys = rows(foreign_keys);
+
+    Ok(rows(columns)
+        .iter()
+        .filter_map(|row| {
+            let name = text(row.first())?;
+            let data_type = text(row.get(1)).unwrap_or_default();
+            let extra = text(row.get(3)).unwrap_or_default().to_lowercase();
+            let key = text(row.get(4)).unwrap_or_default();
+            let references = foreign_keys
+                .iter()
+                .find(|fk| text(fk.first()).as_deref() == Some(name.as_str()))
+                .and_then(|fk| Some((quote_identifier(&text(fk.get(1))?, Dialect::MySql), text(fk.get(2))?)));
+            Some(ColumnSpec {
+                enum_values: mysql_enum_values(&data_type),
+                data_type: data_type.to_lowercase(),
+                nullable: text(row.get(2)).as_deref() == Some("YES"),
+                automatic: extra.contains("auto_increment") || extra.contains("generated"),
+                unique: key == "PRI" || key == "UNI",
+                max_length: text(row.get(5)).and_then(|v| v.parse().ok()),
+                references,
+                name,
+            })
+        })
+        .collect())
+}
+
+async fn sqlite_columns(executor: &QueryExecutor, table: &str) -> Result<Vec<ColumnSpec>> {
+    let name = [Value::from(table)];
+    let columns = executor
+        .execute_prepared(
+            // SQLite only decodes text reliably from pragma functions, so the numbers are cast
+            "SELECT name, type, CAST(\"notnull\" AS TEXT), CAST(pk > 0 AS TEXT), \
+             CAST((SELECT count(*) FROM pragma_table_info(?1) WHERE pk > 0) AS TEXT) \
+             FROM pragma_table_info(?1) ORDER BY cid",
+            &name,
+        )
+        .await?;
+    let foreign_keys = rows(
+        executor
+            .execute_prepared("SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?1)", &name)
+            .await?,
+    );
+    let unique = rows(
+        executor
+            .execute_prepared(
+                "SELECT min(ii.name) FROM pragma_index_list(?1) il, pragma_index_info(il.name) ii \
+                 WHERE il.\"unique\" = 1 GROUP BY il.name HAVING count(*) = 1",
+                &name,
+            )
+            .await?,
+    );
+
+    Ok(rows(columns)
+        .iter()
+        .filter_map(|row| {
+            let name = text(row.first())?;
+            let data_type = text(row.get(1)).unwrap_or_default().to_lowercase();
+            let primary_key = truthy(row.get(3));
+            let key_columns: u32 = text(row.get(4)).and_then(|n| n.parse().ok()).unwrap_or(0);
+            let references = foreign_keys
+                .iter()
+                .find(|fk| text(fk.first()).as_deref() == Some(name.as_str()))
+                .and_then(|fk| Some((quote_identifier(&text(fk.get(1))?, Dialect::Sqlite), text(fk.get(2))?)));
+            Some(ColumnSpec {
+                // A lone INTEGER PRIMARY KEY is the rowid and numbers itself
+                automatic: primary_key && key_columns == 1 && data_type == "integer",
+                nullable: !truthy(row.get(2)) && !primary_key,
+                unique: (primary_key && key_columns == 1)
+                    || unique.iter().any(|u| text(u.first()).as_deref() == Some(name.as_str())),
+                max_length: None,
+                enum_values: Vec::new(),
+                references,
+                data_type,
+                name,
+            })
+        })
+        .collect())
+}
+
+/// Kind of value a column gets, from its type and then its name
+#[derive(Debug, Clone, PartialEq)]
+enum Generator {
+    Integer { min: i64, max: i64 },
+    Decimal,
+    Boolean,
+    Date,
+    Timestamp,
+    Time,
+    Uuid,
+    Json,
+    Email,
+    FirstName,
+    LastName,
+    FullName,
+    Username,
+    City,
+    Country,
+    Phone,
+    Url,
+    Address,
+    Sentence,
+    Words,
+    OneOf(Vec<String>),
+    Reference(Vec<Value>),
+}
+
+impl Generator {
+    fn for_column(column: &ColumnSpec) -> Result<Self> {
+        let t = column.data_type.as_str();
+        let name = column.name.to_lowercase();
+        if !column.enum_values.is_empty() {
+            return Ok(Generator::OneOf(column.enum_values.clone()));
+        }
+        Ok(if t == "tinyint(1)" || t.starts_with("bool") {
+            Generator::Boolean
+        } else if t.contains("int") || t.contains("serial") {
+            let max = if t.starts_with("tinyint") {
+                100
+            } else if t.starts_with("smallint") || t == "int2" {
+                1000
+            } else if name.contains("age") {
+                90
+            } else {
+                100_000
+            };
+            Generator::Integer { min: if name.contains("age") { 18 } else { 0 }, max }
+        } else if ["numeric", "decimal", "real", "double", "float", "money"].iter().any(|n| t.contains(n)) {
+            Generator::Decimal
+        } else if t.contains("timestamp") || t.contains("datetime") {
+            Generator::Timestamp
+        } else if t == "date" {
+            Generator::Date
+        } else if t.starts_with("time") {
+            Generator::Time
+        } else if t == "uuid" {
+            Generator::Uuid
+        } else if t.contains("json") {
+            Generator::Json
+        } else if t.is_empty() || ["char", "text", "clob", "string"].iter().any(|n| t.contains(n)) {
+            Self::for_text(&name)
+        } else if column.nullable {
+            // Leaving unknown types empty is better than refusing the whole table
+            Generator::OneOf(Vec::new())
+        } else {
+            bail!("Don't know how to generate values for column {} of type {}", column.name, column.data_type);
+        })
+    }
+
+    fn for_text(name: &str) -> Self {
+        if name.contains("email") || name.contains("mail") {
+            Generator::Email
+        } else if name.contains("first") {
+            Generator::FirstName
+        } else if name.contains("last") || name.contains("surname") {
+            Generator::LastName
+        } else if name.contains("user") || name.contains("login") {
+            Generator::Username
+        } else if name.contains("name") {
+            Generator::FullName
+        } else if name.contains("city") {
+            Generator::City
+        } else if name.contains("country") {
+            Generator::Country
+        } else if name.contains("phone") || name.contains("mobile") {
+            Generator::Phone
+        } else if name.contains("url") || name.contains("website") {
+            Generator::Url
+        } else if name.contains("address") || name.contains("street") {
+            Generator::Address
+        } else if ["description", "comment", "note", "body", "bio", "text"].iter().any(|n| name.contains(n)) {
+            Generator::Sentence
+        } else {
+            Generator::Words
+        }
+    }
+
+    /// A value for row `sequence`, which is unique within the run
+    fn generate(&self, rng: &mut StdRng, sequence: u64) -> Value {
+        let pick = |rng: &mut StdRng, list: &[&str]| list.choose(rng).copied().unwrap_or_default().to_string();
+        match self {
+            Generator::Integer { min, max } => Value::Int(rng.gen_range(*min..=*max)),
+            Generator::Decimal => Value::Float((rng.gen_range(0.0..1000.0_f64) * 100.0).round() / 100.0),
+            Generator::Boolean => Value::Bool(rng.gen_bool(0.5)),
+            Generator::Date => {
+                let days = rng.gen_range(0..730);
+                Value::Text((Local::now().date_naive() - Duration::days(days)).to_string())
+            }
+            Generator::Timestamp => {
+                let seconds = rng.gen_range(0..730 * 86_400);
+                let at = Local::now().naive_local() - Duration::seconds(seconds);
+                Value::Text(at.format("%Y-%m-%d %H:%M:%S").to_string())
+            }
+            Generator::Time => Value::Text(format!(
+                "{:02}:{:02}:{:02}",
+                rng.gen_range(0..24),
+                rng.gen_range(0..60),
+                rng.gen_range(0..60)
+            )),
+            Generator::Uuid => {
+                let bytes: [u8; 16] = rng.r#gen();
+                let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                // Version 4, variant 1
+                Value::Text(format!(
+                    "{}-{}-4{}-{:x}{}-{}",
+                    &hex[0..8],
+                    &hex[8..12],
+                    &hex[13..16],
+                    8 + (bytes[8] & 0x3),
+                    &hex[17..20],
+                    &hex[20..32]
+                ))
+            }
+            Generator::Json => Value::Text(format!(
+                "{{\"tag\": \"{}\", \"score\": {}}}",
+                pick(rng, WORDS),
+                rng.gen_range(0..100)
+            )),
+            Generator::Email => Value::Text(format!(
+                "{}.{}{}@{}",
+                pick(rng, FIRST_NAMES).to_lowercase(),
+                pick(rng, LAST_NAMES).to_lowercase(),
+                sequence,
+                pick(rng, DOMAINS)
+            )),
+            Generator::FirstName => Value::Text(pick(rng, FIRST_NAMES)),
+            Generator::LastName => Value::Text(pick(rng, LAST_NAMES)),
+            Generator::FullName => Value::Text(format!("{} {}", pick(rng, FIRST_NAMES), pick(rng, LAST_NAMES))),
+            Generator::Username => Value::Text(format!("{}{}", pick(rng, FIRST_NAMES).to_lowercase(), sequence)),
+            Generator::City => Value::Text(pick(rng, CITIES)),
+            Generator::Country => Value::Text(pick(rng, COUNTRIES)),
+            Generator::Phone => Value::Text(format!(
+                "+1-555-{:03}-{:04}",
+                rng.gen_range(0..1000),
+                rng.gen_range(0..10_000)
+            )),
+            Generator::Url => Value::Text(format!("https://www.{}-{}.test", pick(rng, WORDS), pick(rng, WORDS))),
+            Generator::Address => Value::Text(format!(
+                "{} {} Street, {}",
+                rng.gen_range(1..300),
+                capitalize(&pick(rng, WORDS)),
+                pick(rng, CITIES)
+            )),
+            Generator::Sentence => {
+                let words: Vec<String> = (0..rng.gen_range(6..14)).map(|_| pick(rng, WORDS)).collect();
+                Value::Text(format!("{}.", capitalize(&words.join(" "))))
+            }
+            Generator::Words => Value::Text(format!("{} {}", pick(rng, WORDS), pick(rng, WORDS))),
+            Generator::OneOf(values) => values.choose(rng).map_or(Value::Null, |v| Value::Text(v.clone())),
+            Generator::Reference(values) => values.choose(rng).cloned().unwrap_or(Value::Null),
+        }
+    }
+}
+
+/// A made-up value in the style of `column`, always the same for the same seed.
+/// Numbers, flags and UUIDs keep their kind, the rest becomes text.
+pub fn fake_value(column: &str, original: &Value, seed: u64) -> Value {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let generator = match original {
+        Value::Bool(_) => Generator::Boolean,
+        Value::Int(_) | Value::UInt(_) => Generator::Integer { min: 0, max: 100_000 },
+        Value::Float(_) | Value::Decimal(_) => Generator::Decimal,
+        Value::Timestamp(_) | Value::TimestampTz(_) => Generator::Timestamp,
+        Value::Date(_) => Generator::Date,
+        Value::Uuid(_) => Generator::Uuid,
+        Value::Json(_) => Generator::Json,
+        _ => Generator::for_text(&column.to_lowercase()),
+    };
+    generator.generate(&mut rng, seed % 100_000)
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// A column being filled, with what it needs to honour its constraints
+struct Filler {
+    column: ColumnSpec,
+    generator: Generator,
+    /// Unique integers count up from the largest existing value
+    next_unique: Option<i64>,
+}
+
+impl Filler {
+    fn value(&mut self, rng: &mut StdRng, sequence: u64) -> Value {
+        if self.column.nullable && self.column.references.is_none() && rng.gen_bool(NULL_RATE) {
+            return Value::Null;
+        }
+        if let Some(next) = self.next_unique.as_mut() {
+            *next += 1;
+            return Value::Int(*next);
+        }
+        match self.generator.generate(rng, sequence) {
+            Value::Text(mut text) => {
+                if self.column.unique && !matches!(self.generator, Generator::Email | Generator::Username | Generator::Uuid) {
+                    text = format!("{} {}", text, sequence);
+                }
+                if let Some(max) = self.column.max_length {
+                    // Keep the sequence number of unique values, it's what makes them unique
+                    if self.column.unique && text.chars().count() > max {
+                        let suffix = sequence.to_string();
+                        let head: String = text.chars().take(max.saturating_sub(suffix.len())).collect();
+                        text = format!("{}{}", head, suffix);
+                    }
+                    text = text.chars().take(max).collect();
+                }
+                Value::Text(text)
+            }
+            other => other,
+        }
+    }
+}
+
+/// What the generator will fill for `table`, with the columns the database fills left out
+async fn plan(executor: &QueryExecutor, table: &str) -> Result<Vec<Filler>> {
+    let dialect = executor.dialect();
+    let mut fillers = Vec::new();
+    for column in inspect_columns(executor, table).await?.into_iter().filter(|c| !c.automatic) {
+        let mut generator = Generator::for_column(&column)?;
+        let mut next_unique = None;
+        if let Some((referenced, referenced_column)) = &column.references {
+            let values = executor
+                .execute_sets(&format!(
+                    "SELECT DISTINCT {} FROM {} WHERE {} IS NOT NULL LIMIT {}",
+                    quote_identifier(referenced_column, dialect),
+                    referenced,
+                    quote_identifier(referenced_column, dialect),
+                    MAX_REFERENCED_VALUES
+                ))
+                .await?;
+            let values: Vec<Value> = rows(values).into_iter().filter_map(|row| row.into_iter().next()).collect();
+            if values.is_empty() && !column.nullable {
+                bail!("Column {} references {}, which has no rows yet", column.name, referenced);
+            }
+            generator = Generator::Reference(values);
+        } else if column.unique && matches!(generator, Generator::Integer { .. }) {
+            let max = executor
+                .execute_sets(&format!(
+                    "SELECT MAX({}) FROM {}",
+                    quote_identifier(&column.name, dialect),
+                    quote_identifier(table, dialect)
+                ))
+                .await?;
+            next_unique = Some(match rows(max).first().and_then(|row| row.first()) {
+                Some(Value::Int(v)) => *v,
+                Some(Value::UInt(v)) => *v as i64,
+                Some(other) => other.to_string().parse().unwrap_or(0),
+                None => 0,
+            });
+        }
+        fillers.push(Filler { column, generator, next_unique });
+    }
+    if fillers.is_empty() {
+        bail!("Every column of {} is filled by the database", table);
+    }
+    Ok(fillers)
+}
+
+/// A multi-row INSERT. Postgres doesn't turn text parameters into other types on its
+/// own, so every placeholder is cast to the column type there.
+fn insert_sql(table: &str, fillers: &[Filler], rows: usize, dialect: Dialect) -> String {
+    let names: Vec<String> = fillers.iter().map(|f| quote_identifier(&f.column.name, dialect)).collect();
+    let mut param = 0;
+    let tuples: Vec<String> = (0..rows)
+        .map(|_| {
+            let placeholders: Vec<String> = fillers
+                .iter()
+                .map(|filler| {
+                    param += 1;
+                    match dialect {
+                        Dialect::Postgres => format!("CAST(${} AS {})", param, filler.column.data_type),
+                        _ => "?".to_string(),
+                    }
+                })
+                .collect();
+            format!("({})", placeholders.join(", "))
+        })
+        .collect();
+    format!(
+        "INSERT INTO {} ({}) VALUES {}",
+        quote_identifier(table, dialect),
+        names.join(", "),
+        tuples.join(", ")
+    )
+}
+
+/// Inserts `count` generated rows into `table` in one transaction, or in the one the
+/// user has open. `progress` counts the rows inserted so far.
+pub async fn generate_rows(
+    executor: Arc<QueryExecutor>,
+    table: String,
+    count: u64,
+    progress: Arc<AtomicU64>,
+) -> Result<u64> {
+    let own_transaction = !executor.in_transaction().await;
+    if own_transaction {
+        executor.begin_transaction().await?;
+    }
+
+    let result = insert_generated(&executor, &table, count, &progress).await;
+    if own_transaction {
+        match &result {
+            Ok(_) => executor.commit_transaction().await?,
+            Err(_) => {
+                let _ = executor.rollback_transaction().await;
+            }
+        }
+    }
+    result
+}
+
+async fn insert_generated(executor: &QueryExecutor, table: &str, count: u64, progress: &AtomicU64) -> Result<u64> {
+    let dialect = executor.dialect();
+    let mut fillers = plan(executor, table).await?;
+    let batch_rows = (MAX_BATCH_PARAMS / fillers.len()).clamp(1, MAX_BATCH_ROWS) as u64;
+    let mut rng = StdRng::from_entropy();
+    // Unique text gets this number appended, starting from the time keeps reruns apart
+    let base = Local::now().timestamp() as u64 % 1_000_000 * 1000;
+
+    let mut inserted = 0;
+    while inserted < count {
+        let rows = batch_rows.min(count - inserted);
+        let mut params = Vec::with_capacity(rows as usize * fillers.len());
+        for row in 0..rows {
+            for filler in &mut fillers {
+                params.push(filler.value(&mut rng, base + inserted + row));
+            }
+        }
+        executor
+            .execute_prepared(&insert_sql(table, &fillers, rows as usize, dialect), &params)
+            .await
+            .with_context(|| format!("Inserting rows {}-{} failed", inserted + 1, inserted + rows))?;
+        inserted += rows;
+        progress.store(inserted, Ordering::Relaxed);
+    }
+    Ok(inserted)
+}
+
@@ -0,0 +1,329 @@
+use std::fmt;
+
+/// A PostGIS geometry or geography value, decoded from the EWKB Postgres sends for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geometry {
+    pub srid: Option<u32>,
+    pub has_z: bool,
+    pub has_m: bool,
+    pub shape: Shape,
+}
+
+/// Coordinates are x, y then z and m when the geometry has them
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// No coordinates for POINT EMPTY
+    Point(Vec<f64>),
+    LineString(Vec<Vec<f64>>),
+    /// The outer ring first, then the holes
+    Polygon(Vec<Vec<Vec<f64>>>),
+    MultiPoint(Vec<Shape>),
+    MultiLineString(Vec<Shape>),
+    MultiPolygon(Vec<Shape>),
+    GeometryCollection(Vec<Geometry>),
+}
+
+impl Shape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Point(_) => "POINT",
+            Shape::LineString(_) => "LINESTRING",
+            Shape::Polygon(_) => "POLYGON",
+            Shape::MultiPoint(_) => "MULTIPOINT",
+            Shape::MultiLineString(_) => "MULTILINESTRING",
+            Shape::MultiPolygon(_) => "MULTIPOLYGON",
+            Shape::GeometryCollection(_) => "GEOMETRYCOLLECTION",
+        }
+    }
+
+    fn coordinates<'a>(&'a self, out: &mut Vec<&'a [f64]>) {
+        match self {
+            Shape::Point(point) if point.is_empty() => {}
+            Shape::Point(point) => out.push(point),
+            Shape::LineString(points) => out.extend(points.iter().map(Vec::as_slice)),
+            Shape::Polygon(rings) => out.extend(rings.iter().flatten().map(Vec::as_slice)),
+            Shape::MultiPoint(parts) | Shape::MultiLineString(parts) | Shape::MultiPolygon(parts) => {
+                parts.iter().for_each(|part| part.coordinates(out))
+            }
+            Shape::GeometryCollection(parts) => parts.iter().for_each(|part| part.shape.coordinates(out)),
+        }
+    }
+
+    fn write_wkt(&self, dims: &str, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "{}{}", self.name(), dims)?;
+        let empty = match self {
+            Shape::Point(point) => point.is_empty(),
+            Shape::LineString(items) => items.is_empty(),
+            Shape::Polygon(items) => items.is_empty(),
+            Shape::MultiPoint(items) | Shape::MultiLineString(items) | Shape::MultiPolygon(items) => items.is_empty(),
+            Shape::GeometryCollection(items) => items.is_empty(),
+        };
+        if empty {
+            return write!(f, " EMPTY");
+        }
+        // With dimensions the tag is followed by a space, like ST_AsText writes `POINT Z (1 2 3)`
+        if !dims.is_empty() {
+            write!(f, " ")?;
+        }
+        self.write_body(f)
+    }
+
+    /// The parenthesised part of the WKT, which multi geometries nest without the tags
+    fn write_body(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self {
+            Shape::Point(point) => write!(f, "({})", coordinate_text(point)),
+            Shape::LineString(points) => write!(f, "({})", sequence_text(points)),
+            Shape::Polygon(rings) => {
+                let rings: Vec<String> = rings.iter().map(|ring| format!("({})", sequence_text(ring))).collect();
+                write!(f, "({})", rings.join(","))
+            }
+            Shape::MultiPoint(parts) | Shape::MultiLineString(parts) | Shape::MultiPolygon(parts) => {
+                write!(f, "(")?;
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    part.write_body(f)?;
+                }
+                write!(f, ")")
+            }
+            Shape::GeometryCollection(parts) => {
+                write!(f, "(")?;
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    part.shape.write_wkt(part.dimensions(), f)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Geometry {
+    /// Reads extended WKB, the format PostGIS sends geometry and geography in.
+    /// Plain WKB and its ISO 1000s type codes for Z and M are accepted too.
+    pub fn from_ewkb(data: &[u8]) -> Option<Geometry> {
+        let mut reader = Reader { data };
+        let geometry = reader.geometry(None)?;
+        reader.data.is_empty().then_some(geometry)
+    }
+
+    /// Well-known text, as ST_AsText writes it
+    pub fn wkt(&self) -> String {
+        let mut text = String::new();
+        let _ = self.shape.write_wkt(self.dimensions(), &mut text);
+        text
+    }
+
+    /// Every coordinate, rings and parts one after the other
+    pub fn coordinates(&self) -> Vec<&[f64]> {
+        let mut coordinates = Vec::new();
+        self.shape.coordinates(&mut coordinates);
+        coordinates
+    }
+
+    /// Smallest x and y, then largest, None when the geometry is empty
+    pub fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        let coordinates = self.coordinates();
+        let first = coordinates.first()?;
+        Some(coordinates.iter().fold((first[0], first[1], first[0], first[1]), |(min_x, min_y, max_x, max_y), c| {
+            (min_x.min(c[0]), min_y.min(c[1]), max_x.max(c[0]), max_y.max(c[1]))
+        }))
+    }
+
+    /// The WKT tag saying which extra coordinates there are, e.g. " Z"
+    fn dimensions(&self) -> &'static str {
+        match (self.has_z, self.has_m) {
+            (true, true) => " ZM",
+            (true, false) => " Z",
+            (false, true) => " M",
+            (false, false) => "",
+        }
+    }
+}
+
+/// Extended WKT, the SRID first when there is one, which PostGIS reads back as the same geometry
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(srid) = self.srid {
+            write!(f, "SRID={};", srid)?;
+        }
+        write!(f, "{}", self.wkt())
+    }
+}
+
+fn coordinate_text(coordinate: &[f64]) -> String {
+    coordinate.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn sequence_text(points: &[Vec<f64>]) -> String {
+    points.iter().map(|point| coordinate_text(point)).collect::<Vec<_>>().join(",")
+}
+
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+/// How the geometry being read lays out its numbers
+#[derive(Clone, Copy)]
+struct Layout {
+    little_endian: bool,
+    dims: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.data.split_first_chunk::<N>()?;
+        self.data = rest;
+        Some(*head)
+    }
+
+    fn u32(&mut self, layout: Layout) -> Option<u32> {
+        let bytes = self.take()?;
+        Some(if layout.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    fn f64(&mut self, layout: Layout) -> Option<f64> {
+        let bytes = self.take()?;
+        Some(if layout.little_endian { f64::from_le_bytes(bytes) } else { f64::from_be_bytes(bytes) })
+    }
+
+    /// A count read from the data, refused when it's more than could be left
+    fn count(&mut self, layout: Layout) -> Option<usize> {
+        let count = self.u32(layout)? as usize;
+        (count <= self.data.len()).then_some(count)
+    }
+
+    fn point(&mut self, layout: Layout) -> Option<Vec<f64>> {
+        (0..layout.dims).map(|_| self.f64(layout)).collect()
+    }
+
+    fn points(&mut self, layout: Layout) -> Option<Vec<Vec<f64>>> {
+        let count = self.count(layout)?;
+        (0..count).map(|_| self.point(layout)).collect()
+    }
+
+    /// One geometry with its own byte order, parts inherit the SRID of the whole
+    fn geometry(&mut self, srid: Option<u32>) -> Option<Geometry> {
+        let little_endian = match self.take::<1>()?[0] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let mut layout = Layout { little_endian, dims: 2 };
+        let code = self.u32(layout)?;
+        let srid = if code & EWKB_SRID != 0 { Some(self.u32(layout)?) } else { srid };
+        let (kind, iso) = ((code & 0xFFFF) % 1000, (code & 0xFFFF) / 1000);
+        let has_z = code & EWKB_Z != 0 || iso == 1 || iso == 3;
+        let has_m = code & EWKB_M != 0 || iso == 2 || iso == 3;
+        layout.dims += has_z as usize + has_m as usize;
+
+        let shape = match kind {
+            1 => {
+                let point = self.point(layout)?;
+                // POINT EMPTY is written with NaN coordinates
+                Shape::Point(if point.iter().all(|v| v.is_nan()) { Vec::new() } else { point })
+            }
+            2 => Shape::LineString(self.points(layout)?),
+            3 => {
+                let count = self.count(layout)?;
+                Shape::Polygon((0..count).map(|_| self.points(layout)).collect::<Option<_>>()?)
+            }
+            4..=7 => {
+                let count = self.count(layout)?;
+                let parts = (0..count).map(|_| self.geometry(srid)).collect::<Option<Vec<_>>>()?;
+                let shapes = || parts.iter().map(|part| part.shape.clone()).collect();
+                match kind {
+                    4 => Shape::MultiPoint(shapes()),
+                    5 => Shape::MultiLineString(shapes()),
+                    6 => Shape::MultiPolygon(shapes()),
+                    _ => Shape::GeometryCollection(parts),
+                }
+            }
+            _ => return None,
+        };
+        Some(Geometry { srid, has_z, has_m, shape })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    /// Little-endian WKB built by hand: a type code, then counts and coordinates
+    fn wkb(code: u32, body: &[&[f64]], counts: &[u32]) -> Vec<u8> {
+        let mut bytes = vec![1];
+        bytes.extend(code.to_le_bytes());
+        for count in counts {
+            bytes.extend(count.to_le_bytes());
+        }
+        for value in body.iter().flat_map(|c| c.iter()) {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_points_with_srid() {
+        // ST_AsEWKB('SRID=4326;POINT(1 2)')
+        let geometry = Geometry::from_ewkb(&hex("0101000020E6100000000000000000F03F0000000000000040")).unwrap();
+        assert_eq!(geometry.srid, Some(4326));
+        assert_eq!(geometry.wkt(), "POINT(1 2)");
+        assert_eq!(geometry.to_string(), "SRID=4326;POINT(1 2)");
+        assert_eq!(geometry.bounds(), Some((1.0, 2.0, 1.0, 2.0)));
+
+        let big_endian = hex("00000000013FF00000000000004000000000000000");
+        assert_eq!(Geometry::from_ewkb(&big_endian).unwrap().wkt(), "POINT(1 2)");
+        let empty = wkb(1, &[&[f64::NAN, f64::NAN]], &[]);
+        assert_eq!(Geometry::from_ewkb(&empty).unwrap().wkt(), "POINT EMPTY");
+    }
+
+    #[test]
+    fn reads_polygons_and_collections() {
+        let square: [[f64; 2]; 4] = [[0.0, 0.0], [4.0, 0.0], [4.0, 3.0], [0.0, 0.0]];
+        let hole: [[f64; 2]; 4] = [[1.0, 1.0], [2.0, 1.0], [1.0, 2.0], [1.0, 1.0]];
+        let mut polygon = vec![1];
+        polygon.extend(3u32.to_le_bytes());
+        polygon.extend(2u32.to_le_bytes());
+        for ring in [square, hole] {
+            polygon.extend(4u32.to_le_bytes());
+            polygon.extend(ring.iter().flatten().flat_map(|v| v.to_le_bytes()));
+        }
+        let geometry = Geometry::from_ewkb(&polygon).unwrap();
+        assert_eq!(geometry.wkt(), "POLYGON((0 0,4 0,4 3,0 0),(1 1,2 1,1 2,1 1))");
+        assert_eq!(geometry.bounds(), Some((0.0, 0.0, 4.0, 3.0)));
+        assert_eq!(geometry.coordinates().len(), 8);
+
+        // ISO codes mark Z with 1000, parts are full geometries
+        let mut multipoint = wkb(1004, &[], &[2]);
+        multipoint.extend(wkb(1001, &[&[1.0, 2.0, 3.0]], &[]));
+        multipoint.extend(wkb(1001, &[&[4.0, 5.0, 6.0]], &[]));
+        assert_eq!(Geometry::from_ewkb(&multipoint).unwrap().wkt(), "MULTIPOINT Z ((1 2 3),(4 5 6))");
+
+        let mut collection = wkb(7, &[], &[2]);
+        collection.extend(wkb(1, &[&[1.0, 2.0]], &[]));
+        collection.extend(wkb(2, &[&[0.0, 0.0], &[1.5, -1.0]], &[2]));
+        assert_eq!(
+            Geometry::from_ewkb(&collection).unwrap().wkt(),
+            "GEOMETRYCOLLECTION(POINT(1 2),LINESTRING(0 0,1.5 -1))"
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_or_unknown_data() {
+        let point = wkb(1, &[&[1.0, 2.0]], &[]);
+        assert!(Geometry::from_ewkb(&point[..point.len() - 1]).is_none());
+        assert!(Geometry::from_ewkb(&wkb(2, &[], &[u32::MAX])).is_none());
+        assert!(Geometry::from_ewkb(&wkb(99, &[&[1.0, 2.0]], &[])).is_none());
+    }
+}
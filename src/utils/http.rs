@@ -0,0 +1,122 @@
+use anyhow::{Result, anyhow, bail};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Larger requests are refused, nothing served locally needs more
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// A client that hasn't sent its whole request by then is dropped instead of holding a task
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One HTTP/1.1 request, each connection carries exactly one
+pub struct Request {
+    pub method: String,
+    /// Without the query string
+    pub path: String,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub async fn read_request(stream: &mut BufReader<TcpStream>) -> Result<Request> {
+    tokio::time::timeout(REQUEST_TIMEOUT, read(stream))
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for the request"))?
+}
+
+async fn read(stream: &mut BufReader<TcpStream>) -> Result<Request> {
+    let mut head = Vec::new();
+    loop {
+        let before = head.len();
+        // One byte past the limit at most, a line that never ends can't grow the buffer
+        let room = (MAX_HEADER_BYTES + 1 - head.len()) as u64;
+        if (&mut *stream).take(room).read_until(b'\n', &mut head).await? == 0 {
+            bail!("Connection closed before the request ended");
+        }
+        if head.len() > MAX_HEADER_BYTES {
+            bail!("Request headers too large");
+        }
+        // An empty line ends the headers
+        if head[before..] == *b"\r\n" || head[before..] == *b"\n" {
+            break;
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        bail!("Malformed request line");
+    };
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut request = Request { method: method.to_string(), path: path.to_string(), headers, body: Vec::new() };
+    let length: usize = request.header("Content-Length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        bail!("Request body too large");
+    }
+    request.body.resize(length, 0);
+    stream.read_exact(&mut request.body).await?;
+    Ok(request)
+}
+
+pub async fn write_response(stream: &mut BufReader<TcpStream>, status: u16, content_type: &str, body: &[u8]) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason(status),
+        content_type,
+        body.len()
+    );
+    let stream = stream.get_mut();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Decodes %XX escapes in a path segment, e.g. a connection name with spaces
+pub fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        502 => "Bad Gateway",
+        _ => "",
+    }
+}
@@ -0,0 +1,527 @@
+use crate::utils::query_executor::QueryExecutor;
+use crate::utils::sql::{Dialect, quote_identifier};
+use crate::utils::value::Value;
+use anyhow::{Context, Result, anyhow};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json::Value as JsonValue;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Rows looked at to guess the column types
+const INFER_SAMPLE_ROWS: usize = 1000;
+/// Most rows sent in one INSERT
+pub(crate) const MAX_BATCH_ROWS: usize = 500;
+/// Bind parameters per statement, below the limit of every supported database
+pub(crate) const MAX_BATCH_PARAMS: usize = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    Timestamp,
+    Json,
+}
+
+impl ColumnType {
+    pub const ALL: [ColumnType; 7] = [
+        ColumnType::Text,
+        ColumnType::Integer,
+        ColumnType::Float,
+        ColumnType::Boolean,
+        ColumnType::Date,
+        ColumnType::Timestamp,
+        ColumnType::Json,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColumnType::Text => "text",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Date => "date",
+            ColumnType::Timestamp => "timestamp",
+            ColumnType::Json => "json",
+        }
+    }
+
+    pub fn sql_type(self, dialect: Dialect) -> &'static str {
+        match (self, dialect) {
+            (ColumnType::Text, _) => "TEXT",
+            (ColumnType::Integer, Dialect::Sqlite) => "INTEGER",
+            (ColumnType::Integer, _) => "BIGINT",
+            (ColumnType::Float, Dialect::Postgres) => "DOUBLE PRECISION",
+            (ColumnType::Float, Dialect::MySql) => "DOUBLE",
+            (ColumnType::Float, Dialect::Sqlite) => "REAL",
+            (ColumnType::Boolean, _) => "BOOLEAN",
+            (ColumnType::Date, _) => "DATE",
+            (ColumnType::Timestamp, Dialect::Postgres) => "TIMESTAMP",
+            (ColumnType::Timestamp, _) => "DATETIME",
+            (ColumnType::Json, Dialect::Postgres) => "JSONB",
+            (ColumnType::Json, Dialect::MySql) => "JSON",
+            // SQLite's JSON functions work on text
+            (ColumnType::Json, Dialect::Sqlite) => "TEXT",
+        }
+    }
+
+    /// The type after `self` in `ALL`, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let index = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Whether a cell can be stored in a column of this type. CSV cells are always
+    /// strings, JSON cells keep their own type.
+    fn accepts(self, cell: &JsonValue) -> bool {
+        match (self, cell) {
+            (_, JsonValue::Null) | (ColumnType::Text, _) => true,
+            (ColumnType::Json, JsonValue::String(text)) => serde_json::from_str::<JsonValue>(text).is_ok(),
+            (ColumnType::Json, _) => true,
+            (ColumnType::Integer, JsonValue::Number(n)) => n.is_i64(),
+            (ColumnType::Float, JsonValue::Number(_)) | (ColumnType::Boolean, JsonValue::Bool(_)) => true,
+            (_, JsonValue::String(text)) => self.accepts_text(text),
+            _ => false,
+        }
+    }
+
+    fn accepts_text(self, text: &str) -> bool {
+        match self {
+            ColumnType::Text => true,
+            ColumnType::Integer => text.parse::<i64>().is_ok(),
+            ColumnType::Float => text.parse::<f64>().is_ok() && text.chars().any(|c| c.is_ascii_digit()),
+            ColumnType::Boolean => parse_bool(text).is_some(),
+            ColumnType::Date => NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok(),
+            ColumnType::Timestamp => parse_timestamp(text).is_some(),
+            ColumnType::Json => serde_json::from_str::<JsonValue>(text).is_ok(),
+        }
+    }
+
+    /// Converts a cell to a bind parameter. Dates and timestamps stay text, the
+    /// INSERT casts them so every database parses them itself. JSON columns take
+    /// strings as JSON text, any other cell as the value it is.
+    fn convert(self, cell: &JsonValue) -> Option<Value> {
+        if !self.accepts(cell) {
+            return None;
+        }
+        Some(match (self, cell) {
+            (_, JsonValue::Null) => Value::Null,
+            (ColumnType::Json, JsonValue::String(text)) => Value::Json(serde_json::from_str(text).ok()?),
+            (ColumnType::Json, other) => Value::Json(other.clone()),
+            (ColumnType::Text, JsonValue::String(text)) => Value::Text(text.clone()),
+            (ColumnType::Text, other) => Value::Text(other.to_string()),
+            (ColumnType::Integer, JsonValue::Number(n)) => Value::Int(n.as_i64()?),
+            (ColumnType::Integer, other) => Value::Int(cell_text(other).parse().ok()?),
+            (ColumnType::Float, JsonValue::Number(n)) => Value::Float(n.as_f64()?),
+            (ColumnType::Float, other) => Value::Float(cell_text(other).parse().ok()?),
+            (ColumnType::Boolean, JsonValue::Bool(v)) => Value::Bool(*v),
+            (ColumnType::Boolean, other) => Value::Bool(parse_bool(&cell_text(other))?),
+            (ColumnType::Date | ColumnType::Timestamp, other) => Value::Text(cell_text(other)),
+        })
+    }
+}
+
+/// A cell as the preview and error messages show it, NULL is empty
+pub fn cell_text(cell: &JsonValue) -> String {
+    match cell {
+        JsonValue::Null => String::new(),
+        JsonValue::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    match text.to_lowercase().as_str() {
+        "true" | "t" | "yes" => Some(true),
+        "false" | "f" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_timestamp(text: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+}
+
+/// A column of the file and where it goes in the target table
+#[derive(Debug, Clone)]
+pub struct ImportColumn {
+    /// Header or key in the file
+    pub source: String,
+    /// Column name in the table
+    pub name: String,
+    pub column_type: ColumnType,
+    pub include: bool,
+}
+
+/// What kind of file the rows came from
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SourceFormat {
+    #[default]
+    Csv,
+    /// An array of objects, or one object per line
+    Json,
+}
+
+/// A parsed import file. CSV cells are strings with empty fields as NULL,
+/// JSON cells are the values under each key, missing keys are NULL.
+#[derive(Debug, Clone, Default)]
+pub struct ImportData {
+    pub format: SourceFormat,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+impl ImportData {
+    /// Reads CSV, or JSON for .json, .jsonl and .ndjson files
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let content = content.trim_start_matches('\u{feff}');
+        let is_json = path
+            .extension()
+            .is_some_and(|e| ["json", "jsonl", "ndjson"].iter().any(|j| e.eq_ignore_ascii_case(j)));
+        let data = if is_json { parse_json(content)? } else { parse_csv_data(content) };
+        if data.headers.is_empty() {
+            return Err(anyhow!("{} is empty", path.display()));
+        }
+        Ok(data)
+    }
+
+    /// One entry per header, typed by the values in the first rows
+    pub fn infer_columns(&self) -> Vec<ImportColumn> {
+        self.headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| {
+                let values: Vec<&JsonValue> = self
+                    .rows
+                    .iter()
+                    .take(INFER_SAMPLE_ROWS)
+                    .filter_map(|row| row.get(index))
+                    .filter(|value| !value.is_null())
+                    .collect();
+                let nested = values.iter().any(|v| v.is_object() || v.is_array());
+                let candidates: &[ColumnType] = if nested {
+                    &[ColumnType::Json]
+                } else {
+                    &[
+                        ColumnType::Integer,
+                        ColumnType::Float,
+                        ColumnType::Boolean,
+                        ColumnType::Date,
+                        ColumnType::Timestamp,
+                    ]
+                };
+                let column_type = candidates
+                    .iter()
+                    .copied()
+                    .find(|t| !values.is_empty() && values.iter().all(|v| t.accepts(v)))
+                    .unwrap_or(ColumnType::Text);
+                ImportColumn {
+                    source: header.clone(),
+                    name: header.trim().to_string(),
+                    column_type,
+                    include: true,
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_csv_data(content: &str) -> ImportData {
+    let mut records = parse_csv(content).into_iter();
+    let headers = records.next().unwrap_or_default();
+    let rows = records
+        .map(|record| {
+            record
+                .into_iter()
+                .map(|field| if field.is_empty() { JsonValue::Null } else { JsonValue::String(field) })
+                .collect()
+        })
+        .collect();
+    ImportData { format: SourceFormat::Csv, headers, rows }
+}
+
+/// An object with its keys in file order, serde_json's map sorts them
+struct OrderedObject(Vec<(String, JsonValue)>);
+
+impl<'de> Deserialize<'de> for OrderedObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ObjectVisitor;
+
+        impl<'de> Visitor<'de> for ObjectVisitor {
+            type Value = OrderedObject;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedObject, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(OrderedObject(entries))
+            }
+        }
+
+        deserializer.deserialize_map(ObjectVisitor)
+    }
+}
+
+/// A JSON array of objects, or NDJSON with one object per line. Headers are the
+/// keys in the order they first appear.
+pub fn parse_json(content: &str) -> Result<ImportData> {
+    let objects: Vec<OrderedObject> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content).context("The file is not an array of JSON objects")?
+    } else {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).with_context(|| format!("Line {} is not a JSON object", index + 1))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let mut headers: Vec<String> = Vec::new();
+    for object in &objects {
+        for (key, _) in &object.0 {
+            if !headers.contains(key) {
+                headers.push(key.clone());
+            }
+        }
+    }
+    let rows = objects
+        .into_iter()
+        .map(|object| {
+            let mut row = vec![JsonValue::Null; headers.len()];
+            for (key, value) in object.0 {
+                if let Some(index) = headers.iter().position(|h| *h == key) {
+                    row[index] = value;
+                }
+            }
+            row
+        })
+        .collect();
+    Ok(ImportData { format: SourceFormat::Json, headers, rows })
+}
+
+/// RFC 4180 fields: quoted fields may hold commas, newlines and doubled quotes.
+/// Blank lines are skipped.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                if !(record.len() == 1 && record[0].is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Everything needed to run an import, built by the import wizard
+#[derive(Debug, Clone)]
+pub struct ImportJob {
+    pub table: String,
+    /// Create the table first instead of appending to an existing one
+    pub create: bool,
+    pub columns: Vec<ImportColumn>,
+    /// JSON column that gets the keys left out of the mapping as one object
+    pub extra: Option<String>,
+    pub data: ImportData,
+}
+
+impl ImportJob {
+    fn included(&self) -> Vec<(usize, &ImportColumn)> {
+        self.columns.iter().enumerate().filter(|(_, c)| c.include).collect()
+    }
+
+    /// Names and types of the target columns, the extra column last
+    fn targets(&self) -> Vec<(&str, ColumnType)> {
+        let mut targets: Vec<(&str, ColumnType)> =
+            self.included().iter().map(|(_, c)| (c.name.as_str(), c.column_type)).collect();
+        if let Some(extra) = &self.extra {
+            targets.push((extra, ColumnType::Json));
+        }
+        targets
+    }
+
+    pub fn create_table_sql(&self, dialect: Dialect) -> String {
+        let columns: Vec<String> = self
+            .targets()
+            .iter()
+            .map(|(name, column_type)| format!("{} {}", quote_identifier(name, dialect), column_type.sql_type(dialect)))
+            .collect();
+        format!("CREATE TABLE {} ({})", quote_identifier(&self.table, dialect), columns.join(", "))
+    }
+
+    /// A multi-row INSERT for `rows` rows. Postgres won't turn a text parameter into
+    /// a date or JSON on its own, so typed columns get a cast there.
+    pub fn insert_sql(&self, rows: usize, dialect: Dialect) -> String {
+        let targets = self.targets();
+        let names: Vec<String> = targets.iter().map(|(name, _)| quote_identifier(name, dialect)).collect();
+        let mut param = 0;
+        let tuples: Vec<String> = (0..rows)
+            .map(|_| {
+                let placeholders: Vec<String> = targets
+                    .iter()
+                    .map(|(_, column_type)| {
+                        param += 1;
+                        match dialect {
+                            Dialect::Postgres
+                                if matches!(column_type, ColumnType::Date | ColumnType::Timestamp | ColumnType::Json) =>
+                            {
+                                format!("CAST(${} AS {})", param, column_type.sql_type(dialect))
+                            }
+                            Dialect::Postgres => format!("${}", param),
+                            _ => "?".to_string(),
+                        }
+                    })
+                    .collect();
+                format!("({})", placeholders.join(", "))
+            })
+            .collect();
+        format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            quote_identifier(&self.table, dialect),
+            names.join(", "),
+            tuples.join(", ")
+        )
+    }
+
+    /// Bind parameters of one row, errors name the line or record and the column
+    fn row_params(&self, index: usize, row: &[JsonValue]) -> Result<Vec<Value>> {
+        let mut params = self
+            .included()
+            .iter()
+            .map(|(i, column)| {
+                let cell = row.get(*i).unwrap_or(&JsonValue::Null);
+                column.column_type.convert(cell).ok_or_else(|| {
+                    anyhow!(
+                        "{}, column {}: '{}' is not a valid {}",
+                        self.row_label(index),
+                        column.source,
+                        cell_text(cell),
+                        column.column_type.name()
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if self.extra.is_some() {
+            let unmapped: serde_json::Map<String, JsonValue> = self
+                .columns
+                .iter()
+                .zip(row)
+                .filter(|(column, cell)| !column.include && !cell.is_null())
+                .map(|(column, cell)| (column.source.clone(), cell.clone()))
+                .collect();
+            params.push(if unmapped.is_empty() { Value::Null } else { Value::Json(JsonValue::Object(unmapped)) });
+        }
+        Ok(params)
+    }
+
+    /// "Line n" of a CSV file, where line 1 is the header, or "Record n" of a JSON file
+    fn row_label(&self, index: usize) -> String {
+        match self.data.format {
+            SourceFormat::Csv => format!("Line {}", index + 2),
+            SourceFormat::Json => format!("Record {}", index + 1),
+        }
+    }
+
+    fn batch_label(&self, first: usize, rows: usize) -> String {
+        match self.data.format {
+            SourceFormat::Csv => format!("lines {}-{}", first + 2, first + rows + 1),
+            SourceFormat::Json => format!("records {}-{}", first + 1, first + rows),
+        }
+    }
+
+    pub fn batch_rows(&self) -> usize {
+        let columns = self.targets().len().max(1);
+        (MAX_BATCH_PARAMS / columns).clamp(1, MAX_BATCH_ROWS)
+    }
+}
+
+/// Runs the import in one transaction, so a bad row leaves the table untouched.
+/// Inside a transaction the user opened, the rows join it instead.
+/// `progress` counts the rows inserted so far.
+pub async fn run_import(executor: Arc<QueryExecutor>, job: ImportJob, progress: Arc<AtomicU64>) -> Result<u64> {
+    if job.targets().is_empty() {
+        return Err(anyhow!("No columns selected"));
+    }
+    let own_transaction = !executor.in_transaction().await;
+    if own_transaction {
+        executor.begin_transaction().await?;
+    }
+
+    let result = insert_rows(&executor, &job, &progress).await;
+    if own_transaction {
+        match &result {
+            Ok(_) => executor.commit_transaction().await?,
+            Err(_) => {
+                let _ = executor.rollback_transaction().await;
+            }
+        }
+    }
+    result
+}
+
+async fn insert_rows(executor: &QueryExecutor, job: &ImportJob, progress: &AtomicU64) -> Result<u64> {
+    let dialect = executor.dialect();
+    if job.create {
+        executor.execute_prepared(&job.create_table_sql(dialect), &[]).await?;
+    }
+
+    let batch_rows = job.batch_rows();
+    let mut inserted = 0;
+    for (batch_index, batch) in job.data.rows.chunks(batch_rows).enumerate() {
+        let first = batch_index * batch_rows;
+        let mut params = Vec::with_capacity(batch.len() * job.columns.len());
+        for (offset, row) in batch.iter().enumerate() {
+            params.extend(job.row_params(first + offset, row)?);
+        }
+        executor
+            .execute_prepared(&job.insert_sql(batch.len(), dialect), &params)
+            .await
+            .with_context(|| format!("Inserting {} failed", job.batch_label(first, batch.len())))?;
+        inserted += batch.len() as u64;
+        progress.store(inserted, Ordering::Relaxed);
+    }
+    Ok(inserted)
+}
@@ -1,7 +1,9 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, KeyEventKind};
 use anyhow::Result;
-use crate::gui::{ConnectionListAction, ConnectionListPage, Field, Focus, NewConnectionAction, NewConnectionPage, QueryPage, QueryPageAction, HistoryPage, HistoryPageAction};
-use crate::utils::connection::ConnectionManager;
+use crate::gui::{Confirmation, ConnectionListAction, ConnectionListPage, DiscoverySource, Field, Focus, LineInput, NewConnectionAction, NewConnectionPage, QueryPage, QueryPageAction, HistoryPage, HistoryPageAction, ImportAction, ImportPage, ImportStep, MAPPING_HEADER_ROWS, LocksAction, LocksPage, NotificationsAction, NotificationsInput, NotificationsPage, SessionsAction, SessionsPage, SizeSort, SizesAction, SizesPage, SettingsAction, SettingsField, SettingsPage};
+use crate::gui::toast;
+use crate::utils::masking::MaskMethod;
+use crate::utils::{clipboard, sql::{Dialect, truncate_is_transactional}};
 
 impl QueryPage {
     pub async fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Result<Option<QueryPageAction>> {
@@ -9,17 +11,233 @@ impl QueryPage {
             return Ok(None);
         }
 
-        // Handle input overlay
-        if self.show_input_overlay {
+        // The panel shown on connect gets out of the way of the first key
+        if self.show_server_info {
+            self.show_server_info = false;
+            if key.code == KeyCode::Esc {
+                return Ok(None);
+            }
+        }
+
+        if let Some(pending) = self.pending_confirmation.as_mut() {
+            let dialect = self.executor.as_ref().map(|executor| executor.dialect());
+            match (&mut pending.kind, key.code) {
+                (Confirmation::LargeResult { .. }, KeyCode::Char('y') | KeyCode::Enter) => {
+                    self.pending_confirmation = None;
+                    self.proceed_execute();
+                }
+                (Confirmation::LargeResult { .. }, KeyCode::Char('n')) => self.pending_confirmation = None,
+                (Confirmation::UnfilteredWrite { .. }, KeyCode::Char(c)) => pending.input.push(c),
+                (Confirmation::UnfilteredWrite { .. }, KeyCode::Backspace) => {
+                    pending.input.pop();
+                }
+                (Confirmation::UnfilteredWrite { .. }, KeyCode::Enter)
+                    if pending.input.trim().eq_ignore_ascii_case("yes") =>
+                {
+                    self.pending_confirmation = None;
+                    self.proceed_execute();
+                }
+                (Confirmation::EmptyTable { delete, transaction, .. }, KeyCode::Tab)
+                    if dialect.is_some_and(|dialect| dialect != Dialect::Sqlite) =>
+                {
+                    *delete = !*delete;
+                    if !*delete && dialect.is_some_and(|dialect| !truncate_is_transactional(dialect)) {
+                        *transaction = false;
+                    }
+                }
+                (Confirmation::EmptyTable { delete, transaction, .. }, KeyCode::Char('t'))
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && (*delete || dialect.is_some_and(truncate_is_transactional)) =>
+                {
+                    *transaction = !*transaction;
+                }
+                (Confirmation::EmptyTable { .. }, KeyCode::Char(c)) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    pending.input.push(c)
+                }
+                (Confirmation::EmptyTable { .. }, KeyCode::Backspace) => {
+                    pending.input.pop();
+                }
+                (Confirmation::EmptyTable { table, delete, transaction, .. }, KeyCode::Enter)
+                    if pending.input == *table =>
+                {
+                    let (table, delete, transaction) = (table.clone(), *delete, *transaction);
+                    self.pending_confirmation = None;
+                    self.empty_table(&table, delete, transaction).await?;
+                }
+                (Confirmation::Maintenance { selected, armed, .. }, KeyCode::Up) => {
+                    *selected = selected.saturating_sub(1);
+                    *armed = false;
+                }
+                (Confirmation::Maintenance { operations, selected, armed, .. }, KeyCode::Down) => {
+                    *selected = (*selected + 1).min(operations.len().saturating_sub(1));
+                    *armed = false;
+                }
+                (Confirmation::Maintenance { operations, selected, armed, .. }, KeyCode::Enter)
+                    if operations[*selected].blocking && !*armed =>
+                {
+                    *armed = true;
+                }
+                (Confirmation::Maintenance { table, operations, selected, .. }, KeyCode::Enter) => {
+                    let (table, operation) = (table.clone(), operations[*selected].clone());
+                    self.pending_confirmation = None;
+                    self.run_maintenance(table, operation);
+                }
+                (_, KeyCode::Esc) => self.pending_confirmation = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if self.show_params_overlay {
+            let index = self.param_index;
             match key.code {
-                KeyCode::Char(c) if c.is_ascii_digit() => {
-                    self.input_buffer.push(c);
-                    Ok(None)
+                KeyCode::Up | KeyCode::BackTab => {
+                    self.param_index = index.saturating_sub(1);
+                }
+                KeyCode::Down | KeyCode::Tab if index + 1 < self.params.len() => {
+                    self.param_index = index + 1;
                 }
+                KeyCode::Char(c) => self.params[index].push(c),
                 KeyCode::Backspace => {
-                    self.input_buffer.pop();
-                    Ok(None)
+                    self.params[index].pop();
                 }
+                KeyCode::Enter => self.execute_with_params(),
+                KeyCode::Esc => self.show_params_overlay = false,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if let Some(pending) = self.pending_dump.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => pending.path.push(c),
+                KeyCode::Backspace => {
+                    pending.path.pop();
+                }
+                KeyCode::Enter if !pending.path.trim().is_empty() => self.start_dump(),
+                KeyCode::Esc => self.pending_dump = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if let Some(pending) = self.pending_generate.as_mut() {
+            match key.code {
+                KeyCode::Char(c) if c.is_ascii_digit() => pending.rows.push(c),
+                KeyCode::Backspace => {
+                    pending.rows.pop();
+                }
+                KeyCode::Enter => self.start_generate(),
+                KeyCode::Esc => self.pending_generate = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if let Some(scroll) = self.row_detail.as_mut() {
+            match key.code {
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+                KeyCode::PageDown => *scroll = scroll.saturating_add(10),
+                KeyCode::Enter | KeyCode::Esc => self.row_detail = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if let Some(pending) = self.pending_masking.as_mut() {
+            let selected = pending.state.selected().unwrap_or(0);
+            let last = pending.columns.len().saturating_sub(1);
+            let method = pending.columns.get_mut(selected).map(|(_, method)| method);
+            match (key.code, method) {
+                (KeyCode::Up, _) => pending.state.select(Some(selected.saturating_sub(1))),
+                (KeyCode::Down, _) => pending.state.select(Some((selected + 1).min(last))),
+                (KeyCode::Right | KeyCode::Char(' '), Some(method)) => *method = MaskMethod::cycle(*method, true),
+                (KeyCode::Left, Some(method)) => *method = MaskMethod::cycle(*method, false),
+                (KeyCode::Char('h'), Some(method)) => *method = Some(MaskMethod::Hash),
+                (KeyCode::Char('r'), Some(method)) => *method = Some(MaskMethod::Redact),
+                (KeyCode::Char('f'), Some(method)) => *method = Some(MaskMethod::Fake),
+                (KeyCode::Char('n') | KeyCode::Delete | KeyCode::Backspace, Some(method)) => *method = None,
+                (KeyCode::Enter, _) => return Ok(self.save_masking()),
+                (KeyCode::Esc, _) => self.pending_masking = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if let Some(pending) = self.pending_schedule.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => pending.input.push(c),
+                KeyCode::Backspace => {
+                    pending.input.pop();
+                }
+                KeyCode::Delete if !self.schedule.is_empty() => {
+                    toast::info(format!("Cancelled {} scheduled queries", self.schedule.len()));
+                    self.schedule.clear();
+                }
+                KeyCode::Enter => self.schedule_query(),
+                KeyCode::Esc => self.pending_schedule = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if let Some(pending) = self.pending_pipeline.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => pending.input.push(c),
+                KeyCode::Backspace => {
+                    pending.input.pop();
+                }
+                KeyCode::Enter => self.apply_pipeline(),
+                KeyCode::Esc => self.pending_pipeline = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if let Some(palette) = self.palette.as_mut() {
+            let last = palette.matches(&self.scripts).count().saturating_sub(1);
+            let selected = palette.state.selected().unwrap_or(0);
+            match key.code {
+                KeyCode::Char(c) => {
+                    palette.filter.push(c);
+                    palette.state.select(Some(0));
+                }
+                KeyCode::Backspace => {
+                    palette.filter.pop();
+                    palette.state.select(Some(0));
+                }
+                KeyCode::Up => palette.state.select(Some(selected.saturating_sub(1))),
+                KeyCode::Down => palette.state.select(Some((selected + 1).min(last))),
+                KeyCode::Enter => self.run_palette_command(),
+                KeyCode::Esc => self.palette = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if self.show_error_detail {
+            match key.code {
+                KeyCode::Char('c') => {
+                    if let Some(detail) = &self.error_detail {
+                        match clipboard::copy(&detail.to_text()) {
+                            Ok(()) => toast::success("Copied the error to the clipboard"),
+                            Err(e) => toast::error(format!("Could not copy: {}", e)),
+                        }
+                    }
+                }
+                KeyCode::Esc | KeyCode::Enter => self.show_error_detail = false,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        // Handle input overlay
+        if self.show_input_overlay {
+            match key.code {
+                // Only digits make a row count
+                KeyCode::Char(c) if !c.is_ascii_digit() && !key.modifiers.contains(KeyModifiers::CONTROL) => Ok(None),
                 KeyCode::Enter => {
                     if let Ok(num) = self.input_buffer.parse::<u32>() {
                         self.max_results = num;
@@ -33,17 +251,72 @@ impl QueryPage {
                     self.input_buffer.clear();
                     Ok(None)
                 }
-                _ => Ok(None),
+                _ => {
+                    self.input_cursor.handle_key(&mut self.input_buffer, key);
+                    Ok(None)
+                }
             }
         } else {
             // Normal input handling
             match key.code {
+                KeyCode::Esc if self.watch.is_some() => {
+                    self.stop_watch();
+                    toast::info("Watch stopped");
+                    Ok(None)
+                }
+                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_watch();
+                    Ok(None)
+                }
+                KeyCode::Esc if self.running_query.is_some() || self.checking_query.is_some() => {
+                    self.cancel_query();
+                    Ok(None)
+                }
                 KeyCode::Esc => Ok(Some(QueryPageAction::Back)),
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Ok(Some(QueryPageAction::ToggleSplit))
+                }
+                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Ok(Some(QueryPageAction::SwitchPane))
+                }
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Ok(Some(QueryPageAction::FlipSplit))
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Ok(Some(QueryPageAction::PickSplitConnection))
+                }
+                KeyCode::Char('i') if key.modifiers.contains(KeyModifiers::ALT) && self.executor.is_some() => {
+                    Ok(Some(QueryPageAction::OpenImport))
+                }
+                KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.show_server_info = self.server_info.is_some();
+                    Ok(None)
+                }
+                KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::ALT) && self.executor.is_some() => {
+                    Ok(Some(QueryPageAction::OpenLocks))
+                }
+                KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::ALT) && self.executor.is_some() => {
+                    Ok(Some(QueryPageAction::OpenNotifications))
+                }
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::ALT) && self.executor.is_some() => {
+                    self.open_schedule();
+                    Ok(None)
+                }
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::ALT) && self.executor.is_some() => {
+                    Ok(Some(QueryPageAction::OpenSizes))
+                }
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::ALT) && self.executor.is_some() => {
+                    Ok(Some(QueryPageAction::OpenSessions))
+                }
                 KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     if self.focus == Focus::Explorer {
                         self.focus = Focus::Query;
                     } else {
                         self.focus = Focus::Explorer;
+                        self.maximized = None;
+                        if self.panes.explorer_width == 0 {
+                            self.resize_explorer(1);
+                        }
                     }
                     Ok(None)
                 }
@@ -53,6 +326,67 @@ impl QueryPage {
                         Focus::Results => Focus::Query,
                         Focus::Explorer => Focus::Query,
                     };
+                    // The focused pane has to stay visible
+                    if self.maximized.is_some() {
+                        self.maximized = self.focused_pane();
+                    }
+                    Ok(None)
+                }
+                KeyCode::Up if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.resize_editor(-1);
+                    Ok(None)
+                }
+                KeyCode::Down if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.resize_editor(1);
+                    Ok(None)
+                }
+                KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.resize_explorer(-1);
+                    Ok(None)
+                }
+                KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.resize_explorer(1);
+                    Ok(None)
+                }
+                KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_maximized();
+                    Ok(None)
+                }
+                KeyCode::Char('c') | KeyCode::Char('C') if key.modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) => {
+                    self.commit_transaction().await?;
+                    Ok(None)
+                }
+                KeyCode::Char('r') | KeyCode::Char('R') if key.modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) => {
+                    self.rollback_transaction().await?;
+                    Ok(None)
+                }
+                // Terminals without the keyboard enhancement protocol can't send the Shift chords
+                KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.commit_transaction().await?;
+                    Ok(None)
+                }
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.rollback_transaction().await?;
+                    Ok(None)
+                }
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.prepared_mode = !self.prepared_mode;
+                    Ok(None)
+                }
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.open_pipeline();
+                    Ok(None)
+                }
+                KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.open_palette();
+                    Ok(None)
+                }
+                KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_read_only();
+                    Ok(None)
+                }
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.begin_transaction().await?;
                     Ok(None)
                 }
                 KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
@@ -72,6 +406,7 @@ impl QueryPage {
                 }
                 KeyCode::Down if matches!(self.focus, Focus::Results) => {
                     self.scroll_down();
+                    self.load_more_if_needed().await?;
                     Ok(None)
                 }
                 KeyCode::Up if matches!(self.focus, Focus::Explorer) => {
@@ -86,6 +421,28 @@ impl QueryPage {
                     self.toggle_table_expansion().await?;
                     Ok(None)
                 }
+                KeyCode::Char('d') | KeyCode::Char('D')
+                    if matches!(self.focus, Focus::Explorer) && !key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    self.open_dump(key.code == KeyCode::Char('D'));
+                    Ok(None)
+                }
+                KeyCode::Char('g') if matches!(self.focus, Focus::Explorer) => {
+                    self.open_generate();
+                    Ok(None)
+                }
+                KeyCode::Char('r') if matches!(self.focus, Focus::Explorer) && !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.refresh_tables();
+                    Ok(None)
+                }
+                KeyCode::Char('m') if matches!(self.focus, Focus::Explorer) && self.running_query.is_none() => {
+                    self.open_maintenance();
+                    Ok(None)
+                }
+                KeyCode::Char('x') if matches!(self.focus, Focus::Explorer) && self.running_query.is_none() => {
+                    self.open_empty_table().await;
+                    Ok(None)
+                }
                 KeyCode::Left if matches!(self.focus, Focus::Results) => {
                     if self.horizontal_scroll > 0 {
                         self.horizontal_scroll -= 1;
@@ -104,12 +461,45 @@ impl QueryPage {
                 }
                 KeyCode::PageDown if matches!(self.focus, Focus::Results) => {
                     self.scroll_page_down();
+                    self.load_more_if_needed().await?;
+                    Ok(None)
+                }
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.show_error_detail = self.error.is_some() && self.error_detail.is_some();
+                    Ok(None)
+                }
+                KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.show_notices = !self.show_notices;
+                    Ok(None)
+                }
+                KeyCode::Char('[') if matches!(self.focus, Focus::Results) => {
+                    self.select_result_set(self.active_result.saturating_sub(1));
+                    Ok(None)
+                }
+                KeyCode::Char(']') if matches!(self.focus, Focus::Results) => {
+                    self.select_result_set(self.active_result + 1);
+                    Ok(None)
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') if matches!(self.focus, Focus::Results) => {
+                    self.load_more_rows().await?;
                     Ok(None)
                 }
                 KeyCode::Char('t') | KeyCode::Char('T') if matches!(self.focus, Focus::Results) => {
                     self.table_state.select(Some(0));
                     Ok(None)
                 }
+                KeyCode::Enter if matches!(self.focus, Focus::Results) && self.table_state.selected().is_some() => {
+                    self.row_detail = Some(0);
+                    Ok(None)
+                }
+                KeyCode::Char('m') | KeyCode::Char('M') if matches!(self.focus, Focus::Results) => {
+                    self.open_masking();
+                    Ok(None)
+                }
+                KeyCode::Char('y') | KeyCode::Char('Y') if matches!(self.focus, Focus::Results) => {
+                    self.copy_results();
+                    Ok(None)
+                }
                 KeyCode::Char('b') | KeyCode::Char('B') if matches!(self.focus, Focus::Results) => {
                     if !self.results.is_empty() {
                         let max_len = if self.max_results > 0 {
@@ -151,7 +541,7 @@ impl QueryPage {
                     Ok(None)
                 }
                 KeyCode::Char('s') if matches!(self.focus, Focus::Query) && key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.execute_query().await?;
+                    self.request_execute();
                     Ok(None)
                 }
                 KeyCode::Enter if matches!(self.focus, Focus::Query) => {
@@ -184,6 +574,7 @@ impl QueryPage {
                 }
                 KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.show_input_overlay = true;
+                    self.input_cursor = LineInput::at_end();
                     Ok(None)
 
                 }
@@ -194,12 +585,76 @@ impl QueryPage {
 }
 
 impl ConnectionListPage {
-    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<ConnectionListAction> {
+    /// `count` is the number of saved connections, the "Create New Connection" item follows them
+    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind, count: usize) -> Option<ConnectionListAction> {
         if kind != KeyEventKind::Press {
             return None;
         }
 
+        if let Some(discovery) = &mut self.discovery {
+            let len = discovery.found.len();
+            match key.code {
+                KeyCode::Esc => self.discovery = None,
+                KeyCode::Up => {
+                    let i = discovery.state.selected().unwrap_or(0);
+                    discovery.state.select((len > 0).then(|| i.saturating_sub(1)));
+                }
+                KeyCode::Down => {
+                    let i = discovery.state.selected().unwrap_or(0);
+                    discovery.state.select((len > 0).then(|| (i + 1).min(len - 1)));
+                }
+                KeyCode::Enter => {
+                    let found = discovery.state.selected().and_then(|i| discovery.found.get(i))?;
+                    return Some(ConnectionListAction::AddDiscovered(Box::new(found.connection.clone())));
+                }
+                KeyCode::Char('r') => return Some(ConnectionListAction::Discover(discovery.source)),
+                _ => {}
+            }
+            return None;
+        }
+
+        if let Some(index) = self.pending_delete {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    self.pending_delete = None;
+                    return Some(ConnectionListAction::DeleteConnection(index));
+                }
+                KeyCode::Char('n') | KeyCode::Esc => self.pending_delete = None,
+                _ => {}
+            }
+            return None;
+        }
+
+        // The numbers in front of the first nine connections connect, shifted they modify.
+        // Without the keyboard protocol Shift+digit arrives as the symbol above it.
+        if let KeyCode::Char(c) = key.code
+            && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            let quick = match c {
+                '1'..='9' => Some((c as usize - '1' as usize, key.modifiers.contains(KeyModifiers::SHIFT))),
+                _ => "!@#$%^&*(".find(c).map(|i| (i, true)),
+            };
+            if let Some((index, modify)) = quick
+                && index < count
+            {
+                self.list_state.select(Some(index));
+                return Some(if modify {
+                    ConnectionListAction::ModifyConnection(index)
+                } else {
+                    ConnectionListAction::SelectConnection(index)
+                });
+            }
+        }
+
         match key.code {
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                let i = self.list_state.selected().unwrap_or(0);
+                (i > 0 && i < count).then(|| ConnectionListAction::MoveConnection(i, i - 1))
+            }
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                let i = self.list_state.selected().unwrap_or(0);
+                (i + 1 < count).then(|| ConnectionListAction::MoveConnection(i, i + 1))
+            }
             KeyCode::Up => {
                 let i = self.list_state.selected().unwrap_or(0);
                 if i > 0 {
@@ -214,9 +669,7 @@ impl ConnectionListPage {
             }
             KeyCode::Enter => {
                 let selected = self.list_state.selected().unwrap_or(0);
-                let connections = ConnectionManager::new().ok()?.load_connections().ok()?;
-                
-                if selected == connections.len() {
+                if selected == count {
                     Some(ConnectionListAction::NewConnection)
                 } else {
                     Some(ConnectionListAction::SelectConnection(selected))
@@ -224,29 +677,51 @@ impl ConnectionListPage {
             }
             KeyCode::Char('d') => {
                 let selected = self.list_state.selected().unwrap_or(0);
-                let connections = ConnectionManager::new().ok()?.load_connections().ok()?;
-                
-                if selected < connections.len() {
-                    Some(ConnectionListAction::DeleteConnection(selected))
-                } else {
-                    None
+                if selected < count {
+                    self.pending_delete = Some(selected);
                 }
+                None
             }
+            KeyCode::Char('u') if self.deleted.is_some() => Some(ConnectionListAction::UndoDelete),
             KeyCode::Char('m') => {
                 let selected = self.list_state.selected().unwrap_or(0);
-                let connections = ConnectionManager::new().ok()?.load_connections().ok()?;
-                
-                if selected < connections.len() {
+                if selected < count {
                     Some(ConnectionListAction::ModifyConnection(selected))
                 } else {
                     None
                 }
             }
+            KeyCode::Char('s') => Some(ConnectionListAction::OpenSettings),
+            KeyCode::Char('c') => Some(ConnectionListAction::Discover(DiscoverySource::Docker)),
+            KeyCode::Char('b') => Some(ConnectionListAction::Discover(DiscoverySource::Dbt)),
             _ => None,
         }
     }
 }
 
+impl LineInput {
+    /// Edits `text` for the cursor movement and deletion keys and plain characters,
+    /// false for keys that aren't for the input
+    pub fn handle_key(&mut self, text: &mut String, key: KeyEvent) -> bool {
+        let word = key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char(c) if !word => self.insert(text, c),
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => self.delete_word(text),
+            KeyCode::Backspace if word => self.delete_word(text),
+            KeyCode::Backspace => self.backspace(text),
+            KeyCode::Delete => self.delete(text),
+            KeyCode::Left if word => self.word_left(text),
+            KeyCode::Right if word => self.word_right(text),
+            KeyCode::Left => self.left(text),
+            KeyCode::Right => self.right(text),
+            KeyCode::Home => self.home(),
+            KeyCode::End => self.end(text),
+            _ => return false,
+        }
+        true
+    }
+}
+
 impl NewConnectionPage {
     pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<NewConnectionAction> {
 
@@ -261,6 +736,7 @@ impl NewConnectionPage {
                 let i = self.field_state.selected().unwrap_or(0);
                 if i > 0 {
                     self.field_state.select(Some(i - 1));
+                    self.input = LineInput::at_end();
                 }
                 None
             }
@@ -268,6 +744,7 @@ impl NewConnectionPage {
                 let i = self.field_state.selected().unwrap_or(0);
                 if i < self.fields.len() - 1 {
                     self.field_state.select(Some(i + 1));
+                    self.input = LineInput::at_end();
                 }
                 None
             }
@@ -275,29 +752,117 @@ impl NewConnectionPage {
                 self.validate_and_save()
             }
             KeyCode::Esc => Some(NewConnectionAction::Cancel),
+            // Legacy terminals send Ctrl+H as ^H, which crossterm reports as this too
+            KeyCode::Char('h') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.toggle_password();
+                None
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Enter
+                if self.selected_field() == Some(&Field::DbType) =>
+            {
+                self.cycle_db_type(key.code != KeyCode::Left);
+                None
+            }
+            KeyCode::Char(' ') if self.selected_field() == Some(&Field::AwsIam) => {
+                self.aws_iam = !self.aws_iam;
+                None
+            }
+            _ => {
+                // The cursor is copied out so the field's text can be borrowed alongside it
+                let mut input = self.input;
+                if let Some(field) = self.selected_field().cloned()
+                    && let Some(text) = self.field_text_mut(&field)
+                {
+                    input.handle_key(text, key);
+                }
+                self.input = input;
+                None
+            }
+        }
+    }
+}
+
+impl SettingsPage {
+    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<SettingsAction> {
+        if kind != KeyEventKind::Press {
+            return None;
+        }
+
+        self.error = None;
+        let selected = self.field_state.selected().unwrap_or(0);
+
+        match key.code {
+            KeyCode::Up => {
+                if selected > 0 {
+                    self.field_state.select(Some(selected - 1));
+                }
+                None
+            }
+            KeyCode::Down => {
+                if selected < self.fields.len() - 1 {
+                    self.field_state.select(Some(selected + 1));
+                }
+                None
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.validate_and_save()
+            }
+            KeyCode::Esc => Some(SettingsAction::Cancel),
+            KeyCode::Left | KeyCode::Right => {
+                let forward = key.code == KeyCode::Right;
+                match self.fields[selected] {
+                    SettingsField::Theme => {
+                        let names = self.theme_names();
+                        let current = names.iter().position(|n| *n == self.theme).unwrap_or(0);
+                        let next = if forward {
+                            (current + 1) % names.len()
+                        } else {
+                            (current + names.len() - 1) % names.len()
+                        };
+                        self.theme = names[next].clone();
+                    }
+                    SettingsField::LocalTime => self.local_time = !self.local_time,
+                    SettingsField::CacheSchema => self.cache_schema = !self.cache_schema,
+                    SettingsField::AsciiOnly => {
+                        self.ascii_only = match (self.ascii_only, forward) {
+                            (None, true) | (Some(true), false) => Some(false),
+                            (Some(false), true) | (None, false) => Some(true),
+                            (Some(true), true) | (Some(false), false) => None,
+                        };
+                    }
+                    _ => {}
+                }
+                None
+            }
             KeyCode::Char(c) => {
-                let selected = self.field_state.selected().unwrap_or(0);
                 match self.fields[selected] {
-                    Field::Name => self.name.push(c),
-                    Field::DbType => self.db_type.push(c),
-                    Field::Host => self.host.push(c),
-                    Field::Port => self.port.push(c),
-                    Field::Database => self.database.push(c),
-                    Field::Username => self.username.push(c),
-                    Field::Password => self.password.push(c),
+                    SettingsField::QueryTimeout => self.query_timeout.push(c),
+                    SettingsField::RetryAttempts => self.retry_attempts.push(c),
+                    SettingsField::Keepalive => self.keepalive.push(c),
+                    SettingsField::LargeResultRows => self.large_result_rows.push(c),
+                    SettingsField::ResultMemory => self.result_memory.push(c),
+                    SettingsField::WatchInterval => self.watch_interval.push(c),
+                    SettingsField::TimestampFormat => self.timestamp_format.push(c),
+                    SettingsField::FloatPrecision => self.float_precision.push(c),
+                    SettingsField::DecimalPlaces => self.decimal_places.push(c),
+                    SettingsField::ThousandsSeparator => self.thousands_separator.push(c),
+                    SettingsField::Theme | SettingsField::LocalTime | SettingsField::CacheSchema | SettingsField::AsciiOnly => {}
                 }
                 None
             }
             KeyCode::Backspace => {
-                let selected = self.field_state.selected().unwrap_or(0);
                 match self.fields[selected] {
-                    Field::Name => { self.name.pop(); },
-                    Field::DbType => { self.db_type.pop(); },
-                    Field::Host => { self.host.pop(); },
-                    Field::Port => { self.port.pop(); },
-                    Field::Database => { self.database.pop(); },
-                    Field::Username => { self.username.pop(); },
-                    Field::Password => { self.password.pop(); },
+                    SettingsField::QueryTimeout => { self.query_timeout.pop(); },
+                    SettingsField::RetryAttempts => { self.retry_attempts.pop(); },
+                    SettingsField::Keepalive => { self.keepalive.pop(); },
+                    SettingsField::LargeResultRows => { self.large_result_rows.pop(); },
+                    SettingsField::ResultMemory => { self.result_memory.pop(); },
+                    SettingsField::WatchInterval => { self.watch_interval.pop(); },
+                    SettingsField::TimestampFormat => { self.timestamp_format.pop(); },
+                    SettingsField::FloatPrecision => { self.float_precision.pop(); },
+                    SettingsField::DecimalPlaces => { self.decimal_places.pop(); },
+                    SettingsField::ThousandsSeparator => { self.thousands_separator.pop(); },
+                    SettingsField::Theme | SettingsField::LocalTime | SettingsField::CacheSchema | SettingsField::AsciiOnly => {}
                 }
                 None
             }
@@ -322,18 +887,21 @@ impl HistoryPage {
                 self.scroll_down(history);
                 None
             }
-            KeyCode::Enter => {
-                if let Some(query) = self.get_selected_query() {
-                    Some(HistoryPageAction::SelectQuery(query))
-                } else {
-                    None
-                }
+            KeyCode::Enter => self.get_selected_query().map(HistoryPageAction::SelectQuery),
+            KeyCode::Char('x') => self.get_selected_query().map(HistoryPageAction::ExecuteQuery),
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.get_selected_query().map(HistoryPageAction::ExecuteQuery)
+            }
+            KeyCode::Char(' ') => {
+                self.toggle_mark();
+                None
             }
             KeyCode::Char('d') => {
-                if let Some(query) = self.get_selected_query() {
-                    Some(HistoryPageAction::DeleteQuery(query))
-                } else {
+                let indices = self.entries_to_delete();
+                if indices.is_empty() {
                     None
+                } else {
+                    Some(HistoryPageAction::DeleteEntries(indices))
                 }
             }
             KeyCode::Char('c') => {
@@ -345,12 +913,257 @@ impl HistoryPage {
         }
     }
 
-    fn get_history_length(&self) -> usize {
-        if let Ok(history_manager) = crate::gui::history::HistoryManager::new() {
-            if let Ok(history) = history_manager.load_history() {
-                return if history.is_empty() { 1 } else { history.len() };
-            }
+    pub(crate) fn get_history_length(&self) -> usize {
+        if let Ok(history_manager) = crate::gui::history::HistoryManager::new()
+            && let Ok(history) = history_manager.load_history()
+        {
+            return if history.is_empty() { 1 } else { history.len() };
         }
         1
     }
-}
\ No newline at end of file
+}
+
+impl SessionsPage {
+    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<SessionsAction> {
+        if kind != KeyEventKind::Press {
+            return None;
+        }
+
+        if let Some((session, kill)) = &self.pending_stop {
+            let action = matches!(key.code, KeyCode::Char('y') | KeyCode::Enter)
+                .then(|| SessionsAction::Stop { id: session.id, kill: *kill });
+            if action.is_some() || matches!(key.code, KeyCode::Char('n') | KeyCode::Esc) {
+                self.pending_stop = None;
+            }
+            return action;
+        }
+
+        self.error = None;
+        match key.code {
+            KeyCode::Up => self.scroll_up(),
+            KeyCode::Down => self.scroll_down(),
+            KeyCode::Char('r') => return Some(SessionsAction::Refresh),
+            KeyCode::Char('c') => self.request_stop(false),
+            KeyCode::Char('k') => self.request_stop(true),
+            KeyCode::Esc => return Some(SessionsAction::Back),
+            _ => {}
+        }
+        None
+    }
+}
+
+impl NotificationsPage {
+    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<NotificationsAction> {
+        if kind != KeyEventKind::Press {
+            return None;
+        }
+
+        if let Some((input, text)) = self.input.as_mut() {
+            let input = *input;
+            match key.code {
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Enter => {
+                    let text = text.clone();
+                    if input == NotificationsInput::Filter {
+                        self.input = None;
+                        return None;
+                    }
+                    let action = self.submit_channels(&text);
+                    if action.is_some() {
+                        self.input = None;
+                    }
+                    return action;
+                }
+                KeyCode::Esc => {
+                    self.input = None;
+                    if input == NotificationsInput::Filter {
+                        self.filter.clear();
+                    }
+                }
+                _ => {}
+            }
+            // The filter applies as it is typed
+            if input == NotificationsInput::Filter
+                && let Some((_, text)) = &self.input
+            {
+                self.filter = text.clone();
+            }
+            self.fix_selection();
+            return None;
+        }
+
+        self.error = None;
+        match key.code {
+            KeyCode::Up => self.scroll_up(),
+            KeyCode::Down => self.scroll_down(),
+            KeyCode::Char('c') => self.input = Some((NotificationsInput::Channels, self.channels.join(", "))),
+            KeyCode::Char('/') => self.input = Some((NotificationsInput::Filter, self.filter.clone())),
+            KeyCode::Char('f') => {
+                self.follow = !self.follow;
+                self.fix_selection();
+            }
+            KeyCode::Char('x') => self.clear(),
+            KeyCode::Esc => return Some(NotificationsAction::Back),
+            _ => {}
+        }
+        None
+    }
+}
+
+impl LocksPage {
+    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<LocksAction> {
+        if kind != KeyEventKind::Press {
+            return None;
+        }
+
+        if let Some((session, kill)) = &self.pending_stop {
+            let action = matches!(key.code, KeyCode::Char('y') | KeyCode::Enter)
+                .then(|| LocksAction::Stop { id: session.id, kill: *kill });
+            if action.is_some() || matches!(key.code, KeyCode::Char('n') | KeyCode::Esc) {
+                self.pending_stop = None;
+            }
+            return action;
+        }
+
+        self.error = None;
+        match key.code {
+            KeyCode::Up => self.scroll_up(),
+            KeyCode::Down => self.scroll_down(),
+            KeyCode::Char('r') => return Some(LocksAction::Refresh),
+            KeyCode::Char('l') => self.live = !self.live,
+            KeyCode::Char('c') => self.request_stop(false),
+            KeyCode::Char('k') => self.request_stop(true),
+            KeyCode::Esc => return Some(LocksAction::Back),
+            _ => {}
+        }
+        None
+    }
+}
+
+impl SizesPage {
+    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<SizesAction> {
+        if kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Up => self.scroll_up(),
+            KeyCode::Down => self.scroll_down(),
+            KeyCode::Char(c @ '1'..='5') => {
+                let index = c as usize - '1' as usize;
+                self.sort_by(SizeSort::ALL[index]);
+            }
+            KeyCode::Tab => {
+                self.toggle_scope();
+                return Some(SizesAction::Refresh);
+            }
+            KeyCode::Char('r') => return Some(SizesAction::Refresh),
+            KeyCode::Esc => return Some(SizesAction::Back),
+            _ => {}
+        }
+        None
+    }
+}
+
+impl ImportPage {
+    pub fn handle_input(&mut self, key: KeyEvent, kind: KeyEventKind) -> Option<ImportAction> {
+        if kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match self.step {
+            ImportStep::PickFile => {
+                let selected = self.list_state.selected().unwrap_or(0);
+                match key.code {
+                    KeyCode::Up => self.list_state.select(Some(selected.saturating_sub(1))),
+                    KeyCode::Down => {
+                        self.list_state.select(Some((selected + 1).min(self.entries.len().saturating_sub(1))))
+                    }
+                    KeyCode::Enter => {
+                        if let Some(entry) = self.entries.get(selected) {
+                            let path = entry.path.clone();
+                            if entry.is_dir {
+                                self.change_dir(path);
+                            } else {
+                                self.pick_file(&path);
+                            }
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(parent) = self.dir.parent() {
+                            self.change_dir(parent.to_path_buf());
+                        }
+                    }
+                    KeyCode::Esc => return Some(ImportAction::Cancel),
+                    _ => {}
+                }
+                None
+            }
+            ImportStep::Map => {
+                self.error = None;
+                let selected = self.field_state.selected().unwrap_or(0);
+                let column = selected.checked_sub(MAPPING_HEADER_ROWS);
+                match key.code {
+                    KeyCode::Up => self.field_state.select(Some(selected.saturating_sub(1))),
+                    KeyCode::Down => self
+                        .field_state
+                        .select(Some((selected + 1).min(self.columns.len() + MAPPING_HEADER_ROWS - 1))),
+                    KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return self.validate().map(ImportAction::Start);
+                    }
+                    KeyCode::Esc => {
+                        self.step = ImportStep::PickFile;
+                    }
+                    KeyCode::Left | KeyCode::Right => {
+                        let forward = key.code == KeyCode::Right;
+                        match column.and_then(|i| self.columns.get_mut(i)) {
+                            Some(column) => {
+                                column.column_type = if forward {
+                                    column.column_type.next()
+                                } else {
+                                    column.column_type.previous()
+                                };
+                            }
+                            None if selected == 1 => self.create = !self.create,
+                            None => {}
+                        }
+                    }
+                    KeyCode::Char(' ') if column.is_some() => {
+                        if let Some(column) = column.and_then(|i| self.columns.get_mut(i)) {
+                            column.include = !column.include;
+                        }
+                    }
+                    KeyCode::Char(c) => match column.and_then(|i| self.columns.get_mut(i)) {
+                        Some(column) => column.name.push(c),
+                        None if selected == 0 => self.table.push(c),
+                        None if selected == 2 => self.extra.push(c),
+                        None => {}
+                    },
+                    KeyCode::Backspace => match column.and_then(|i| self.columns.get_mut(i)) {
+                        Some(column) => {
+                            column.name.pop();
+                        }
+                        None if selected == 0 => {
+                            self.table.pop();
+                        }
+                        None if selected == 2 => {
+                            self.extra.pop();
+                        }
+                        None => {}
+                    },
+                    _ => {}
+                }
+                None
+            }
+            // The import runs in its own transaction and can't be stopped halfway
+            ImportStep::Running => None,
+            ImportStep::Finished => match key.code {
+                KeyCode::Enter | KeyCode::Esc => Some(ImportAction::Done),
+                _ => None,
+            },
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use crate::utils::tunnel::Tunnel;
+
+/// How long kubectl may take to find the pod and open the local port
+const START_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A database inside a cluster, reached through `kubectl port-forward`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KubeTarget {
+    /// What to forward to, e.g. svc/postgres or pod/postgres-0
+    pub resource: String,
+    /// The context's default namespace when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// kubeconfig context, the current one when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+impl KubeTarget {
+    /// Forwards a free local port to `remote_port` of the target, returning once kubectl listens
+    pub async fn port_forward(&self, remote_port: u16) -> Result<Tunnel> {
+        let mut command = Command::new("kubectl");
+        command.arg("port-forward");
+        if let Some(context) = &self.context {
+            command.args(["--context", context]);
+        }
+        if let Some(namespace) = &self.namespace {
+            command.args(["--namespace", namespace]);
+        }
+        command
+            .args(["--address", "127.0.0.1", &self.resource, &format!(":{}", remote_port)])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = command.spawn().context("Could not run kubectl, is it installed?")?;
+        let (Some(stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            bail!("Could not read kubectl output");
+        };
+        let mut stdout = BufReader::new(stdout).lines();
+
+        let listening = timeout(START_TIMEOUT, async {
+            while let Some(line) = stdout.next_line().await? {
+                if let Some(port) = forwarded_port(&line) {
+                    return Ok(Some(port));
+                }
+            }
+            Ok::<_, std::io::Error>(None)
+        })
+        .await;
+        let local_port = match listening {
+            Ok(Ok(Some(port))) => port,
+            Ok(Ok(None)) => {
+                // kubectl quit, its reason is on stderr
+                let mut message = String::new();
+                let _ = stderr.read_to_string(&mut message).await;
+                bail!("kubectl port-forward to {} failed: {}", self.resource, message.trim());
+            }
+            Ok(Err(e)) => return Err(e).context("Could not read kubectl output"),
+            Err(_) => bail!(
+                "kubectl port-forward to {} didn't start within {}s",
+                self.resource,
+                START_TIMEOUT.as_secs()
+            ),
+        };
+
+        // kubectl logs every connection it forwards, a full pipe would stall it
+        tokio::spawn(async move { while let Ok(Some(_)) = stdout.next_line().await {} });
+        tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await;
+        });
+
+        Ok(Tunnel::new(child, local_port))
+    }
+}
+
+/// The local port of a "Forwarding from 127.0.0.1:41234 -> 5432" line
+fn forwarded_port(line: &str) -> Option<u16> {
+    let local = line.strip_prefix("Forwarding from ")?.split(" -> ").next()?;
+    local.rsplit(':').next()?.parse().ok()
+}
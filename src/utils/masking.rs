@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::utils::generate::fake_value;
+use crate::utils::value::{Value, ValueFormat};
+
+/// How a masked column is hidden
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaskMethod {
+    /// A short digest, equal values still match each other
+    Hash,
+    Redact,
+    /// A made-up value in the style of the column, the same for the same original
+    Fake,
+}
+
+impl MaskMethod {
+    pub fn label(self) -> &'static str {
+        match self {
+            MaskMethod::Hash => "hash",
+            MaskMethod::Redact => "redact",
+            MaskMethod::Fake => "fake",
+        }
+    }
+
+    /// The next method when cycling through them, None leaves the column as it is
+    pub fn cycle(method: Option<MaskMethod>, forward: bool) -> Option<MaskMethod> {
+        const ORDER: [Option<MaskMethod>; 4] = [None, Some(MaskMethod::Hash), Some(MaskMethod::Redact), Some(MaskMethod::Fake)];
+        let index = ORDER.iter().position(|m| *m == method).unwrap_or(0);
+        let next = if forward { index + 1 } else { index + ORDER.len() - 1 };
+        ORDER[next % ORDER.len()]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaskRule {
+    /// Matched against result headers, ignoring case
+    pub column: String,
+    pub method: MaskMethod,
+}
+
+/// Columns masked whenever results leave rsquid: copied with Y or written by `rsquid exec`.
+/// Set under [masking] in the config, or with M on the results.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Masking {
+    /// Mixed into hashes and fakes, so a masked value can't be found by hashing likely originals
+    pub salt: String,
+    pub rules: Vec<MaskRule>,
+}
+
+impl Masking {
+    pub fn method(&self, column: &str) -> Option<MaskMethod> {
+        self.rules.iter().find(|rule| rule.column.eq_ignore_ascii_case(column)).map(|rule| rule.method)
+    }
+
+    pub fn set(&mut self, column: &str, method: Option<MaskMethod>) {
+        self.rules.retain(|rule| !rule.column.eq_ignore_ascii_case(column));
+        if let Some(method) = method {
+            self.rules.push(MaskRule { column: column.to_string(), method });
+        }
+    }
+
+    /// The method for each of `headers`, None when nothing among them is masked
+    pub fn methods(&self, headers: &[String]) -> Option<Vec<Option<MaskMethod>>> {
+        let methods: Vec<_> = headers.iter().map(|header| self.method(header)).collect();
+        methods.iter().any(Option::is_some).then_some(methods)
+    }
+
+    pub fn mask_row<'a>(
+        &self,
+        headers: &[String],
+        methods: &[Option<MaskMethod>],
+        row: impl IntoIterator<Item = &'a Value>,
+    ) -> Vec<Value> {
+        row.into_iter()
+            .zip(headers.iter().zip(methods))
+            .map(|(value, (header, method))| match method {
+                Some(method) => self.mask(*method, header, value),
+                None => value.clone(),
+            })
+            .collect()
+    }
+
+    /// Masks owned rows in place, for results that aren't kept afterwards
+    pub fn mask_rows(&self, headers: &[String], rows: &mut [Vec<Value>]) {
+        let Some(methods) = self.methods(headers) else {
+            return;
+        };
+        for row in rows {
+            *row = self.mask_row(headers, &methods, row.iter());
+        }
+    }
+
+    fn mask(&self, method: MaskMethod, column: &str, value: &Value) -> Value {
+        // There is nothing to hide in a NULL, or in a value that never decoded
+        if matches!(value, Value::Null | Value::Unsupported(_) | Value::Invalid) {
+            return value.clone();
+        }
+        match method {
+            MaskMethod::Redact => Value::Text("REDACTED".to_string()),
+            MaskMethod::Hash => {
+                let digest = self.digest(value);
+                Value::Text(digest[..8].iter().map(|b| format!("{:02x}", b)).collect())
+            }
+            MaskMethod::Fake => {
+                let digest = self.digest(value);
+                let seed = u64::from_le_bytes(digest[..8].try_into().unwrap_or_default());
+                fake_value(column, value, seed)
+            }
+        }
+    }
+
+    fn digest(&self, value: &Value) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        match value {
+            Value::Bytes(bytes) => hasher.update(bytes),
+            Value::Text(text) => hasher.update(text.as_bytes()),
+            other => hasher.update(other.format(&ValueFormat::default()).as_bytes()),
+        }
+        hasher.finalize().into()
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+
+use crate::utils::http::{read_request, write_response};
+use crate::utils::sql::Dialect;
+
+/// Upper bounds of the query duration histogram, in seconds
+const DURATION_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0];
+
+const DIALECTS: [(Dialect, &str); 3] = [(Dialect::Postgres, "postgres"), (Dialect::MySql, "mysql"), (Dialect::Sqlite, "sqlite")];
+
+/// Counters for one database kind since startup
+struct Counters {
+    queries: AtomicU64,
+    errors: AtomicU64,
+    /// Queries per histogram bucket, each counted in the first bucket it fits
+    buckets: [AtomicU64; DURATION_BUCKETS.len()],
+    duration_micros: AtomicU64,
+    open_pools: AtomicI64,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            queries: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            buckets: [const { AtomicU64::new(0) }; DURATION_BUCKETS.len()],
+            duration_micros: AtomicU64::new(0),
+            open_pools: AtomicI64::new(0),
+        }
+    }
+}
+
+static COUNTERS: [Counters; DIALECTS.len()] = [const { Counters::new() }; DIALECTS.len()];
+
+fn counters(dialect: Dialect) -> &'static Counters {
+    let index = DIALECTS.iter().position(|(d, _)| *d == dialect).unwrap_or(0);
+    &COUNTERS[index]
+}
+
+/// Runs a statement, counting it, its duration and whether it failed
+pub async fn measure<T>(dialect: Dialect, run: impl Future<Output = Result<T>>) -> Result<T> {
+    let started = Instant::now();
+    let result = run.await;
+    record_query(dialect, started.elapsed(), result.is_ok());
+    result
+}
+
+fn record_query(dialect: Dialect, elapsed: Duration, ok: bool) {
+    let counters = counters(dialect);
+    counters.queries.fetch_add(1, Ordering::Relaxed);
+    if !ok {
+        counters.errors.fetch_add(1, Ordering::Relaxed);
+    }
+    counters.duration_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    if let Some(bucket) = DURATION_BUCKETS.iter().position(|le| elapsed.as_secs_f64() <= *le) {
+        counters.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub fn pool_opened(dialect: Dialect) {
+    counters(dialect).open_pools.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn pool_closed(dialect: Dialect) {
+    counters(dialect).open_pools.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Everything counted so far in the Prometheus text format
+pub fn render() -> String {
+    let mut out = String::new();
+    let each = |out: &mut String, name: &str, kind: &str, help: &str, value: &dyn Fn(&Counters) -> String| {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        for ((_, label), counters) in DIALECTS.iter().zip(&COUNTERS) {
+            let _ = writeln!(out, "{}{{dialect=\"{}\"}} {}", name, label, value(counters));
+        }
+    };
+    each(&mut out, "rsquid_queries_total", "counter", "Statements run", &|c| c.queries.load(Ordering::Relaxed).to_string());
+    each(&mut out, "rsquid_query_errors_total", "counter", "Statements that failed", &|c| {
+        c.errors.load(Ordering::Relaxed).to_string()
+    });
+    each(&mut out, "rsquid_open_pools", "gauge", "Connection pools open", &|c| c.open_pools.load(Ordering::Relaxed).to_string());
+
+    let name = "rsquid_query_duration_seconds";
+    let _ = writeln!(out, "# HELP {} Time until a statement's first rows or its result\n# TYPE {} histogram", name, name);
+    for ((_, label), counters) in DIALECTS.iter().zip(&COUNTERS) {
+        let mut cumulative = 0;
+        for (le, bucket) in DURATION_BUCKETS.iter().zip(&counters.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{dialect=\"{}\",le=\"{}\"}} {}", name, label, le, cumulative);
+        }
+        let count = counters.queries.load(Ordering::Relaxed);
+        let sum = counters.duration_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_bucket{{dialect=\"{}\",le=\"+Inf\"}} {}", name, label, count);
+        let _ = writeln!(out, "{}_sum{{dialect=\"{}\"}} {}", name, label, sum);
+        let _ = writeln!(out, "{}_count{{dialect=\"{}\"}} {}", name, label, count);
+    }
+    out
+}
+
+/// Binds first, so a taken address is reported before the UI starts
+pub async fn bind(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Could not listen for metrics on {}", addr))
+}
+
+/// Answers GET /metrics on `listener` until the process ends
+pub async fn serve(listener: TcpListener) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(async move {
+            let mut stream = BufReader::new(stream);
+            let Ok(request) = read_request(&mut stream).await else {
+                return;
+            };
+            let _ = match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/metrics") => write_response(&mut stream, 200, "text/plain; version=0.0.4", render().as_bytes()).await,
+                (_, "/metrics") => write_response(&mut stream, 405, "text/plain", b"Only GET is supported\n").await,
+                _ => write_response(&mut stream, 404, "text/plain", b"Metrics are at /metrics\n").await,
+            };
+        });
+    }
+}
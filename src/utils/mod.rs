@@ -1,6 +1,40 @@
+pub mod admin;
+pub mod clipboard;
+pub mod cloud_sql;
+pub mod columns;
+pub mod config;
+pub mod dbt;
+pub mod desktop;
+pub mod docker;
+pub mod dump;
 pub mod connection;
+pub mod errors;
+pub mod export;
+pub mod generate;
+pub mod geometry;
+pub mod http;
+pub mod import;
+pub mod kube;
+pub mod masking;
 pub mod query_executor;
+pub mod schema_cache;
+pub mod scripting;
+pub mod secrets;
 pub mod keyboard;
+pub mod metrics;
+pub mod mouse;
 pub mod mysql;
+pub mod notices;
+pub mod parquet;
+pub mod pipeline;
+pub mod pool_manager;
 pub mod postgres;
-pub mod sqlite;
\ No newline at end of file
+pub mod sqlite;
+pub mod ssh;
+pub mod sql;
+pub mod state;
+pub mod swap;
+pub mod text;
+pub mod tunnel;
+pub mod value;
+pub mod xlsx;
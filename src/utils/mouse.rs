@@ -0,0 +1,221 @@
+use anyhow::Result;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{layout::{Position, Rect}, widgets::{ListState, TableState}};
+
+use crate::gui::{ConnectionListPage, Focus, HistoryPage, ImportPage, ImportStep, LineInput, MAPPING_HEADER_ROWS, LocksPage, NewConnectionPage, NotificationsPage, QueryPage, SessionsPage, SettingsPage, SizesPage};
+
+fn contains(area: Rect, event: &MouseEvent) -> bool {
+    area.contains(Position::new(event.column, event.row))
+}
+
+/// Item under the pointer in a bordered list, `header_rows` are lines drawn
+/// above the first item inside the border
+fn item_at(area: Rect, offset: usize, header_rows: u16, event: &MouseEvent) -> Option<usize> {
+    let first = area.y + 1 + header_rows;
+    if !contains(area, event) || event.row < first || event.row + 1 >= area.bottom() {
+        return None;
+    }
+    Some(offset + (event.row - first) as usize)
+}
+
+/// Clicks select the item under the pointer, the wheel moves the selection
+fn select_in_list(state: &mut ListState, area: Rect, len: usize, event: &MouseEvent) {
+    if len == 0 {
+        return;
+    }
+    match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(index) = item_at(area, state.offset(), 0, event)
+                && index < len
+            {
+                state.select(Some(index));
+            }
+        }
+        MouseEventKind::ScrollUp if contains(area, event) => {
+            state.select(Some(state.selected().unwrap_or(0).saturating_sub(1)));
+        }
+        MouseEventKind::ScrollDown if contains(area, event) => {
+            state.select(Some((state.selected().unwrap_or(0) + 1).min(len - 1)));
+        }
+        _ => {}
+    }
+}
+
+/// Like `select_in_list` for a bordered table with a header row and its bottom margin
+fn select_in_table(state: &mut TableState, area: Rect, len: usize, event: &MouseEvent) {
+    if len == 0 {
+        return;
+    }
+    match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(index) = item_at(area, state.offset(), 2, event)
+                && index < len
+            {
+                state.select(Some(index));
+            }
+        }
+        MouseEventKind::ScrollUp if contains(area, event) => {
+            state.select(Some(state.selected().unwrap_or(0).saturating_sub(1)));
+        }
+        MouseEventKind::ScrollDown if contains(area, event) => {
+            state.select(Some((state.selected().unwrap_or(0) + 1).min(len - 1)));
+        }
+        _ => {}
+    }
+}
+
+impl QueryPage {
+    /// Whether the event is over one of this page's panes
+    pub fn contains(&self, event: &MouseEvent) -> bool {
+        [self.explorer_area, self.query_area, self.results_area]
+            .into_iter()
+            .any(|area| contains(area, event))
+    }
+
+    pub async fn handle_mouse(&mut self, event: MouseEvent) -> Result<()> {
+        if self.show_server_info && matches!(event.kind, MouseEventKind::Down(_)) {
+            self.show_server_info = false;
+            return Ok(());
+        }
+        if let Some(scroll) = self.row_detail.as_mut() {
+            match event.kind {
+                MouseEventKind::ScrollUp => *scroll = scroll.saturating_sub(3),
+                MouseEventKind::ScrollDown => *scroll = scroll.saturating_add(3),
+                _ => {}
+            }
+            return Ok(());
+        }
+        // Overlays take the keyboard, clicks behind them shouldn't change anything
+        if self.show_input_overlay || self.show_params_overlay || self.show_error_detail || self.pending_confirmation.is_some() || self.pending_dump.is_some() || self.pending_generate.is_some() || self.palette.is_some() || self.pending_pipeline.is_some() || self.pending_schedule.is_some() || self.pending_masking.is_some() {
+            return Ok(());
+        }
+
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if contains(self.explorer_area, &event) {
+                    self.focus = Focus::Explorer;
+                    if let Some(index) = item_at(self.explorer_area, self.explorer_state.offset(), 0, &event)
+                        && index < self.explorer_len()
+                    {
+                        self.explorer_state.select(Some(index));
+                    }
+                } else if contains(self.query_area, &event) {
+                    self.focus = Focus::Query;
+                } else if contains(self.results_area, &event) {
+                    self.focus = Focus::Results;
+                    // The header row and its bottom margin sit above the first row
+                    if let Some(index) = item_at(self.results_area, self.table_state.offset(), 2, &event)
+                        && index < self.visible_rows()
+                    {
+                        self.table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollUp => {
+                if contains(self.explorer_area, &event) {
+                    self.explorer_scroll_up();
+                } else if contains(self.query_area, &event) {
+                    self.query_scroll = self.query_scroll.saturating_sub(1);
+                } else if contains(self.results_area, &event) {
+                    self.scroll_up();
+                }
+            }
+            MouseEventKind::ScrollDown => {
+                if contains(self.explorer_area, &event) {
+                    self.explorer_scroll_down();
+                } else if contains(self.query_area, &event) {
+                    let lines = self.query.lines().count() as u16;
+                    self.query_scroll = (self.query_scroll + 1).min(lines.saturating_sub(1));
+                } else if contains(self.results_area, &event) {
+                    self.scroll_down();
+                    self.load_more_if_needed().await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl ConnectionListPage {
+    /// `len` counts the trailing "Create New Connection" item
+    pub fn handle_mouse(&mut self, event: MouseEvent, len: usize) {
+        if self.discovery.is_some() || self.pending_delete.is_some() {
+            return;
+        }
+        select_in_list(&mut self.list_state, self.list_area, len, &event);
+    }
+}
+
+impl NewConnectionPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        let before = self.field_state.selected();
+        select_in_list(&mut self.field_state, self.list_area, self.fields.len(), &event);
+        if self.field_state.selected() != before {
+            self.input = LineInput::at_end();
+        }
+    }
+}
+
+impl SettingsPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        select_in_list(&mut self.field_state, self.list_area, self.fields.len(), &event);
+    }
+}
+
+impl HistoryPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        let len = self.get_history_length();
+        select_in_list(&mut self.list_state, self.list_area, len, &event);
+    }
+}
+
+impl ImportPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        match self.step {
+            ImportStep::PickFile => select_in_list(&mut self.list_state, self.list_area, self.entries.len(), &event),
+            ImportStep::Map => select_in_list(
+                &mut self.field_state,
+                self.list_area,
+                self.columns.len() + MAPPING_HEADER_ROWS,
+                &event,
+            ),
+            ImportStep::Running | ImportStep::Finished => {}
+        }
+    }
+}
+
+impl SessionsPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        if self.pending_stop.is_some() {
+            return;
+        }
+        select_in_table(&mut self.table_state, self.table_area, self.sessions.len(), &event);
+    }
+}
+
+impl LocksPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        if self.pending_stop.is_some() {
+            return;
+        }
+        select_in_table(&mut self.table_state, self.table_area, self.tree.len(), &event);
+    }
+}
+
+impl NotificationsPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        if self.input.is_some() {
+            return;
+        }
+        let count = self.visible().len();
+        select_in_table(&mut self.table_state, self.table_area, count, &event);
+        self.follow = self.table_state.selected().is_some_and(|selected| selected + 1 >= count);
+    }
+}
+
+impl SizesPage {
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        select_in_table(&mut self.table_state, self.table_area, self.entries.len(), &event);
+    }
+}
@@ -1,119 +1,262 @@
-use crate::utils::query_executor::QueryExecutor;
+use crate::utils::query_executor::{Pooled, QueryExecutor, RowBatch, RowStream, StatementOutput, send_batches};
+use crate::utils::notices;
+use crate::utils::value::Value;
+use crate::utils::sql::strip_leading_comments;
+use futures_util::TryStreamExt;
+use tokio::sync::mpsc;
 use anyhow::{Result};
-use sqlx::mysql::{MySqlColumn, MySqlPool, MySqlRow};
-use sqlx::{Column, Row, TypeInfo, ValueRef};
+use sqlx::mysql::{MySqlArguments, MySqlColumn, MySqlConnection, MySqlRow};
+use sqlx::query::Query;
+use sqlx::{Column, Either, MySql, Row, TypeInfo, ValueRef};
 use bigdecimal::BigDecimal;
 
 impl QueryExecutor {
-    pub async fn execute_mysql(
+    pub async fn execute_mysql<'e, E>(
         &self,
-        pool: &MySqlPool,
-        query: &str,
+        executor: E,
+        query: &'e str,
+        params: &'e [Value],
         is_query: bool,
-    ) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    ) -> Result<StatementOutput>
+    where
+        E: sqlx::Executor<'e, Database = MySql>,
+    {
         // MySQL `EXPLAIN` and `DESCRIBE` act like queries
+        let statement = strip_leading_comments(query).to_lowercase();
         let actual_is_query = is_query
-            || query.to_lowercase().starts_with("describe")
-            || query.to_lowercase().starts_with("explain");
-
-        if !actual_is_query {
-            let result = sqlx::query(query).execute(pool).await?;
-            return Ok((
-                vec!["Result".to_string()],
-                vec![vec![format!("{} row(s) affected", result.rows_affected())]],
-            ));
+            || statement.starts_with("describe")
+            || statement.starts_with("explain");
+
+        // A stored procedure `CALL` can return several result sets, each one ends
+        // with its own OK packet, so rows are grouped at every query result
+        let mut stream = executor.fetch_many(Self::bind_mysql(sqlx::query(query), params));
+        let mut result_sets = Vec::new();
+        let mut current: RowBatch = (Vec::new(), Vec::new());
+        let mut rows_affected = 0;
+
+        while let Some(item) = stream.try_next().await? {
+            match item {
+                Either::Left(result) => {
+                    rows_affected += result.rows_affected();
+                    if !current.1.is_empty() {
+                        result_sets.push(std::mem::take(&mut current));
+                    }
+                }
+                Either::Right(row) => {
+                    if current.0.is_empty() {
+                        current.0 = row.columns().iter().map(|c| c.name().to_string()).collect();
+                    }
+                    let row_data = row
+                        .columns()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, col)| Self::mysql_to_value(&row, i, col))
+                        .collect();
+                    current.1.push(row_data);
+                }
+            }
+        }
+        if !current.1.is_empty() {
+            result_sets.push(current);
         }
 
-        let rows = sqlx::query(query).fetch_all(pool).await?;
-        if rows.is_empty() {
-            return Ok((Vec::new(), Vec::new()));
+        if result_sets.is_empty() && !actual_is_query {
+            return Ok(StatementOutput::Affected(rows_affected));
         }
 
-        let headers: Vec<String> = rows[0]
-            .columns()
-            .iter()
-            .map(|c| c.name().to_string())
-            .collect();
-        let mut result_rows = Vec::new();
+        Ok(StatementOutput::Sets(result_sets))
+    }
+
+    /// Runs a statement and reports its warnings as notices. `SHOW WARNINGS` only
+    /// describes the previous statement of the same session, so both share `conn`.
+    pub async fn execute_mysql_with_warnings(
+        &self,
+        conn: &mut MySqlConnection,
+        query: &str,
+        params: &[Value],
+        is_query: bool,
+    ) -> Result<StatementOutput> {
+        let output = self.execute_mysql(&mut *conn, query, params, is_query).await?;
 
-        for row in rows {
-            let mut row_data = Vec::new();
-            for (i, col) in row.columns().iter().enumerate() {
-                row_data.push(self.mysql_value_to_string(&row, i, col));
+        if let Ok(rows) = sqlx::query("SHOW WARNINGS").fetch_all(&mut *conn).await {
+            for row in rows {
+                let fields: Vec<String> = row
+                    .columns()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, col)| Self::mysql_to_value(&row, i, col).to_string())
+                    .collect();
+                if let [level, code, message] = fields.as_slice() {
+                    notices::push(format!("{} {}: {}", level.to_uppercase(), code, message));
+                }
             }
-            result_rows.push(row_data);
         }
 
-        Ok((headers, result_rows))
+        Ok(output)
+    }
+
+    fn bind_mysql<'q>(
+        mut query: Query<'q, MySql, MySqlArguments>,
+        params: &'q [Value],
+    ) -> Query<'q, MySql, MySqlArguments> {
+        for param in params {
+            query = match param {
+                Value::Null => query.bind(None::<String>),
+                Value::Bool(v) => query.bind(*v),
+                Value::Int(v) => query.bind(*v),
+                Value::Float(v) => query.bind(*v),
+                other => query.bind(other.to_string()),
+            };
+        }
+        query
     }
 
-    fn mysql_value_to_string(&self, row: &MySqlRow, index: usize, col: &MySqlColumn) -> String {
+    /// Streams the rows on `conn`, which goes back to the pool once they ran out
+    pub(crate) fn stream_mysql(&self, mut conn: Pooled<MySql>, query: &str) -> RowStream {
+        let query = query.to_string();
+        let (sender, receiver) = mpsc::channel(1);
+
+        let handle = tokio::spawn(async move {
+            let rows = sqlx::query(&query).fetch(&mut *conn);
+            send_batches(rows, sender, |row: &MySqlRow| {
+                row.columns().iter().enumerate().map(|(i, col)| Self::mysql_to_value(row, i, col)).collect()
+            })
+            .await;
+            conn.release().await;
+        });
+
+        RowStream::new(receiver, handle)
+    }
+
+    fn mysql_to_value(row: &MySqlRow, index: usize, col: &MySqlColumn) -> Value {
         if row.try_get_raw(index).map_or(true, |v| v.is_null()) {
-            return "NULL".to_string();
+            return Value::Null;
         }
 
         let type_name = col.type_info().name();
 
         match type_name {
-            "BOOLEAN" => row
-                .try_get::<bool, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
-
-            "TINYINT" | "SMALLINT" | "INT" | "BIGINT" => row
-                .try_get::<i64, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
-
-            "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "INT UNSIGNED" | "BIGINT UNSIGNED" => row
-                .try_get::<u64, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
-
-            "FLOAT" | "DOUBLE" => row
-                .try_get::<f64, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "BOOLEAN" => Value::from_decode(row.try_get::<bool, _>(index), Value::Bool),
+
+            // Each width is decoded with its own Rust type, like the column would be in a query_as
+            "TINYINT" => Value::from_decode(row.try_get::<i8, _>(index), |v| Value::Int(v.into())),
+            "SMALLINT" => Value::from_decode(row.try_get::<i16, _>(index), |v| Value::Int(v.into())),
+            "MEDIUMINT" | "INT" => Value::from_decode(row.try_get::<i32, _>(index), |v| Value::Int(v.into())),
+            "BIGINT" => Value::from_decode(row.try_get::<i64, _>(index), Value::Int),
+
+            "TINYINT UNSIGNED" => Value::from_decode(row.try_get::<u8, _>(index), |v| Value::UInt(v.into())),
+            "SMALLINT UNSIGNED" => Value::from_decode(row.try_get::<u16, _>(index), |v| Value::UInt(v.into())),
+            "MEDIUMINT UNSIGNED" | "INT UNSIGNED" => {
+                Value::from_decode(row.try_get::<u32, _>(index), |v| Value::UInt(v.into()))
+            }
+            "BIGINT UNSIGNED" => Value::from_decode(row.try_get::<u64, _>(index), Value::UInt),
+
+            "YEAR" => Value::from_decode(row.try_get::<u16, _>(index), |v| Value::Int(v.into())),
+
+            "FLOAT" | "DOUBLE" => Value::from_decode(row.try_get::<f64, _>(index), Value::Float),
                 
-            "DECIMAL" | "NEWDECIMAL" => row
-                .try_get::<BigDecimal, _>(index)
-                .map(|v| v.to_string())        
-                .unwrap_or_else(|_| "err".to_string()),
-
-            "DATETIME" | "TIMESTAMP" => row
-                .try_get::<chrono::NaiveDateTime, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
-
-            "DATE" => row
-                .try_get::<chrono::NaiveDate, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
-
-            "JSON" => row
-                .try_get::<serde_json::Value, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "DECIMAL" | "NEWDECIMAL" => Value::from_decode(row.try_get::<BigDecimal, _>(index), Value::Decimal),
+
+            "DATETIME" | "TIMESTAMP" => Value::from_decode(row.try_get::<chrono::NaiveDateTime, _>(index), Value::Timestamp),
+
+            "DATE" => Value::from_decode(row.try_get::<chrono::NaiveDate, _>(index), Value::Date),
+
+            "JSON" => Value::from_decode(row.try_get::<serde_json::Value, _>(index), Value::Json),
 
             "VARCHAR" | "CHAR" | "TEXT" | "VAR_STRING" | "BLOB" | "BINARY" => {
                 if let Ok(s) = row.try_get::<String, _>(index) {
-                    return s;
+                    return Value::Text(s);
                 }
                 if let Ok(bytes) = row.try_get::<Vec<u8>, _>(index) {
-                    return String::from_utf8_lossy(&bytes).to_string();
+                    return Value::Bytes(bytes);
                 }
-                format!("<{}>", type_name)
+                Value::Unsupported(type_name.to_string())
             }
 
             _ => {
                 if let Ok(s) = row.try_get::<String, _>(index) {
-                    s
+                    Value::Text(s)
                 } else if let Ok(bytes) = row.try_get::<Vec<u8>, _>(index) {
-                    String::from_utf8_lossy(&bytes).to_string()
+                    Value::Bytes(bytes)
                 } else {
-                    format!("<{}>", type_name)
+                    Value::Unsupported(type_name.to_string())
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::{Connection, Executor};
+    use std::str::FromStr;
+
+    /// Runs against the server in RSQUID_TEST_MYSQL_URL, skipped when it isn't set
+    #[tokio::test]
+    async fn decodes_every_supported_type() {
+        let Ok(url) = std::env::var("RSQUID_TEST_MYSQL_URL") else {
+            eprintln!("RSQUID_TEST_MYSQL_URL not set, skipping");
+            return;
+        };
+        let mut conn = MySqlConnection::connect(&url).await.expect("connect to RSQUID_TEST_MYSQL_URL");
+        conn.execute(
+            "CREATE TEMPORARY TABLE rsquid_types (
+                b BOOLEAN, ti TINYINT, si SMALLINT, mi MEDIUMINT, i INT, bi BIGINT,
+                tiu TINYINT UNSIGNED, siu SMALLINT UNSIGNED, miu MEDIUMINT UNSIGNED, iu INT UNSIGNED, biu BIGINT UNSIGNED,
+                y YEAR, f FLOAT, d DOUBLE, dec_col DECIMAL(30, 10), dt DATETIME, ts TIMESTAMP, da DATE, j JSON,
+                vc VARCHAR(10), c CHAR(3), tx TEXT, bl BLOB, bin BINARY(2), n INT
+            )",
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "INSERT INTO rsquid_types VALUES (
+                TRUE, -128, -32768, -8388608, -2147483648, -9223372036854775808,
+                255, 65535, 16777215, 4294967295, 18446744073709551615,
+                2024, 1.5, -2.25, 12345678901234567890.0123456789, '2024-02-29 13:14:15', '2024-02-29 13:14:15', '1999-12-31',
+                '{\"a\": [1, null]}', 'varchar', 'abc', 'text', x'00ff41', x'ff00', NULL
+            )",
+        )
+        .await
+        .unwrap();
+
+        let row = sqlx::query("SELECT * FROM rsquid_types").fetch_one(&mut conn).await.unwrap();
+        let values: Vec<Value> = row
+            .columns()
+            .iter()
+            .enumerate()
+            .map(|(i, col)| QueryExecutor::mysql_to_value(&row, i, col))
+            .collect();
+        let timestamp = || Value::Timestamp("2024-02-29T13:14:15".parse().unwrap());
+        assert_eq!(
+            values,
+            [
+                Value::Bool(true),
+                Value::Int(-128),
+                Value::Int(-32768),
+                Value::Int(-8388608),
+                Value::Int(-2147483648),
+                Value::Int(i64::MIN),
+                Value::UInt(255),
+                Value::UInt(65535),
+                Value::UInt(16777215),
+                Value::UInt(4294967295),
+                Value::UInt(u64::MAX),
+                Value::Int(2024),
+                Value::Float(1.5),
+                Value::Float(-2.25),
+                Value::Decimal(BigDecimal::from_str("12345678901234567890.0123456789").unwrap()),
+                timestamp(),
+                timestamp(),
+                Value::Date("1999-12-31".parse().unwrap()),
+                Value::Json(serde_json::json!({"a": [1, null]})),
+                Value::Text("varchar".to_string()),
+                Value::Text("abc".to_string()),
+                Value::Text("text".to_string()),
+                Value::Bytes(vec![0, 255, 65]),
+                Value::Bytes(vec![255, 0]),
+                Value::Null,
+            ]
+        );
+    }
+}
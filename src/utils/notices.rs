@@ -0,0 +1,105 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+// sqlx doesn't expose Postgres NOTICE messages, it only logs them under this target
+const PG_NOTICE_TARGET: &str = "sqlx::postgres::notice";
+
+/// Notices and warnings of one execution, shared with the tasks it starts
+#[derive(Clone, Default)]
+struct Sink(Arc<Mutex<Vec<String>>>);
+
+tokio::task_local! {
+    /// The sink of the execution running on this task, see `collect`
+    static SINK: Sink;
+}
+
+struct NoticeLogger;
+
+impl Log for NoticeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == PG_NOTICE_TARGET
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let severity = match record.level() {
+            Level::Error => "ERROR",
+            Level::Warn => "WARNING",
+            Level::Info => "NOTICE",
+            Level::Debug => "DEBUG",
+            Level::Trace => "INFO",
+        };
+        // sqlx logs while the connection is polled, so on the task of the query that reads it
+        push(format!("{}: {}", severity, record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Routes Postgres notices into the sink of their query, must be called once at startup
+pub fn install() {
+    if log::set_logger(&NoticeLogger).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
+
+/// Runs `future` with a sink of its own and returns its output with the notices raised
+/// meanwhile. Notices raised outside of any `collect`, by keepalive pings or explorer
+/// lookups, are dropped.
+pub async fn collect<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    let sink = Sink::default();
+    let output = SINK.scope(sink.clone(), future).await;
+    let notices = sink.0.lock().map(|mut notices| std::mem::take(&mut *notices)).unwrap_or_default();
+    (output, notices)
+}
+
+/// `future` reporting into the sink of the current execution, for tasks it spawns
+pub fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let sink = SINK.try_with(Sink::clone).unwrap_or_default();
+    SINK.scope(sink, future)
+}
+
+pub fn push(notice: String) {
+    let _ = SINK.try_with(|sink| {
+        if let Ok(mut notices) = sink.0.lock() {
+            notices.push(notice);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_notices_with_their_execution() {
+        push("from a keepalive ping".to_string());
+        let ((), notices) = collect(async {
+            push("NOTICE: from the query".to_string());
+            tokio::spawn(inherit(async { push("NOTICE: from its stream".to_string()) })).await.unwrap();
+            tokio::spawn(async { push("from a lookup it started".to_string()) }).await.unwrap();
+        })
+        .await;
+        assert_eq!(notices, ["NOTICE: from the query", "NOTICE: from its stream"]);
+    }
+
+    /// The two panes of the split view run their queries at the same time
+    #[tokio::test]
+    async fn concurrent_executions_keep_apart() {
+        let pane = |name: &'static str| {
+            tokio::spawn(collect(async move {
+                for i in 0..3 {
+                    push(format!("{} {}", name, i));
+                    tokio::task::yield_now().await;
+                }
+            }))
+        };
+        let (main, split) = (pane("main"), pane("split"));
+        assert_eq!(main.await.unwrap().1, ["main 0", "main 1", "main 2"]);
+        assert_eq!(split.await.unwrap().1, ["split 0", "split 1", "split 2"]);
+    }
+}
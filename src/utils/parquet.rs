@@ -0,0 +1,320 @@
+use anyhow::{Result, anyhow, bail};
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float64Array, Int64Array, RecordBatch,
+    StringArray, TimestampMicrosecondArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::Datelike;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::utils::value::Value;
+
+/// Rows per row group, a reader loads one row group at a time
+const ROW_GROUP_ROWS: usize = 64 * 1024;
+/// Most digits a Decimal128 column holds
+const MAX_DECIMAL_DIGITS: u8 = 38;
+/// Days from 0001-01-01 to 1970-01-01, Date32 counts from the latter
+const UNIX_EPOCH_FROM_CE: i32 = 719_163;
+
+/// How a column is stored, from the first value that isn't NULL. JSON, UUIDs, arrays
+/// and geometries are kept in their text form, like the CSV export writes them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Bool,
+    Int,
+    UInt,
+    Float,
+    /// NUMERIC with the most decimals seen, Parquet fixes the scale per column
+    Decimal(i8),
+    Text,
+    Bytes,
+    Timestamp,
+    TimestampTz,
+    Date,
+}
+
+impl ColumnKind {
+    /// Reads the kind off the values of column `index`. Columns with only NULLs are text.
+    fn infer(rows: &[Vec<Value>], index: usize) -> Self {
+        let mut values = rows.iter().filter_map(|row| row.get(index));
+        let Some(first) = values.by_ref().find(|value| !is_missing(value)) else {
+            return ColumnKind::Text;
+        };
+        match first {
+            Value::Bool(_) => ColumnKind::Bool,
+            Value::Int(_) => ColumnKind::Int,
+            Value::UInt(_) => ColumnKind::UInt,
+            Value::Float(_) => ColumnKind::Float,
+            Value::Decimal(first) => {
+                let scale = std::iter::once(first)
+                    .chain(values.filter_map(|value| match value {
+                        Value::Decimal(v) => Some(v),
+                        _ => None,
+                    }))
+                    .map(|v| v.fractional_digit_count().max(0))
+                    .max()
+                    .unwrap_or(0);
+                ColumnKind::Decimal(scale.min(MAX_DECIMAL_DIGITS as i64) as i8)
+            }
+            Value::Bytes(_) => ColumnKind::Bytes,
+            Value::Timestamp(_) => ColumnKind::Timestamp,
+            Value::TimestampTz(_) => ColumnKind::TimestampTz,
+            Value::Date(_) => ColumnKind::Date,
+            _ => ColumnKind::Text,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnKind::Bool => DataType::Boolean,
+            ColumnKind::Int => DataType::Int64,
+            ColumnKind::UInt => DataType::UInt64,
+            ColumnKind::Float => DataType::Float64,
+            ColumnKind::Decimal(scale) => DataType::Decimal128(MAX_DECIMAL_DIGITS, scale),
+            ColumnKind::Text => DataType::Utf8,
+            ColumnKind::Bytes => DataType::Binary,
+            ColumnKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            ColumnKind::TimestampTz => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            ColumnKind::Date => DataType::Date32,
+        }
+    }
+}
+
+/// Values written as NULL: the ones the driver couldn't decode have nothing to store
+fn is_missing(value: &Value) -> bool {
+    matches!(value, Value::Null | Value::Invalid | Value::Unsupported(_))
+}
+
+/// A Parquet file written batch by batch. The column types come from the first batch,
+/// every finished row group goes to the output right away, so only the one being
+/// filled is held in memory.
+#[derive(Default)]
+pub struct ParquetWriter {
+    file: Option<(ArrowWriter<Vec<u8>>, SchemaRef, Vec<ColumnKind>)>,
+}
+
+impl ParquetWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_batch(&mut self, out: &mut impl Write, headers: &[String], rows: &[Vec<Value>]) -> Result<()> {
+        let (writer, schema, kinds) = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(open(headers, rows)?),
+        };
+        let columns = headers
+            .iter()
+            .zip(kinds.iter())
+            .enumerate()
+            .map(|(index, (name, kind))| column(rows, index, name, *kind))
+            .collect::<Result<Vec<_>>>()?;
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+
+        // Bytes only reach the buffer once a row group is complete
+        let buffer = writer.inner_mut();
+        out.write_all(buffer)?;
+        buffer.clear();
+        Ok(())
+    }
+
+    /// Writes the last row group and the footer. A result without any batch gets a
+    /// file without columns.
+    pub fn finish(self, out: &mut impl Write) -> Result<()> {
+        let (writer, _, _) = match self.file {
+            Some(file) => file,
+            None => open(&[], &[])?,
+        };
+        out.write_all(&writer.into_inner()?)?;
+        Ok(())
+    }
+}
+
+fn open(headers: &[String], rows: &[Vec<Value>]) -> Result<(ArrowWriter<Vec<u8>>, SchemaRef, Vec<ColumnKind>)> {
+    let kinds: Vec<ColumnKind> = (0..headers.len()).map(|index| ColumnKind::infer(rows, index)).collect();
+    let fields: Vec<Field> = headers
+        .iter()
+        .zip(&kinds)
+        .map(|(name, kind)| Field::new(name, kind.data_type(), true))
+        .collect();
+    let schema = Arc::new(Schema::new(fields));
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(ROW_GROUP_ROWS)
+        .build();
+    let writer = ArrowWriter::try_new(Vec::new(), schema.clone(), Some(properties))?;
+    Ok((writer, schema, kinds))
+}
+
+/// Column `index` of `rows` as an Arrow array of `kind`
+fn column(rows: &[Vec<Value>], index: usize, name: &str, kind: ColumnKind) -> Result<ArrayRef> {
+    let values = rows.iter().map(|row| row.get(index).unwrap_or(&Value::Null));
+    let mismatch = |value: &Value| {
+        anyhow!(
+            "Column {} holds a {} value among {:?} ones, Parquet needs one type per column",
+            name,
+            value.type_name(),
+            kind
+        )
+    };
+    // Every kind shares the NULL handling, `convert` returns None for a value of another type
+    fn cells<'a, T>(
+        values: impl Iterator<Item = &'a Value>,
+        mismatch: impl Fn(&Value) -> anyhow::Error,
+        convert: impl Fn(&'a Value) -> Option<T>,
+    ) -> Result<Vec<Option<T>>> {
+        values
+            .map(|value| match value {
+                value if is_missing(value) => Ok(None),
+                value => convert(value).map(Some).ok_or_else(|| mismatch(value)),
+            })
+            .collect()
+    }
+
+    let array: ArrayRef = match kind {
+        ColumnKind::Bool => Arc::new(BooleanArray::from(cells(values, mismatch, |value| match value {
+            Value::Bool(v) => Some(*v),
+            _ => None,
+        })?)),
+        ColumnKind::Int => Arc::new(Int64Array::from(cells(values, mismatch, |value| match value {
+            Value::Int(v) => Some(*v),
+            Value::UInt(v) => i64::try_from(*v).ok(),
+            _ => None,
+        })?)),
+        ColumnKind::UInt => Arc::new(UInt64Array::from(cells(values, mismatch, |value| match value {
+            Value::UInt(v) => Some(*v),
+            Value::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        })?)),
+        ColumnKind::Float => Arc::new(Float64Array::from(cells(values, mismatch, |value| match value {
+            Value::Float(v) => Some(*v),
+            Value::Int(v) => Some(*v as f64),
+            Value::UInt(v) => Some(*v as f64),
+            _ => None,
+        })?)),
+        ColumnKind::Decimal(scale) => {
+            let mut units = Vec::with_capacity(rows.len());
+            for value in values {
+                units.push(match value {
+                    value if is_missing(value) => None,
+                    Value::Decimal(v) => Some(decimal_units(v, scale, name)?),
+                    Value::Int(v) => Some(decimal_units(&BigDecimal::from(*v), scale, name)?),
+                    other => return Err(mismatch(other)),
+                });
+            }
+            Arc::new(Decimal128Array::from(units).with_precision_and_scale(MAX_DECIMAL_DIGITS, scale)?)
+        }
+        ColumnKind::Text => Arc::new(StringArray::from(cells(values, mismatch, |value| match value {
+            Value::Text(v) => Some(v.clone()),
+            other => Some(other.to_string()),
+        })?)),
+        ColumnKind::Bytes => Arc::new(BinaryArray::from(cells(values, mismatch, |value| match value {
+            Value::Bytes(v) => Some(v.as_slice()),
+            _ => None,
+        })?)),
+        ColumnKind::Timestamp => Arc::new(TimestampMicrosecondArray::from(cells(values, mismatch, |value| match value {
+            Value::Timestamp(v) => Some(v.and_utc().timestamp_micros()),
+            _ => None,
+        })?)),
+        ColumnKind::TimestampTz => Arc::new(
+            TimestampMicrosecondArray::from(cells(values, mismatch, |value| match value {
+                Value::TimestampTz(v) => Some(v.timestamp_micros()),
+                _ => None,
+            })?)
+            .with_timezone("UTC"),
+        ),
+        ColumnKind::Date => Arc::new(Date32Array::from(cells(values, mismatch, |value| match value {
+            Value::Date(v) => Some(v.num_days_from_ce() - UNIX_EPOCH_FROM_CE),
+            _ => None,
+        })?)),
+    };
+    Ok(array)
+}
+
+/// `value` in units of 10^-scale, refusing digits the column has no room for rather
+/// than rounding them away
+fn decimal_units(value: &BigDecimal, scale: i8, name: &str) -> Result<i128> {
+    if value.fractional_digit_count() > scale as i64 {
+        bail!(
+            "Column {} has values with more than {} decimals past the first rows, cast it to NUMERIC(p, s) to export it as Parquet",
+            name,
+            scale
+        );
+    }
+    let (units, _) = value.with_scale(scale as i64).into_bigint_and_exponent();
+    units
+        .to_i128()
+        .filter(|units| units.unsigned_abs() < 10u128.pow(MAX_DECIMAL_DIGITS as u32))
+        .ok_or_else(|| anyhow!("Column {} has a value wider than {} digits, Parquet can't hold it", name, MAX_DECIMAL_DIGITS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+    use bytes::Bytes;
+    use chrono::NaiveDate;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::str::FromStr;
+
+    fn read_back(file: Vec<u8>) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_column_types_across_batches() {
+        let headers: Vec<String> = ["id", "price", "name", "day", "empty"].map(String::from).to_vec();
+        let day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let first = vec![
+            vec![Value::Int(1), Value::Decimal(BigDecimal::from_str("9.99").unwrap()), Value::from("a"), Value::Date(day), Value::Null],
+            vec![Value::Int(2), Value::Null, Value::Null, Value::Date(day), Value::Null],
+        ];
+        let second = vec![vec![Value::Int(3), Value::Decimal(BigDecimal::from_str("-0.5").unwrap()), Value::from("c"), Value::Null, Value::Int(7)]];
+
+        let mut file = Vec::new();
+        let mut writer = ParquetWriter::new();
+        writer.write_batch(&mut file, &headers, &first).unwrap();
+        writer.write_batch(&mut file, &headers, &second).unwrap();
+        writer.finish(&mut file).unwrap();
+
+        let batches = read_back(file);
+        let schema = batches[0].schema();
+        let types: Vec<&DataType> = schema.fields().iter().map(|field| field.data_type()).collect();
+        assert_eq!(
+            types,
+            [&DataType::Int64, &DataType::Decimal128(38, 2), &DataType::Utf8, &DataType::Date32, &DataType::Utf8]
+        );
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 3);
+        let ids = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[1, 2, 3]);
+        let prices = batch.column(1).as_any().downcast_ref::<Decimal128Array>().unwrap();
+        assert_eq!(prices.value_as_string(0), "9.99");
+        assert!(prices.is_null(1));
+        assert_eq!(prices.value_as_string(2), "-0.50");
+        let days = batch.column(3).as_any().downcast_ref::<Date32Array>().unwrap();
+        assert_eq!(days.value_as_date(0), Some(day));
+        let empty = batch.column(4).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(empty.value(2), "7");
+    }
+
+    #[test]
+    fn refuses_mixed_types() {
+        let headers = vec!["flag".to_string()];
+        let mut writer = ParquetWriter::new();
+        let mut file = Vec::new();
+        writer.write_batch(&mut file, &headers, &[vec![Value::Bool(true)]]).unwrap();
+        assert!(writer.write_batch(&mut file, &headers, &[vec![Value::from("yes")]]).is_err());
+    }
+}
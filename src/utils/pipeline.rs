@@ -0,0 +1,640 @@
+use anyhow::{Result, anyhow, bail};
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use std::cmp::Ordering;
+
+use crate::utils::query_executor::RowBatch;
+use crate::utils::value::Value;
+
+/// Stages run over fetched rows, separated by `|`:
+/// `select a, b, a * b as total | where total > 0 and b is not null | rename a as id`.
+/// Every stage looks at one row at a time, so rows fetched later go through the same way.
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+enum Stage {
+    Select(Vec<SelectItem>),
+    Where(Expr),
+    Rename(Vec<(String, String)>),
+}
+
+enum SelectItem {
+    /// `*`, every column as it is
+    All,
+    Expr { expr: Expr, name: String },
+}
+
+#[derive(Clone)]
+enum Expr {
+    Literal(Value),
+    Column(String),
+    /// A column resolved against the headers of the rows being processed
+    Index(usize),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    IsNull { expr: Box<Expr>, negated: bool },
+    Binary { op: BinOp, left: Box<Expr>, right: Box<Expr> },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Pipeline {
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut stages = Vec::new();
+        for stage in tokens.split(|token| token.kind == Kind::Pipe) {
+            let mut parser = Parser { text, tokens: stage, pos: 0 };
+            stages.push(parser.stage()?);
+        }
+        Ok(Self { stages })
+    }
+
+    /// Runs `rows` with the columns `headers` through every stage
+    pub fn apply(&self, headers: &[String], mut rows: Vec<Vec<Value>>) -> Result<RowBatch> {
+        let mut headers = headers.to_vec();
+        for stage in &self.stages {
+            match stage {
+                Stage::Where(expr) => {
+                    let expr = expr.resolve(&headers)?;
+                    let mut kept = Vec::with_capacity(rows.len());
+                    for row in rows {
+                        if matches!(expr.eval(&row)?, Value::Bool(true)) {
+                            kept.push(row);
+                        }
+                    }
+                    rows = kept;
+                }
+                Stage::Select(items) => {
+                    let mut columns = Vec::new();
+                    let mut names = Vec::new();
+                    for item in items {
+                        match item {
+                            SelectItem::All => {
+                                columns.extend((0..headers.len()).map(Expr::Index));
+                                names.extend(headers.iter().cloned());
+                            }
+                            SelectItem::Expr { expr, name } => {
+                                columns.push(expr.resolve(&headers)?);
+                                names.push(name.clone());
+                            }
+                        }
+                    }
+                    rows = rows
+                        .iter()
+                        .map(|row| columns.iter().map(|expr| expr.eval(row)).collect())
+                        .collect::<Result<_>>()?;
+                    headers = names;
+                }
+                Stage::Rename(renames) => {
+                    for (from, to) in renames {
+                        let index = column_index(&headers, from)?;
+                        headers[index] = to.clone();
+                    }
+                }
+            }
+        }
+        Ok((headers, rows))
+    }
+}
+
+/// Exact names first, so `Name` and `name` can both be picked when both exist
+fn column_index(headers: &[String], name: &str) -> Result<usize> {
+    headers
+        .iter()
+        .position(|h| h == name)
+        .or_else(|| headers.iter().position(|h| h.eq_ignore_ascii_case(name)))
+        .ok_or_else(|| anyhow!("Unknown column {} (columns: {})", name, headers.join(", ")))
+}
+
+impl Expr {
+    fn resolve(&self, headers: &[String]) -> Result<Expr> {
+        let boxed = |expr: &Expr| expr.resolve(headers).map(Box::new);
+        Ok(match self {
+            Expr::Column(name) => Expr::Index(column_index(headers, name)?),
+            Expr::Literal(_) | Expr::Index(_) => self.clone(),
+            Expr::Not(expr) => Expr::Not(boxed(expr)?),
+            Expr::Negate(expr) => Expr::Negate(boxed(expr)?),
+            Expr::IsNull { expr, negated } => Expr::IsNull { expr: boxed(expr)?, negated: *negated },
+            Expr::Binary { op, left, right } => Expr::Binary { op: *op, left: boxed(left)?, right: boxed(right)? },
+        })
+    }
+
+    fn eval(&self, row: &[Value]) -> Result<Value> {
+        Ok(match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Index(index) => row.get(*index).cloned().unwrap_or(Value::Null),
+            Expr::Column(name) => bail!("Unresolved column {}", name),
+            Expr::Not(expr) => match expr.eval(row)? {
+                Value::Bool(b) => Value::Bool(!b),
+                Value::Null => Value::Null,
+                other => bail!("NOT needs a boolean, got {}", other),
+            },
+            Expr::Negate(expr) => arithmetic(BinOp::Sub, Value::Int(0), expr.eval(row)?)?,
+            Expr::IsNull { expr, negated } => Value::Bool(expr.eval(row)?.is_null() != *negated),
+            Expr::Binary { op: BinOp::And, left, right } => logic(left.eval(row)?, || right.eval(row), false)?,
+            Expr::Binary { op: BinOp::Or, left, right } => logic(left.eval(row)?, || right.eval(row), true)?,
+            Expr::Binary { op, left, right } => {
+                let (left, right) = (left.eval(row)?, right.eval(row)?);
+                match op {
+                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem => arithmetic(*op, left, right)?,
+                    _ => compare(*op, &left, &right),
+                }
+            }
+        })
+    }
+}
+
+/// AND and OR the SQL way: NULL is unknown, and the left side can decide alone
+fn logic(left: Value, right: impl FnOnce() -> Result<Value>, or: bool) -> Result<Value> {
+    let truth = |value: &Value| match value {
+        Value::Bool(b) => Ok(Some(*b)),
+        Value::Null => Ok(None),
+        other => Err(anyhow!("{} needs booleans, got {}", if or { "OR" } else { "AND" }, other)),
+    };
+    let left = truth(&left)?;
+    if left == Some(or) {
+        return Ok(Value::Bool(or));
+    }
+    let right = truth(&right()?)?;
+    Ok(match (left, right) {
+        (_, Some(b)) if b == or => Value::Bool(or),
+        (Some(_), Some(_)) => Value::Bool(!or),
+        _ => Value::Null,
+    })
+}
+
+/// A value as a number, numeric text included since drivers hand some types over as text
+enum Number {
+    Int(i64),
+    Float(f64),
+    Decimal(BigDecimal),
+}
+
+fn number(value: &Value) -> Option<Number> {
+    match value {
+        Value::Int(v) => Some(Number::Int(*v)),
+        Value::UInt(v) => Some(i64::try_from(*v).map_or_else(|_| Number::Decimal(BigDecimal::from(*v)), Number::Int)),
+        Value::Float(v) => Some(Number::Float(*v)),
+        Value::Decimal(v) => Some(Number::Decimal(v.clone())),
+        Value::Text(text) => {
+            let text = text.trim();
+            text.parse()
+                .map(Number::Int)
+                .ok()
+                .or_else(|| text.parse::<BigDecimal>().ok().map(Number::Decimal))
+        }
+        _ => None,
+    }
+}
+
+/// NULL in, NULL out. Dividing by zero gives NULL too, one odd row shouldn't fail the rest.
+fn arithmetic(op: BinOp, left: Value, right: Value) -> Result<Value> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    let (Some(a), Some(b)) = (number(&left), number(&right)) else {
+        // + also joins text
+        if op == BinOp::Add {
+            return Ok(Value::Text(format!("{}{}", left, right)));
+        }
+        bail!("Can't compute {} {} {}, both sides must be numbers", left, symbol(op), right);
+    };
+    Ok(match (a, b) {
+        (Number::Int(a), Number::Int(b)) => {
+            let result = match op {
+                BinOp::Add => a.checked_add(b),
+                BinOp::Sub => a.checked_sub(b),
+                BinOp::Mul => a.checked_mul(b),
+                BinOp::Rem if b == 0 => return Ok(Value::Null),
+                BinOp::Rem => a.checked_rem(b),
+                // Only whole quotients stay integers, 1 / 2 is 0.5 like everywhere but SQL
+                BinOp::Div if b == 0 => return Ok(Value::Null),
+                BinOp::Div if a % b == 0 => a.checked_div(b),
+                _ => None,
+            };
+            match result {
+                Some(v) => Value::Int(v),
+                None => float_arithmetic(op, a as f64, b as f64),
+            }
+        }
+        (Number::Float(a), b) => float_arithmetic(op, a, to_f64(&b)),
+        (a, Number::Float(b)) => float_arithmetic(op, to_f64(&a), b),
+        (a, b) => {
+            let (a, b) = (to_decimal(a), to_decimal(b));
+            match op {
+                BinOp::Add => Value::Decimal(a + b),
+                BinOp::Sub => Value::Decimal(a - b),
+                BinOp::Mul => Value::Decimal(a * b),
+                BinOp::Div | BinOp::Rem if b.is_zero() => Value::Null,
+                BinOp::Div => Value::Decimal(a / b),
+                _ => Value::Decimal(a % b),
+            }
+        }
+    })
+}
+
+fn float_arithmetic(op: BinOp, a: f64, b: f64) -> Value {
+    match op {
+        BinOp::Add => Value::Float(a + b),
+        BinOp::Sub => Value::Float(a - b),
+        BinOp::Mul => Value::Float(a * b),
+        BinOp::Div | BinOp::Rem if b == 0.0 => Value::Null,
+        BinOp::Div => Value::Float(a / b),
+        _ => Value::Float(a % b),
+    }
+}
+
+fn to_f64(number: &Number) -> f64 {
+    match number {
+        Number::Int(v) => *v as f64,
+        Number::Float(v) => *v,
+        Number::Decimal(v) => v.to_f64().unwrap_or(f64::NAN),
+    }
+}
+
+fn to_decimal(number: Number) -> BigDecimal {
+    match number {
+        Number::Int(v) => BigDecimal::from(v),
+        Number::Decimal(v) => v,
+        Number::Float(v) => BigDecimal::try_from(v).unwrap_or_default(),
+    }
+}
+
+/// Numbers by value, booleans as booleans and anything else by its text, NULL never matches
+fn compare(op: BinOp, left: &Value, right: &Value) -> Value {
+    if left.is_null() || right.is_null() {
+        return Value::Null;
+    }
+    let ordering = match (left, right) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        _ => match (number(left), number(right)) {
+            (Some(Number::Int(a)), Some(Number::Int(b))) => a.cmp(&b),
+            (Some(a @ Number::Float(_)), Some(b)) | (Some(a), Some(b @ Number::Float(_))) => {
+                to_f64(&a).partial_cmp(&to_f64(&b)).unwrap_or(Ordering::Equal)
+            }
+            (Some(a), Some(b)) => to_decimal(a).cmp(&to_decimal(b)),
+            _ => left.to_string().cmp(&right.to_string()),
+        },
+    };
+    Value::Bool(match op {
+        BinOp::Eq => ordering == Ordering::Equal,
+        BinOp::NotEq => ordering != Ordering::Equal,
+        BinOp::Lt => ordering == Ordering::Less,
+        BinOp::LtEq => ordering != Ordering::Greater,
+        BinOp::Gt => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    })
+}
+
+fn symbol(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Or => "or",
+        BinOp::And => "and",
+        BinOp::Eq => "=",
+        BinOp::NotEq => "!=",
+        BinOp::Lt => "<",
+        BinOp::LtEq => "<=",
+        BinOp::Gt => ">",
+        BinOp::GtEq => ">=",
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Rem => "%",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Ident(String),
+    /// A "quoted" name, never taken for a keyword
+    Quoted(String),
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Pipe,
+    Comma,
+    Open,
+    Close,
+    Star,
+    Op(&'static str),
+}
+
+struct Token {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'\'' | b'"' => {
+                let quote = bytes[i];
+                let mut content = Vec::new();
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        None => bail!("Unterminated {} at column {}", quote as char, start + 1),
+                        // Doubled quotes stand for one, like in SQL
+                        Some(&b) if b == quote && bytes.get(i + 1) == Some(&quote) => {
+                            content.push(quote);
+                            i += 2;
+                        }
+                        Some(&b) if b == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&b) => {
+                            content.push(b);
+                            i += 1;
+                        }
+                    }
+                }
+                let content = String::from_utf8_lossy(&content).into_owned();
+                if quote == b'\'' { Kind::Text(content) } else { Kind::Quoted(content) }
+            }
+            b if b.is_ascii_digit() => {
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                let number = &text[start..i];
+                match number.parse() {
+                    Ok(v) => Kind::Int(v),
+                    Err(_) => Kind::Float(number.parse().map_err(|_| anyhow!("Invalid number {}", number))?),
+                }
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                Kind::Ident(text[start..i].to_string())
+            }
+            _ => {
+                let two = text.get(i..i + 2).unwrap_or_default();
+                let (kind, len) = match two {
+                    "==" => (Kind::Op("="), 2),
+                    "!=" | "<>" => (Kind::Op("!="), 2),
+                    "<=" => (Kind::Op("<="), 2),
+                    ">=" => (Kind::Op(">="), 2),
+                    _ => match bytes[i] {
+                        b'|' => (Kind::Pipe, 1),
+                        b',' => (Kind::Comma, 1),
+                        b'(' => (Kind::Open, 1),
+                        b')' => (Kind::Close, 1),
+                        b'*' => (Kind::Star, 1),
+                        b'=' => (Kind::Op("="), 1),
+                        b'<' => (Kind::Op("<"), 1),
+                        b'>' => (Kind::Op(">"), 1),
+                        b'+' => (Kind::Op("+"), 1),
+                        b'-' => (Kind::Op("-"), 1),
+                        b'/' => (Kind::Op("/"), 1),
+                        b'%' => (Kind::Op("%"), 1),
+                        _ => bail!("Unexpected {:?} at column {}", text[i..].chars().next().unwrap_or_default(), i + 1),
+                    },
+                };
+                i += len;
+                kind
+            }
+        };
+        tokens.push(Token { kind, start, end: i });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Kind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Kind::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat(&mut self, kind: &Kind) -> bool {
+        let found = self.peek() == Some(kind);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn unexpected(&self) -> anyhow::Error {
+        match self.tokens.get(self.pos) {
+            Some(token) => anyhow!("Unexpected {} at column {}", &self.text[token.start..token.end], token.start + 1),
+            None => anyhow!("Unexpected end of the stage"),
+        }
+    }
+
+    fn stage(&mut self) -> Result<Stage> {
+        let stage = if self.eat_keyword("select") {
+            Stage::Select(self.list(Self::select_item)?)
+        } else if self.eat_keyword("where") {
+            Stage::Where(self.expr()?)
+        } else if self.eat_keyword("rename") {
+            Stage::Rename(self.list(|parser| {
+                let from = parser.name()?;
+                if !parser.eat_keyword("as") {
+                    return Err(parser.unexpected());
+                }
+                Ok((from, parser.name()?))
+            })?)
+        } else if self.peek().is_none() {
+            bail!("Empty stage, expected select, where or rename");
+        } else {
+            bail!("Stages start with select, where or rename, not {}", self.unexpected_text());
+        };
+        if self.peek().is_some() {
+            return Err(self.unexpected());
+        }
+        Ok(stage)
+    }
+
+    fn unexpected_text(&self) -> &str {
+        self.tokens.get(self.pos).map_or("", |t| &self.text[t.start..t.end])
+    }
+
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let mut items = vec![item(self)?];
+        while self.eat(&Kind::Comma) {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn select_item(&mut self) -> Result<SelectItem> {
+        if self.eat(&Kind::Star) {
+            return Ok(SelectItem::All);
+        }
+        let start = self.pos;
+        let expr = self.expr()?;
+        let name = if self.eat_keyword("as") {
+            self.name()?
+        } else if let Expr::Column(name) = &expr {
+            name.clone()
+        } else {
+            // Unnamed expressions are headed by their own text
+            let (first, last) = (&self.tokens[start], &self.tokens[self.pos - 1]);
+            self.text[first.start..last.end].to_string()
+        };
+        Ok(SelectItem::Expr { expr, name })
+    }
+
+    fn name(&mut self) -> Result<String> {
+        match self.peek().cloned() {
+            Some(Kind::Ident(name) | Kind::Quoted(name)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut left = self.and()?;
+        while self.eat_keyword("or") {
+            left = binary(BinOp::Or, left, self.and()?);
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut left = self.not()?;
+        while self.eat_keyword("and") {
+            left = binary(BinOp::And, left, self.not()?);
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let left = self.additive()?;
+        if self.eat_keyword("is") {
+            let negated = self.eat_keyword("not");
+            if !self.eat_keyword("null") {
+                return Err(self.unexpected());
+            }
+            return Ok(Expr::IsNull { expr: Box::new(left), negated });
+        }
+        let op = match self.peek() {
+            Some(Kind::Op("=")) => BinOp::Eq,
+            Some(Kind::Op("!=")) => BinOp::NotEq,
+            Some(Kind::Op("<")) => BinOp::Lt,
+            Some(Kind::Op("<=")) => BinOp::LtEq,
+            Some(Kind::Op(">")) => BinOp::Gt,
+            Some(Kind::Op(">=")) => BinOp::GtEq,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        Ok(binary(op, left, self.additive()?))
+    }
+
+    fn additive(&mut self) -> Result<Expr> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Kind::Op("+")) => BinOp::Add,
+                Some(Kind::Op("-")) => BinOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = binary(op, left, self.multiplicative()?);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Kind::Star) => BinOp::Mul,
+                Some(Kind::Op("/")) => BinOp::Div,
+                Some(Kind::Op("%")) => BinOp::Rem,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = binary(op, left, self.unary()?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat(&Kind::Op("-")) {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let Some(kind) = self.peek().cloned() else {
+            return Err(self.unexpected());
+        };
+        self.pos += 1;
+        Ok(match kind {
+            Kind::Int(v) => Expr::Literal(Value::Int(v)),
+            Kind::Float(v) => Expr::Literal(Value::Float(v)),
+            Kind::Text(v) => Expr::Literal(Value::Text(v)),
+            Kind::Quoted(name) => Expr::Column(name),
+            Kind::Ident(word) if word.eq_ignore_ascii_case("null") => Expr::Literal(Value::Null),
+            Kind::Ident(word) if word.eq_ignore_ascii_case("true") => Expr::Literal(Value::Bool(true)),
+            Kind::Ident(word) if word.eq_ignore_ascii_case("false") => Expr::Literal(Value::Bool(false)),
+            Kind::Ident(name) => Expr::Column(name),
+            Kind::Open => {
+                let expr = self.expr()?;
+                if !self.eat(&Kind::Close) {
+                    return Err(self.unexpected());
+                }
+                expr
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected());
+            }
+        })
+    }
+}
+
+fn binary(op: BinOp, left: Expr, right: Expr) -> Expr {
+    Expr::Binary { op, left: Box::new(left), right: Box::new(right) }
+}
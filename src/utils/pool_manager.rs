@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::utils::connection::Connection;
+use crate::utils::query_executor::QueryExecutor;
+
+/// Pools kept open after leaving their connection, so going back to it skips the connect
+pub struct PoolManager {
+    /// Least recently used first
+    warm: Vec<(Connection, Arc<QueryExecutor>)>,
+    capacity: usize,
+}
+
+impl PoolManager {
+    pub fn new(capacity: usize) -> Self {
+        Self { warm: Vec::new(), capacity }
+    }
+
+    /// The warm pool of `connection`, only when it was opened with the same settings
+    pub fn take(&mut self, connection: &Connection) -> Option<Arc<QueryExecutor>> {
+        let index = self.warm.iter().position(|(c, _)| c == connection)?;
+        Some(self.warm.remove(index).1)
+    }
+
+    /// Keeps `executor` open for the next visit to `connection`, closing the least
+    /// recently used pools past the capacity. Open transactions are rolled back first.
+    pub async fn release(&mut self, connection: Connection, executor: Arc<QueryExecutor>) {
+        if executor.in_transaction().await {
+            let _ = executor.rollback_transaction().await;
+        }
+        // A split on the same connection hands back a second pool, one is enough
+        if let Some(previous) = self.take(&connection) {
+            close(previous);
+        }
+        self.warm.push((connection, executor));
+        self.trim();
+    }
+
+    /// Closes the pools of connections that were edited or deleted since they were opened
+    pub fn retain(&mut self, connections: &[Connection]) {
+        let (keep, stale): (Vec<_>, Vec<_>) = std::mem::take(&mut self.warm)
+            .into_iter()
+            .partition(|(c, _)| connections.contains(c));
+        self.warm = keep;
+        stale.into_iter().for_each(|(_, executor)| close(executor));
+    }
+
+    /// Closes every pool, e.g. after settings they were opened with changed
+    pub fn clear(&mut self) {
+        self.warm.drain(..).for_each(|(_, executor)| close(executor));
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        let excess = self.warm.len().saturating_sub(self.capacity);
+        self.warm.drain(..excess).for_each(|(_, executor)| close(executor));
+    }
+}
+
+/// Closing waits for checked out connections, which the UI shouldn't
+fn close(executor: Arc<QueryExecutor>) {
+    tokio::spawn(async move {
+        let _ = executor.close().await;
+    });
+}
@@ -1,106 +1,579 @@
-use anyhow::{Result};
-use sqlx::postgres::{PgColumn, PgPool, PgRow};
-use sqlx::{Column, Row, TypeInfo, ValueRef};
-use crate::utils::query_executor::QueryExecutor;
+use anyhow::{Context, Result};
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::{PgArguments, PgColumn, PgConnection, PgCopyIn, PgListener, PgPool, PgRow, PgTypeKind};
+use sqlx::query::Query;
+use sqlx::{Column, Either, Postgres, Row, TypeInfo, ValueRef};
+use crate::utils::geometry::Geometry;
+use crate::utils::notices;
+use crate::utils::query_executor::{Pooled, QueryExecutor, RowStream, StatementOutput, send_batches};
+use crate::utils::sql::{CopyCommand, CopyDirection};
+use crate::utils::value::Value;
+use futures_util::{Stream, TryStreamExt};
+use std::ops::DerefMut;
+use std::sync::atomic::Ordering;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// Bytes read from the local file per COPY FROM message
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A NOTIFY that arrived on one of the channels being listened on
+pub struct Notification {
+    pub received: chrono::DateTime<chrono::Local>,
+    pub channel: String,
+    pub payload: String,
+    /// Backend that sent it
+    pub process_id: u32,
+}
 
 impl QueryExecutor {
-    pub async fn execute_postgres(
+    pub async fn execute_postgres<'e, E>(
         &self,
-        pool: &PgPool,
-        query: &str,
+        executor: E,
+        query: &'e str,
+        params: &'e [Value],
         is_query: bool,
-    ) -> Result<(Vec<String>, Vec<Vec<String>>)> {
-        if !is_query {
-            let result = sqlx::query(query).execute(pool).await?;
-            return Ok((
-                vec!["Result".to_string()],
-                vec![vec![format!("{} row(s) affected", result.rows_affected())]],
-            ));
+    ) -> Result<StatementOutput>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        // Rows decide, so INSERT ... RETURNING shows what it returned. Without any, a
+        // read is an empty result and anything else reports the rows it changed.
+        let mut stream = executor.fetch_many(Self::bind_postgres(sqlx::query(query), params));
+        let mut headers: Vec<String> = Vec::new();
+        let mut result_rows = Vec::new();
+        let mut rows_affected = 0;
+
+        while let Some(item) = stream.try_next().await? {
+            match item {
+                Either::Left(result) => rows_affected += result.rows_affected(),
+                Either::Right(row) => {
+                    if headers.is_empty() {
+                        headers = row.columns().iter().map(|c| c.name().to_string()).collect();
+                    }
+                    let row_data = row
+                        .columns()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, col)| Self::pg_to_value(&row, i, col))
+                        .collect();
+                    result_rows.push(row_data);
+                }
+            }
         }
 
-        let rows = sqlx::query(query).fetch_all(pool).await?;
-        if rows.is_empty() {
-            return Ok((Vec::new(), Vec::new()));
+        if !result_rows.is_empty() {
+            return Ok(StatementOutput::Sets(vec![(headers, result_rows)]));
+        }
+        if is_query {
+            return Ok(StatementOutput::Sets(Vec::new()));
         }
+        Ok(StatementOutput::Affected(rows_affected))
+    }
 
-        let headers: Vec<String> = rows[0]
-            .columns()
-            .iter()
-            .map(|c| c.name().to_string())
-            .collect();
-        let mut result_rows = Vec::new();
+    fn bind_postgres<'q>(
+        mut query: Query<'q, Postgres, PgArguments>,
+        params: &'q [Value],
+    ) -> Query<'q, Postgres, PgArguments> {
+        for param in params {
+            query = match param {
+                Value::Null => query.bind(None::<String>),
+                Value::Bool(v) => query.bind(*v),
+                Value::Int(v) => query.bind(*v),
+                Value::Float(v) => query.bind(*v),
+                other => query.bind(other.to_string()),
+            };
+        }
+        query
+    }
+
+    /// Runs a client-side COPY, streaming the local file to or from the server.
+    /// Uses the transaction connection when one is open, otherwise the pool.
+    pub async fn copy_postgres(
+        &self,
+        conn: &mut PgConnection,
+        command: &CopyCommand,
+    ) -> Result<StatementOutput> {
+        self.copy_progress.store(0, Ordering::Relaxed);
 
-        for row in rows {
-            let mut row_data = Vec::new();
-            for (i, col) in row.columns().iter().enumerate() {
-                row_data.push(self.pg_value_to_string(&row, i, col));
+        let result = match command.direction {
+            CopyDirection::From => {
+                let file = File::open(&command.path)
+                    .await
+                    .with_context(|| format!("Could not open {}", command.path))?;
+                let rows = self.copy_in(conn.copy_in_raw(&command.statement).await?, file).await;
+                rows.map(|rows| format!("{} row(s) copied from {}", rows, command.path))
             }
-            result_rows.push(row_data);
+            CopyDirection::To => {
+                let bytes = self.copy_out(conn.copy_out_raw(&command.statement).await?, &command.path).await;
+                bytes.map(|bytes| format!("{} byte(s) written to {}", bytes, command.path))
+            }
+        };
+
+        self.copy_progress.store(0, Ordering::Relaxed);
+        Ok(StatementOutput::Sets(vec![(vec!["Result".to_string()], vec![vec![Value::Text(result?)]])]))
+    }
+
+    async fn copy_in<C: DerefMut<Target = PgConnection>>(
+        &self,
+        mut copy: PgCopyIn<C>,
+        mut file: File,
+    ) -> Result<u64> {
+        let mut buffer = vec![0; COPY_CHUNK_SIZE];
+        loop {
+            let read = match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) => {
+                    copy.abort(e.to_string()).await?;
+                    return Err(e.into());
+                }
+            };
+            copy.send(&buffer[..read]).await?;
+            self.copy_progress.fetch_add(read as u64, Ordering::Relaxed);
+        }
+        Ok(copy.finish().await?)
+    }
+
+    async fn copy_out<B: AsRef<[u8]>>(
+        &self,
+        mut stream: impl Stream<Item = sqlx::Result<B>> + Unpin,
+        path: &str,
+    ) -> Result<u64> {
+        let mut file = File::create(path)
+            .await
+            .with_context(|| format!("Could not create {}", path))?;
+        let mut written = 0;
+        while let Some(chunk) = stream.try_next().await? {
+            file.write_all(chunk.as_ref()).await?;
+            written += chunk.as_ref().len() as u64;
+            self.copy_progress.store(written, Ordering::Relaxed);
         }
+        file.flush().await?;
+        Ok(written)
+    }
 
-        Ok((headers, result_rows))
+    /// LISTENs on `channels` with a connection of its own and forwards every notification
+    /// until the receiving end is dropped. Errors when the connection can't be kept.
+    pub async fn listen_postgres(
+        &self,
+        pool: &PgPool,
+        channels: &[String],
+        sender: mpsc::UnboundedSender<Notification>,
+    ) -> Result<()> {
+        let mut listener = PgListener::connect_with(pool).await.context("Could not open a connection to listen on")?;
+        listener.listen_all(channels.iter().map(String::as_str)).await?;
+        loop {
+            let notification = listener.recv().await?;
+            let notification = Notification {
+                received: chrono::Local::now(),
+                channel: notification.channel().to_string(),
+                payload: notification.payload().to_string(),
+                process_id: notification.process_id(),
+            };
+            if sender.send(notification).is_err() {
+                return Ok(());
+            }
+        }
     }
 
-    fn pg_value_to_string(&self, row: &PgRow, index: usize, col: &PgColumn) -> String {
+    /// Streams the rows on `conn`, which goes back to the pool once they ran out
+    pub(crate) fn stream_postgres(&self, mut conn: Pooled<Postgres>, query: &str) -> RowStream {
+        let query = query.to_string();
+        let (sender, receiver) = mpsc::channel(1);
+
+        // The rows are read on this task, its notices go to the query that started it
+        let handle = tokio::spawn(notices::inherit(async move {
+            let rows = sqlx::query(&query).fetch(&mut *conn);
+            send_batches(rows, sender, |row: &PgRow| {
+                row.columns().iter().enumerate().map(|(i, col)| Self::pg_to_value(row, i, col)).collect()
+            })
+            .await;
+            conn.release().await;
+        }));
+
+        RowStream::new(receiver, handle)
+    }
+
+    fn pg_to_value(row: &PgRow, index: usize, col: &PgColumn) -> Value {
         if row.try_get_raw(index).map_or(true, |v| v.is_null()) {
-            return "NULL".to_string();
+            return Value::Null;
         }
 
         let type_name = col.type_info().name();
 
         match type_name {
-            "BOOL" => row
-                .try_get::<bool, _>(index)
-                .map(|b| b.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "BOOL" => Value::from_decode(row.try_get::<bool, _>(index), Value::Bool),
+
+            // sqlx only decodes a column into the Rust type of its exact width
+            "INT2" => Value::from_decode(row.try_get::<i16, _>(index), |v| Value::Int(v.into())),
+            "INT4" => Value::from_decode(row.try_get::<i32, _>(index), |v| Value::Int(v.into())),
+            "INT8" => Value::from_decode(row.try_get::<i64, _>(index), Value::Int),
+            "OID" => Value::from_decode(row.try_get::<Oid, _>(index), |v| Value::Int(v.0.into())),
 
-            "INT2" | "INT4" | "INT8" => row
-                .try_get::<i64, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "FLOAT4" => Value::from_decode(row.try_get::<f32, _>(index), |v| Value::Float(v.into())),
+            "FLOAT8" => Value::from_decode(row.try_get::<f64, _>(index), Value::Float),
 
-            "FLOAT4" | "FLOAT8" | "NUMERIC" => row
-                .try_get::<f64, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            // Decoded from the wire format into a BigDecimal, exact where f64 would round, and
+            // keeping the column's scale which sqlx's own decoding pads to groups of four digits
+            "NUMERIC" => row
+                .try_get_raw(index)
+                .ok()
+                .and_then(|raw| raw.as_bytes().ok().and_then(pg_numeric))
+                .unwrap_or(Value::Invalid),
 
             "TEXT" | "VARCHAR" | "CHAR" | "NAME" => {
-                row.try_get::<String, _>(index).unwrap_or_default()
+                Value::Text(row.try_get::<String, _>(index).unwrap_or_default())
             }
 
-            "TIMESTAMP" => row
-                .try_get::<chrono::NaiveDateTime, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "TIMESTAMP" => Value::from_decode(row.try_get::<chrono::NaiveDateTime, _>(index), Value::Timestamp),
+
+            "TIMESTAMPTZ" => Value::from_decode(row.try_get::<chrono::DateTime<chrono::Utc>, _>(index), Value::TimestampTz),
+
+            "DATE" => Value::from_decode(row.try_get::<chrono::NaiveDate, _>(index), Value::Date),
+
+            "UUID" => Value::from_decode(row.try_get::<sqlx::types::Uuid, _>(index), Value::Uuid),
+
+            "JSON" | "JSONB" => Value::from_decode(row.try_get::<serde_json::Value, _>(index), Value::Json),
 
-            "TIMESTAMPTZ" => row
-                .try_get::<chrono::DateTime<chrono::Utc>, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            // Read from the wire format like array elements are, sqlx's own types for
+            // these need features this crate doesn't enable
+            "BYTEA" | "TIME" | "TIMETZ" | "INTERVAL" | "INET" | "CIDR" | "MACADDR" | "MACADDR8" => {
+                let oid = col.type_info().oid().map_or(0, |oid| oid.0);
+                row.try_get_raw(index)
+                    .ok()
+                    .and_then(|raw| raw.as_bytes().ok().map(|data| pg_binary_element(oid, data, type_name)))
+                    .unwrap_or(Value::Invalid)
+            }
 
-            "DATE" => row
-                .try_get::<chrono::NaiveDate, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            // PostGIS sends extended WKB
+            "geometry" | "geography" => row
+                .try_get_raw(index)
+                .ok()
+                .and_then(|raw| raw.as_bytes().ok().map(|data| pg_binary_element(0, data, type_name)))
+                .unwrap_or(Value::Invalid),
 
-            "UUID" => row
-                .try_get::<sqlx::types::Uuid, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            // Enum labels are sent as their text, but sqlx won't decode them into a String
+            _ if matches!(col.type_info().kind(), PgTypeKind::Enum(_)) => row
+                .try_get_raw(index)
+                .ok()
+                .and_then(|raw| raw.as_str().ok().map(|label| Value::Text(label.to_string())))
+                .unwrap_or(Value::Invalid),
 
-            "JSON" | "JSONB" => row
-                .try_get::<serde_json::Value, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            // User-defined types are named like Postgres does, with their arrays starting with _
+            name if name.ends_with("[]") || name.starts_with('_') => {
+                let element = name.trim_end_matches("[]").trim_start_matches('_');
+                row.try_get_raw(index)
+                    .ok()
+                    .and_then(|raw| raw.as_bytes().ok().and_then(|bytes| parse_pg_array(bytes, element)))
+                    .unwrap_or_else(|| Value::Unsupported(name.to_string()))
+            }
 
             _ => {
-                // Fallback: try as string, then generic debug
+                // Fallback: try as string, then report the type
                 if let Ok(s) = row.try_get::<String, _>(index) {
-                    s
+                    Value::Text(s)
                 } else {
-                    format!("<{}>", type_name)
+                    Value::Unsupported(type_name.to_string())
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+/// Days between the Unix epoch and 2000-01-01, where Postgres counts dates and times from
+const PG_EPOCH_DAYS: i64 = 10_957;
+
+/// Reads an array in the binary wire format: dimensions, then each element prefixed
+/// by its length. Arrays of more than one dimension become arrays of arrays.
+fn parse_pg_array(bytes: &[u8], element: &str) -> Option<Value> {
+    let mut reader = bytes;
+    let mut take = |len: usize| {
+        let (head, rest) = reader.split_at_checked(len)?;
+        reader = rest;
+        Some(head)
+    };
+    let dimensions = i32::from_be_bytes(take(4)?.try_into().ok()?);
+    // The null flag only tells whether any element is NULL, each one says so anyway
+    take(4)?;
+    let oid = u32::from_be_bytes(take(4)?.try_into().ok()?);
+    let mut lengths = Vec::new();
+    for _ in 0..dimensions {
+        lengths.push(usize::try_from(i32::from_be_bytes(take(4)?.try_into().ok()?)).ok()?);
+        // Lower bound, arrays are shown from their first element whatever it's numbered
+        take(4)?;
+    }
+
+    let count = if lengths.is_empty() { 0 } else { lengths.iter().product() };
+    let mut elements = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = i32::from_be_bytes(take(4)?.try_into().ok()?);
+        elements.push(match usize::try_from(len) {
+            Ok(len) => pg_binary_element(oid, take(len)?, element),
+            Err(_) => Value::Null,
+        });
+    }
+    Some(nest_array(elements, &lengths))
+}
+
+fn nest_array(elements: Vec<Value>, lengths: &[usize]) -> Value {
+    match lengths {
+        [_, inner @ ..] if !inner.is_empty() => {
+            let size = inner.iter().product::<usize>().max(1);
+            Value::Array(elements.chunks(size).map(|chunk| nest_array(chunk.to_vec(), inner)).collect())
+        }
+        _ => Value::Array(elements),
+    }
+}
+
+/// One array element in the binary format of the element type `oid`. Types without a
+/// case here, like enums, are sent as their text.
+fn pg_binary_element(oid: u32, data: &[u8], element: &str) -> Value {
+    let int = |data: &[u8]| -> Option<i64> {
+        Some(match data.len() {
+            2 => i16::from_be_bytes(data.try_into().ok()?).into(),
+            4 => i32::from_be_bytes(data.try_into().ok()?).into(),
+            8 => i64::from_be_bytes(data.try_into().ok()?),
+            _ => return None,
+        })
+    };
+    let since_epoch = |micros: i64| {
+        chrono::DateTime::from_timestamp_micros(micros + PG_EPOCH_DAYS * 86_400_000_000)
+    };
+    let decoded = match oid {
+        // Extension types have no fixed oid, they're known by name
+        _ if matches!(element, "geometry" | "geography") => Geometry::from_ewkb(data).map(Value::Geometry),
+        16 => data.first().map(|b| Value::Bool(*b != 0)),
+        20 | 21 | 23 | 26 => int(data).map(Value::Int),
+        700 => data.try_into().ok().map(|b| Value::Float(f32::from_be_bytes(b).into())),
+        701 => data.try_into().ok().map(|b| Value::Float(f64::from_be_bytes(b))),
+        1700 => pg_numeric(data),
+        17 => Some(Value::Bytes(data.to_vec())),
+        114 => serde_json::from_slice(data).ok().map(Value::Json),
+        // jsonb starts with a format version
+        3802 => data.split_first().and_then(|(_, json)| serde_json::from_slice(json).ok()).map(Value::Json),
+        2950 => sqlx::types::Uuid::from_slice(data).ok().map(Value::Uuid),
+        1082 => int(data).and_then(|days| since_epoch(days * 86_400_000_000)).map(|at| Value::Date(at.date_naive())),
+        1114 => int(data).and_then(since_epoch).map(|at| Value::Timestamp(at.naive_utc())),
+        1184 => int(data).and_then(since_epoch).map(Value::TimestampTz),
+        1083 => int(data).and_then(pg_time).map(|time| Value::Text(time.to_string())),
+        // The zone is stored as seconds west of UTC
+        1266 => data.split_at_checked(8).and_then(|(time, zone)| {
+            let time = pg_time(int(time)?)?;
+            let offset = chrono::FixedOffset::west_opt(i32::from_be_bytes(zone.try_into().ok()?))?;
+            Some(Value::Text(format!("{}{}", time, offset)))
+        }),
+        1186 => pg_interval(data).map(Value::Text),
+        869 | 650 => pg_inet(data, oid == 650).map(Value::Text),
+        829 | 774 if data.len() == 6 || data.len() == 8 => Some(Value::Text(
+            data.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":"),
+        )),
+        _ => std::str::from_utf8(data)
+            .ok()
+            .filter(|text| !text.contains(|c: char| c.is_control() && !c.is_whitespace()))
+            .map(|text| Value::Text(text.to_string())),
+    };
+    decoded.unwrap_or_else(|| Value::Unsupported(element.to_uppercase()))
+}
+
+/// TIME is microseconds since midnight
+fn pg_time(micros: i64) -> Option<chrono::NaiveTime> {
+    let seconds = u32::try_from(micros / 1_000_000).ok()?;
+    chrono::NaiveTime::from_num_seconds_from_midnight_opt(seconds, (micros % 1_000_000) as u32 * 1000)
+}
+
+/// INTERVAL is microseconds, days and months, kept apart since their lengths vary.
+/// Written the way psql shows them, e.g. `1 year 2 mons 3 days 04:05:06.5`.
+fn pg_interval(data: &[u8]) -> Option<String> {
+    let micros = i64::from_be_bytes(data.get(0..8)?.try_into().ok()?);
+    let days = i32::from_be_bytes(data.get(8..12)?.try_into().ok()?);
+    let months = i32::from_be_bytes(data.get(12..16)?.try_into().ok()?);
+
+    let mut parts = Vec::new();
+    for (count, unit, plural) in [(months / 12, "year", "years"), (months % 12, "mon", "mons"), (days, "day", "days")] {
+        if count != 0 {
+            parts.push(format!("{} {}", count, if count == 1 { unit } else { plural }));
+        }
+    }
+    if micros != 0 || parts.is_empty() {
+        let sign = if micros < 0 { "-" } else { "" };
+        let micros = micros.unsigned_abs();
+        let seconds = micros / 1_000_000;
+        let mut time = format!("{}{:02}:{:02}:{:02}", sign, seconds / 3600, seconds / 60 % 60, seconds % 60);
+        if micros % 1_000_000 != 0 {
+            time.push_str(format!(".{:06}", micros % 1_000_000).trim_end_matches('0'));
+        }
+        parts.push(time);
+    }
+    Some(parts.join(" "))
+}
+
+/// INET and CIDR: family, prefix bits, a CIDR flag and the address bytes. Like Postgres,
+/// an INET holding a single host leaves out the prefix.
+fn pg_inet(data: &[u8], cidr: bool) -> Option<String> {
+    let (header, address) = data.split_at_checked(4)?;
+    let bits = header[1];
+    let address = match address.len() {
+        4 => std::net::IpAddr::from(<[u8; 4]>::try_from(address).ok()?),
+        16 => std::net::IpAddr::from(<[u8; 16]>::try_from(address).ok()?),
+        _ => return None,
+    };
+    let host_bits = if address.is_ipv4() { 32 } else { 128 };
+    Some(if cidr || bits != host_bits { format!("{}/{}", address, bits) } else { address.to_string() })
+}
+
+/// NUMERIC in the binary format: base-10000 digits, the weight of the first one,
+/// the sign and the digits shown after the point
+fn pg_numeric(data: &[u8]) -> Option<Value> {
+    let word = |i: usize| data.get(i * 2..i * 2 + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let (count, weight, sign, scale) = (word(0)? as usize, word(1)? as i16, word(2)?, word(3)? as usize);
+    match sign {
+        0xC000 => return Some(Value::Float(f64::NAN)),
+        0xD000 => return Some(Value::Float(f64::INFINITY)),
+        0xF000 => return Some(Value::Float(f64::NEG_INFINITY)),
+        _ => {}
+    }
+    let digits = (0..count).map(|i| word(4 + i)).collect::<Option<Vec<u16>>>()?;
+    // Every base-10000 digit is four decimal ones, the point sits after digit `weight`
+    let mut text = String::new();
+    let integer_groups = (weight as i32 + 1).max(0) as usize;
+    for i in 0..integer_groups {
+        let digit = digits.get(i).copied().unwrap_or(0);
+        if i == 0 {
+            text.push_str(&digit.to_string());
+        } else {
+            text.push_str(&format!("{:04}", digit));
+        }
+    }
+    if text.is_empty() {
+        text.push('0');
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let leading_zero_groups = (-(weight as i32) - 1).max(0) as usize;
+        fraction.push_str(&"0000".repeat(leading_zero_groups));
+        for digit in digits.iter().skip(integer_groups) {
+            fraction.push_str(&format!("{:04}", digit));
+        }
+        let mut fraction: String = fraction.chars().chain(std::iter::repeat('0')).take(scale).collect();
+        fraction.insert(0, '.');
+        text.push_str(&fraction);
+    }
+    if sign == 0x4000 {
+        text.insert(0, '-');
+    }
+    text.parse::<bigdecimal::BigDecimal>().ok().map(Value::Decimal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use sqlx::Connection;
+    use std::str::FromStr;
+
+    /// Runs against the server in RSQUID_TEST_POSTGRES_URL, skipped when it isn't set
+    async fn connect() -> Option<PgConnection> {
+        let Ok(url) = std::env::var("RSQUID_TEST_POSTGRES_URL") else {
+            eprintln!("RSQUID_TEST_POSTGRES_URL not set, skipping");
+            return None;
+        };
+        Some(PgConnection::connect(&url).await.expect("connect to RSQUID_TEST_POSTGRES_URL"))
+    }
+
+    /// Each column of the single row `sql` returns, by name
+    async fn decode(conn: &mut PgConnection, sql: &str) -> Vec<(String, Value)> {
+        let row = sqlx::query(sql).fetch_one(conn).await.expect(sql);
+        row.columns()
+            .iter()
+            .enumerate()
+            .map(|(i, col)| (col.name().to_string(), QueryExecutor::pg_to_value(&row, i, col)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn decodes_every_supported_type() {
+        let Some(mut conn) = connect().await else {
+            return;
+        };
+        sqlx::query("CREATE TYPE pg_temp.rsquid_mood AS ENUM ('sad', 'happy')")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let decimal = |text: &str| Value::Decimal(BigDecimal::from_str(text).unwrap());
+        let text = |text: &str| Value::Text(text.to_string());
+        let expected = [
+            ("bool", Value::Bool(true)),
+            ("int2", Value::Int(-32768)),
+            ("int4", Value::Int(2147483647)),
+            ("int8", Value::Int(-9223372036854775808)),
+            ("oid", Value::Int(4294967295)),
+            ("float4", Value::Float(1.5)),
+            ("float8", Value::Float(-2.25)),
+            ("numeric", decimal("12345678901234567890.123456789")),
+            ("small_numeric", decimal("-0.00001")),
+            ("text", text("text")),
+            ("varchar", text("varchar")),
+            ("char", text("c")),
+            ("name", text("name")),
+            ("timestamp", Value::Timestamp("2024-02-29T13:14:15.5".parse().unwrap())),
+            ("timestamptz", Value::TimestampTz("2024-02-29T13:14:15Z".parse().unwrap())),
+            ("date", Value::Date("1999-12-31".parse().unwrap())),
+            ("uuid", Value::Uuid("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11".parse().unwrap())),
+            ("json", Value::Json(serde_json::json!({"a": [1, null]}))),
+            ("jsonb", Value::Json(serde_json::json!({"b": true}))),
+            ("bytea", Value::Bytes(vec![0, 255, 65])),
+            ("time", text("13:14:15.250")),
+            ("timetz", text("13:14:15+02:00")),
+            ("interval", text("1 year 2 mons 3 days 04:05:06.5")),
+            ("negative_interval", text("-00:00:01")),
+            ("inet", text("10.0.0.1")),
+            ("inet_prefix", text("::1/64")),
+            ("cidr", text("10.0.0.0/8")),
+            ("macaddr", text("08:00:2b:01:02:03")),
+            ("enum", text("happy")),
+            ("int_array", Value::Array(vec![Value::Int(1), Value::Null, Value::Int(3)])),
+            ("text_array", Value::Array(vec![Value::Array(vec![text("a"), text("b c")])])),
+            ("enum_array", Value::Array(vec![text("sad"), text("happy")])),
+            ("null", Value::Null),
+        ];
+        let row = decode(
+            &mut conn,
+            "SELECT true AS bool, (-32768)::int2 AS int2, 2147483647::int4 AS int4,
+                (-9223372036854775808)::int8 AS int8, 4294967295::oid AS oid,
+                1.5::float4 AS float4, (-2.25)::float8 AS float8,
+                12345678901234567890.123456789::numeric AS numeric, (-0.00001)::numeric AS small_numeric,
+                'text'::text AS text, 'varchar'::varchar AS varchar, 'c'::char AS char, 'name'::name AS name,
+                '2024-02-29 13:14:15.5'::timestamp AS timestamp, '2024-02-29 15:14:15+02'::timestamptz AS timestamptz,
+                '1999-12-31'::date AS date, 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::uuid AS uuid,
+                '{\"a\": [1, null]}'::json AS json, '{\"b\": true}'::jsonb AS jsonb, '\\x00ff41'::bytea AS bytea,
+                '13:14:15.25'::time AS time, '13:14:15+02'::timetz AS timetz,
+                '1 year 2 mons 3 days 04:05:06.5'::interval AS interval, '-1 second'::interval AS negative_interval,
+                '10.0.0.1'::inet AS inet, '::1/64'::inet AS inet_prefix, '10.0.0.0/8'::cidr AS cidr,
+                '08:00:2b:01:02:03'::macaddr AS macaddr, 'happy'::pg_temp.rsquid_mood AS enum,
+                ARRAY[1, NULL, 3]::int4[] AS int_array, ARRAY[['a', 'b c']]::text[] AS text_array,
+                ARRAY['sad', 'happy']::pg_temp.rsquid_mood[] AS enum_array, NULL::int4 AS null",
+        )
+        .await;
+
+        assert_eq!(row.len(), expected.len());
+        for ((column, value), (name, expected)) in row.iter().zip(expected) {
+            assert_eq!(column, name);
+            assert_eq!(value, &expected, "column {}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_numeric_specials() {
+        let Some(mut conn) = connect().await else {
+            return;
+        };
+        let row = decode(&mut conn, "SELECT 'NaN'::numeric, 'Infinity'::numeric, '-Infinity'::numeric, 100::numeric(10, 2)").await;
+        assert!(matches!(row[0].1, Value::Float(v) if v.is_nan()));
+        assert_eq!(row[1].1, Value::Float(f64::INFINITY));
+        assert_eq!(row[2].1, Value::Float(f64::NEG_INFINITY));
+        // The column's scale is kept
+        assert_eq!(row[3].1.to_string(), "100.00");
+    }
+}
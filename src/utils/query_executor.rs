@@ -1,41 +1,310 @@
+use crate::utils::config::Config;
 use crate::utils::connection::Connection;
+use crate::utils::postgres::Notification;
+use crate::utils::{cloud_sql, ssh};
+use crate::utils::tunnel::Tunnel;
+use crate::utils::{metrics, notices};
+use crate::utils::secrets::{is_reference, rds_auth_token, resolve_password};
+use crate::utils::sql::{Dialect, is_read_only, is_row_returning, parse_copy, split_statements, starts_transaction};
+use crate::utils::text::single_line;
+use crate::utils::value::Value;
 use anyhow::{Result, anyhow};
-use sqlx::mysql::{MySqlPool, MySqlPoolOptions};
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
-use std::time::Duration;
+use futures_util::{Stream, StreamExt};
+use sqlx::pool::PoolConnection;
+use sqlx::{Column, Database, Executor, MySql, Pool, Postgres, Row, Sqlite, Transaction};
+use std::ops::{Deref, DerefMut};
+use std::pin::pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 
+/// Rows sent per batch by a streaming fetch
+pub const STREAM_BATCH_SIZE: usize = 500;
+
+/// How long a keepalive ping may take before the connection counts as lost
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// IAM tokens last 15 minutes, new pool connections must never get an expired one
+const IAM_TOKEN_REFRESH: Duration = Duration::from_secs(10 * 60);
+const IAM_TOKEN_RETRY: Duration = Duration::from_secs(30);
+
+/// Pause before the first retry of a transient failure, doubled for every further one
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+pub type RowBatch = (Vec<String>, Vec<Vec<Value>>);
+
+/// What a single statement gave back
+pub enum StatementOutput {
+    /// The result sets it returned, none for a read that matched nothing
+    Sets(Vec<RowBatch>),
+    /// The rows it changed, for a statement that returned none
+    Affected(u64),
+}
+
+impl StatementOutput {
+    /// The result sets to show, a change is reported in a "row(s) affected" set
+    pub fn into_sets(self) -> Vec<RowBatch> {
+        match self {
+            StatementOutput::Sets(sets) => sets,
+            StatementOutput::Affected(rows) => {
+                vec![(vec!["Result".to_string()], vec![vec![Value::Text(format!("{} row(s) affected", rows))]])]
+            }
+        }
+    }
+}
+
+/// Context on the error of a failed script statement, with the breakdown of the
+/// statements run up to it. The failing one is the last row.
+#[derive(Debug)]
+pub struct ScriptFailure {
+    pub statement: usize,
+    pub breakdown: RowBatch,
+}
+
+impl std::fmt::Display for ScriptFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Statement {} of the script failed", self.statement)
+    }
+}
+
+#[derive(Clone)]
 pub enum DbPool {
     Postgres(PgPool),
     MySql(MySqlPool),
     Sqlite(SqlitePool),
 }
 
+/// An open transaction holding its own dedicated connection from the pool
+pub enum DbTransaction {
+    Postgres(Transaction<'static, Postgres>),
+    MySql(Transaction<'static, MySql>),
+    Sqlite(Transaction<'static, Sqlite>),
+}
+
 pub struct QueryExecutor {
     pool: DbPool,
+    transaction: Mutex<Option<DbTransaction>>,
+    statement_timeout: Option<Duration>,
+    /// Extra attempts for statements that fail with a transient error
+    retry_attempts: u32,
+    /// Bytes moved by the COPY currently running, 0 when there is none
+    pub(crate) copy_progress: AtomicU64,
+    /// The kubectl, ssh or Cloud SQL proxy tunnel the pool connects through, stopped on close
+    tunnel: Mutex<Option<Tunnel>>,
+    /// Keeps the pool's IAM token fresh for the connections it opens later
+    token_refresh: Option<JoinHandle<()>>,
+    /// Has the server refuse writes, see `set_read_only`
+    read_only: AtomicBool,
+}
+
+/// A pooled connection taken for one statement or stream. In read-only mode its session
+/// was switched to read-only, `release` switches it back. One dropped without being
+/// released, by an aborted task for instance, is closed rather than handed back read-only.
+pub(crate) struct Pooled<DB: Database> {
+    conn: PoolConnection<DB>,
+    /// Statement switching the session back, None when there is nothing to undo
+    reset: Option<&'static str>,
+}
+
+impl<DB: Database> Pooled<DB>
+where
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    /// `session` is the pair of statements from `read_only_session`, for read-only mode
+    async fn acquire(pool: &Pool<DB>, session: Option<(&'static str, &'static str)>) -> Result<Self> {
+        let mut pooled = Self { conn: pool.acquire().await?, reset: None };
+        if let Some((on, off)) = session {
+            pooled.conn.execute(on).await?;
+            pooled.reset = Some(off);
+        }
+        Ok(pooled)
+    }
+
+    /// Hands the connection back to the pool. A connection that can't be switched back
+    /// is closed rather than reused.
+    pub(crate) async fn release(mut self) {
+        if let Some(reset) = self.reset.take()
+            && self.conn.execute(reset).await.is_err()
+        {
+            self.conn.close_on_drop();
+        }
+    }
+}
+
+impl<DB: Database> Deref for Pooled<DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<DB: Database> DerefMut for Pooled<DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<DB: Database> Drop for Pooled<DB> {
+    fn drop(&mut self) {
+        if self.reset.is_some() {
+            self.conn.close_on_drop();
+        }
+    }
+}
+
+pub(crate) enum PooledConnection {
+    Postgres(Pooled<Postgres>),
+    MySql(Pooled<MySql>),
+    Sqlite(Pooled<Sqlite>),
+}
+
+/// What the server reports about itself, shown after connecting
+#[derive(Debug, Clone, Default)]
+pub struct ServerInfo {
+    pub version: String,
+    pub user: String,
+    pub encoding: String,
+    pub timezone: String,
+    /// Time since the server started, SQLite has no server to ask
+    pub uptime: Option<Duration>,
+}
+
+/// Handle on a row stream running in a background task, batches are pulled on demand
+pub struct RowStream {
+    receiver: mpsc::Receiver<Result<RowBatch>>,
+    handle: JoinHandle<()>,
 }
 
+impl RowStream {
+    pub(crate) fn new(receiver: mpsc::Receiver<Result<RowBatch>>, handle: JoinHandle<()>) -> Self {
+        Self { receiver, handle }
+    }
+
+    /// Next batch of rows, or None once the result set is exhausted
+    pub async fn next_batch(&mut self) -> Option<Result<RowBatch>> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for RowStream {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Sends `rows` in batches of `STREAM_BATCH_SIZE`, each row converted by `to_values`,
+/// until the rows run out, one fails or the `RowStream` receiving them is dropped
+pub(crate) async fn send_batches<R: Row, E: Into<anyhow::Error>>(
+    rows: impl Stream<Item = std::result::Result<R, E>>,
+    sender: mpsc::Sender<Result<RowBatch>>,
+    to_values: impl Fn(&R) -> Vec<Value>,
+) {
+    let mut rows = pin!(rows);
+    let mut headers = Vec::new();
+    let mut batch = Vec::with_capacity(STREAM_BATCH_SIZE);
+
+    loop {
+        match rows.next().await {
+            Some(Ok(row)) => {
+                if headers.is_empty() {
+                    headers = row.columns().iter().map(|c| c.name().to_string()).collect();
+                }
+                batch.push(to_values(&row));
+
+                if batch.len() >= STREAM_BATCH_SIZE {
+                    let full = std::mem::replace(&mut batch, Vec::with_capacity(STREAM_BATCH_SIZE));
+                    if sender.send(Ok((headers.clone(), full))).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            None => break,
+            Some(Err(e)) => {
+                let _ = sender.send(Err(e.into())).await;
+                return;
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        let _ = sender.send(Ok((headers, batch))).await;
+    }
+}
+
+
 impl QueryExecutor {
-    pub async fn new(connection: &Connection) -> Result<Self> {
-        let conn_str = connection.to_connection_string();
+    pub async fn new(connection: &Connection, config: &Config) -> Result<Self> {
+        let statement_timeout = connection.query_timeout(config);
+        let tunnel = match (&connection.kube, &connection.ssh, &connection.cloud_sql) {
+            (Some(target), _, _) => Some(target.port_forward(connection.port).await?),
+            (None, Some(bastion), _) => Some(ssh::tunnel(bastion, &connection.url_host(), connection.port).await?),
+            (None, None, Some(instance)) => Some(cloud_sql::proxy(instance).await?),
+            (None, None, None) => None,
+        };
+        // Secret references are read on every connect, so rotated passwords just work.
+        // Generated secrets hold characters the URL would need escaped, like the IAM
+        // tokens below they go in separately.
+        let secret = match is_reference(&connection.password) {
+            true => Some(resolve_password(&connection.password).await?),
+            false => None,
+        };
+        let mut target = connection.clone();
+        if secret.is_some() {
+            target.password.clear();
+        }
+        if let Some(tunnel) = &tunnel {
+            target.host = "127.0.0.1".to_string();
+            target.port = tunnel.local_port;
+        }
+        let conn_str = target.to_connection_string();
         let timeout_duration = Duration::from_secs(5);
+        // Tokens are full of characters the URL would need escaped, so they go in separately
+        let iam_token = match connection.aws_iam {
+            true => Some(rds_auth_token(connection).await?),
+            false => None,
+        };
 
         let pool = match connection.db_type.as_str() {
             "postgres" => {
-                let p = timeout(
-                    timeout_duration,
-                    PgPoolOptions::new().max_connections(5).connect(&conn_str),
-                )
-                .await??;
+                let mut options = PgPoolOptions::new().max_connections(5);
+                // Let the server cancel the statement too, so it doesn't keep running after we give up
+                if let Some(limit) = statement_timeout {
+                    let set_timeout = format!("SET statement_timeout = {}", limit.as_millis());
+                    options = options.after_connect(move |conn, _| {
+                        let set_timeout = set_timeout.clone();
+                        Box::pin(async move {
+                            conn.execute(set_timeout.as_str()).await?;
+                            Ok(())
+                        })
+                    });
+                }
+                let mut connect = PgConnectOptions::from_str(&conn_str)?;
+                if let Some(password) = iam_token.as_ref().or(secret.as_ref()) {
+                    connect = connect.password(password);
+                }
+                let p = timeout(timeout_duration, options.connect_with(connect)).await??;
                 DbPool::Postgres(p)
             }
             "mysql" | "mariadb" => {
+                let mut connect = MySqlConnectOptions::from_str(&conn_str)?;
+                if let Some(token) = &iam_token {
+                    // RDS takes IAM tokens through the cleartext plugin only, over TLS
+                    connect = connect.password(token).enable_cleartext_plugin(true);
+                } else if let Some(secret) = &secret {
+                    connect = connect.password(secret);
+                }
                 let p = timeout(
                     timeout_duration,
                     MySqlPoolOptions::new()
                         .max_connections(5)
-                        .connect(&conn_str),
+                        .connect_with(connect),
                 )
                 .await??;
                 DbPool::MySql(p)
@@ -52,61 +321,549 @@ impl QueryExecutor {
             }
             _ => return Err(anyhow!("Unsupported database type")),
         };
+        let token_refresh = connection.aws_iam.then(|| refresh_iam_token(pool.clone(), connection.clone()));
+        let executor = Self {
+            pool,
+            transaction: Mutex::new(None),
+            statement_timeout,
+            retry_attempts: config.retry_attempts,
+            copy_progress: AtomicU64::new(0),
+            tunnel: Mutex::new(tunnel),
+            token_refresh,
+            read_only: AtomicBool::new(false),
+        };
+        metrics::pool_opened(executor.dialect());
+        Ok(executor)
+    }
+
+    /// Applies the statement timeout, reporting server-side cancellations the same way
+    async fn with_timeout<T>(&self, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(limit) = self.statement_timeout else {
+            return fut.await;
+        };
 
-        Ok(Self { pool })
+        let timed_out = || anyhow!("Query timed out after {}s", limit.as_secs_f64());
+        match timeout(limit, fut).await {
+            Ok(Err(e)) if is_statement_timeout(&e) => Err(timed_out()),
+            Ok(result) => result,
+            Err(_) => Err(timed_out()),
+        }
+    }
+
+    /// Bytes transferred so far by a running COPY
+    pub fn copy_progress(&self) -> u64 {
+        self.copy_progress.load(Ordering::Relaxed)
     }
 
-    pub async fn execute(&self, query: &str) -> Result<(Vec<String>, Vec<Vec<String>>)> {
-        // Split queries by semicolon to handle multiple statements
-        let queries: Vec<&str> = query
-            .split(';')
-            .map(|q| q.trim())
-            .filter(|q| !q.is_empty())
-            .collect();
+    /// Makes the server refuse writes as well, the keyword check can't tell that a call
+    /// such as `nextval` writes. Statements outside a transaction run on a connection
+    /// switched to read-only for the session and back, transactions begin read-only.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
 
-        if queries.is_empty() {
-            return Ok((Vec::new(), Vec::new()));
+    pub fn dialect(&self) -> Dialect {
+        match self.pool {
+            DbPool::Postgres(_) => Dialect::Postgres,
+            DbPool::MySql(_) => Dialect::MySql,
+            DbPool::Sqlite(_) => Dialect::Sqlite,
         }
+    }
+
+    /// Runs every statement and returns each result set separately, statements
+    /// such as stored procedure calls may contribute more than one. A script that
+    /// changes data starts with a set listing every statement, its rows and time,
+    /// in place of a "row(s) affected" set per statement. When one of its statements
+    /// fails, the error carries that list as a `ScriptFailure`.
+    pub async fn execute_sets(&self, query: &str) -> Result<Vec<RowBatch>> {
+        // Split into statements, semicolons in literals and comments don't count
+        let queries = split_statements(query, self.dialect());
+        let breakdown = queries.len() > 1 && !queries.iter().all(|q| is_row_returning(q, self.dialect()));
+        // A deadlock rolls back the transaction the script opened, not just the statement
+        let opens_transaction = queries.iter().any(|q| starts_transaction(q));
+        let mut result_sets = Vec::new();
+        let mut statements = Vec::new();
+        let mut transaction = self.transaction.lock().await;
 
-        let mut all_headers = Vec::new();
-        let mut all_rows = Vec::new();
+        let headers = || ["#", "Statement", "Status", "Rows", "Time (ms)"].map(String::from).to_vec();
 
-        for (i, q) in queries.iter().enumerate() {
-            // Check if it's a SELECT-like query or an Action query
-            let trimmed = q.to_lowercase();
-            let query_type = trimmed.starts_with("select")
-                || trimmed.starts_with("show")
-                || trimmed.starts_with("describe")
-                || trimmed.starts_with("explain")
-                || trimmed.starts_with("with")
-                || trimmed.starts_with("values");
+        for (index, q) in queries.into_iter().enumerate() {
+            let started = Instant::now();
+            let output = metrics::measure(self.dialect(), async {
+                match transaction.as_mut() {
+                    Some(tx) => self.run_in_transaction(tx, q, &[]).await,
+                    None => self.with_retry(q, !opens_transaction, |conn| self.run_on_connection(conn, q, &[])).await,
+                }
+            })
+            .await;
+            if !breakdown {
+                result_sets.extend(output?.into_sets());
+                continue;
+            }
 
-            let (headers, rows) = match &self.pool {
-                DbPool::Postgres(p) => self.execute_postgres(p, q, query_type).await?,
-                DbPool::MySql(p) => self.execute_mysql(p, q, query_type).await?,
-                DbPool::Sqlite(p) => self.execute_sqlite(p, q, query_type).await?,
+            let elapsed = Value::Float(started.elapsed().as_micros() as f64 / 1000.0);
+            let (status, rows) = match output {
+                Ok(StatementOutput::Affected(rows)) => (Value::from("OK"), Value::UInt(rows)),
+                Ok(StatementOutput::Sets(sets)) => {
+                    let rows = sets.iter().map(|(_, rows)| rows.len() as u64).sum();
+                    result_sets.extend(sets);
+                    (Value::from("OK"), Value::UInt(rows))
+                }
+                Err(e) => {
+                    let status = Value::Text(format!("ERROR: {}", single_line(&e.to_string())));
+                    statements.push(vec![Value::Int(index as i64 + 1), Value::Text(single_line(q.trim())), status, Value::Null, elapsed]);
+                    let breakdown = (headers(), statements);
+                    return Err(e.context(ScriptFailure { statement: index + 1, breakdown }));
+                }
             };
+            statements.push(vec![Value::Int(index as i64 + 1), Value::Text(single_line(q.trim())), status, rows, elapsed]);
+        }
 
-            // Separator for multiple queries
-            if i > 0 && !all_rows.is_empty() {
-                all_rows.push(vec!["---".to_string(); headers.len().max(1)]);
+        if breakdown {
+            result_sets.insert(0, (headers(), statements));
+        }
+        Ok(result_sets)
+    }
+
+    /// Runs a single statement with bound parameters through the driver's prepared statement API
+    pub async fn execute_prepared(
+        &self,
+        query: &str,
+        params: &[Value],
+    ) -> Result<Vec<RowBatch>> {
+        let queries = split_statements(query, self.dialect());
+        let [statement] = queries.as_slice() else {
+            return Err(anyhow!("Prepared mode runs exactly one statement"));
+        };
+        let mut transaction = self.transaction.lock().await;
+
+        metrics::measure(self.dialect(), async {
+            match transaction.as_mut() {
+                Some(tx) => self.run_in_transaction(tx, statement, params).await,
+                None => self.with_retry(statement, true, |conn| self.run_on_connection(conn, statement, params)).await,
             }
+        })
+        .await
+        .map(StatementOutput::into_sets)
+    }
 
-            if all_headers.is_empty() {
-                all_headers = headers;
+    /// Runs a lookup on a pooled connection of its own, so it doesn't wait for the
+    /// statement currently running. It never sees the open transaction.
+    pub async fn execute_detached(&self, statement: &str, params: &[Value]) -> Result<Vec<RowBatch>> {
+        self.run_on_pool(statement, params).await.map(StatementOutput::into_sets)
+    }
+
+    /// A trivial statement on a pooled connection, so the server doesn't close it for idling
+    pub async fn ping(&self) -> Result<()> {
+        match timeout(PING_TIMEOUT, self.run_on_pool("SELECT 1", &[])).await {
+            Ok(result) => result.map(|_| ()),
+            Err(_) => Err(anyhow!("No answer within {}s", PING_TIMEOUT.as_secs())),
+        }
+    }
+
+    /// Forwards NOTIFYs sent on `channels`, see `listen_postgres`
+    pub async fn listen(&self, channels: &[String], sender: mpsc::UnboundedSender<Notification>) -> Result<()> {
+        match &self.pool {
+            DbPool::Postgres(p) => self.listen_postgres(p, channels, sender).await,
+            _ => Err(anyhow!("Only PostgreSQL has LISTEN/NOTIFY")),
+        }
+    }
+
+    /// Runs a single row-returning statement as a stream, returning the first batch
+    /// right away. Anything else goes through `execute_sets` and yields no stream.
+    pub async fn execute_streaming(&self, query: &str) -> Result<(Vec<RowBatch>, Option<RowStream>)> {
+        let queries = split_statements(query, self.dialect());
+        // The stream holds a pooled connection of its own, which can't see the transaction.
+        // In read-only mode that connection's session is read-only until the rows run out.
+        if queries.len() != 1
+            || !is_row_returning(queries[0], self.dialect())
+            || self.in_transaction().await
+        {
+            return Ok((self.execute_sets(query).await?, None));
+        }
+
+        let first_batch = self.with_retry(queries[0], true, |conn| async {
+            let mut stream = match conn {
+                PooledConnection::Postgres(conn) => self.stream_postgres(conn, queries[0]),
+                PooledConnection::MySql(conn) => self.stream_mysql(conn, queries[0]),
+                PooledConnection::Sqlite(conn) => self.stream_sqlite(conn, queries[0]),
+            };
+
+            match self.with_timeout(async { Ok(stream.next_batch().await) }).await? {
+                Some(batch) => Ok((vec![batch?], Some(stream))),
+                None => Ok((Vec::new(), None)),
+            }
+        });
+        metrics::measure(self.dialect(), first_batch).await
+    }
+
+    /// Takes a connection from the pool, switched to a read-only session in read-only mode
+    /// so the server refuses writes as well. Nothing of the statement was sent yet when
+    /// this fails.
+    async fn acquire(&self) -> Result<PooledConnection> {
+        let session = self.is_read_only().then(|| read_only_session(self.dialect()));
+        Ok(match &self.pool {
+            DbPool::Postgres(p) => PooledConnection::Postgres(Pooled::acquire(p, session).await?),
+            DbPool::MySql(p) => PooledConnection::MySql(Pooled::acquire(p, session).await?),
+            DbPool::Sqlite(p) => PooledConnection::Sqlite(Pooled::acquire(p, session).await?),
+        })
+    }
+
+    async fn run_on_pool(&self, statement: &str, params: &[Value]) -> Result<StatementOutput> {
+        let conn = self.acquire().await?;
+        self.run_on_connection(conn, statement, params).await
+    }
+
+    /// Runs a statement on a connection from `acquire` and hands the connection back
+    async fn run_on_connection(&self, conn: PooledConnection, statement: &str, params: &[Value]) -> Result<StatementOutput> {
+        let query_type = is_row_returning(statement, self.dialect());
+        match conn {
+            PooledConnection::Postgres(mut conn) => {
+                let result = match parse_copy(statement) {
+                    // Bulk loads are expected to be slow, so only the server-side timeout applies
+                    Some(command) if params.is_empty() => self.copy_postgres(&mut conn, &command).await,
+                    _ => self.with_timeout(self.execute_postgres(&mut *conn, statement, params, query_type)).await,
+                };
+                conn.release().await;
+                result
+            }
+            PooledConnection::MySql(mut conn) => {
+                let result = self.with_timeout(self.execute_mysql_with_warnings(&mut conn, statement, params, query_type)).await;
+                conn.release().await;
+                result
+            }
+            PooledConnection::Sqlite(mut conn) => {
+                let result = self.with_timeout(self.execute_sqlite(&mut *conn, statement, params, query_type)).await;
+                conn.release().await;
+                result
             }
-            all_rows.extend(rows);
         }
+    }
+
+    /// Inside a transaction every statement runs on its dedicated connection
+    async fn run_in_transaction(
+        &self,
+        transaction: &mut DbTransaction,
+        statement: &str,
+        params: &[Value],
+    ) -> Result<StatementOutput> {
+        let query_type = is_row_returning(statement, self.dialect());
 
-        Ok((all_headers, all_rows))
+        match transaction {
+            DbTransaction::Postgres(tx) => match parse_copy(statement) {
+                Some(command) if params.is_empty() => self.copy_postgres(tx, &command).await,
+                _ => self.with_timeout(self.execute_postgres(&mut **tx, statement, params, query_type)).await,
+            },
+            DbTransaction::MySql(tx) => self.with_timeout(self.execute_mysql_with_warnings(tx, statement, params, query_type)).await,
+            DbTransaction::Sqlite(tx) => self.with_timeout(self.execute_sqlite(&mut **tx, statement, params, query_type)).await,
+        }
     }
 
-    pub async fn close(self) -> Result<()> {
+    /// Runs `statement` on a pooled connection, again after transient failures up to the
+    /// configured number of retries, pausing longer before each one. Only used outside
+    /// transactions, where a failed statement already aborted the transaction.
+    ///
+    /// Failing to get a connection is always retried, nothing was sent yet. A connection
+    /// lost while the statement ran is only retried for reads: a write may have committed
+    /// before the connection went, and running it again would apply it twice. Deadlocks
+    /// and serialization failures were rolled back by the server, they are retried unless
+    /// the script opened a transaction of its own, `rolled_back_alone` false.
+    async fn with_retry<T, F, Fut>(&self, statement: &str, rolled_back_alone: bool, mut run: F) -> Result<T>
+    where
+        F: FnMut(PooledConnection) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let read = is_read_only(statement, self.dialect());
+        let mut attempt = 0;
+        loop {
+            let (result, sent) = match self.acquire().await {
+                Ok(conn) => (run(conn).await, true),
+                Err(e) => (Err(e), false),
+            };
+            match result {
+                Err(e) if attempt < self.retry_attempts && is_transient(&e, read || !sent, rolled_back_alone) => {
+                    tokio::time::sleep(RETRY_BACKOFF * 2u32.pow(attempt.min(4))).await;
+                    attempt += 1;
+                    notices::push(format!(
+                        "RETRY: statement retried after a transient error ({}/{}): {}",
+                        attempt, self.retry_attempts, e
+                    ));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Row count from the planner statistics, which is what EXPLAIN would estimate for a
+    /// full table write without running one. SQLite keeps no such statistics.
+    /// Runs detached, so a query holding the transaction doesn't hold it up.
+    pub async fn estimate_table_rows(&self, table: &str) -> Option<u64> {
+        let (query, params) = match self.pool {
+            DbPool::Postgres(_) => (
+                "SELECT reltuples::bigint FROM pg_class WHERE oid = to_regclass($1)",
+                vec![Value::from(table)],
+            ),
+            DbPool::MySql(_) => {
+                let unquoted = table.replace('`', "");
+                let (schema, name) = match unquoted.split_once('.') {
+                    Some((schema, name)) => (Value::from(schema), Value::from(name)),
+                    None => (Value::Null, Value::from(unquoted.as_str())),
+                };
+                (
+                    "SELECT TABLE_ROWS FROM information_schema.tables WHERE table_schema = COALESCE(?, DATABASE()) AND table_name = ?",
+                    vec![schema, name],
+                )
+            }
+            DbPool::Sqlite(_) => return None,
+        };
+
+        let result_sets = self.execute_detached(query, &params).await.ok()?;
+        let value = result_sets.first()?.1.first()?.first()?;
+        // Postgres reports -1 for tables that were never analyzed
+        value.to_string().parse::<i64>().ok().and_then(|rows| u64::try_from(rows).ok())
+    }
+
+    /// Planner estimate of the rows a query returns, from an EXPLAIN that doesn't run it.
+    /// Detached as well, tables created in the open transaction get no estimate.
+    pub async fn estimate_query_rows(&self, statement: &str) -> Option<u64> {
         match self.pool {
+            DbPool::Postgres(_) => {
+                let result_sets = self.execute_detached(&format!("EXPLAIN (FORMAT JSON) {}", statement), &[]).await.ok()?;
+                let Value::Json(plan) = result_sets.first()?.1.first()?.first()? else {
+                    return None;
+                };
+                plan.get(0)?.get("Plan")?.get("Plan Rows")?.as_f64().map(|rows| rows as u64)
+            }
+            DbPool::MySql(_) => {
+                let result_sets = self.execute_detached(&format!("EXPLAIN {}", statement), &[]).await.ok()?;
+                let (headers, rows) = result_sets.first()?;
+                let rows_index = headers.iter().position(|h| h == "rows")?;
+                let filtered_index = headers.iter().position(|h| h == "filtered");
+
+                // Each EXPLAIN row is a table in the join, the result size is roughly
+                // the product of the rows examined times the share that passes the filter
+                let mut estimate = 1.0;
+                for row in rows {
+                    let Ok(examined) = row[rows_index].to_string().parse::<f64>() else {
+                        continue;
+                    };
+                    let filtered = filtered_index
+                        .and_then(|i| row[i].to_string().parse::<f64>().ok())
+                        .unwrap_or(100.0);
+                    estimate *= examined * filtered / 100.0;
+                }
+                Some(estimate as u64)
+            }
+            DbPool::Sqlite(_) => None,
+        }
+    }
+
+    /// Version, user, encoding, time zone and uptime of the server. Fields the server
+    /// won't tell stay empty.
+    pub async fn server_info(&self) -> Result<ServerInfo> {
+        let query = match self.pool {
+            DbPool::Postgres(_) => {
+                "SELECT 'PostgreSQL ' || current_setting('server_version'), current_user::text, \
+                 current_setting('server_encoding'), current_setting('TimeZone'), \
+                 EXTRACT(EPOCH FROM now() - pg_postmaster_start_time())::float8"
+            }
+            DbPool::MySql(_) => {
+                "SELECT VERSION(), CURRENT_USER(), @@character_set_server, \
+                 IF(@@time_zone = 'SYSTEM', CONCAT('SYSTEM (', @@system_time_zone, ')'), @@time_zone), NULL"
+            }
+            // Expression columns only decode as text
+            DbPool::Sqlite(_) => {
+                "SELECT 'SQLite ' || sqlite_version(), '', CAST((SELECT encoding FROM pragma_encoding) AS TEXT), 'local', NULL"
+            }
+        };
+        let result_sets = self.execute_detached(query, &[]).await?;
+        let row = result_sets
+            .first()
+            .and_then(|(_, rows)| rows.first())
+            .ok_or_else(|| anyhow!("The server sent no details"))?;
+        let text = |index: usize| match row.get(index) {
+            Some(Value::Null) | None => String::new(),
+            Some(Value::Bytes(bytes)) => String::from_utf8_lossy(bytes).into_owned(),
+            Some(value) => value.to_string(),
+        };
+        let mut info = ServerInfo {
+            version: text(0),
+            user: text(1),
+            encoding: text(2),
+            timezone: text(3),
+            uptime: match row.get(4) {
+                Some(Value::Float(seconds)) if seconds.is_finite() && *seconds >= 0.0 => {
+                    Some(Duration::from_secs_f64(*seconds))
+                }
+                _ => None,
+            },
+        };
+        // MySQL keeps the uptime in a status variable, not a function
+        if let DbPool::MySql(_) = self.pool
+            && let Ok(status) = self.execute_detached("SHOW GLOBAL STATUS LIKE 'Uptime'", &[]).await
+            && let Some(value) = status.first().and_then(|(_, rows)| rows.first()).and_then(|row| row.get(1))
+            && let Ok(seconds) = value.to_string().parse::<u64>()
+        {
+            info.uptime = Some(Duration::from_secs(seconds));
+        }
+        Ok(info)
+    }
+
+    pub async fn in_transaction(&self) -> bool {
+        self.transaction.lock().await.is_some()
+    }
+
+    pub async fn begin_transaction(&self) -> Result<()> {
+        let mut transaction = self.transaction.lock().await;
+        if transaction.is_some() {
+            return Err(anyhow!("A transaction is already open"));
+        }
+
+        *transaction = Some(match (&self.pool, self.is_read_only()) {
+            (DbPool::Postgres(p), true) => DbTransaction::Postgres(p.begin_with("BEGIN READ ONLY").await?),
+            (DbPool::MySql(p), true) => DbTransaction::MySql(p.begin_with("START TRANSACTION READ ONLY").await?),
+            (DbPool::Sqlite(p), true) => {
+                // SQLite has no read-only transactions, the pragma is switched off again on commit or rollback
+                let mut tx = p.begin().await?;
+                tx.execute(read_only_session(Dialect::Sqlite).0).await?;
+                DbTransaction::Sqlite(tx)
+            }
+            (DbPool::Postgres(p), false) => DbTransaction::Postgres(p.begin().await?),
+            (DbPool::MySql(p), false) => DbTransaction::MySql(p.begin().await?),
+            (DbPool::Sqlite(p), false) => DbTransaction::Sqlite(p.begin().await?),
+        });
+        Ok(())
+    }
+
+    pub async fn commit_transaction(&self) -> Result<()> {
+        match self.transaction.lock().await.take() {
+            Some(DbTransaction::Postgres(tx)) => tx.commit().await?,
+            Some(DbTransaction::MySql(tx)) => tx.commit().await?,
+            Some(DbTransaction::Sqlite(mut tx)) => {
+                tx.execute(read_only_session(Dialect::Sqlite).1).await?;
+                tx.commit().await?
+            }
+            None => return Err(anyhow!("No transaction is open")),
+        }
+        Ok(())
+    }
+
+    pub async fn rollback_transaction(&self) -> Result<()> {
+        match self.transaction.lock().await.take() {
+            Some(DbTransaction::Postgres(tx)) => tx.rollback().await?,
+            Some(DbTransaction::MySql(tx)) => tx.rollback().await?,
+            Some(DbTransaction::Sqlite(mut tx)) => {
+                tx.execute(read_only_session(Dialect::Sqlite).1).await?;
+                tx.rollback().await?
+            }
+            None => return Err(anyhow!("No transaction is open")),
+        }
+        Ok(())
+    }
+
+    pub async fn close(&self) -> Result<()> {
+        // Never leave uncommitted work behind when leaving a connection
+        if self.in_transaction().await {
+            let _ = self.rollback_transaction().await;
+        }
+
+        match &self.pool {
             DbPool::Postgres(p) => p.close().await,
             DbPool::MySql(p) => p.close().await,
             DbPool::Sqlite(p) => p.close().await,
         }
+        if let Some(tunnel) = self.tunnel.lock().await.take() {
+            tunnel.stop();
+        }
         Ok(())
     }
 }
+
+impl Drop for QueryExecutor {
+    fn drop(&mut self) {
+        metrics::pool_closed(self.dialect());
+        if let Some(refresh) = &self.token_refresh {
+            refresh.abort();
+        }
+    }
+}
+
+/// Statements switching a session to read-only and back
+fn read_only_session(dialect: Dialect) -> (&'static str, &'static str) {
+    match dialect {
+        Dialect::Postgres => (
+            "SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY",
+            "SET SESSION CHARACTERISTICS AS TRANSACTION READ WRITE",
+        ),
+        Dialect::MySql => ("SET SESSION TRANSACTION READ ONLY", "SET SESSION TRANSACTION READ WRITE"),
+        Dialect::Sqlite => ("PRAGMA query_only = ON", "PRAGMA query_only = OFF"),
+    }
+}
+
+/// Swaps a new IAM token into the pool's options before the old one expires, until the pool closes
+fn refresh_iam_token(pool: DbPool, connection: Connection) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut wait = IAM_TOKEN_REFRESH;
+        loop {
+            tokio::time::sleep(wait).await;
+            let Ok(token) = rds_auth_token(&connection).await else {
+                wait = IAM_TOKEN_RETRY;
+                continue;
+            };
+            wait = IAM_TOKEN_REFRESH;
+            match &pool {
+                DbPool::Postgres(p) if !p.is_closed() => p.set_connect_options((*p.connect_options()).clone().password(&token)),
+                DbPool::MySql(p) if !p.is_closed() => p.set_connect_options((*p.connect_options()).clone().password(&token)),
+                _ => break,
+            }
+        }
+    })
+}
+
+/// Connection drops, serialization failures and deadlocks, which usually succeed when run
+/// again. A dropped connection only counts when `resend_safe`, the statement can't have
+/// changed anything. Deadlocks only count when `rolled_back_alone`, see `with_retry`.
+fn is_transient(error: &anyhow::Error, resend_safe: bool, rolled_back_alone: bool) -> bool {
+    let Some(error) = error.downcast_ref::<sqlx::Error>() else {
+        return false;
+    };
+
+    match error {
+        sqlx::Error::Io(_) => resend_safe,
+        // 40001 serialization_failure (also MySQL deadlocks), 40P01 deadlock_detected
+        sqlx::Error::Database(e) => rolled_back_alone && e.code().is_some_and(|code| code == "40001" || code == "40P01"),
+        other => {
+            let message = other.to_string().to_lowercase();
+            resend_safe
+                && (message.contains("connection reset")
+                    || message.contains("server closed the connection")
+                    || message.contains("broken pipe"))
+        }
+    }
+}
+
+// Postgres reports `statement_timeout` cancellations as SQLSTATE 57014
+fn is_statement_timeout(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<sqlx::Error>()
+        .and_then(|e| e.as_database_error())
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "57014")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_dropped_connections_only_when_safe() {
+        let dropped = || anyhow::Error::from(sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()));
+        assert!(is_transient(&dropped(), true, true));
+        assert!(!is_transient(&dropped(), false, true));
+        assert!(!is_transient(&anyhow!("syntax error"), true, true));
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::utils::query_executor::QueryExecutor;
+use crate::utils::sql::Dialect;
+
+/// Tables and the columns looked up so far for one connection, valid while the
+/// server reports the same schema version
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchemaCache {
+    pub version: String,
+    pub tables: Vec<String>,
+    /// Columns of the tables expanded in the explorer
+    pub columns: BTreeMap<String, Vec<String>>,
+}
+
+/// Caches of the connections used since startup, by connection name
+static MEMORY: Mutex<BTreeMap<String, SchemaCache>> = Mutex::new(BTreeMap::new());
+
+/// Changes with every DDL statement: the xmin of a catalog row is the transaction
+/// that last wrote it, and SQLite counts schema changes itself
+const POSTGRES_SCHEMA_VERSION: &str = "\
+    SELECT CONCAT(COUNT(*), ':', MAX(c.xmin::text::bigint), ':', \
+    (SELECT MAX(a.xmin::text::bigint) FROM pg_attribute a JOIN pg_class r ON r.oid = a.attrelid \
+    WHERE r.relnamespace = 'public'::regnamespace)) \
+    FROM pg_class c WHERE c.relnamespace = 'public'::regnamespace";
+
+/// ALTER TABLE rebuilds the table, which gives it a new CREATE_TIME
+const MYSQL_SCHEMA_VERSION: &str = "\
+    SELECT CONCAT(COUNT(*), ':', COALESCE(MAX(CREATE_TIME), ''), ':', \
+    (SELECT COUNT(*) FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE())) \
+    FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE()";
+
+const SQLITE_SCHEMA_VERSION: &str = "SELECT CAST(schema_version AS TEXT) FROM pragma_schema_version";
+
+/// A cheap fingerprint of the schema, None when the server won't tell
+pub async fn schema_version(executor: &QueryExecutor) -> Option<String> {
+    let query = match executor.dialect() {
+        Dialect::Postgres => POSTGRES_SCHEMA_VERSION,
+        Dialect::MySql => MYSQL_SCHEMA_VERSION,
+        Dialect::Sqlite => SQLITE_SCHEMA_VERSION,
+    };
+    // Detached, an open transaction would otherwise hold up the explorer
+    let sets = executor.execute_detached(query, &[]).await.ok()?;
+    sets.first()?.1.first()?.first().map(|value| value.to_string())
+}
+
+/// The cache of `connection` if it was taken at `version`, from memory or else from disk
+pub fn load(connection: &str, version: &str, disk: bool) -> Option<SchemaCache> {
+    let cached = MEMORY.lock().ok()?.get(connection).cloned();
+    let cached = match cached {
+        Some(cache) => cache,
+        None if disk => {
+            let cache: SchemaCache = serde_json::from_str(&fs::read_to_string(cache_path(connection).ok()?).ok()?).ok()?;
+            if let Ok(mut memory) = MEMORY.lock() {
+                memory.insert(connection.to_string(), cache.clone());
+            }
+            cache
+        }
+        None => return None,
+    };
+    (cached.version == version).then_some(cached)
+}
+
+/// Keeps `cache` for `connection`, writing it to disk too with `disk`
+pub fn store(connection: &str, cache: SchemaCache, disk: bool) -> Result<()> {
+    if disk {
+        fs::write(cache_path(connection)?, serde_json::to_string(&cache)?)?;
+    }
+    if let Ok(mut memory) = MEMORY.lock() {
+        memory.insert(connection.to_string(), cache);
+    }
+    Ok(())
+}
+
+/// Adds the columns of a table to the cache of `connection`, if it has one
+pub fn store_columns(connection: &str, table: &str, columns: Vec<String>, disk: bool) -> Result<()> {
+    let Some(mut cache) = MEMORY.lock().ok().and_then(|memory| memory.get(connection).cloned()) else {
+        return Ok(());
+    };
+    cache.columns.insert(table.to_string(), columns);
+    store(connection, cache, disk)
+}
+
+/// Forgets what is cached for `connection`, in memory and on disk
+pub fn invalidate(connection: &str) {
+    if let Ok(mut memory) = MEMORY.lock() {
+        memory.remove(connection);
+    }
+    if let Ok(path) = cache_path(connection) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// One file per connection, named after it with anything unusual replaced
+fn cache_path(connection: &str) -> Result<PathBuf> {
+    let dir = dirs::config_dir()
+        .context("Could not find config directory")?
+        .join("rsquid")
+        .join("schema");
+    fs::create_dir_all(&dir)?;
+    let name: String = connection
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    Ok(dir.join(format!("{}.json", name)))
+}
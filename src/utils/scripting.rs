@@ -0,0 +1,232 @@
+use anyhow::{Context, Result, anyhow, bail};
+use rhai::{AST, Array, Blob, CallFnOptions, Dynamic, Engine, FuncArgs, Map, Scope};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::utils::connection::Connection;
+use crate::utils::query_executor::RowBatch;
+use crate::utils::value::{Value, ValueFormat};
+
+/// A palette entry a script added with `register_command(label, function)`
+#[derive(Clone)]
+pub struct ScriptCommand {
+    pub label: String,
+    /// The script that registered it, shown next to the label
+    pub script: String,
+    index: usize,
+    function: String,
+}
+
+struct Script {
+    name: String,
+    ast: AST,
+}
+
+/// The user's scripts, the *.rhai files in ~/.config/rsquid/scripts. Each runs once when
+/// loaded, which is when it registers its commands. Hooks are functions a script defines:
+/// `on_connect(connection)`, `on_query(query, outcome)` and `transform(result)`.
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<Script>,
+    pub commands: Vec<ScriptCommand>,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self { engine: Engine::new(), scripts: Vec::new(), commands: Vec::new() }
+    }
+}
+
+impl Scripts {
+    /// Compiles and runs every script, `report` gets what they `print` or `notify`.
+    /// A broken script is left out and described in the returned messages.
+    pub fn load(report: fn(&str)) -> (Self, Vec<String>) {
+        let registered = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        let commands = registered.clone();
+        engine.register_fn("register_command", move |label: &str, function: &str| {
+            if let Ok(mut commands) = commands.lock() {
+                commands.push((label.to_string(), function.to_string()));
+            }
+        });
+        engine.register_fn("notify", move |message: &str| report(message));
+        // Stdout belongs to the TUI or to the exported rows
+        engine.on_print(report);
+        engine.on_debug(move |text, _, _| report(text));
+
+        let mut scripts = Self { engine, ..Self::default() };
+        let mut errors = Vec::new();
+        let paths = match script_paths() {
+            Ok(paths) => paths,
+            Err(e) => return (scripts, vec![format!("{:#}", e)]),
+        };
+        for path in paths {
+            let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            let ast = match scripts.engine.compile_file(path.clone()) {
+                Ok(ast) => ast,
+                Err(e) => {
+                    errors.push(format!("Script {}: {}", name, e));
+                    continue;
+                }
+            };
+            if let Err(e) = scripts.engine.run_ast(&ast) {
+                errors.push(format!("Script {}: {}", name, e));
+                registered.lock().map(|mut r| r.clear()).ok();
+                continue;
+            }
+            let index = scripts.scripts.len();
+            for (label, function) in registered.lock().map(|mut r| std::mem::take(&mut *r)).unwrap_or_default() {
+                if !has_function(&ast, &function, 1) {
+                    errors.push(format!("Script {}: command {:?} needs a function {}(context)", name, label, function));
+                    continue;
+                }
+                scripts.commands.push(ScriptCommand { label, script: name.clone(), index, function });
+            }
+            scripts.scripts.push(Script { name, ast });
+        }
+        (scripts, errors)
+    }
+
+    pub fn has_transform(&self) -> bool {
+        self.scripts.iter().any(|script| has_function(&script.ast, "transform", 1))
+    }
+
+    /// Hands `on_connect` the connection, without its password
+    pub fn on_connect(&self, connection: &Connection) -> Result<()> {
+        let mut info = Map::new();
+        info.insert("name".into(), connection.name.clone().into());
+        info.insert("type".into(), connection.db_type.clone().into());
+        info.insert("host".into(), connection.host_label().into());
+        info.insert("port".into(), (connection.port as i64).into());
+        info.insert("database".into(), connection.database.clone().into());
+        info.insert("username".into(), connection.username.clone().into());
+        self.each("on_connect", 1, || (info.clone(),))
+    }
+
+    /// Tells `on_query` how a query ended: `ok`, `duration_ms`, `rows` and `error`
+    pub fn on_query(&self, query: &str, elapsed: Duration, outcome: Result<usize, &str>) -> Result<()> {
+        let mut info = Map::new();
+        info.insert("ok".into(), outcome.is_ok().into());
+        info.insert("duration_ms".into(), (elapsed.as_millis() as i64).into());
+        info.insert("rows".into(), (*outcome.as_ref().unwrap_or(&0) as i64).into());
+        info.insert("error".into(), outcome.err().map_or(Dynamic::UNIT, |e| e.to_string().into()));
+        self.each("on_query", 2, || (query.to_string(), info.clone()))
+    }
+
+    /// Passes a batch of rows through every script's `transform`, which gets and returns
+    /// `#{headers, rows}`. Cells Rhai has no type for arrive as text.
+    pub fn transform(&self, (headers, rows): RowBatch) -> Result<RowBatch> {
+        let mut result = Map::new();
+        result.insert("headers".into(), headers.into_iter().map(Dynamic::from).collect::<Array>().into());
+        result.insert(
+            "rows".into(),
+            rows.into_iter()
+                .map(|row| row.into_iter().map(to_dynamic).collect::<Array>().into())
+                .collect::<Array>()
+                .into(),
+        );
+        for script in self.scripts.iter().filter(|s| has_function(&s.ast, "transform", 1)) {
+            result = self
+                .call(script, "transform", (result,))?
+                .try_cast::<Map>()
+                .with_context(|| format!("{}: transform must return #{{headers, rows}}", script.name))?;
+        }
+
+        let take = |key: &str| result.get(key).and_then(|v| v.clone().try_cast::<Array>());
+        let (Some(headers), Some(rows)) = (take("headers"), take("rows")) else {
+            bail!("transform must return #{{headers, rows}} with arrays in both");
+        };
+        let headers = headers.into_iter().map(|h| h.to_string()).collect();
+        let rows = rows
+            .into_iter()
+            .map(|row| match row.try_cast::<Array>() {
+                Some(cells) => Ok(cells.into_iter().map(from_dynamic).collect()),
+                None => Err(anyhow!("transform must return each row as an array")),
+            })
+            .collect::<Result<_>>()?;
+        Ok((headers, rows))
+    }
+
+    /// Runs a palette command with `#{query, connection}`, a string it returns replaces the query
+    pub fn run_command(&self, command: &ScriptCommand, query: &str, connection: Option<&str>) -> Result<Option<String>> {
+        let script = &self.scripts[command.index];
+        let mut context = Map::new();
+        context.insert("query".into(), query.into());
+        context.insert("connection".into(), connection.map_or(Dynamic::UNIT, |name| name.into()));
+        let returned = self.call(script, &command.function, (context,))?;
+        Ok(returned.into_string().ok())
+    }
+
+    /// Calls `function` in each script defining it, stopping at the first error
+    fn each<A: FuncArgs>(&self, function: &str, params: usize, args: impl Fn() -> A) -> Result<()> {
+        for script in self.scripts.iter().filter(|s| has_function(&s.ast, function, params)) {
+            // Events only notify, whatever the hook returns is ignored
+            let _ = self.call(script, function, args())?;
+        }
+        Ok(())
+    }
+
+    fn call(&self, script: &Script, function: &str, args: impl FuncArgs) -> Result<Dynamic> {
+        // The top level already ran on load, running it again would register everything twice
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &script.ast, function, args)
+            .map_err(|e| anyhow!("{}: {}", script.name, e))
+    }
+}
+
+/// Scripts in name order, so the ones that transform results run in a known sequence
+fn script_paths() -> Result<Vec<PathBuf>> {
+    let dir = dirs::config_dir().context("Could not find config directory")?.join("rsquid").join("scripts");
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .with_context(|| format!("Could not read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn has_function(ast: &AST, name: &str, params: usize) -> bool {
+    ast.iter_functions().any(|f| f.name == name && f.params.len() == params)
+}
+
+fn to_dynamic(value: Value) -> Dynamic {
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::Bool(v) => v.into(),
+        Value::Int(v) => v.into(),
+        Value::UInt(v) => i64::try_from(v).map_or_else(|_| v.to_string().into(), Dynamic::from),
+        Value::Float(v) => v.into(),
+        Value::Text(v) => v.into(),
+        Value::Bytes(v) => Dynamic::from_blob(v),
+        Value::Array(items) => items.into_iter().map(to_dynamic).collect::<Array>().into(),
+        other => other.format(&ValueFormat::default()).into(),
+    }
+}
+
+fn from_dynamic(value: Dynamic) -> Value {
+    if value.is_unit() {
+        return Value::Null;
+    }
+    if let Ok(v) = value.as_bool() {
+        return Value::Bool(v);
+    }
+    if let Ok(v) = value.as_int() {
+        return Value::Int(v);
+    }
+    if let Ok(v) = value.as_float() {
+        return Value::Float(v);
+    }
+    if value.is_blob() {
+        return Value::Bytes(value.cast::<Blob>());
+    }
+    if value.is_array() {
+        return Value::Array(value.cast::<Array>().into_iter().map(from_dynamic).collect());
+    }
+    Value::Text(value.to_string())
+}
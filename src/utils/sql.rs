@@ -0,0 +1,639 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl Dialect {
+    // MySQL treats backslash as an escape inside every string literal
+    fn backslash_escapes(self) -> bool {
+        self == Dialect::MySql
+    }
+}
+
+/// Splits a script into statements on `;`, ignoring semicolons inside string
+/// literals, quoted identifiers, comments and Postgres dollar-quoted bodies.
+/// Statements that are empty or only contain comments are dropped.
+pub fn split_statements(query: &str, dialect: Dialect) -> Vec<&str> {
+    let bytes = query.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_code = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'\'' | b'"' | b'`' => {
+                let escapes = c == b'\'' && (dialect.backslash_escapes() || is_escape_string(bytes, i));
+                i = skip_quoted(bytes, i, c, escapes);
+                has_code = true;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = skip_line_comment(bytes, i);
+            }
+            b'#' if dialect == Dialect::MySql => {
+                i = skip_line_comment(bytes, i);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i);
+            }
+            b'$' if dialect == Dialect::Postgres => {
+                match dollar_tag(bytes, i) {
+                    Some(tag) => i = skip_dollar_quoted(bytes, i, tag),
+                    None => i += 1,
+                }
+                has_code = true;
+            }
+            b';' => {
+                if has_code {
+                    statements.push(query[start..i].trim());
+                }
+                i += 1;
+                start = i;
+                has_code = false;
+            }
+            _ => {
+                if !c.is_ascii_whitespace() {
+                    has_code = true;
+                }
+                i += 1;
+            }
+        }
+    }
+
+    if has_code {
+        statements.push(query[start..].trim());
+    }
+
+    statements
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopyDirection {
+    /// Load a local file into the database
+    From,
+    /// Write the table or query output to a local file
+    To,
+}
+
+/// A `COPY ... FROM/TO 'file'` (or psql style `\copy`) statement rewritten to use
+/// STDIN/STDOUT, so the file is read or written on the client side
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyCommand {
+    pub direction: CopyDirection,
+    pub statement: String,
+    pub path: String,
+}
+
+/// Recognises client-side COPY statements. Returns None for plain `COPY ... FROM STDIN`,
+/// `PROGRAM` and anything else the server should handle itself.
+pub fn parse_copy(statement: &str) -> Option<CopyCommand> {
+    let statement = strip_leading_comments(statement);
+    let lower = statement.to_lowercase();
+    let keyword_len = if lower.starts_with("\\copy") {
+        5
+    } else if lower.starts_with("copy") {
+        4
+    } else {
+        return None;
+    };
+    if !statement[keyword_len..].starts_with(|c: char| c.is_whitespace() || c == '(') {
+        return None;
+    }
+
+    let bytes = statement.as_bytes();
+    let mut depth = 0;
+    let mut i = keyword_len;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => {
+                i = skip_quoted(bytes, i, bytes[i], false);
+                continue;
+            }
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            _ if depth == 0 && !is_ident_byte(bytes[i - 1]) => {
+                let direction = if lower[i..].starts_with("from") {
+                    Some((CopyDirection::From, 4))
+                } else if lower[i..].starts_with("to") {
+                    Some((CopyDirection::To, 2))
+                } else {
+                    None
+                };
+
+                if let Some((direction, len)) = direction
+                    && bytes.get(i + len).is_some_and(|b| b.is_ascii_whitespace())
+                {
+                    let after = &statement[i + len..];
+                    let target_start = i + len + after.len() - after.trim_start().len();
+                    let (path, target_end) = if bytes.get(target_start) == Some(&b'\'') {
+                        let end = skip_quoted(bytes, target_start, b'\'', false);
+                        let literal = &statement[target_start + 1..(end - 1).max(target_start + 1)];
+                        (literal.replace("''", "'"), end)
+                    } else {
+                        // Only psql style \copy accepts a bare file name
+                        let end = statement[target_start..]
+                            .find(char::is_whitespace)
+                            .map_or(statement.len(), |pos| target_start + pos);
+                        let word = &statement[target_start..end];
+                        let lower_word = word.to_lowercase();
+                        if keyword_len == 4
+                            || matches!(lower_word.as_str(), "stdin" | "stdout" | "program" | "")
+                        {
+                            return None;
+                        }
+                        (word.to_string(), end)
+                    };
+
+                    let stream = match direction {
+                        CopyDirection::From => "STDIN",
+                        CopyDirection::To => "STDOUT",
+                    };
+                    let rewritten = format!(
+                        "COPY{} {} {}{}",
+                        &statement[keyword_len..i].trim_end(),
+                        &statement[i..i + len].to_uppercase(),
+                        stream,
+                        &statement[target_end..]
+                    );
+
+                    return Some(CopyCommand {
+                        direction,
+                        statement: rewritten,
+                        path,
+                    });
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Number of bind parameters a statement expects: the highest `$N` for Postgres,
+/// the number of `?` markers otherwise. Literals and comments are skipped.
+pub fn count_placeholders(statement: &str, dialect: Dialect) -> usize {
+    let bytes = statement.as_bytes();
+    let mut count = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            c @ (b'\'' | b'"' | b'`') => {
+                let escapes = c == b'\'' && (dialect.backslash_escapes() || is_escape_string(bytes, i));
+                i = skip_quoted(bytes, i, c, escapes);
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => i = skip_line_comment(bytes, i),
+            b'#' if dialect == Dialect::MySql => i = skip_line_comment(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i),
+            b'$' if dialect == Dialect::Postgres => {
+                if let Some(tag) = dollar_tag(bytes, i) {
+                    i = skip_dollar_quoted(bytes, i, tag);
+                    continue;
+                }
+                let digits = bytes[i + 1..].iter().take_while(|b| b.is_ascii_digit()).count();
+                if digits > 0 {
+                    let number = &statement[i + 1..i + 1 + digits];
+                    count = count.max(number.parse().unwrap_or(0));
+                }
+                i += 1 + digits;
+            }
+            b'?' if dialect != Dialect::Postgres => {
+                count += 1;
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    count
+}
+
+/// Adds a `LIMIT` to a plain SELECT so the database stops after `limit` rows.
+/// Returns None when the statement can't take one safely: it isn't a query,
+/// already limits itself, or has a clause that must come after the limit.
+pub fn push_down_limit(statement: &str, limit: u32, dialect: Dialect) -> Option<String> {
+    let words = top_level_words(statement, dialect);
+    if !matches!(words.first().map(|(_, w)| w.as_str()), Some("select" | "with" | "values"))
+        || !is_row_returning(statement, dialect)
+    {
+        return None;
+    }
+    // MySQL needs `LIMIT` before locking and `INTO`, `TOP` and `FETCH` already limit the rows
+    if words
+        .iter()
+        .any(|(_, w)| matches!(w.as_str(), "limit" | "fetch" | "top" | "for" | "into" | "lock"))
+    {
+        return None;
+    }
+
+    // On its own line so a trailing `--` comment can't swallow it
+    Some(format!("{}\nLIMIT {}", statement.trim_end(), limit))
+}
+
+/// An UPDATE or DELETE without a WHERE clause, it touches every row of `table`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnfilteredWrite {
+    pub verb: &'static str,
+    pub table: String,
+}
+
+pub fn find_unfiltered_write(statement: &str, dialect: Dialect) -> Option<UnfilteredWrite> {
+    let words = top_level_words(statement, dialect);
+    if !matches!(words.first().map(|(_, w)| w.as_str()), Some("update" | "delete" | "with")) {
+        return None;
+    }
+
+    // Only the main verb counts, `SELECT ... FOR UPDATE` locks rows without writing them
+    let verb_index = main_verb(&words).filter(|&i| matches!(words[i].1.as_str(), "update" | "delete"))?;
+    let rest = &words[verb_index + 1..];
+    if rest.iter().any(|(_, w)| w == "where") {
+        return None;
+    }
+
+    let (verb, modifiers): (_, &[&str]) = if words[verb_index].1 == "update" {
+        ("UPDATE", &["low_priority", "ignore", "only", "or", "rollback", "abort", "replace", "fail"])
+    } else {
+        ("DELETE", &["low_priority", "quick", "ignore", "from", "only"])
+    };
+
+    // Step over modifiers that directly follow the verb, the table comes next
+    let (verb_start, verb_word) = &words[verb_index];
+    let mut pos = verb_start + verb_word.len();
+    for (start, word) in rest {
+        if !modifiers.contains(&word.as_str()) || !statement[pos..*start].trim().is_empty() {
+            break;
+        }
+        pos = start + word.len();
+    }
+
+    // The table may be schema qualified or quoted, take the raw text up to whitespace
+    let bytes = statement.as_bytes();
+    let table_start = pos + statement[pos..].len() - statement[pos..].trim_start().len();
+    let mut end = table_start;
+    while end < bytes.len() && !bytes[end].is_ascii_whitespace() && !matches!(bytes[end], b'(' | b';' | b',') {
+        end = match bytes[end] {
+            c @ (b'"' | b'`') => skip_quoted(bytes, end, c, false),
+            _ => end + 1,
+        };
+    }
+    let table = &statement[table_start..end];
+
+    Some(UnfilteredWrite {
+        verb,
+        table: table.to_string(),
+    })
+}
+
+/// Whether a statement only reads data. It must be a query and mention no writing
+/// keyword anywhere, which also catches data-modifying CTEs and `EXPLAIN ANALYZE`.
+/// Functions can still write, so read-only mode relies on the server for the rest.
+pub fn is_read_only(statement: &str, dialect: Dialect) -> bool {
+    const WRITES: [&str; 12] = [
+        "insert", "update", "delete", "merge", "truncate", "drop", "alter", "create", "grant",
+        "revoke", "into", "analyze",
+    ];
+
+    is_row_returning(statement, dialect)
+        && !scan_words(statement, dialect, false)
+            .iter()
+            .any(|(_, w)| WRITES.contains(&w.as_str()))
+}
+
+/// Keywords and identifiers outside parentheses, literals and comments, lowercased
+/// and paired with their byte offset in the statement
+fn top_level_words(statement: &str, dialect: Dialect) -> Vec<(usize, String)> {
+    scan_words(statement, dialect, true)
+}
+
+/// Index of the verb the statement runs. After a CTE list that's the first verb outside
+/// the CTE bodies, which are in parentheses; a later one, as in `FOR UPDATE`, is a clause.
+fn main_verb(words: &[(usize, String)]) -> Option<usize> {
+    if words.first()?.1 != "with" {
+        return Some(0);
+    }
+    words.iter().position(|(_, w)| {
+        matches!(w.as_str(), "select" | "insert" | "update" | "delete" | "merge" | "values" | "table")
+    })
+}
+
+fn scan_words(statement: &str, dialect: Dialect, top_level_only: bool) -> Vec<(usize, String)> {
+    let bytes = statement.as_bytes();
+    let mut words = Vec::new();
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            c @ (b'\'' | b'"' | b'`') => {
+                let escapes = c == b'\'' && (dialect.backslash_escapes() || is_escape_string(bytes, i));
+                i = skip_quoted(bytes, i, c, escapes);
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => i = skip_line_comment(bytes, i),
+            b'#' if dialect == Dialect::MySql => i = skip_line_comment(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i),
+            b'$' if dialect == Dialect::Postgres => match dollar_tag(bytes, i) {
+                Some(tag) => i = skip_dollar_quoted(bytes, i, tag),
+                None => i += 1,
+            },
+            b'(' => {
+                depth += 1;
+                i += 1;
+            }
+            b')' => {
+                depth -= 1;
+                i += 1;
+            }
+            c if is_ident_byte(c) => {
+                let end = i + bytes[i..].iter().take_while(|&&b| is_ident_byte(b)).count();
+                if depth == 0 || !top_level_only {
+                    words.push((i, statement[i..end].to_lowercase()));
+                }
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    words
+}
+
+/// Quotes a possibly schema-qualified name such as `public.users`, each part separately
+pub fn quote_identifier(name: &str, dialect: Dialect) -> String {
+    let quote = match dialect {
+        Dialect::MySql => '`',
+        Dialect::Postgres | Dialect::Sqlite => '"',
+    };
+    name.split('.')
+        .map(|part| {
+            let escaped = part.replace(quote, &format!("{}{}", quote, quote));
+            format!("{}{}{}", quote, escaped, quote)
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Statement removing every row of `table`. SQLite has no TRUNCATE, so it always gets DELETE.
+pub fn empty_table_statement(table: &str, dialect: Dialect, delete: bool) -> String {
+    let table = quote_identifier(table, dialect);
+    if delete || dialect == Dialect::Sqlite {
+        format!("DELETE FROM {}", table)
+    } else {
+        format!("TRUNCATE TABLE {}", table)
+    }
+}
+
+/// A maintenance statement offered for a table in the explorer
+#[derive(Debug, Clone)]
+pub struct Maintenance {
+    pub label: &'static str,
+    pub statement: String,
+    pub note: &'static str,
+    /// Keeps other sessions from reading or writing the table while it runs
+    pub blocking: bool,
+}
+
+/// Maintenance the dialect can run on `table`. SQLite only vacuums whole databases.
+pub fn maintenance_statements(table: &str, dialect: Dialect) -> Vec<Maintenance> {
+    let quoted = quote_identifier(table, dialect);
+    let entry = |label, statement: String, note, blocking| Maintenance { label, statement, note, blocking };
+    match dialect {
+        Dialect::Postgres => vec![
+            entry("VACUUM", format!("VACUUM (VERBOSE) {}", quoted), "Makes the space of dead rows reusable, reads and writes go on", false),
+            entry("VACUUM ANALYZE", format!("VACUUM (VERBOSE, ANALYZE) {}", quoted), "Vacuums, then refreshes the planner statistics", false),
+            entry("ANALYZE", format!("ANALYZE VERBOSE {}", quoted), "Refreshes the planner statistics", false),
+            entry("VACUUM FULL", format!("VACUUM (FULL, VERBOSE) {}", quoted), "Rewrites the table to give space back, locks it until done", true),
+        ],
+        Dialect::MySql => vec![
+            entry("ANALYZE TABLE", format!("ANALYZE TABLE {}", quoted), "Refreshes the index statistics", false),
+            entry("OPTIMIZE TABLE", format!("OPTIMIZE TABLE {}", quoted), "Rebuilds the table and its indexes, writes wait until done", true),
+        ],
+        Dialect::Sqlite => vec![
+            entry("ANALYZE", format!("ANALYZE {}", quoted), "Refreshes the statistics the planner uses", false),
+            entry("VACUUM", "VACUUM".to_string(), "Rebuilds the whole database file, not only this table", true),
+        ],
+    }
+}
+
+/// MySQL commits the open transaction before a TRUNCATE, so it can't be rolled back
+pub fn truncate_is_transactional(dialect: Dialect) -> bool {
+    dialect != Dialect::MySql
+}
+
+/// Whether the statement opens a transaction by hand, `BEGIN` or `START TRANSACTION`
+pub fn starts_transaction(statement: &str) -> bool {
+    let trimmed = strip_leading_comments(statement).to_lowercase();
+    let mut words = trimmed.split_whitespace();
+    match words.next() {
+        Some("begin") => true,
+        Some("start") => words.next() == Some("transaction"),
+        _ => false,
+    }
+}
+
+/// Strips leading whitespace and comments so a statement can be classified by its first keyword
+pub fn strip_leading_comments(statement: &str) -> &str {
+    let mut rest = statement.trim_start();
+    loop {
+        if rest.starts_with("--") {
+            rest = match rest.find('\n') {
+                Some(pos) => rest[pos + 1..].trim_start(),
+                None => "",
+            };
+        } else if rest.starts_with("/*") {
+            let end = skip_block_comment(rest.as_bytes(), 0);
+            rest = rest[end..].trim_start();
+        } else {
+            return rest;
+        }
+    }
+}
+
+/// Whether the statement is a read, whose result is its rows even when there are none.
+/// Writes can return rows too, with RETURNING, the backends keep whatever rows the
+/// driver sends and only fall back to the affected count when there are none.
+pub fn is_row_returning(query: &str, dialect: Dialect) -> bool {
+    let trimmed = strip_leading_comments(query).to_lowercase();
+    if trimmed.starts_with("with") {
+        // A CTE ending in a write, `WITH ... DELETE FROM ...`, is a write
+        let words = top_level_words(query, dialect);
+        return !main_verb(&words)
+            .is_some_and(|i| matches!(words[i].1.as_str(), "insert" | "update" | "delete" | "merge"));
+    }
+    trimmed.starts_with("select")
+        || trimmed.starts_with("show")
+        || trimmed.starts_with("describe")
+        || trimmed.starts_with("explain")
+        || trimmed.starts_with("values")
+}
+
+// Postgres E'...' strings honour backslash escapes
+fn is_escape_string(bytes: &[u8], quote: usize) -> bool {
+    quote > 0
+        && matches!(bytes[quote - 1], b'e' | b'E')
+        && (quote == 1 || !is_ident_byte(bytes[quote - 2]))
+}
+
+fn skip_quoted(bytes: &[u8], open: usize, quote: u8, backslash_escapes: bool) -> usize {
+    let mut i = open + 1;
+    while i < bytes.len() {
+        if backslash_escapes && bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i] == quote {
+            // A doubled quote is an escaped quote, not the end of the literal
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+            } else {
+                return i + 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+fn skip_line_comment(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |pos| start + pos + 1)
+}
+
+fn skip_block_comment(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'/' && bytes[i + 1] == b'*' {
+            depth += 1;
+            i += 2;
+        } else if bytes[i] == b'*' && bytes[i + 1] == b'/' {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the full `$tag$` delimiter starting at `start`, if there is one.
+/// `$1` style placeholders are not tags since a tag can't start with a digit.
+fn dollar_tag(bytes: &[u8], start: usize) -> Option<&[u8]> {
+    if start > 0 && is_ident_byte(bytes[start - 1]) {
+        return None;
+    }
+    let mut i = start + 1;
+    if i < bytes.len() && bytes[i].is_ascii_digit() {
+        return None;
+    }
+    while i < bytes.len() && is_ident_byte(bytes[i]) {
+        i += 1;
+    }
+    if i < bytes.len() && bytes[i] == b'$' {
+        Some(&bytes[start..=i])
+    } else {
+        None
+    }
+}
+
+fn skip_dollar_quoted(bytes: &[u8], start: usize, tag: &[u8]) -> usize {
+    let body = start + tag.len();
+    bytes[body..]
+        .windows(tag.len())
+        .position(|w| w == tag)
+        .map_or(bytes.len(), |pos| body + pos + tag.len())
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_semicolons_outside_literals() {
+        let script = "SELECT 'a;b', \"c;d\"; SELECT E'it\\'s;' ; SELECT 1";
+        assert_eq!(
+            split_statements(script, Dialect::Postgres),
+            vec!["SELECT 'a;b', \"c;d\"", "SELECT E'it\\'s;'", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn skips_comments_and_comment_only_statements() {
+        let script = "/* outer /* inner; */ still; */ SELECT 1; -- done;\n;";
+        assert_eq!(split_statements(script, Dialect::Postgres), vec!["/* outer /* inner; */ still; */ SELECT 1"]);
+
+        let script = "SELECT 1 # not; split\n; SELECT 2";
+        assert_eq!(split_statements(script, Dialect::MySql), vec!["SELECT 1 # not; split", "SELECT 2"]);
+        // Only MySQL reads `#` as a comment
+        assert_eq!(split_statements("SELECT 1 # a; SELECT 2", Dialect::Sqlite).len(), 2);
+    }
+
+    #[test]
+    fn keeps_dollar_quoted_bodies_whole() {
+        let script = "CREATE FUNCTION f() RETURNS int AS $body$ BEGIN; RETURN $1; END; $body$ LANGUAGE plpgsql; SELECT $$;$$";
+        let statements = split_statements(script, Dialect::Postgres);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].ends_with("LANGUAGE plpgsql"));
+        assert_eq!(statements[1], "SELECT $$;$$");
+    }
+
+    #[test]
+    fn limits_only_statements_that_read() {
+        assert_eq!(
+            push_down_limit("WITH ids AS (SELECT 1) SELECT * FROM ids", 100, Dialect::Postgres).as_deref(),
+            Some("WITH ids AS (SELECT 1) SELECT * FROM ids\nLIMIT 100")
+        );
+        for write in [
+            "WITH ids AS (SELECT id FROM t LIMIT 5) DELETE FROM t WHERE id IN (SELECT id FROM ids)",
+            "WITH ids AS (SELECT 1) UPDATE t SET a = 1 WHERE id IN (SELECT * FROM ids)",
+            "WITH ids AS (SELECT 1) INSERT INTO t SELECT * FROM ids",
+        ] {
+            assert_eq!(push_down_limit(write, 100, Dialect::MySql), None, "{}", write);
+            assert!(!is_row_returning(write, Dialect::Postgres), "{}", write);
+        }
+        assert_eq!(push_down_limit("SELECT * FROM t LIMIT 5", 100, Dialect::Postgres), None);
+    }
+
+    #[test]
+    fn recognizes_transaction_starts() {
+        for statement in ["BEGIN", "begin work", "-- tx\nSTART TRANSACTION READ ONLY", "BEGIN IMMEDIATE"] {
+            assert!(starts_transaction(statement), "{}", statement);
+        }
+        for statement in ["START SLAVE", "DO $$ BEGIN PERFORM 1; END $$", "SELECT 'begin'"] {
+            assert!(!starts_transaction(statement), "{}", statement);
+        }
+    }
+
+    #[test]
+    fn finds_writes_without_where() {
+        let found = find_unfiltered_write("WITH a AS (SELECT 1) DELETE FROM public.t", Dialect::Postgres);
+        assert_eq!(found, Some(UnfilteredWrite { verb: "DELETE", table: "public.t".to_string() }));
+        assert_eq!(find_unfiltered_write("UPDATE t SET a = 1 WHERE id = 1", Dialect::Postgres), None);
+        for locking in [
+            "WITH a AS (SELECT 1) SELECT * FROM t WHERE id = 1 FOR UPDATE",
+            "WITH a AS (SELECT 1) SELECT * FROM t FOR UPDATE",
+        ] {
+            assert_eq!(find_unfiltered_write(locking, Dialect::Postgres), None, "{}", locking);
+        }
+    }
+
+    #[test]
+    fn backslash_escapes_depend_on_dialect() {
+        // Standard strings end at the quote after the backslash, MySQL's don't
+        assert_eq!(split_statements(r"SELECT '\'; SELECT 2", Dialect::Postgres).len(), 2);
+        assert_eq!(split_statements(r"SELECT '\'; SELECT 2", Dialect::MySql).len(), 1);
+    }
+}
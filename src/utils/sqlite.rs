@@ -1,87 +1,167 @@
-use crate::utils::query_executor::QueryExecutor;
+use crate::utils::query_executor::{Pooled, QueryExecutor, RowStream, StatementOutput, send_batches};
+use crate::utils::value::Value;
+use futures_util::TryStreamExt;
+use tokio::sync::mpsc;
 use anyhow::{Result};
-use sqlx::sqlite::{SqliteColumn, SqlitePool, SqliteRow};
-use sqlx::{Column, Row, TypeInfo, ValueRef};
+use sqlx::sqlite::{SqliteArguments, SqliteColumn, SqliteRow};
+use sqlx::query::Query;
+use sqlx::{Column, Either, Sqlite, Row, TypeInfo, ValueRef};
 
 impl QueryExecutor {
-    pub async fn execute_sqlite(
+    pub async fn execute_sqlite<'e, E>(
         &self,
-        pool: &SqlitePool,
-        query: &str,
+        executor: E,
+        query: &'e str,
+        params: &'e [Value],
         is_query: bool,
-    ) -> Result<(Vec<String>, Vec<Vec<String>>)> {
-        if !is_query {
-            let result = sqlx::query(query).execute(pool).await?;
-            return Ok((
-                vec!["Result".to_string()],
-                vec![vec![format!("{} row(s) affected", result.rows_affected())]],
-            ));
-        }
+    ) -> Result<StatementOutput>
+    where
+        E: sqlx::Executor<'e, Database = Sqlite>,
+    {
+        // Rows decide, so INSERT ... RETURNING shows what it returned. Without any, a
+        // read is an empty result and anything else reports the rows it changed.
+        let mut stream = executor.fetch_many(Self::bind_sqlite(sqlx::query(query), params));
+        let mut headers: Vec<String> = Vec::new();
+        let mut result_rows = Vec::new();
+        let mut rows_affected = 0;
 
-        let rows = sqlx::query(query).fetch_all(pool).await?;
-        if rows.is_empty() {
-            return Ok((Vec::new(), Vec::new()));
+        while let Some(item) = stream.try_next().await? {
+            match item {
+                Either::Left(result) => rows_affected += result.rows_affected(),
+                Either::Right(row) => {
+                    if headers.is_empty() {
+                        headers = row.columns().iter().map(|c| c.name().to_string()).collect();
+                    }
+                    let row_data = row
+                        .columns()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, col)| Self::sqlite_to_value(&row, i, col))
+                        .collect();
+                    result_rows.push(row_data);
+                }
+            }
         }
 
-        let headers: Vec<String> = rows[0]
-            .columns()
-            .iter()
-            .map(|c| c.name().to_string())
-            .collect();
-        let mut result_rows = Vec::new();
+        if !result_rows.is_empty() {
+            return Ok(StatementOutput::Sets(vec![(headers, result_rows)]));
+        }
+        if is_query {
+            return Ok(StatementOutput::Sets(Vec::new()));
+        }
+        Ok(StatementOutput::Affected(rows_affected))
+    }
 
-        for row in rows {
-            let mut row_data = Vec::new();
-            for (i, col) in row.columns().iter().enumerate() {
-                row_data.push(self.sqlite_value_to_string(&row, i, col));
-            }
-            result_rows.push(row_data);
+    fn bind_sqlite<'q>(
+        mut query: Query<'q, Sqlite, SqliteArguments<'q>>,
+        params: &'q [Value],
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        for param in params {
+            query = match param {
+                Value::Null => query.bind(None::<String>),
+                Value::Bool(v) => query.bind(*v),
+                Value::Int(v) => query.bind(*v),
+                Value::Float(v) => query.bind(*v),
+                other => query.bind(other.to_string()),
+            };
         }
+        query
+    }
+
+    /// Streams the rows on `conn`, which goes back to the pool once they ran out
+    pub(crate) fn stream_sqlite(&self, mut conn: Pooled<Sqlite>, query: &str) -> RowStream {
+        let query = query.to_string();
+        let (sender, receiver) = mpsc::channel(1);
 
-        Ok((headers, result_rows))
+        let handle = tokio::spawn(async move {
+            let rows = sqlx::query(&query).fetch(&mut *conn);
+            send_batches(rows, sender, |row: &SqliteRow| {
+                row.columns().iter().enumerate().map(|(i, col)| Self::sqlite_to_value(row, i, col)).collect()
+            })
+            .await;
+            conn.release().await;
+        });
+
+        RowStream::new(receiver, handle)
     }
 
-    fn sqlite_value_to_string(&self, row: &SqliteRow, index: usize, col: &SqliteColumn) -> String {
+    fn sqlite_to_value(row: &SqliteRow, index: usize, col: &SqliteColumn) -> Value {
         if row.try_get_raw(index).map_or(true, |v| v.is_null()) {
-            return "NULL".to_string();
+            return Value::Null;
         }
 
         let type_name = col.type_info().name();
 
         match type_name {
-            "BOOLEAN" => row
-                .try_get::<bool, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "BOOLEAN" => Value::from_decode(row.try_get::<bool, _>(index), Value::Bool),
 
-            "INTEGER" => row
-                .try_get::<i64, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "INTEGER" => Value::from_decode(row.try_get::<i64, _>(index), Value::Int),
 
-            "REAL" => row
-                .try_get::<f64, _>(index)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "err".to_string()),
+            "REAL" => Value::from_decode(row.try_get::<f64, _>(index), Value::Float),
 
-            "TEXT" => row.try_get::<String, _>(index).unwrap_or_default(),
+            "TEXT" => Value::Text(row.try_get::<String, _>(index).unwrap_or_default()),
 
             "DATETIME" => row
                 .try_get::<chrono::NaiveDateTime, _>(index)
-                .map(|v| v.to_string())
+                .map(Value::Timestamp)
                 .unwrap_or_else(|_| {
                     // Sometimes SQLite stores dates as strings
-                    row.try_get::<String, _>(index)
-                        .unwrap_or_else(|_| "err".to_string())
+                    Value::from_decode(row.try_get::<String, _>(index), Value::Text)
                 }),
 
+            "BLOB" => Value::from_decode(row.try_get::<Vec<u8>, _>(index), Value::Bytes),
+
             _ => {
                 if let Ok(s) = row.try_get::<String, _>(index) {
-                    s
+                    Value::Text(s)
                 } else {
-                    format!("<{}>", type_name)
+                    Value::Unsupported(type_name.to_string())
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::{Connection, SqliteConnection};
+
+    #[tokio::test]
+    async fn decodes_every_supported_type() {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        sqlx::query(
+            "CREATE TABLE t (b BOOLEAN, i INTEGER, big INTEGER, r REAL, s TEXT, d DATETIME, d_text DATETIME, bl BLOB, n INTEGER)",
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO t VALUES (1, -42, 9223372036854775807, 2.5, 'text', '2024-02-29 13:14:15', 'tomorrow', x'00ff41', NULL)",
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let row = sqlx::query("SELECT * FROM t").fetch_one(&mut conn).await.unwrap();
+        let values: Vec<Value> = row
+            .columns()
+            .iter()
+            .enumerate()
+            .map(|(i, col)| QueryExecutor::sqlite_to_value(&row, i, col))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                Value::Bool(true),
+                Value::Int(-42),
+                Value::Int(i64::MAX),
+                Value::Float(2.5),
+                Value::Text("text".to_string()),
+                Value::Timestamp("2024-02-29T13:14:15".parse().unwrap()),
+                Value::Text("tomorrow".to_string()),
+                Value::Bytes(vec![0, 255, 65]),
+                Value::Null,
+            ]
+        );
+    }
+}
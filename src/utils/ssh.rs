@@ -0,0 +1,24 @@
+use anyhow::{Context, Result};
+use std::process::Stdio;
+use tokio::process::Command;
+
+use crate::utils::tunnel::{Tunnel, free_port};
+
+/// Forwards a free local port to `host:port` as seen from `bastion`, returning once the
+/// port is open. `bastion` goes to the ssh client as is, so aliases, users, identities and
+/// ProxyJump chains from ~/.ssh/config apply.
+pub async fn tunnel(bastion: &str, host: &str, port: u16) -> Result<Tunnel> {
+    let local_port = free_port()?;
+    let forward = format!("127.0.0.1:{}:{}:{}", local_port, host, port);
+    let child = Command::new("ssh")
+        // Nothing to type a password or confirm a host key into from the TUI. `--` keeps a
+        // bastion starting with `-` from being read as an option, like -oProxyCommand=...
+        .args(["-N", "-o", "ExitOnForwardFailure=yes", "-o", "BatchMode=yes", "-L", &forward, "--", bastion])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Could not run ssh, is it installed?")?;
+    Tunnel::open(child, local_port, &format!("ssh tunnel through {}", bastion)).await
+}
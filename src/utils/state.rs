@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Working context restored on the next launch, keyed by connection name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    pub last_connection: Option<String>,
+    /// Unsent query buffer of each connection
+    pub queries: BTreeMap<String, String>,
+    /// Selected table in the explorer of each connection
+    pub explorer_selected: BTreeMap<String, usize>,
+    pub max_results: u32,
+    pub panes: PaneSizes,
+}
+
+/// Query page layout, shared by all connections
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaneSizes {
+    pub editor_height: u16,
+    /// 0 keeps the explorer collapsed
+    pub explorer_width: u16,
+}
+
+impl Default for PaneSizes {
+    fn default() -> Self {
+        Self {
+            editor_height: 10,
+            explorer_width: 30,
+        }
+    }
+}
+
+pub struct StateManager {
+    state_path: PathBuf,
+}
+
+impl StateManager {
+    pub fn new() -> Result<Self> {
+        let config_dir = dirs::config_dir()
+            .context("Could not find config directory")?
+            .join("rsquid");
+
+        fs::create_dir_all(&config_dir)?;
+
+        let state_path = config_dir.join("state.json");
+
+        Ok(Self { state_path })
+    }
+
+    pub fn load_state(&self) -> Result<UiState> {
+        if !self.state_path.exists() {
+            return Ok(UiState::default());
+        }
+
+        let content = fs::read_to_string(&self.state_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save_state(&self, state: &UiState) -> Result<()> {
+        let content = serde_json::to_string_pretty(state)?;
+        fs::write(&self.state_path, content)?;
+        Ok(())
+    }
+}
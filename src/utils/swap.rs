@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Query buffers written while the app runs, so a crash doesn't lose them.
+/// A clean exit removes the file, finding one at startup means the last run crashed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwapFile {
+    pub saved_at: String,
+    /// Connection of the main query page
+    pub connection: Option<String>,
+    /// Query buffer of each open connection
+    pub queries: BTreeMap<String, String>,
+}
+
+/// Latest buffers, written by the panic hook when the app goes down
+static RECORDED: Mutex<Option<SwapFile>> = Mutex::new(None);
+
+pub fn record(swap: &SwapFile) {
+    if let Ok(mut recorded) = RECORDED.lock() {
+        *recorded = Some(swap.clone());
+    }
+}
+
+/// Writes the last recorded buffers, called from the panic hook
+pub fn save_recorded() {
+    // The panic may have happened while the lock was held
+    let Ok(recorded) = RECORDED.try_lock() else {
+        return;
+    };
+    if let Some(swap) = recorded.as_ref()
+        && let Ok(manager) = SwapManager::new()
+    {
+        let _ = manager.save(swap);
+    }
+}
+
+pub struct SwapManager {
+    swap_path: PathBuf,
+}
+
+impl SwapManager {
+    pub fn new() -> Result<Self> {
+        let config_dir = dirs::config_dir()
+            .context("Could not find config directory")?
+            .join("rsquid");
+
+        fs::create_dir_all(&config_dir)?;
+
+        let swap_path = config_dir.join("swap.json");
+
+        Ok(Self { swap_path })
+    }
+
+    /// Buffers left behind by a crashed run, if any
+    pub fn load(&self) -> Result<Option<SwapFile>> {
+        if !self.swap_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&self.swap_path)?;
+        let swap: SwapFile = serde_json::from_str(&content)?;
+        Ok(Some(swap).filter(|s| s.queries.values().any(|q| !q.trim().is_empty())))
+    }
+
+    pub fn save(&self, swap: &SwapFile) -> Result<()> {
+        let content = serde_json::to_string_pretty(swap)?;
+        fs::write(&self.swap_path, content)?;
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        if self.swap_path.exists() {
+            fs::remove_file(&self.swap_path)?;
+        }
+        Ok(())
+    }
+}
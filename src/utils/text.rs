@@ -0,0 +1,39 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const ELLIPSIS: &str = "...";
+
+/// Terminal columns a string occupies, wide CJK characters and emoji count as two
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Shortens `text` to at most `max_width` terminal columns, cutting on grapheme
+/// boundaries and marking the cut with an ellipsis
+pub fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    if max_width <= ELLIPSIS.len() {
+        return ELLIPSIS[..max_width].to_string();
+    }
+
+    let budget = max_width - ELLIPSIS.len();
+    let mut width = 0;
+    let mut result = String::new();
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = display_width(grapheme);
+        if width + grapheme_width > budget {
+            break;
+        }
+        width += grapheme_width;
+        result.push_str(grapheme);
+    }
+    result.push_str(ELLIPSIS);
+    result
+}
+
+/// Collapses line breaks and tabs so a value fits on a single row
+pub fn single_line(text: &str) -> String {
+    text.replace(['\n', '\r', '\t'], " ")
+}
@@ -0,0 +1,60 @@
+use anyhow::{Context, Result, bail};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::process::Child;
+
+/// How long a helper may take to log in and open the local port
+const START_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A local port forwarded by a helper process like kubectl or ssh, killed when stopped or dropped
+pub struct Tunnel {
+    child: Child,
+    pub local_port: u16,
+}
+
+impl Tunnel {
+    /// `child` must have been spawned with `kill_on_drop`
+    pub(crate) fn new(child: Child, local_port: u16) -> Self {
+        Self { child, local_port }
+    }
+
+    /// Waits until `child` listens on `local_port`, or fails with what it printed to stderr
+    /// when it quits first. `what` names the tunnel in errors.
+    pub(crate) async fn open(mut child: Child, local_port: u16, what: &str) -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                let mut message = String::new();
+                if let Some(mut stderr) = child.stderr.take() {
+                    let _ = stderr.read_to_string(&mut message).await;
+                }
+                bail!("{} failed ({}): {}", what, status, message.trim());
+            }
+            if TcpStream::connect(("127.0.0.1", local_port)).await.is_ok() {
+                // Helpers complain about every connection they can't forward, a full pipe would stall them
+                if let Some(mut stderr) = child.stderr.take() {
+                    tokio::spawn(async move {
+                        let _ = tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await;
+                    });
+                }
+                return Ok(Self::new(child, local_port));
+            }
+            if started.elapsed() > START_TIMEOUT {
+                bail!("{} didn't open within {}s", what, START_TIMEOUT.as_secs());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    pub fn stop(mut self) {
+        let _ = self.child.start_kill();
+    }
+}
+
+/// A port nothing listens on right now, for a helper to take
+pub(crate) fn free_port() -> Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0").context("Could not find a free local port")?;
+    Ok(listener.local_addr()?.port())
+}
@@ -0,0 +1,263 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
+use crate::utils::geometry::Geometry;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+use std::fmt::{self, Write};
+
+/// A single decoded result cell. Backends convert driver values into this,
+/// and the UI only turns it into a string when rendering.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Decimal(BigDecimal),
+    Text(String),
+    Bytes(Vec<u8>),
+    Json(serde_json::Value),
+    Timestamp(NaiveDateTime),
+    TimestampTz(DateTime<Utc>),
+    Date(NaiveDate),
+    Uuid(Uuid),
+    /// A Postgres array, its elements decoded like single values
+    Array(Vec<Value>),
+    /// A PostGIS geometry or geography
+    Geometry(Geometry),
+    /// A column type we don't know how to decode, holds the type name
+    Unsupported(String),
+    /// The driver reported a type we handle but the value failed to decode
+    Invalid,
+}
+
+/// How values are turned into text in the results table, set under [format] in the config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValueFormat {
+    /// strftime-style pattern for timestamps, empty keeps the database's own layout
+    pub timestamp_format: String,
+    /// Show timezone-aware timestamps in the local timezone instead of UTC
+    pub local_time: bool,
+    /// Digits after the decimal point for floats, unset prints them as decoded
+    pub float_precision: Option<usize>,
+    /// Digits NUMERIC/DECIMAL values are rounded to for display, unset shows every digit stored
+    pub decimal_places: Option<usize>,
+    /// Groups the integer part of numbers in threes, e.g. ',' or '_'
+    pub thousands_separator: Option<char>,
+}
+
+impl ValueFormat {
+    fn timestamp(&self, value: &impl fmt::Display, formatted: impl fmt::Display) -> String {
+        if self.timestamp_format.is_empty() {
+            return value.to_string();
+        }
+        // An invalid pattern makes chrono fail while writing, show the raw value then
+        let mut out = String::new();
+        match write!(out, "{}", formatted) {
+            Ok(()) => out,
+            Err(_) => value.to_string(),
+        }
+    }
+
+    fn number(&self, text: String) -> String {
+        let Some(separator) = self.thousands_separator else {
+            return text;
+        };
+        let (sign, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text.as_str()),
+        };
+        let int_len = unsigned
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(unsigned.len());
+        let (int_part, rest) = unsigned.split_at(int_len);
+
+        let mut grouped = String::with_capacity(text.len() + int_len / 3);
+        grouped.push_str(sign);
+        for (i, digit) in int_part.chars().enumerate() {
+            if i > 0 && (int_len - i) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped.push_str(rest);
+        grouped
+    }
+}
+
+impl Value {
+    /// Renders the value for display, numbers and timestamps follow `format`
+    pub fn format(&self, format: &ValueFormat) -> String {
+        match self {
+            Value::Int(v) => format.number(v.to_string()),
+            Value::UInt(v) => format.number(v.to_string()),
+            Value::Float(v) if v.is_finite() => format.number(match format.float_precision {
+                Some(precision) => format!("{:.*}", precision, v),
+                None => v.to_string(),
+            }),
+            Value::Decimal(v) => format.number(match format.decimal_places {
+                Some(places) => v.with_scale_round(places as i64, RoundingMode::HalfEven).to_plain_string(),
+                None => v.to_plain_string(),
+            }),
+            Value::Timestamp(v) => format.timestamp(v, v.format(&format.timestamp_format)),
+            Value::TimestampTz(v) if format.local_time => {
+                let local = v.with_timezone(&Local);
+                format.timestamp(&local, local.format(&format.timestamp_format))
+            }
+            Value::TimestampTz(v) => format.timestamp(v, v.format(&format.timestamp_format)),
+            Value::Array(items) => array_text(items, |item| item.format(format)),
+            // Binary data is unreadable in a cell, the row detail shows it in hex
+            Value::Bytes(v) => format!("<{} bytes>", v.len()),
+            // The SRID is left for the row detail, the cell shows the shape
+            Value::Geometry(v) => v.wkt(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Rough bytes the value takes in memory, for the result memory limit
+    pub fn memory_size(&self) -> usize {
+        let heap = match self {
+            Value::Text(v) | Value::Unsupported(v) => v.capacity(),
+            Value::Bytes(v) => v.capacity(),
+            // Counting every node would cost as much as the fetch, the text form is close enough
+            Value::Json(v) => v.to_string().len(),
+            Value::Decimal(v) => v.digits() as usize / 2,
+            Value::Array(items) => items.iter().map(Value::memory_size).sum(),
+            Value::Geometry(v) => v.coordinates().iter().map(|c| c.len() * 8).sum(),
+            _ => 0,
+        };
+        std::mem::size_of::<Value>() + heap
+    }
+
+    /// Keeps numbers, booleans, NULL and JSON documents typed, everything else becomes a string
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(v) => serde_json::Value::Bool(*v),
+            Value::Int(v) => serde_json::Value::from(*v),
+            Value::UInt(v) => serde_json::Value::from(*v),
+            Value::Float(v) => serde_json::Number::from_f64(*v)
+                .map(serde_json::Value::Number)
+                .unwrap_or_else(|| serde_json::Value::String(v.to_string())),
+            Value::Json(v) => v.clone(),
+            Value::Array(items) => serde_json::Value::Array(items.iter().map(Value::to_json).collect()),
+            other => serde_json::Value::String(other.to_string()),
+        }
+    }
+
+    /// Name of the decoded type, the database type for values rsquid can't decode
+    pub fn type_name(&self) -> &str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::UInt(_) => "uint",
+            Value::Float(_) => "float",
+            Value::Decimal(_) => "decimal",
+            Value::Text(_) => "text",
+            Value::Bytes(_) => "bytes",
+            Value::Json(_) => "json",
+            Value::Timestamp(_) => "timestamp",
+            Value::TimestampTz(_) => "timestamptz",
+            Value::Date(_) => "date",
+            Value::Uuid(_) => "uuid",
+            Value::Array(_) => "array",
+            Value::Geometry(_) => "geometry",
+            Value::Unsupported(type_name) => type_name,
+            Value::Invalid => "invalid",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Interprets text typed by the user as a bind parameter. Numbers, booleans and
+    /// NULL are recognised, wrapping the input in single quotes forces plain text.
+    pub fn from_input(input: &str) -> Value {
+        let trimmed = input.trim();
+        if trimmed.len() >= 2 && trimmed.starts_with('\'') && trimmed.ends_with('\'') {
+            return Value::Text(trimmed[1..trimmed.len() - 1].to_string());
+        }
+        if trimmed.eq_ignore_ascii_case("null") {
+            return Value::Null;
+        }
+        if let Ok(v) = trimmed.parse::<i64>() {
+            return Value::Int(v);
+        }
+        // f64 parsing also accepts things like "inf" and "nan", only take real numbers
+        if trimmed.chars().any(|c| c.is_ascii_digit())
+            && let Ok(v) = trimmed.parse::<f64>()
+        {
+            return Value::Float(v);
+        }
+        match trimmed.to_lowercase().as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::Text(input.to_string()),
+        }
+    }
+
+    pub fn from_decode<T, E>(result: Result<T, E>, wrap: impl FnOnce(T) -> Value) -> Value {
+        result.map(wrap).unwrap_or(Value::Invalid)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::Int(v) => write!(f, "{}", v),
+            Value::UInt(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            // Exports keep every digit, without switching to exponents for small or large values
+            Value::Decimal(v) => write!(f, "{}", v.to_plain_string()),
+            Value::Text(v) => write!(f, "{}", v),
+            Value::Bytes(v) => write!(f, "{}", String::from_utf8_lossy(v)),
+            Value::Json(v) => write!(f, "{}", v),
+            Value::Timestamp(v) => write!(f, "{}", v),
+            Value::TimestampTz(v) => write!(f, "{}", v),
+            Value::Date(v) => write!(f, "{}", v),
+            Value::Uuid(v) => write!(f, "{}", v),
+            Value::Array(items) => write!(f, "{}", array_text(items, |item| item.to_string())),
+            Value::Geometry(v) => write!(f, "{}", v),
+            Value::Unsupported(type_name) => write!(f, "<{}>", type_name),
+            Value::Invalid => write!(f, "err"),
+        }
+    }
+}
+
+/// Elements the way Postgres writes arrays, `{a, b, NULL}`. Elements that would be
+/// ambiguous are double-quoted, so the text also reads back as an array literal.
+fn array_text(items: &[Value], show: impl Fn(&Value) -> String) -> String {
+    let elements: Vec<String> = items
+        .iter()
+        .map(|item| {
+            let text = show(item);
+            let ambiguous = text.is_empty()
+                || text.eq_ignore_ascii_case("null")
+                || text.contains(|c: char| matches!(c, ',' | '{' | '}' | '"' | '\\') || c.is_whitespace());
+            match item {
+                Value::Null | Value::Array(_) => text,
+                _ if ambiguous => format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")),
+                _ => text,
+            }
+        })
+        .collect();
+    format!("{{{}}}", elements.join(", "))
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Text(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Text(s.to_string())
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use rust_xlsxwriter::{Color, Format, Workbook, Worksheet};
+use std::io::{self, Seek, Write};
+
+use crate::utils::value::Value;
+
+/// Rows Excel shows in one sheet, the header row included
+const MAX_SHEET_ROWS: u32 = 1_048_576;
+/// Longest text Excel keeps in a cell
+const MAX_CELL_CHARS: usize = 32_767;
+/// Longest sheet name Excel accepts
+const MAX_SHEET_NAME: usize = 31;
+/// Decimals with more significant digits than a double holds are written as text
+const MAX_NUMERIC_DIGITS: usize = 15;
+
+/// Writes result sets as an Excel workbook, row batch by row batch. Every result set
+/// gets its own sheet with a bold, frozen header row; one that doesn't fit in a sheet
+/// continues on the next, with the header repeated. Sheets are in constant memory
+/// mode, each row goes to a temporary file once the next one starts.
+pub struct XlsxWriter {
+    workbook: Workbook,
+    header: Format,
+    date: Format,
+    datetime: Format,
+    /// Name for the sheets of the current result set
+    set_name: String,
+    /// Sheets the current result set took so far
+    set_sheets: usize,
+    /// Rows in the open sheet, None when no sheet is open
+    sheet_rows: Option<u32>,
+    headers: Vec<String>,
+}
+
+impl XlsxWriter {
+    pub fn new() -> Self {
+        Self {
+            workbook: Workbook::new(),
+            header: Format::new().set_bold().set_background_color(Color::RGB(0xD9D9D9)),
+            date: Format::new().set_num_format("yyyy-mm-dd"),
+            datetime: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+            set_name: "Result".to_string(),
+            set_sheets: 0,
+            sheet_rows: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Closes the open sheet so the next rows start a new one named `name`
+    pub fn begin_set(&mut self, name: &str) {
+        self.sheet_rows = None;
+        self.set_name = name.to_string();
+        self.set_sheets = 0;
+    }
+
+    pub fn write_batch(&mut self, headers: &[String], rows: &[Vec<Value>]) -> Result<()> {
+        if self.sheet_rows.is_none() {
+            self.open_sheet(headers)?;
+        }
+        for row in rows {
+            if self.sheet_rows.is_some_and(|n| n >= MAX_SHEET_ROWS) {
+                let headers = std::mem::take(&mut self.headers);
+                self.open_sheet(&headers)?;
+            }
+            let index = self.sheet_rows.unwrap_or(0);
+            let last = self.workbook.worksheets().len() - 1;
+            let sheet = self.workbook.worksheet_from_index(last)?;
+            for (column, value) in row.iter().enumerate() {
+                write_cell(sheet, index, column as u16, value, &self.date, &self.datetime)?;
+            }
+            self.sheet_rows = Some(index + 1);
+        }
+        Ok(())
+    }
+
+    /// Assembles the workbook in a temporary file and copies it to `out`, a zip can't
+    /// be finished without going back to its start
+    pub fn finish(&mut self, out: &mut impl Write) -> Result<()> {
+        if self.workbook.worksheets().is_empty() {
+            self.open_sheet(&[])?;
+        }
+        let mut file = tempfile::tempfile()?;
+        self.workbook.save_to_writer(&mut file)?;
+        file.rewind()?;
+        io::copy(&mut file, out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn open_sheet(&mut self, headers: &[String]) -> Result<()> {
+        self.set_sheets += 1;
+        let name = match self.set_sheets {
+            1 => self.set_name.clone(),
+            part => format!("{} ({})", self.set_name, part),
+        };
+        let name = sheet_name(&name, self.workbook.worksheets().len() + 1);
+        let sheet = self.workbook.add_worksheet_with_constant_memory();
+        sheet.set_name(name)?;
+        if !headers.is_empty() {
+            sheet.set_freeze_panes(1, 0)?;
+            for (column, header) in headers.iter().enumerate() {
+                sheet.write_string_with_format(0, column as u16, truncate(header), &self.header)?;
+            }
+        }
+        self.sheet_rows = Some(if headers.is_empty() { 0 } else { 1 });
+        self.headers = headers.to_vec();
+        Ok(())
+    }
+}
+
+/// Sheet names can't hold some characters, be longer than 31 or repeat
+fn sheet_name(name: &str, index: usize) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\') { '_' } else { c })
+        .take(MAX_SHEET_NAME)
+        .collect();
+    if cleaned.trim().is_empty() || cleaned.starts_with('\'') || cleaned.ends_with('\'') {
+        format!("Sheet{}", index)
+    } else {
+        cleaned
+    }
+}
+
+fn write_cell(sheet: &mut Worksheet, row: u32, column: u16, value: &Value, date: &Format, datetime: &Format) -> Result<()> {
+    match value {
+        Value::Null => {}
+        Value::Bool(v) => {
+            sheet.write_boolean(row, column, *v)?;
+        }
+        Value::Int(v) => {
+            sheet.write_number(row, column, *v as f64)?;
+        }
+        Value::UInt(v) => {
+            sheet.write_number(row, column, *v as f64)?;
+        }
+        Value::Float(v) if v.is_finite() => {
+            sheet.write_number(row, column, *v)?;
+        }
+        Value::Decimal(v) => {
+            let text = v.normalized().to_string();
+            // Excel rounds to a double, keep long decimals exact as text
+            match text.parse::<f64>() {
+                Ok(number)
+                    if text.chars().filter(char::is_ascii_digit).count() <= MAX_NUMERIC_DIGITS
+                        && !text.contains('e') =>
+                {
+                    sheet.write_number(row, column, number)?;
+                }
+                _ => {
+                    sheet.write_string(row, column, text)?;
+                }
+            }
+        }
+        Value::Date(v) => {
+            sheet.write_date_with_format(row, column, v, date)?;
+        }
+        Value::Timestamp(v) => {
+            sheet.write_datetime_with_format(row, column, v, datetime)?;
+        }
+        Value::TimestampTz(v) => {
+            sheet.write_datetime_with_format(row, column, v.naive_utc(), datetime)?;
+        }
+        other => {
+            sheet.write_string(row, column, truncate(&other.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Cuts text to what Excel keeps in a cell, it refuses longer strings
+fn truncate(text: &str) -> String {
+    text.chars().take(MAX_CELL_CHARS).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{Data, DataType, Reader, Xlsx};
+    use chrono::NaiveDate;
+    use std::io::Cursor;
+
+    #[test]
+    fn calamine_reads_the_workbook() {
+        let headers = vec!["id".to_string(), "name".to_string(), "day".to_string()];
+        let day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let mut writer = XlsxWriter::new();
+        writer.begin_set("Result 1");
+        writer
+            .write_batch(&headers, &[vec![Value::Int(1), Value::from("a & <b>"), Value::Date(day)]])
+            .unwrap();
+        writer.write_batch(&headers, &[vec![Value::Int(2), Value::Null, Value::Bool(true)]]).unwrap();
+        writer.begin_set("Result 2");
+        writer.write_batch(&["n".to_string()], &[vec![Value::Float(0.5)]]).unwrap();
+        let mut file = Vec::new();
+        writer.finish(&mut file).unwrap();
+
+        let mut workbook: Xlsx<_> = calamine::open_workbook_from_rs(Cursor::new(file)).unwrap();
+        assert_eq!(workbook.sheet_names(), ["Result 1", "Result 2"]);
+        let first = workbook.worksheet_range("Result 1").unwrap();
+        let rows: Vec<&[Data]> = first.rows().collect();
+        assert_eq!(rows[0], [Data::String("id".into()), Data::String("name".into()), Data::String("day".into())]);
+        assert_eq!(rows[1][0], Data::Float(1.0));
+        assert_eq!(rows[1][1], Data::String("a & <b>".into()));
+        assert_eq!(rows[1][2].as_date(), Some(day));
+        assert_eq!(rows[2], [Data::Float(2.0), Data::Empty, Data::Bool(true)]);
+        let second = workbook.worksheet_range("Result 2").unwrap();
+        assert_eq!(second.get_value((1, 0)), Some(&Data::Float(0.5)));
+    }
+}